@@ -1,4 +1,11 @@
-use std::{env, fs::File, io::Write, path::PathBuf, process};
+use std::{
+    env,
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use which::which;
 
 // macro_rules! warn {
@@ -20,6 +27,30 @@ fn run_command(tag: &str, command: &mut process::Command) -> process::Output {
     output
 }
 
+// UTC date, or of SOURCE_DATE_EPOCH for reproducible builds
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        });
+    // Civil date from days since epoch, by Howard Hinnant's algorithm
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
 fn generate_rc(add_icon: bool) -> String {
     #[cfg(not(any(debug_assertions, feature = "dep-only")))]
     let rc_template = include_bytes!("assets\\monmouse.rc");
@@ -77,6 +108,7 @@ fn main() {
         "cargo:rustc-env=VERSION_SHA={}",
         if sha.len() <= 7 { &sha[..] } else { &sha[..7] }
     );
+    println!("cargo:rustc-env=VERSION_BUILD_DATE={}", build_date());
 
     if cfg!(feature = "dep-only") {
         println!("cargo::rerun-if-changed=dep-only")
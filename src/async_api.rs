@@ -0,0 +1,197 @@
+// Async wrappers of roundtrip messages to mouse control, for embedding into async apps
+// instead of the GUI. Built with feature "async", independent of any runtime.
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+
+use crate::{
+    bridge::{BusEvent, EventBus, Topic},
+    errors::{Error, Result},
+    message::{DeviceStatus, GenericDevice, Message, MessageSender, RoundtripData, UIReactor},
+    setting::ProcessorSettings,
+};
+
+type Responder<T> = oneshot::Sender<Result<T>>;
+
+// Responses come back in the order of requests of the same kind
+#[derive(Default)]
+struct Pending {
+    // Mouse control is gone, no more responses
+    closed: bool,
+    scan: VecDeque<Responder<Vec<GenericDevice>>>,
+    status: VecDeque<Responder<Vec<(String, DeviceStatus)>>>,
+    apply: VecDeque<Responder<()>>,
+}
+
+fn respond<R, T>(queue: &mut VecDeque<Responder<T>>, data: RoundtripData<R, T>) {
+    if let Some(tx) = queue.pop_front() {
+        let _ = tx.send(data.take_rsp());
+    }
+}
+
+pub struct AsyncClient {
+    mouse_control_tx: MessageSender,
+    pending: Arc<Mutex<Pending>>,
+    bus: EventBus,
+}
+
+impl AsyncClient {
+    // Takes the UI side of reactors. Messages to UI other than responses are dropped,
+    // subscribe events() for them instead.
+    pub fn new(ui: UIReactor) -> Self {
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let pending1 = pending.clone();
+        let ui_rx = ui.ui_rx;
+        std::thread::spawn(move || loop {
+            let msg = ui_rx.recv();
+            let mut p = pending1.lock().unwrap();
+            match msg {
+                Message::Exit => {
+                    // Waiting requests are canceled, answered with NotRunning
+                    *p = Pending {
+                        closed: true,
+                        ..Default::default()
+                    };
+                    return;
+                }
+                Message::ScanDevices(data) => respond(&mut p.scan, data),
+                Message::InspectDevicesStatus(data) => respond(&mut p.status, data),
+                Message::ApplyProcessorSetting(data) => respond(&mut p.apply, data),
+                _ => (),
+            }
+        });
+        AsyncClient {
+            mouse_control_tx: ui.mouse_control_tx,
+            pending,
+            bus: ui.bus,
+        }
+    }
+
+    fn request<T>(
+        &self,
+        queue: fn(&mut Pending) -> &mut VecDeque<Responder<T>>,
+        msg: Message,
+    ) -> Result<oneshot::Receiver<Result<T>>> {
+        let (tx, rx) = oneshot::channel();
+        let mut p = self.pending.lock().unwrap();
+        if p.closed {
+            return Err(Error::NotRunning);
+        }
+        queue(&mut p).push_back(tx);
+        self.mouse_control_tx.send(msg);
+        Ok(rx)
+    }
+
+    pub async fn scan_devices(&self) -> Result<Vec<GenericDevice>> {
+        let msg = Message::ScanDevices(RoundtripData::default());
+        let rx = self.request(|p| &mut p.scan, msg)?;
+        rx.await.unwrap_or(Err(Error::NotRunning))
+    }
+
+    pub async fn inspect_devices_status(&self) -> Result<Vec<(String, DeviceStatus)>> {
+        let msg = Message::InspectDevicesStatus(RoundtripData::default());
+        let rx = self.request(|p| &mut p.status, msg)?;
+        rx.await.unwrap_or(Err(Error::NotRunning))
+    }
+
+    pub async fn apply_settings(&self, settings: ProcessorSettings) -> Result<()> {
+        let msg = Message::ApplyProcessorSetting(RoundtripData::new(settings));
+        let rx = self.request(|p| &mut p.apply, msg)?;
+        rx.await.unwrap_or(Err(Error::NotRunning))
+    }
+
+    // Events of given topics since now. Events are forwarded by a thread, which ends at
+    // the next event after the stream is dropped.
+    pub fn events(&self, topics: &[Topic]) -> EventStream {
+        let (tx, rx) = mpsc::unbounded();
+        let sub = self.bus.subscribe(topics);
+        std::thread::spawn(move || {
+            while let Some(event) = sub.recv() {
+                if tx.unbounded_send(event).is_err() {
+                    return;
+                }
+            }
+        });
+        EventStream(rx)
+    }
+}
+
+pub struct EventStream(mpsc::UnboundedReceiver<BusEvent>);
+
+impl Stream for EventStream {
+    type Item = BusEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{setup_reactors, UINotifyNoop};
+    use std::{
+        future::Future,
+        task::{Wake, Waker},
+        time::Duration,
+    };
+
+    // Polled in a loop anyway, nothing to wake
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let mut f = std::pin::pin!(f);
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return v;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_and_events() {
+        let (_, mouse_ctrl, ui) =
+            setup_reactors(Box::new(UINotifyNoop {}), Box::new(UINotifyNoop {}));
+        let client = AsyncClient::new(ui);
+        let mut events = client.events(&[Topic::Status]);
+        let mouse_ctrl = std::thread::spawn(move || {
+            match mouse_ctrl.mouse_control_rx.recv() {
+                Message::ScanDevices(mut data) => {
+                    data.set_ok(vec![GenericDevice::id_only("dev1".to_owned())]);
+                    mouse_ctrl.return_msg(Message::ScanDevices(data));
+                }
+                msg => panic!("unexpected {:?}", msg),
+            }
+            mouse_ctrl.report_user_pause_changed(true);
+            // Exit is sent to UI when mouse control is gone
+            mouse_ctrl.ui_tx.send(Message::Exit);
+        });
+
+        let devices = block_on(client.scan_devices()).unwrap();
+        assert_eq!(devices[0].id, "dev1");
+        let event = block_on(std::future::poll_fn(|cx| {
+            Pin::new(&mut events).poll_next(cx)
+        }));
+        assert!(matches!(event, Some(BusEvent::UserPauseChanged(true))));
+
+        mouse_ctrl.join().unwrap();
+        assert!(matches!(
+            block_on(client.scan_devices()),
+            Err(Error::NotRunning)
+        ));
+    }
+}
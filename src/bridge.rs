@@ -0,0 +1,174 @@
+// Typed events published by mouse control, received by any number of subscribers
+// independently, e.g. tray and GUI. Unlike Message, which goes to one fixed receiver,
+// publishers don't need to know who is listening, nor do subscribers need new match arms of
+// return_msg.
+
+use std::sync::{
+    mpsc::{channel, Receiver},
+    Arc, Mutex,
+};
+
+use crate::{
+    message::{ActiveDeviceEvent, GenericDevice, TrackingMode},
+    mouse_control::MousePos,
+    setting::ProcessorSettings,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topic {
+    Devices,
+    Status,
+    Settings,
+    Relocations,
+}
+
+#[derive(Clone, Debug)]
+pub enum BusEvent {
+    ActiveDeviceChanged(ActiveDeviceEvent),
+    DevicesUpdated(Vec<GenericDevice>),
+    BatteryUpdated(Vec<(String, u8)>),
+    ProcessorCrashed(String),
+    SessionChanged(bool /* paused */),
+    UserPauseChanged(bool /* paused */),
+    TrackingModeChanged(TrackingMode),
+    KvmRemoteChanged(bool /* remote */),
+    // Paused for any reason, by user, session, KVM or monitors settling
+    PausedChanged(bool /* paused */),
+    // Devices may still enumerate late after startup
+    InitialScan(bool /* in progress */),
+    // Locking and switching of all devices dropped by failsafe shortcut
+    FailsafeTriggered,
+    // Monitors changed much and settled, locks held until confirmed. With the layout.
+    MonitorsChanged(String),
+    // Names of shortcuts taken by other programs, being registered again
+    ShortcutsPending(Vec<String>),
+    SettingsApplied(Arc<ProcessorSettings>),
+    MonitorJumped,
+    Relocated(MousePos),
+}
+
+impl BusEvent {
+    pub fn topic(&self) -> Topic {
+        match self {
+            BusEvent::ActiveDeviceChanged(_)
+            | BusEvent::DevicesUpdated(_)
+            | BusEvent::BatteryUpdated(_) => Topic::Devices,
+            BusEvent::ProcessorCrashed(_)
+            | BusEvent::SessionChanged(_)
+            | BusEvent::UserPauseChanged(_)
+            | BusEvent::TrackingModeChanged(_)
+            | BusEvent::KvmRemoteChanged(_)
+            | BusEvent::PausedChanged(_)
+            | BusEvent::InitialScan(_)
+            | BusEvent::FailsafeTriggered
+            | BusEvent::MonitorsChanged(_)
+            | BusEvent::ShortcutsPending(_) => Topic::Status,
+            BusEvent::SettingsApplied(_) => Topic::Settings,
+            BusEvent::MonitorJumped | BusEvent::Relocated(_) => Topic::Relocations,
+        }
+    }
+}
+
+struct Subscriber {
+    topics: Vec<Topic>,
+    // False once the receiving end is gone
+    sink: Box<dyn Fn(BusEvent) -> bool + Send>,
+}
+
+#[derive(Clone, Default)]
+pub struct EventBus(Arc<Mutex<Vec<Subscriber>>>);
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Events are queued until received, or dropped along with the subscription
+    pub fn subscribe(&self, topics: &[Topic]) -> Subscription {
+        let (tx, rx) = channel();
+        self.forward(topics, move |event| tx.send(event).is_ok());
+        Subscription(rx)
+    }
+
+    // Events are passed to sink in the publishing thread, e.g. into a queue of the
+    // subscriber's own. Removed once sink returns false.
+    pub fn forward(&self, topics: &[Topic], sink: impl Fn(BusEvent) -> bool + Send + 'static) {
+        self.0.lock().unwrap().push(Subscriber {
+            topics: topics.to_vec(),
+            sink: Box::new(sink),
+        });
+    }
+
+    // For skipping building costly events
+    pub fn has_subscribers(&self, topic: Topic) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|s| s.topics.contains(&topic))
+    }
+
+    pub fn publish(&self, event: BusEvent) {
+        let topic = event.topic();
+        let mut subscribers = self.0.lock().unwrap();
+        // Dropped subscriptions are removed here
+        subscribers.retain(|s| !s.topics.contains(&topic) || (s.sink)(event.clone()));
+    }
+}
+
+pub struct Subscription(Receiver<BusEvent>);
+
+impl Subscription {
+    pub fn try_recv(&self) -> Option<BusEvent> {
+        self.0.try_recv().ok()
+    }
+
+    // Blocks until an event comes, or None if the bus is dropped
+    pub fn recv(&self) -> Option<BusEvent> {
+        self.0.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribe_by_topics() {
+        let bus = EventBus::new();
+        let status = bus.subscribe(&[Topic::Status]);
+        let all = bus.subscribe(&[Topic::Status, Topic::Relocations]);
+        assert!(!bus.has_subscribers(Topic::Devices));
+
+        bus.publish(BusEvent::UserPauseChanged(true));
+        bus.publish(BusEvent::Relocated(MousePos::from(1, 2)));
+        assert!(matches!(
+            status.try_recv(),
+            Some(BusEvent::UserPauseChanged(true))
+        ));
+        assert!(status.try_recv().is_none());
+        assert!(matches!(
+            all.try_recv(),
+            Some(BusEvent::UserPauseChanged(true))
+        ));
+        assert!(matches!(all.try_recv(), Some(BusEvent::Relocated(_))));
+
+        drop(all);
+        bus.publish(BusEvent::MonitorJumped);
+        assert!(!bus.has_subscribers(Topic::Relocations));
+        assert!(bus.has_subscribers(Topic::Status));
+    }
+
+    #[test]
+    fn test_forward_until_sink_fails() {
+        let bus = EventBus::new();
+        let (tx, rx) = channel();
+        bus.forward(&[Topic::Devices], move |event| tx.send(event).is_ok());
+
+        bus.publish(BusEvent::BatteryUpdated(vec![("mouse".to_owned(), 80)]));
+        assert!(matches!(rx.try_recv(), Ok(BusEvent::BatteryUpdated(_))));
+        drop(rx);
+        bus.publish(BusEvent::DevicesUpdated(Vec::new()));
+        assert!(!bus.has_subscribers(Topic::Devices));
+    }
+}
@@ -1,31 +1,44 @@
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{debug, error, info};
 use monmouse::{
+    build_info,
     errors::Error,
-    message::{setup_reactors, GenericDevice, UINotifyNoop},
-    setting::{read_config, CONFIG_FILE_NAME},
-    SingleProcess,
+    inventory::{export_devices, InventoryFormat},
+    message::{setup_reactors, DiagnosticCheck, GenericDevice, UINotifyNoop},
+    paths::{
+        config_file_in, deployed_config_file, remove_artifacts, AppPaths, DEFAULT_CONFIG_DIR,
+        TRACE_FILE_NAME,
+    },
+    remote_action::RemoteAction,
+    run_watchdog, send_remote_action,
+    setting::{bootstrap_config, read_config, Settings},
+    settings_doc::{explain, settings_doc},
+    spawn_watchdog,
+    update_check::{latest_release, MANUAL_CHECK_CACHE_SECS},
+    watchdog_args, SingleProcess,
 };
 
-#[cfg(not(debug_assertions))]
-const CLI_DEFAULT_CONFIG_DIR: &str = ".";
-#[cfg(debug_assertions)]
-const CLI_DEFAULT_CONFIG_DIR: &str = "debug";
-
 fn default_config_file() -> String {
-    PathBuf::from(CLI_DEFAULT_CONFIG_DIR)
-        .join(CONFIG_FILE_NAME)
+    config_file_in(&PathBuf::from(DEFAULT_CONFIG_DIR))
         .to_str()
         .unwrap()
         .to_owned()
 }
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, about, long_about = None, disable_version_flag = true)]
 struct Args {
+    /// Print version, with build details if --verbose
+    #[arg(short = 'V', long)]
+    version: bool,
+
+    #[arg(long, requires = "version")]
+    verbose: bool,
+
     #[arg(short, long, default_value_t = default_config_file())]
     config_file: String,
 
@@ -34,8 +47,83 @@ struct Args {
 
     #[arg(short, long)]
     print_devices: bool,
+
+    /// Print devices as "json" or "csv" instead of text
+    #[arg(long, requires = "print_devices")]
+    format: Option<String>,
+
+    /// Start with default settings, ignoring config file. Mouse hook and shortcuts are disabled
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Record raw events of the first such seconds into a trace file next to config file
+    #[arg(long, value_name = "SECS")]
+    capture_trace: Option<u32>,
+
+    /// Describe a setting, e.g. "processor.turbo_speed_percent" or just "turbo_speed_percent"
+    #[arg(long, value_name = "KEY")]
+    explain: Option<String>,
+
+    /// Merge a config deployed by administrators into config file and exit, e.g. by installers.
+    /// Values already in config file are kept. Exit with 1 if it cannot be merged
+    #[arg(long, value_name = "PATH")]
+    bootstrap_config: Option<String>,
+
+    /// Print errors as one line of JSON with stable "code" and "category", for scripts. Exit
+    /// codes of failing to start or run are by category: config 10, shortcut 11, instance 12,
+    /// remote 13, win-api 14, io 15, internal 16
+    #[arg(long)]
+    json_errors: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check config file, exit with 0 if valid, 1 if invalid, 2 if cannot be read
+    Validate {
+        #[arg(short, long, default_value_t = default_config_file())]
+        config_file: String,
+    },
+    /// Check whether mouse control can work on this system, exit with 1 if any check fails
+    Doctor {
+        #[arg(short, long, default_value_t = default_config_file())]
+        config_file: String,
+    },
+    /// Trigger an action in the running instance, e.g. "jump_next", "lock_toggle", "pause".
+    /// Exit with 1 if action is unknown, 2 if MonMouse is not running
+    Action { name: String },
+    /// Remove config, data files beside it and autostart of MonMouse, after confirmation.
+    /// Exit with 1 if any removal fails, 2 if MonMouse is running
+    Cleanup {
+        #[arg(short, long, default_value_t = default_config_file())]
+        config_file: String,
+
+        /// Remove without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Ask GitHub for the latest release, answered by the cache beside config if checked
+    /// within an hour. Exit with 1 if a newer one is available, 2 if the check fails
+    CheckUpdate {
+        #[arg(short, long, default_value_t = default_config_file())]
+        config_file: String,
+    },
 }
 
+const VALIDATE_EXIT_INVALID: i32 = 1;
+const VALIDATE_EXIT_UNREADABLE: i32 = 2;
+const DOCTOR_EXIT_FAILED: i32 = 1;
+const EXPLAIN_EXIT_UNKNOWN: i32 = 1;
+const ACTION_EXIT_UNKNOWN: i32 = 1;
+const ACTION_EXIT_NOT_RUNNING: i32 = 2;
+const CLEANUP_EXIT_FAILED: i32 = 1;
+const CLEANUP_EXIT_RUNNING: i32 = 2;
+const UPDATE_EXIT_AVAILABLE: i32 = 1;
+const UPDATE_EXIT_FAILED: i32 = 2;
+const BOOTSTRAP_EXIT_FAILED: i32 = 1;
+
 fn setup_logger(o: Option<String>) -> Result<(), Error> {
     if let Some(log_level) = o {
         match log::LevelFilter::from_str(log_level.as_str()) {
@@ -48,12 +136,79 @@ fn setup_logger(o: Option<String>) -> Result<(), Error> {
     Ok(())
 }
 
-fn main() -> Result<(), Error> {
+fn print_error(e: &Error, json: bool) {
+    if json {
+        println!("{}", e.to_json());
+    } else {
+        println!("error[{}]: {}", e.code(), e);
+    }
+}
+
+fn main() {
+    // Spawned by another instance with restart_on_crash, arguments are not parsed
+    if let Some(args) = watchdog_args() {
+        env_logger::builder().init();
+        if let Err(e) = run_watchdog(&args) {
+            error!("Watchdog failed: {}", e);
+            std::process::exit(e.exit_code());
+        }
+        return;
+    }
     let args = Args::parse();
+    let json = args.json_errors;
+    if let Err(e) = run(args) {
+        print_error(&e, json);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let json = args.json_errors;
+    if args.version {
+        let info = build_info();
+        if args.verbose {
+            println!("{}", info.verbose());
+        } else {
+            println!("monmouse-cli {}", info);
+        }
+        return Ok(());
+    }
+    if let Some(key) = &args.explain {
+        std::process::exit(explain_setting(key));
+    }
+    if let Some(deployed) = &args.bootstrap_config {
+        std::process::exit(bootstrap(&args.config_file, deployed, json));
+    }
+    match args.command {
+        Some(Command::Validate { config_file }) => {
+            std::process::exit(validate_config(&config_file, json))
+        }
+        Some(Command::Doctor { config_file }) => std::process::exit(doctor(&config_file)),
+        Some(Command::Action { name }) => std::process::exit(remote_action(&name, json)),
+        Some(Command::Cleanup { config_file, yes }) => {
+            std::process::exit(cleanup(&config_file, yes, json))
+        }
+        Some(Command::CheckUpdate { config_file }) => {
+            std::process::exit(check_update(&config_file, json))
+        }
+        None => (),
+    }
     setup_logger(args.log_level)?;
     let single_process = SingleProcess::create()?;
 
-    let config = read_config(&PathBuf::from(args.config_file))?;
+    let config = if args.safe_mode {
+        info!("Safe mode, config file ignored");
+        Settings::default()
+    } else {
+        // Config deployed by administrators becomes the config file, if there is none yet
+        let path = PathBuf::from(&args.config_file);
+        if let (false, Some(deployed)) = (path.exists(), deployed_config_file()) {
+            if let Err(e) = bootstrap_config(&path, &deployed) {
+                error!("Bootstrap config from {} failed: {}", deployed.display(), e);
+            }
+        }
+        read_config(&PathBuf::from(&args.config_file))?
+    };
     debug!("Config loaded: {:?}", config);
 
     let (_, mouse_control_reactor, _) = setup_reactors(
@@ -61,14 +216,37 @@ fn main() -> Result<(), Error> {
         Box::<UINotifyNoop>::default(),
     ); // useless, but still setup
     let mut eventloop = monmouse::Eventloop::new(true, mouse_control_reactor);
+    eventloop.set_safe_mode(args.safe_mode);
 
     if args.print_devices {
+        let format = match &args.format {
+            Some(name) => Some(
+                InventoryFormat::from_name(name)
+                    .ok_or_else(|| Error::InvalidParam("format".to_owned(), name.clone()))?,
+            ),
+            None => None,
+        };
         let devices = eventloop.scan_devices()?;
-        print_devices(devices);
+        match format {
+            Some(format) => print!("{}", export_devices(&devices, format)),
+            None => print_devices(devices),
+        }
         return Ok(());
     }
 
+    if config.processor.restart_on_crash {
+        if let Err(e) = spawn_watchdog() {
+            error!("Start watchdog failed: {}", e);
+        }
+    }
+    if !args.safe_mode {
+        eventloop.set_config_file(PathBuf::from(&args.config_file), config.clone());
+    }
     eventloop.load_config(config)?;
+    if let Some(secs) = args.capture_trace {
+        let path = PathBuf::from(&args.config_file).with_file_name(TRACE_FILE_NAME);
+        eventloop.start_trace(&path, secs)?;
+    }
     info!("monmouse-cli started");
     let result = eventloop.run();
     match &result {
@@ -80,6 +258,210 @@ fn main() -> Result<(), Error> {
     result
 }
 
+fn explain_setting(key: &str) -> i32 {
+    if let Some(doc) = explain(key) {
+        println!("{}", doc);
+        return 0;
+    }
+    println!("unknown setting: {}, known ones are:", key);
+    for doc in settings_doc() {
+        println!("  {}", doc.key);
+    }
+    EXPLAIN_EXIT_UNKNOWN
+}
+
+fn bootstrap(config_file: &str, deployed: &str, json: bool) -> i32 {
+    match bootstrap_config(&PathBuf::from(config_file), Path::new(deployed)) {
+        Ok(true) => {
+            println!("ok: {} merged into {}", deployed, config_file);
+            0
+        }
+        Ok(false) => {
+            println!("ok: {} unchanged", config_file);
+            0
+        }
+        Err(e) => {
+            print_error(&e, json);
+            BOOTSTRAP_EXIT_FAILED
+        }
+    }
+}
+
+fn remote_action(name: &str, json: bool) -> i32 {
+    let Some(action) = RemoteAction::from_name(name) else {
+        let names: Vec<&str> = RemoteAction::VARIANTS.iter().map(|a| a.name()).collect();
+        println!(
+            "unknown action {}, expected one of: {}",
+            name,
+            names.join(", ")
+        );
+        return ACTION_EXIT_UNKNOWN;
+    };
+    match send_remote_action(action) {
+        Ok(_) => 0,
+        Err(e) => {
+            print_error(&e, json);
+            ACTION_EXIT_NOT_RUNNING
+        }
+    }
+}
+
+fn app_paths(config_file: &str) -> AppPaths {
+    match Path::new(config_file).parent() {
+        Some(v) if !v.as_os_str().is_empty() => AppPaths::new(v),
+        _ => AppPaths::new(Path::new(".")),
+    }
+}
+
+fn check_update(config_file: &str, json: bool) -> i32 {
+    let cache_file = app_paths(config_file).update_cache_file();
+    let release = match latest_release(&cache_file, MANUAL_CHECK_CACHE_SECS) {
+        Ok(v) => v,
+        Err(e) => {
+            print_error(&e, json);
+            return UPDATE_EXIT_FAILED;
+        }
+    };
+    if release.is_newer() {
+        println!("{} available: {}", release.tag, release.url);
+        return UPDATE_EXIT_AVAILABLE;
+    }
+    println!("up to date, latest is {}", release.tag);
+    0
+}
+
+fn cleanup(config_file: &str, yes: bool, json: bool) -> i32 {
+    // Also keeps it from being started while removing
+    let single_process = match SingleProcess::create() {
+        Ok(v) => v,
+        Err(e) => {
+            print_error(&e, json);
+            if !json {
+                println!("quit the running MonMouse first");
+            }
+            return CLEANUP_EXIT_RUNNING;
+        }
+    };
+    let artifacts = app_paths(config_file).artifacts();
+    if artifacts.is_empty() {
+        println!("nothing to remove");
+        return 0;
+    }
+    println!("to be removed:");
+    for a in artifacts.iter() {
+        println!("  {}", a);
+    }
+    if !yes {
+        print!("remove them? [y/N] ");
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("canceled");
+            return 0;
+        }
+    }
+
+    let failed = remove_artifacts(&artifacts);
+    drop(single_process);
+    for (a, e) in failed.iter() {
+        println!("error: {}: {}", a, e);
+    }
+    if !failed.is_empty() {
+        return CLEANUP_EXIT_FAILED;
+    }
+    println!("removed {} item(s)", artifacts.len());
+    0
+}
+
+fn validate_config(config_file: &str, json: bool) -> i32 {
+    let config = match read_config(&PathBuf::from(config_file)) {
+        Ok(v) => v,
+        Err(e) => {
+            print_error(&e, json);
+            return VALIDATE_EXIT_UNREADABLE;
+        }
+    };
+    let issues = config.validate();
+    if issues.is_empty() {
+        println!("ok: {}", config_file);
+        return 0;
+    }
+    for issue in issues.iter() {
+        println!("error: {}", issue);
+    }
+    println!("{} issue(s) found in {}", issues.len(), config_file);
+    VALIDATE_EXIT_INVALID
+}
+
+fn doctor(config_file: &str) -> i32 {
+    let mut checks = Vec::new();
+
+    let config = match read_config(&PathBuf::from(config_file)) {
+        Ok(v) => {
+            let issues = v.validate();
+            checks.push(DiagnosticCheck::new(
+                "config",
+                match issues.len() {
+                    0 => Ok(config_file.to_owned()),
+                    n => Err(format!("{} issue(s) in {}", n, config_file)),
+                },
+                "Run `monmouse-cli validate` for details",
+            ));
+            v
+        }
+        Err(Error::ConfigFileNotExists(_)) => {
+            checks.push(DiagnosticCheck::new::<_, &str>(
+                "config",
+                Ok(format!("{} not found, using defaults", config_file)),
+                "",
+            ));
+            Settings::default()
+        }
+        Err(e) => {
+            checks.push(DiagnosticCheck::new::<&str, _>(
+                "config",
+                Err(e),
+                "Fix the config file, or remove it to use defaults",
+            ));
+            Settings::default()
+        }
+    };
+
+    // Registrations of a running instance conflict with the checks
+    let single_process = SingleProcess::create();
+    checks.push(DiagnosticCheck::new(
+        "single_instance",
+        single_process.as_ref().map(|_| "no other instance"),
+        "Quit the running MonMouse first",
+    ));
+    if single_process.is_ok() {
+        let (_, mouse_control_reactor, _) = setup_reactors(
+            Box::<UINotifyNoop>::default(),
+            Box::<UINotifyNoop>::default(),
+        );
+        let mut eventloop = monmouse::Eventloop::new(true, mouse_control_reactor);
+        checks.extend(eventloop.diagnose(&config));
+    }
+
+    let mut failed = 0;
+    for c in checks.iter() {
+        match &c.result {
+            Ok(details) => println!("PASS  {}: {}", c.name, details),
+            Err(e) => {
+                failed += 1;
+                println!("FAIL  {}: {}", c.name, e);
+                println!("      hint: {}", c.hint);
+            }
+        }
+    }
+    if failed > 0 {
+        println!("{} check(s) failed", failed);
+        return DOCTOR_EXIT_FAILED;
+    }
+    0
+}
+
 fn print_devices(devices: Vec<GenericDevice>) {
     for (i, d) in devices.iter().enumerate() {
         println!("Device[{}]", i);
@@ -0,0 +1,347 @@
+// Settings pushed between machines on the LAN, e.g. several computers sharing one keyboard
+// and mouse by a KVM. A push is a YAML document over TCP, answered by a line of "ok" or
+// "error: <Error::to_json()>". The token is sent as is, so only use it in trusted networks.
+
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    io::{Read, Write},
+    net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{Error, ErrorReport},
+    setting::{ProcessorSettings, RawinputUsage, Settings, ShortcutSettings},
+    utils::from_json_or_yaml,
+};
+
+const PROTOCOL_VERSION: u32 = 1;
+const MAX_PUSH_BYTES: u64 = 256 * 1024;
+const MAX_REPLY_BYTES: u64 = 4096;
+const IO_TIMEOUT: Duration = Duration::from_secs(3);
+// A whole push must arrive within it, not to hold the listener by sending slowly
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+// How often the listener checks whether it is stopped, while no push comes
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Parts of processor settings not tied to devices, monitors or paths of one machine. Risky features
+// confirmed are not pushed, each machine confirms them by itself.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncedSettings {
+    pub shortcuts: ShortcutSettings,
+    pub options: ProcessorSettings,
+}
+
+impl SyncedSettings {
+    pub fn from(p: &ProcessorSettings) -> Self {
+        let mut options = ProcessorSettings {
+            devices: Vec::new(),
+            ignored_devices: Vec::new(),
+            switch_cycle: Vec::new(),
+            rawinput_usages: RawinputUsage::defaults(),
+            shortcuts: ShortcutSettings::default(),
+            on_event_command: BTreeMap::new(),
+            device_rules: Vec::new(),
+            app_rules: Vec::new(),
+            defaults_by_type: BTreeMap::new(),
+            confirmed_risks: Vec::new(),
+            monitor_groups: Vec::new(),
+            ..p.clone()
+        };
+        options.hot_corners.only_device.clear();
+        options.kvm.state_file.clear();
+        SyncedSettings {
+            shortcuts: p.shortcuts.clone(),
+            options,
+        }
+    }
+
+    // Parts tied to the local machine are kept
+    pub fn apply_to(self, p: &mut ProcessorSettings) {
+        let only_device = std::mem::take(&mut p.hot_corners.only_device);
+        let state_file = std::mem::take(&mut p.kvm.state_file);
+        *p = ProcessorSettings {
+            devices: std::mem::take(&mut p.devices),
+            ignored_devices: std::mem::take(&mut p.ignored_devices),
+            switch_cycle: std::mem::take(&mut p.switch_cycle),
+            rawinput_usages: std::mem::take(&mut p.rawinput_usages),
+            shortcuts: self.shortcuts,
+            on_event_command: std::mem::take(&mut p.on_event_command),
+            device_rules: std::mem::take(&mut p.device_rules),
+            app_rules: std::mem::take(&mut p.app_rules),
+            defaults_by_type: std::mem::take(&mut p.defaults_by_type),
+            confirmed_risks: std::mem::take(&mut p.confirmed_risks),
+            monitor_groups: std::mem::take(&mut p.monitor_groups),
+            ..self.options
+        };
+        p.hot_corners.only_device = only_device;
+        p.kvm.state_file = state_file;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncPush {
+    version: u32,
+    token: String,
+    settings: SyncedSettings,
+}
+
+// Peer is "host" or "host:port"
+fn peer_addr(peer: &str, default_port: u16) -> Result<SocketAddr, Error> {
+    let with_port = match peer.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => peer.to_owned(),
+        _ => format!("{}:{}", peer, default_port),
+    };
+    with_port
+        .to_socket_addrs()
+        .map_err(Error::IO)?
+        .next()
+        .ok_or_else(|| Error::InvalidParam("peer".to_owned(), peer.to_owned()))
+}
+
+pub fn push(
+    peer: &str,
+    default_port: u16,
+    token: &str,
+    settings: &SyncedSettings,
+) -> Result<(), Error> {
+    let content = serde_yaml::to_string(&SyncPush {
+        version: PROTOCOL_VERSION,
+        token: token.to_owned(),
+        settings: settings.clone(),
+    })
+    .map_err(|e| Error::InvalidParam("settings".to_owned(), e.to_string()))?;
+
+    let addr = peer_addr(peer, default_port)?;
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT).map_err(Error::IO)?;
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .map_err(Error::IO)?;
+    stream
+        .set_write_timeout(Some(IO_TIMEOUT))
+        .map_err(Error::IO)?;
+    stream.write_all(content.as_bytes()).map_err(Error::IO)?;
+    stream.shutdown(Shutdown::Write).map_err(Error::IO)?;
+
+    let mut reply = String::new();
+    stream
+        .take(MAX_REPLY_BYTES)
+        .read_to_string(&mut reply)
+        .map_err(Error::IO)?;
+    let reply = reply.trim();
+    if reply == "ok" {
+        return Ok(());
+    }
+    let reason = reply.strip_prefix("error: ").unwrap_or(reply);
+    // Peers of older versions reply only a reason
+    match from_json_or_yaml::<ErrorReport>(reason) {
+        Ok(report) => Err(report.into()),
+        Err(_) => Err(Error::SyncRejected(reason.to_owned())),
+    }
+}
+
+// Not to tell how much of the token matched by timing
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+// Reads until peer shuts down writing, within RECEIVE_TIMEOUT as a whole
+fn read_push(stream: &mut TcpStream) -> Result<String, Error> {
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    let mut content = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::SyncRejected("timed out".to_owned()));
+        }
+        let _ = stream.set_read_timeout(Some(remaining.min(IO_TIMEOUT)));
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => content.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::IO(e)),
+        }
+        if content.len() as u64 > MAX_PUSH_BYTES {
+            return Err(Error::SyncRejected("too large".to_owned()));
+        }
+    }
+    String::from_utf8(content).map_err(|e| Error::SyncRejected(e.to_string()))
+}
+
+fn receive(stream: &mut TcpStream, token: &str) -> Result<SyncedSettings, Error> {
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let content = read_push(stream)?;
+    let push: SyncPush =
+        serde_yaml::from_str(&content).map_err(|e| Error::SyncRejected(e.to_string()))?;
+    if push.version != PROTOCOL_VERSION {
+        return Err(Error::SyncRejected(format!(
+            "unsupported version {}",
+            push.version
+        )));
+    }
+    if !token_eq(&push.token, token) {
+        return Err(Error::SyncRejected("wrong token".to_owned()));
+    }
+    // Checked the same way as a local config, applied over defaults
+    let mut settings = Settings::default();
+    push.settings.clone().apply_to(&mut settings.processor);
+    if let Some(issue) = settings.validate().into_iter().next() {
+        return Err(Error::InvalidParam(issue.field, issue.message));
+    }
+    Ok(push.settings)
+}
+
+// Accepts pushes in a background thread, until dropped
+pub struct SyncListener {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncListener {
+    pub fn start(
+        addr: &str,
+        port: u16,
+        token: String,
+        on_push: impl Fn(SyncedSettings) + Send + 'static,
+    ) -> Result<Self, Error> {
+        if token.is_empty() {
+            return Err(Error::InvalidParam(
+                "ui.sync.token".to_owned(),
+                "empty".to_owned(),
+            ));
+        }
+        let ip: IpAddr = addr
+            .parse()
+            .map_err(|_| Error::InvalidParam("ui.sync.listen_addr".to_owned(), addr.to_owned()))?;
+        let listener = TcpListener::bind((ip, port)).map_err(Error::IO)?;
+        // Polled, so that stopping doesn't depend on waking a blocking accept
+        listener.set_nonblocking(true).map_err(Error::IO)?;
+        let port = listener.local_addr().map_err(Error::IO)?.port();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop1 = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("config_sync".to_owned())
+            .spawn(move || {
+                while !stop1.load(Ordering::Relaxed) {
+                    let mut stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("Accept sync push failed: {}", e);
+                            std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                            continue;
+                        }
+                    };
+                    let peer = stream.peer_addr().map_or("?".to_owned(), |a| a.to_string());
+                    if let Err(e) = stream.set_nonblocking(false) {
+                        warn!("Sync push from {} dropped: {}", peer, e);
+                        continue;
+                    }
+                    let reply = match receive(&mut stream, &token) {
+                        Ok(settings) => {
+                            debug!("Sync push accepted from {}", peer);
+                            on_push(settings);
+                            "ok\n".to_owned()
+                        }
+                        Err(e) => {
+                            warn!("Sync push rejected from {}: {}", peer, e);
+                            format!("error: {}\n", e.to_json())
+                        }
+                    };
+                    let _ = stream.write_all(reply.as_bytes());
+                }
+            })
+            .map_err(Error::IO)?;
+        Ok(SyncListener {
+            port,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for SyncListener {
+    // Returns within ACCEPT_POLL_INTERVAL, or RECEIVE_TIMEOUT if a push is being received
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_push_and_receive() {
+        let (tx, rx) = channel();
+        let listener = SyncListener::start("127.0.0.1", 0, "secret-token".to_owned(), move |s| {
+            let _ = tx.send(s);
+        })
+        .unwrap();
+        let peer = format!("127.0.0.1:{}", listener.port());
+
+        let sent = ProcessorSettings {
+            turbo_speed_percent: 500,
+            shortcuts: ShortcutSettings {
+                cur_mouse_lock: "Ctrl+Alt+K".to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settings = SyncedSettings::from(&sent);
+
+        let e = push(&peer, 0, "wrong-token!", &settings).unwrap_err();
+        assert_eq!(e.code(), "sync-rejected");
+        let mut invalid = settings.clone();
+        invalid.options.turbo_speed_percent = 1;
+        let e = push(&peer, 0, "secret-token", &invalid).unwrap_err();
+        assert_eq!(e.code(), "invalid-param");
+        assert!(e.to_string().contains("turbo_speed_percent"));
+        push(&peer, 0, "secret-token", &settings).unwrap();
+        let received = rx.recv_timeout(IO_TIMEOUT).unwrap();
+        assert_eq!(received, settings);
+        assert!(rx.try_recv().is_err());
+
+        let mut local = ProcessorSettings::default();
+        local.ignored_devices.push("*RDP_MOU*".to_owned());
+        received.apply_to(&mut local);
+        assert_eq!(local.turbo_speed_percent, 500);
+        assert_eq!(local.shortcuts.cur_mouse_lock, "Ctrl+Alt+K");
+        assert_eq!(local.ignored_devices, vec!["*RDP_MOU*".to_owned()]);
+    }
+
+    #[test]
+    fn test_listener_stops_without_pushes() {
+        let listener =
+            SyncListener::start("127.0.0.1", 0, "secret-token".to_owned(), |_| {}).unwrap();
+        let start = Instant::now();
+        drop(listener);
+        assert!(start.elapsed() < IO_TIMEOUT);
+    }
+}
@@ -0,0 +1,231 @@
+// Config written by a background thread, so UI never waits on slow or unavailable drives.
+// Only the latest settings submitted are written, older ones not written yet are dropped.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::{
+    errors::{Error, ErrorReport},
+    setting::{write_config, Settings},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SaveState {
+    #[default]
+    Idle,
+    Writing,
+    Saved,
+    // Config dir may be on a network drive or synced folder, unavailable for a while. Retried
+    // with growing delays, until written or replaced by newer settings.
+    Retrying {
+        error: String,
+        attempts: u32,
+    },
+    // Not retried, e.g. settings not serializable. Kept with its code, to be shown in alerts.
+    Failed(ErrorReport),
+}
+
+struct Job {
+    path: PathBuf,
+    settings: Settings,
+    seq: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    // Latest submitted and not written yet
+    job: Option<Job>,
+    retry_at: Option<Instant>,
+    state: SaveState,
+    submitted: u64,
+    done: u64,
+    stop: bool,
+}
+
+pub struct ConfigWriter {
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ConfigWriter {
+    const RETRY_BASE_MS: u64 = 1000;
+    const RETRY_MAX_MS: u64 = 60000;
+    const FLUSH_ON_DROP: Duration = Duration::from_secs(3);
+
+    // Doubled after each failed attempt, from 1s up to a minute
+    fn retry_delay(attempts: u32) -> Duration {
+        let ms = Self::RETRY_BASE_MS << attempts.saturating_sub(1).min(16);
+        Duration::from_millis(ms.min(Self::RETRY_MAX_MS))
+    }
+
+    // Changes of state are reported from the writing thread
+    pub fn start(on_state: impl Fn(SaveState) + Send + 'static) -> Self {
+        let shared = Arc::new((Mutex::new(Shared::default()), Condvar::new()));
+        let shared1 = shared.clone();
+        let thread = std::thread::spawn(move || Self::run(&shared1, on_state));
+        ConfigWriter {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    fn run(shared: &(Mutex<Shared>, Condvar), on_state: impl Fn(SaveState)) {
+        let (lock, cvar) = shared;
+        let mut s = lock.lock().unwrap();
+        let mut attempts = 0;
+        loop {
+            if s.stop {
+                if s.job.is_some() {
+                    warn!("Config writer stopped, latest settings not saved");
+                }
+                return;
+            }
+            if let Some(at) = s.retry_at {
+                let now = Instant::now();
+                if now < at {
+                    s = cvar.wait_timeout(s, at - now).unwrap().0;
+                    continue;
+                }
+            }
+            let Some(job) = s.job.take() else {
+                s = cvar.wait(s).unwrap();
+                continue;
+            };
+            s.retry_at = None;
+            s.state = SaveState::Writing;
+            drop(s);
+            on_state(SaveState::Writing);
+
+            let result = write_config(&job.path, &job.settings);
+
+            s = lock.lock().unwrap();
+            s.state = match result {
+                Ok(_) => {
+                    attempts = 0;
+                    s.done = job.seq;
+                    SaveState::Saved
+                }
+                Err(Error::IO(e)) => {
+                    attempts += 1;
+                    if s.job.is_none() {
+                        s.job = Some(job);
+                        s.retry_at = Some(Instant::now() + Self::retry_delay(attempts));
+                    }
+                    SaveState::Retrying {
+                        error: e.to_string(),
+                        attempts,
+                    }
+                }
+                Err(e) => {
+                    attempts = 0;
+                    s.done = job.seq;
+                    SaveState::Failed(ErrorReport::from(&e))
+                }
+            };
+            cvar.notify_all();
+            let state = s.state.clone();
+            drop(s);
+            on_state(state);
+            s = lock.lock().unwrap();
+        }
+    }
+
+    // Replaces settings submitted but not written yet, and retries at once
+    pub fn submit(&self, path: PathBuf, settings: Settings) {
+        let (lock, cvar) = &*self.shared;
+        let mut s = lock.lock().unwrap();
+        s.submitted += 1;
+        s.job = Some(Job {
+            path,
+            settings,
+            seq: s.submitted,
+        });
+        s.retry_at = None;
+        cvar.notify_all();
+    }
+
+    pub fn state(&self) -> SaveState {
+        self.shared.0.lock().unwrap().state.clone()
+    }
+
+    // Waits until all submitted are written or failed, false if timed out
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.shared;
+        let s = lock.lock().unwrap();
+        let (s, _) = cvar
+            .wait_timeout_while(s, timeout, |s| s.done < s.submitted)
+            .unwrap();
+        s.done >= s.submitted
+    }
+}
+
+impl Drop for ConfigWriter {
+    fn drop(&mut self) {
+        if !self.flush(Self::FLUSH_ON_DROP) {
+            warn!("Config not written in {:?}", Self::FLUSH_ON_DROP);
+        }
+        let (lock, cvar) = &*self.shared;
+        lock.lock().unwrap().stop = true;
+        cvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::read_config;
+    use std::{fs, sync::mpsc::channel};
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(ConfigWriter::retry_delay(1), Duration::from_secs(1));
+        assert_eq!(ConfigWriter::retry_delay(2), Duration::from_secs(2));
+        assert_eq!(ConfigWriter::retry_delay(6), Duration::from_secs(32));
+        assert_eq!(ConfigWriter::retry_delay(7), Duration::from_secs(60));
+        assert_eq!(ConfigWriter::retry_delay(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_config_writer() {
+        let dir = crate::utils::test_temp_dir("config_writer");
+        let config_dir = dir.join("config");
+        let path = config_dir.join("monmouse.yml");
+
+        let (tx, rx) = channel();
+        let writer = ConfigWriter::start(move |s| {
+            let _ = tx.send(s);
+        });
+        let mut settings = Settings::default();
+        settings.processor.turbo_speed_percent = 200;
+        // Dir not existing yet
+        writer.submit(path.clone(), settings.clone());
+        assert!(!writer.flush(Duration::from_millis(300)));
+        assert!(matches!(
+            writer.state(),
+            SaveState::Retrying { attempts: 1, .. }
+        ));
+
+        // The latest wins, written at once without waiting for the retry
+        fs::create_dir_all(&config_dir).unwrap();
+        settings.processor.turbo_speed_percent = 300;
+        writer.submit(path.clone(), settings.clone());
+        assert!(writer.flush(Duration::from_secs(3)));
+        assert_eq!(writer.state(), SaveState::Saved);
+        assert_eq!(
+            read_config(&path).unwrap().processor.turbo_speed_percent,
+            300
+        );
+        drop(writer);
+        assert!(rx.iter().any(|s| s == SaveState::Saved));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,106 @@
+// Reports written on panic, into a folder beside config file, so that crashes can be
+// attached to bug reports even after the message box is gone.
+
+use std::{
+    backtrace::Backtrace,
+    collections::hash_map::DefaultHasher,
+    fmt::Write as _,
+    fs,
+    hash::{Hash, Hasher},
+    panic::PanicInfo,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::build_info;
+
+#[derive(Default)]
+struct CrashContext {
+    dir: Option<PathBuf>,
+    // Hash of config file content, to tell whether reports share the same config
+    config_hash: Option<u64>,
+    devices: Vec<String>,
+    last_report: Option<PathBuf>,
+}
+
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    dir: None,
+    config_hash: None,
+    devices: Vec::new(),
+    last_report: None,
+});
+
+fn with_context<R>(f: impl FnOnce(&mut CrashContext) -> R) -> R {
+    // A panic while holding the lock shouldn't stop later reports
+    let mut ctx = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut ctx)
+}
+
+// Reports are only written after this is called
+pub fn set_crashes_dir(dir: &Path) {
+    with_context(|ctx| ctx.dir = Some(dir.to_path_buf()));
+}
+
+pub fn set_config_file(config_file: &Path) {
+    let hash = fs::read(config_file).ok().map(|content| {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    });
+    with_context(|ctx| ctx.config_hash = hash);
+}
+
+pub fn set_devices(devices: Vec<String>) {
+    with_context(|ctx| ctx.devices = devices);
+}
+
+pub fn crashes_dir() -> Option<PathBuf> {
+    with_context(|ctx| ctx.dir.clone())
+}
+
+// Report written by the latest panic, if any
+pub fn last_report() -> Option<PathBuf> {
+    with_context(|ctx| ctx.last_report.clone())
+}
+
+fn format_report(ctx: &CrashContext, panic_info: &PanicInfo, now_secs: u64) -> String {
+    let mut s = String::new();
+    let _ = writeln!(s, "time: {}", now_secs);
+    let _ = writeln!(s, "{}", build_info().verbose());
+    let _ = writeln!(
+        s,
+        "thread: {}",
+        thread::current().name().unwrap_or("unnamed")
+    );
+    let _ = writeln!(s, "panic: {}", panic_info);
+    let config_hash = ctx
+        .config_hash
+        .map_or("none".to_owned(), |h| format!("{:016x}", h));
+    let _ = writeln!(s, "config_hash: {}", config_hash);
+    let _ = writeln!(s, "devices:");
+    for d in ctx.devices.iter() {
+        let _ = writeln!(s, "  {}", d);
+    }
+    let _ = writeln!(s, "backtrace:");
+    let _ = writeln!(s, "{}", Backtrace::force_capture());
+    s
+}
+
+// Return path of the report, None if config dir is not set or writing failed
+pub fn write_report(panic_info: &PanicInfo) -> Option<PathBuf> {
+    with_context(|ctx| {
+        let dir = ctx.dir.clone()?;
+        let now_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!("crash-{}.txt", now_secs));
+        let content = format_report(ctx, panic_info, now_secs);
+        fs::create_dir_all(&dir).ok()?;
+        fs::write(&path, content).ok()?;
+        ctx.last_report = Some(path.clone());
+        Some(path)
+    })
+}
@@ -4,7 +4,9 @@
 
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum DeviceType {
     Unknown,
     Dummy,
@@ -60,6 +62,31 @@ impl DeviceType {
         }
     }
 
+    pub const VARIANTS: [DeviceType; 18] = [
+        Self::Unknown,
+        Self::Dummy,
+        Self::UnknownHID,
+        Self::Pointer,
+        Self::Mouse,
+        Self::Joystick,
+        Self::Gamepad,
+        Self::Keyboard,
+        Self::Keypad,
+        Self::OtherGenericDesktop,
+        Self::Digitizer,
+        Self::Pen,
+        Self::LightPen,
+        Self::TouchScreen,
+        Self::TouchPad,
+        Self::Whiteboard,
+        Self::OtherDigitizer,
+        Self::VendorDefined,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+
     pub fn is_pointer(&self) -> bool {
         matches!(
             self,
@@ -87,9 +114,14 @@ pub struct WindowsRawinput {}
 
 impl WindowsRawinput {
     pub const ALL: u16 = 0;
-    pub const REGISTER_USAGE_SET: [(u16, u16); 3] = [
+    pub const GENERIC_DESKTOP: u16 = 0x01;
+    pub const MOUSE: (u16, u16) = (Self::GENERIC_DESKTOP, 0x02);
+    pub const KEYBOARD: (u16, u16) = (Self::GENERIC_DESKTOP, 0x06);
+    pub const KEYPAD: (u16, u16) = (Self::GENERIC_DESKTOP, 0x07);
+    // Registered unless processor.rawinput_usages is set
+    pub const DEFAULT_USAGE_SET: [(u16, u16); 3] = [
         (0x0D, Self::ALL), // Digitizer, All
         (0x01, 0x01),      // Generic Desktop, Pointer
-        (0x01, 0x02),      // Generic Desktop, Mouse
+        Self::MOUSE,       // Generic Desktop, Mouse
     ];
 }
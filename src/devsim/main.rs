@@ -0,0 +1,88 @@
+// Injects mouse input by SendInput in repeatable patterns, to reproduce races reported by
+// users on any machine, e.g. relative and absolute events alternating within the merge
+// window. Start MonMouse first, then run this.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use log::{error, info};
+use monmouse::{
+    send_mouse_move,
+    simulate::{InjectPattern, InjectedMove},
+};
+
+#[derive(Parser, Debug)]
+#[command(about, long_about = None)]
+struct Args {
+    /// Events per second
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=1000))]
+    rate: u32,
+
+    /// Chance of each burst being absolute, the others are relative
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(0..=100))]
+    absolute_percent: u32,
+
+    /// Events in a row of the same kind, 1 for alternating as fast as possible
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    burst: u32,
+
+    #[arg(long, default_value_t = 10)]
+    duration_secs: u64,
+
+    /// The same seed injects the same events
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Time for moving the real mouse away before injecting
+    #[arg(long, default_value_t = 3)]
+    start_delay_secs: u64,
+}
+
+fn main() {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+    let args = Args::parse();
+    let pattern = InjectPattern {
+        rate_hz: args.rate,
+        absolute_percent: args.absolute_percent,
+        burst: args.burst,
+        seed: args.seed,
+    };
+    let events = pattern.schedule(args.duration_secs * 1000);
+    info!(
+        "Injecting {} events in {}s after {}s, {:?}",
+        events.len(),
+        args.duration_secs,
+        args.start_delay_secs,
+        pattern
+    );
+    thread::sleep(Duration::from_secs(args.start_delay_secs));
+
+    let start = Instant::now();
+    let mut failed = 0;
+    for (at_ms, mv) in events.iter() {
+        let due = start + Duration::from_millis(*at_ms);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        let result = match *mv {
+            InjectedMove::Relative { dx, dy } => send_mouse_move(dx, dy, false),
+            InjectedMove::Absolute { x, y } => send_mouse_move(x, y, true),
+        };
+        if let Err(e) = result {
+            // Blocked by UIPI if a window of higher integrity is in foreground
+            error!("Inject at {}ms failed: {}", at_ms, e);
+            failed += 1;
+        }
+    }
+    info!(
+        "Injected {} events in {:?}, {} failed",
+        events.len() - failed,
+        start.elapsed(),
+        failed
+    );
+}
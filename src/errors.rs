@@ -1,7 +1,10 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
+use crate::utils::json_str;
+
 #[derive(ThisError, Debug)]
 pub enum Error {
     #[error("ErrorNoConfigFile(path={0})")]
@@ -10,6 +13,8 @@ pub enum Error {
     IO(std::io::Error),
     #[error("ErrorInvalidConfigFile({0})")]
     InvalidConfigFile(String),
+    #[error("ErrorConfigIncludeCycle(path={0})")]
+    ConfigIncludeCycle(String),
     #[error("ErrorInvalidParam(field={0}; {1})")]
     InvalidParam(String, String),
     #[error("ErrorInvalidShortCut({0})")]
@@ -18,6 +23,23 @@ pub enum Error {
     ShortcutConflict(PrintableOptionString),
     #[error("ErrorAlreadyLaunched")]
     AlreadyLaunched,
+    #[error("ErrorNotRunning")]
+    NotRunning,
+    #[error("ErrorSyncRejected({0})")]
+    SyncRejected(String),
+    #[error("ErrorViewRejected({0})")]
+    ViewRejected(String),
+    #[error("ErrorViewOnly")]
+    ViewOnly,
+    #[error("ErrorUpdateCheck({0})")]
+    UpdateCheck(String),
+    // Reported by another process, see ErrorReport. Code and category are kept as reported.
+    #[error("{2}")]
+    Reported(
+        String, /* code */
+        ErrorCategory,
+        String, /* message */
+    ),
 
     #[error("ErrorInited")]
     MessageInited,
@@ -38,9 +60,163 @@ pub enum Error {
     WinInvalidHandle(isize),
 }
 
+// Kinds of errors scripts may handle differently. Names and exit codes are stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Config,
+    Shortcut,
+    Instance,
+    Remote,
+    WinApi,
+    IO,
+    Internal,
+}
+
+impl ErrorCategory {
+    pub const VARIANTS: [ErrorCategory; 7] = [
+        ErrorCategory::Config,
+        ErrorCategory::Shortcut,
+        ErrorCategory::Instance,
+        ErrorCategory::Remote,
+        ErrorCategory::WinApi,
+        ErrorCategory::IO,
+        ErrorCategory::Internal,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.iter().find(|c| c.name() == name).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCategory::Config => "config",
+            ErrorCategory::Shortcut => "shortcut",
+            ErrorCategory::Instance => "instance",
+            ErrorCategory::Remote => "remote",
+            ErrorCategory::WinApi => "win-api",
+            ErrorCategory::IO => "io",
+            ErrorCategory::Internal => "internal",
+        }
+    }
+
+    // Of monmouse-cli failing to start or run. Above those of subcommands, which are 1 or 2.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCategory::Config => 10,
+            ErrorCategory::Shortcut => 11,
+            ErrorCategory::Instance => 12,
+            ErrorCategory::Remote => 13,
+            ErrorCategory::WinApi => 14,
+            ErrorCategory::IO => 15,
+            ErrorCategory::Internal => 16,
+        }
+    }
+}
+
+impl Error {
+    // Stable, unlike display strings, which may change with details
+    pub fn code(&self) -> &str {
+        match self {
+            Error::ConfigFileNotExists(_) => "config-missing",
+            Error::IO(_) => "io",
+            Error::InvalidConfigFile(_) => "config-invalid",
+            Error::ConfigIncludeCycle(_) => "config-include-cycle",
+            Error::InvalidParam(_, _) => "invalid-param",
+            Error::InvalidShortcut(_) => "hotkey-invalid",
+            Error::ShortcutConflict(_) => "hotkey-conflict",
+            Error::AlreadyLaunched => "already-launched",
+            Error::NotRunning => "not-running",
+            Error::SyncRejected(_) => "sync-rejected",
+            Error::ViewRejected(_) => "view-rejected",
+            Error::ViewOnly => "view-only",
+            Error::UpdateCheck(_) => "update-check",
+            Error::Reported(code, _, _) => code,
+            Error::MessageInited => "message-inited",
+            Error::WinUnknown => "win-unknown",
+            Error::WinCore(_) => "win-core",
+            Error::WinConfigRet(_) => "win-config-ret",
+            Error::WinPredefineBufSmall(_, _) => "win-buffer-small",
+            Error::WinDeviceNoInstanceID(_) => "win-device-no-instance-id",
+            Error::WinDeviceInterfaceListEmpty(_) => "win-device-no-interface",
+            Error::WinInvalidHandle(_) => "win-invalid-handle",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::ConfigFileNotExists(_)
+            | Error::InvalidConfigFile(_)
+            | Error::ConfigIncludeCycle(_)
+            | Error::InvalidParam(_, _) => ErrorCategory::Config,
+            Error::InvalidShortcut(_) | Error::ShortcutConflict(_) => ErrorCategory::Shortcut,
+            Error::AlreadyLaunched | Error::NotRunning | Error::ViewOnly => ErrorCategory::Instance,
+            Error::SyncRejected(_) | Error::ViewRejected(_) | Error::UpdateCheck(_) => {
+                ErrorCategory::Remote
+            }
+            Error::Reported(_, category, _) => *category,
+            Error::IO(_) => ErrorCategory::IO,
+            Error::MessageInited => ErrorCategory::Internal,
+            Error::WinUnknown
+            | Error::WinCore(_)
+            | Error::WinConfigRet(_)
+            | Error::WinPredefineBufSmall(_, _)
+            | Error::WinDeviceNoInstanceID(_)
+            | Error::WinDeviceInterfaceListEmpty(_)
+            | Error::WinInvalidHandle(_) => ErrorCategory::WinApi,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.category().exit_code()
+    }
+
+    // One line, for scripts and status responses, e.g.
+    // {"code":"hotkey-conflict","category":"shortcut","message":"ErrorShortCutConflict(Ctrl+A)"}
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"code\":\"{}\",\"category\":\"{}\",\"message\":{}}}",
+            self.code(),
+            self.category().name(),
+            json_str(&self.to_string())
+        )
+    }
+}
+
+// An error sent to another process, in the same fields as Error::to_json()
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub code: String,
+    pub category: String,
+    pub message: String,
+}
+
+impl From<&Error> for ErrorReport {
+    fn from(e: &Error) -> Self {
+        ErrorReport {
+            code: e.code().to_owned(),
+            category: e.category().name().to_owned(),
+            message: e.to_string(),
+        }
+    }
+}
+
+// Categories unknown to this version, e.g. reported by a newer peer, are taken as remote
+impl From<ErrorReport> for Error {
+    fn from(r: ErrorReport) -> Self {
+        let category = ErrorCategory::from_name(&r.category).unwrap_or(ErrorCategory::Remote);
+        Error::Reported(r.code, category, r.message)
+    }
+}
+
 #[derive(Debug)]
 pub struct PrintableOptionString(Option<String>);
 
+impl PrintableOptionString {
+    pub fn as_deref(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
 impl Display for PrintableOptionString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let PrintableOptionString(Some(v)) = self {
@@ -70,3 +246,51 @@ impl From<Option<String>> for PrintableOptionString {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::from_json_or_yaml;
+
+    #[test]
+    fn test_error_codes() {
+        let e = Error::ShortcutConflict("Ctrl+\"A\"".into());
+        assert_eq!(e.category(), ErrorCategory::Shortcut);
+        assert_eq!(
+            e.to_json(),
+            r#"{"code":"hotkey-conflict","category":"shortcut","message":"ErrorShortCutConflict(Ctrl+\"A\")"}"#
+        );
+        assert_eq!(Error::WinCore(5).category().name(), "win-api");
+
+        let codes: std::collections::HashSet<i32> = ErrorCategory::VARIANTS
+            .iter()
+            .map(|c| c.exit_code())
+            .collect();
+        assert_eq!(codes.len(), ErrorCategory::VARIANTS.len());
+        assert!(codes.iter().all(|&c| c > 2));
+        for c in ErrorCategory::VARIANTS {
+            assert_eq!(ErrorCategory::from_name(c.name()), Some(c));
+        }
+    }
+
+    #[test]
+    fn test_error_report() {
+        let e = Error::ShortcutConflict("Ctrl+\"A\"".into());
+        let report = from_json_or_yaml::<ErrorReport>(&e.to_json()).unwrap();
+        assert_eq!(report, ErrorReport::from(&e));
+
+        let reported = Error::from(report);
+        assert_eq!(reported.code(), "hotkey-conflict");
+        assert_eq!(reported.category(), ErrorCategory::Shortcut);
+        assert_eq!(reported.to_string(), e.to_string());
+        assert_eq!(reported.to_json(), e.to_json());
+
+        let unknown = Error::from(ErrorReport {
+            code: "new-code".to_owned(),
+            category: "new-category".to_owned(),
+            message: "m".to_owned(),
+        });
+        assert_eq!(unknown.category(), ErrorCategory::Remote);
+        assert!(from_json_or_yaml::<ErrorReport>("not json").is_err());
+    }
+}
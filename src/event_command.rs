@@ -0,0 +1,229 @@
+// External commands run on device events, e.g. to drive keyboard LEDs, stream deck
+// buttons or desktop widgets. Commands run one by one in a separate thread, without
+// shell or console, and are killed if not finished in time.
+
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    process::{Command, Stdio},
+    sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+
+use crate::setting::{DeviceSetting, SwitchMode};
+
+// Commands given as a named pipe get a line of "<event> <device id>" written instead
+pub const NAMED_PIPE_PREFIX: &str = r"\\.\pipe\";
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Events coming faster than commands finish are dropped beyond this
+const QUEUE_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Locked,
+    Unlocked,
+    // Per-device switching enabled or disabled
+    SwitchOn,
+    SwitchOff,
+    // Device became the one moving the cursor
+    Activated,
+    Jumped,
+    // Not for devices already there when MonMouse starts
+    Connected,
+    Disconnected,
+}
+
+impl DeviceEvent {
+    pub const VARIANTS: [DeviceEvent; 8] = [
+        Self::Locked,
+        Self::Unlocked,
+        Self::SwitchOn,
+        Self::SwitchOff,
+        Self::Activated,
+        Self::Jumped,
+        Self::Connected,
+        Self::Disconnected,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Locked => "locked",
+            Self::Unlocked => "unlocked",
+            Self::SwitchOn => "switch_on",
+            Self::SwitchOff => "switch_off",
+            Self::Activated => "activated",
+            Self::Jumped => "jumped",
+            Self::Connected => "connected",
+            Self::Disconnected => "disconnected",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|e| e.name() == name)
+    }
+
+    // Events of a device whose setting changed from old to new
+    pub fn toggled(old: &DeviceSetting, new: &DeviceSetting) -> Vec<DeviceEvent> {
+        let mut events = Vec::new();
+        if old.locked_in_monitor != new.locked_in_monitor {
+            events.push(match new.locked_in_monitor {
+                true => Self::Locked,
+                false => Self::Unlocked,
+            });
+        }
+        let (old_on, new_on) = (
+            old.switch != SwitchMode::Shared,
+            new.switch != SwitchMode::Shared,
+        );
+        if old_on != new_on {
+            events.push(match new_on {
+                true => Self::SwitchOn,
+                false => Self::SwitchOff,
+            });
+        }
+        events
+    }
+}
+
+struct Job {
+    argv: Vec<String>,
+    event: DeviceEvent,
+    device: String,
+}
+
+#[derive(Default)]
+pub struct EventCommandRunner {
+    // Thread is started by the first command
+    tx: Option<SyncSender<Job>>,
+}
+
+impl EventCommandRunner {
+    // Commands are keyed by event name, as program followed by its arguments
+    pub fn run(
+        &mut self,
+        commands: &BTreeMap<String, Vec<String>>,
+        event: DeviceEvent,
+        device: Option<&str>,
+    ) {
+        let Some(argv) = commands.get(event.name()).filter(|v| !v.is_empty()) else {
+            return;
+        };
+        let job = Job {
+            argv: argv.clone(),
+            event,
+            device: device.unwrap_or_default().to_owned(),
+        };
+        let tx = self.tx.get_or_insert_with(|| {
+            let (tx, rx) = sync_channel(QUEUE_SIZE);
+            thread::spawn(move || run_jobs(rx));
+            tx
+        });
+        match tx.try_send(job) {
+            Ok(_) => (),
+            Err(TrySendError::Full(job)) => {
+                warn!(
+                    "Command of event {} dropped, too many pending",
+                    job.event.name()
+                )
+            }
+            Err(TrySendError::Disconnected(_)) => self.tx = None,
+        }
+    }
+}
+
+fn run_jobs(rx: Receiver<Job>) {
+    while let Ok(job) = rx.recv() {
+        let program = &job.argv[0];
+        let result = if program.starts_with(NAMED_PIPE_PREFIX) {
+            write_pipe(program, &job)
+        } else {
+            run_command(&job)
+        };
+        match result {
+            Ok(_) => debug!("Command of event {} done", job.event.name()),
+            Err(e) => warn!("Command of event {} failed: {}", job.event.name(), e),
+        }
+    }
+}
+
+fn write_pipe(path: &str, job: &Job) -> std::io::Result<()> {
+    let mut pipe = OpenOptions::new().write(true).open(path)?;
+    writeln!(pipe, "{} {}", job.event.name(), job.device)
+}
+
+fn run_command(job: &Job) -> std::io::Result<()> {
+    let mut cmd = Command::new(&job.argv[0]);
+    cmd.args(&job.argv[1..])
+        .env("MONMOUSE_EVENT", job.event.name())
+        .env("MONMOUSE_DEVICE", &job.device)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    hide_console(&mut cmd);
+    let mut child = cmd.spawn()?;
+    let start = Instant::now();
+    while child.try_wait()?.is_none() {
+        if start.elapsed() > COMMAND_TIMEOUT {
+            child.kill()?;
+            child.wait()?;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "killed for timeout",
+            ));
+        }
+        thread::sleep(COMMAND_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn hide_console(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    cmd.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(not(target_os = "windows"))]
+fn hide_console(_cmd: &mut Command) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_event_toggled() {
+        let setting = |locked_in_monitor, switch| DeviceSetting {
+            locked_in_monitor,
+            switch,
+            ..DeviceSetting::default()
+        };
+        let shared = setting(false, SwitchMode::Shared);
+        assert!(DeviceEvent::toggled(&shared, &shared).is_empty());
+        assert_eq!(
+            DeviceEvent::toggled(&shared, &setting(true, SwitchMode::PerDeviceMonitor)),
+            vec![DeviceEvent::Locked, DeviceEvent::SwitchOn]
+        );
+        assert_eq!(
+            DeviceEvent::toggled(
+                &setting(true, SwitchMode::PerDevicePosition),
+                &setting(true, SwitchMode::Shared)
+            ),
+            vec![DeviceEvent::SwitchOff]
+        );
+        // Changing between switching modes is not toggling
+        assert!(DeviceEvent::toggled(
+            &setting(false, SwitchMode::PerDevicePosition),
+            &setting(false, SwitchMode::PerDeviceMonitor)
+        )
+        .is_empty());
+        assert_eq!(
+            DeviceEvent::from_name("switch_off"),
+            Some(DeviceEvent::SwitchOff)
+        );
+    }
+}
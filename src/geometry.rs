@@ -0,0 +1,439 @@
+use std::fmt::Display;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MousePos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl MousePos {
+    pub fn from(x: i32, y: i32) -> Self {
+        MousePos { x, y }
+    }
+}
+
+impl Display for MousePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.x, self.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// Identity of a monitor, the same when monitors are enumerated again in another order.
+// 0 if unknown.
+pub type MonitorId = u64;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MonitorArea {
+    pub lefttop: MousePos,
+    pub rigtbtm: MousePos,
+    pub id: MonitorId,
+}
+
+impl MonitorArea {
+    pub fn contains(&self, p: &MousePos) -> bool {
+        (self.lefttop.x <= p.x && p.x <= self.rigtbtm.x)
+            && (self.lefttop.y <= p.y && p.y <= self.rigtbtm.y)
+    }
+    const RESERVE_PIXEL: i32 = 3;
+    pub fn capture_pos(&self, p: &MousePos) -> MousePos {
+        let rp = Self::RESERVE_PIXEL;
+        let x1 = match (p.x < self.lefttop.x, p.x > self.rigtbtm.x - rp) {
+            (true, _) => self.lefttop.x,
+            (_, true) => self.rigtbtm.x - rp,
+            _ => p.x,
+        };
+        let y1 = match (p.y < self.lefttop.y, p.y > self.rigtbtm.y - rp) {
+            (true, _) => self.lefttop.y,
+            (_, true) => self.rigtbtm.y - rp,
+            _ => p.y,
+        };
+        MousePos::from(x1, y1)
+    }
+    // Nearest point inside area
+    pub fn clamp(&self, p: &MousePos) -> MousePos {
+        MousePos::from(
+            p.x.clamp(self.lefttop.x, self.rigtbtm.x),
+            p.y.clamp(self.lefttop.y, self.rigtbtm.y),
+        )
+    }
+    // Point in `to` at the same relative position as p in self. p outside is clamped first.
+    pub fn map_relative(&self, p: &MousePos, to: &MonitorArea) -> MousePos {
+        let p = self.clamp(p);
+        let scale = |v: i32, lo: i32, hi: i32, to_lo: i32, to_hi: i32| {
+            if hi == lo {
+                return (to_lo + to_hi) / 2;
+            }
+            let offset = (v - lo) as i64 * (to_hi - to_lo) as i64 / (hi - lo) as i64;
+            to_lo + offset as i32
+        };
+        MousePos::from(
+            scale(
+                p.x,
+                self.lefttop.x,
+                self.rigtbtm.x,
+                to.lefttop.x,
+                to.rigtbtm.x,
+            ),
+            scale(
+                p.y,
+                self.lefttop.y,
+                self.rigtbtm.y,
+                to.lefttop.y,
+                to.rigtbtm.y,
+            ),
+        )
+    }
+    // Corner which p is within margin pixels of, both horizontally and vertically
+    pub fn corner_at(&self, p: &MousePos, margin: i32) -> Option<Corner> {
+        if !self.contains(p) {
+            return None;
+        }
+        let left = p.x <= self.lefttop.x + margin;
+        let right = p.x >= self.rigtbtm.x - margin;
+        let top = p.y <= self.lefttop.y + margin;
+        let bottom = p.y >= self.rigtbtm.y - margin;
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(Corner::TopLeft),
+            (_, true, true, _) => Some(Corner::TopRight),
+            (true, _, _, true) => Some(Corner::BottomLeft),
+            (_, true, _, true) => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+    pub fn center(&self) -> MousePos {
+        MousePos::from(
+            (self.lefttop.x + self.rigtbtm.x) / 2,
+            (self.lefttop.y + self.rigtbtm.y) / 2,
+        )
+    }
+    // Squared distance from p to area, 0 if inside
+    pub fn distance2(&self, p: &MousePos) -> i64 {
+        let c = self.clamp(p);
+        let (dx, dy) = ((p.x - c.x) as i64, (p.y - c.y) as i64);
+        dx * dx + dy * dy
+    }
+    // Smallest area containing both
+    pub fn union(&self, other: &MonitorArea) -> MonitorArea {
+        MonitorArea {
+            lefttop: MousePos::from(
+                self.lefttop.x.min(other.lefttop.x),
+                self.lefttop.y.min(other.lefttop.y),
+            ),
+            rigtbtm: MousePos::from(
+                self.rigtbtm.x.max(other.rigtbtm.x),
+                self.rigtbtm.y.max(other.rigtbtm.y),
+            ),
+            id: 0,
+        }
+    }
+}
+
+impl Display for MonitorArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{{}.{}-{}.{}}}",
+            self.lefttop.x, self.lefttop.y, self.rigtbtm.x, self.rigtbtm.y,
+        )
+    }
+}
+
+// Gap between ranges [a1,a2] and [b1,b2], 0 if overlapped
+fn range_gap(a1: i32, a2: i32, b1: i32, b2: i32) -> i64 {
+    if a2 < b1 {
+        b1 as i64 - a2 as i64
+    } else if b2 < a1 {
+        a1 as i64 - b2 as i64
+    } else {
+        0
+    }
+}
+
+// Index of area containing p, or the nearest one
+pub fn nearest(areas: &[MonitorArea], p: &MousePos) -> Option<usize> {
+    areas
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, a)| a.distance2(p))
+        .map(|(i, _)| i)
+}
+
+// Index of the neighbor area of areas[from] in direction. Areas overlapped with it
+// in the orthogonal axis are preferred, then the closer ones in the direction.
+pub fn neighbor(areas: &[MonitorArea], from: usize, dir: Direction) -> Option<usize> {
+    let cur = areas.get(from)?;
+    let cc = cur.center();
+    areas
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != from)
+        .filter_map(|(i, a)| {
+            let ac = a.center();
+            let (ahead, gap, orth_gap, orth_offset) = match dir {
+                Direction::Left => (
+                    ac.x < cc.x,
+                    range_gap(a.lefttop.x, a.rigtbtm.x, cur.lefttop.x, cur.rigtbtm.x),
+                    range_gap(a.lefttop.y, a.rigtbtm.y, cur.lefttop.y, cur.rigtbtm.y),
+                    (ac.y - cc.y).abs(),
+                ),
+                Direction::Right => (
+                    ac.x > cc.x,
+                    range_gap(a.lefttop.x, a.rigtbtm.x, cur.lefttop.x, cur.rigtbtm.x),
+                    range_gap(a.lefttop.y, a.rigtbtm.y, cur.lefttop.y, cur.rigtbtm.y),
+                    (ac.y - cc.y).abs(),
+                ),
+                Direction::Up => (
+                    ac.y < cc.y,
+                    range_gap(a.lefttop.y, a.rigtbtm.y, cur.lefttop.y, cur.rigtbtm.y),
+                    range_gap(a.lefttop.x, a.rigtbtm.x, cur.lefttop.x, cur.rigtbtm.x),
+                    (ac.x - cc.x).abs(),
+                ),
+                Direction::Down => (
+                    ac.y > cc.y,
+                    range_gap(a.lefttop.y, a.rigtbtm.y, cur.lefttop.y, cur.rigtbtm.y),
+                    range_gap(a.lefttop.x, a.rigtbtm.x, cur.lefttop.x, cur.rigtbtm.x),
+                    (ac.x - cc.x).abs(),
+                ),
+            };
+            ahead.then_some((i, (orth_gap > 0, gap, orth_offset)))
+        })
+        .min_by_key(|&(_, score)| score)
+        .map(|(i, _)| i)
+}
+
+// Smallest area containing all areas
+pub fn spanning_rect(areas: &[MonitorArea]) -> Option<MonitorArea> {
+    let (first, rest) = areas.split_first()?;
+    Some(rest.iter().fold(*first, |acc, a| acc.union(a)))
+}
+
+// Indexes of areas in reading order: rows from top to bottom, each row from left to right,
+// or columns from left to right, each from top to bottom. An area joins a row if its center
+// is within the first area of the row, so portrait or offset monitors beside it count in.
+pub fn reading_order(areas: &[MonitorArea], columns: bool) -> Vec<usize> {
+    // (start, end) along a row, and across rows
+    let spans = |a: &MonitorArea| {
+        if columns {
+            ((a.lefttop.y, a.rigtbtm.y), (a.lefttop.x, a.rigtbtm.x))
+        } else {
+            ((a.lefttop.x, a.rigtbtm.x), (a.lefttop.y, a.rigtbtm.y))
+        }
+    };
+    let mut by_top: Vec<usize> = (0..areas.len()).collect();
+    by_top.sort_by_key(|&i| spans(&areas[i]).1);
+
+    let mut rows: Vec<((i32, i32), Vec<usize>)> = Vec::new();
+    for i in by_top {
+        let (start, end) = spans(&areas[i]).1;
+        let center = start + (end - start) / 2;
+        match rows.last_mut() {
+            Some(((top, bottom), row)) if center >= *top && center < *bottom => row.push(i),
+            _ => rows.push(((start, end), vec![i])),
+        }
+    }
+    rows.into_iter()
+        .flat_map(|(_, mut row)| {
+            row.sort_by_key(|&i| spans(&areas[i]).0);
+            row
+        })
+        .collect()
+}
+
+// Sizes of areas, like "1920x1080", sorted
+pub fn layout_sizes(areas: &[MonitorArea]) -> Vec<String> {
+    let mut sizes: Vec<(i32, i32)> = areas
+        .iter()
+        .map(|a| (a.rigtbtm.x - a.lefttop.x, a.rigtbtm.y - a.lefttop.y))
+        .collect();
+    sizes.sort();
+    sizes
+        .into_iter()
+        .map(|(w, h)| format!("{}x{}", w, h))
+        .collect()
+}
+
+// Monitors added or removed, or resolution of any changed. Only moving them around is not.
+pub fn layout_changed_much(old: &[MonitorArea], new: &[MonitorArea]) -> bool {
+    layout_sizes(old) != layout_sizes(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(l: i32, t: i32, r: i32, b: i32) -> MonitorArea {
+        MonitorArea {
+            lefttop: MousePos::from(l, t),
+            rigtbtm: MousePos::from(r, b),
+            id: 0,
+        }
+    }
+
+    #[test]
+    fn test_monitor_area_capture_pos() {
+        let pt = MousePos::from;
+        let m = MonitorArea {
+            lefttop: pt(-100, 500),
+            rigtbtm: pt(300, 1500),
+            id: 0,
+        };
+        assert_eq!(m.capture_pos(&pt(50, 700)), pt(50, 700));
+        assert_eq!(m.capture_pos(&pt(-150, 1500)), pt(-100, 1500));
+        assert_eq!(m.capture_pos(&pt(350, 500)), pt(300, 500));
+        assert_eq!(m.capture_pos(&pt(-100, 490)), pt(-100, 500));
+        assert_eq!(m.capture_pos(&pt(300, 3000)), pt(300, 1500));
+        assert_eq!(m.capture_pos(&pt(-120, 1300)), pt(-100, 1300));
+        assert_eq!(m.capture_pos(&pt(-200, 1800)), pt(-100, 1500));
+    }
+
+    #[test]
+    fn test_monitor_area_clamp() {
+        let pt = MousePos::from;
+        let m = area(-100, 500, 300, 1500);
+        assert_eq!(m.clamp(&pt(50, 700)), pt(50, 700));
+        assert_eq!(m.clamp(&pt(-150, 1500)), pt(-100, 1500));
+        assert_eq!(m.clamp(&pt(350, 400)), pt(300, 500));
+        assert_eq!(m.clamp(&pt(300, 3000)), pt(300, 1500));
+        assert_eq!(m.distance2(&pt(50, 700)), 0);
+        assert_eq!(m.distance2(&pt(303, 1504)), 25);
+    }
+
+    #[test]
+    fn test_monitor_area_map_relative() {
+        let pt = MousePos::from;
+        let src = area(0, 0, 1920, 1080);
+        let dst = area(-1280, -200, 0, 824);
+        assert_eq!(src.map_relative(&pt(0, 0), &dst), pt(-1280, -200));
+        assert_eq!(src.map_relative(&pt(1920, 1080), &dst), pt(0, 824));
+        assert_eq!(src.map_relative(&pt(960, 540), &dst), pt(-640, 312));
+        assert_eq!(src.map_relative(&pt(480, 270), &dst), pt(-960, 56));
+        assert_eq!(src.map_relative(&pt(-50, 2000), &dst), pt(-1280, 824));
+        assert_eq!(dst.map_relative(&pt(-640, 312), &src), pt(960, 540));
+    }
+
+    #[test]
+    fn test_monitor_area_corner_at() {
+        let pt = MousePos::from;
+        let m = area(0, 0, 1920, 1080);
+        assert_eq!(m.corner_at(&pt(0, 0), 3), Some(Corner::TopLeft));
+        assert_eq!(m.corner_at(&pt(1918, 2), 3), Some(Corner::TopRight));
+        assert_eq!(m.corner_at(&pt(3, 1079), 3), Some(Corner::BottomLeft));
+        assert_eq!(m.corner_at(&pt(1919, 1079), 3), Some(Corner::BottomRight));
+        assert_eq!(m.corner_at(&pt(0, 500), 3), None);
+        assert_eq!(m.corner_at(&pt(960, 0), 3), None);
+        assert_eq!(m.corner_at(&pt(-1, -1), 3), None);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let pt = MousePos::from;
+        let areas = [area(0, 0, 1920, 1080), area(1920, -200, 3840, 880)];
+        assert_eq!(nearest(&[], &pt(0, 0)), None);
+        assert_eq!(nearest(&areas, &pt(100, 100)), Some(0));
+        assert_eq!(nearest(&areas, &pt(2000, 100)), Some(1));
+        assert_eq!(nearest(&areas, &pt(3000, 1000)), Some(1));
+        assert_eq!(nearest(&areas, &pt(1000, 2000)), Some(0));
+        assert_eq!(nearest(&areas, &pt(5000, -500)), Some(1));
+    }
+
+    #[test]
+    fn test_neighbor() {
+        use Direction::*;
+        // Layout:
+        //   [3]
+        //   [0][1]
+        //      [2]
+        let areas = [
+            area(0, 0, 1920, 1080),
+            area(1920, 0, 3840, 1080),
+            area(1920, 1080, 3840, 2160),
+            area(0, -1080, 1920, 0),
+        ];
+        assert_eq!(neighbor(&areas, 0, Right), Some(1));
+        assert_eq!(neighbor(&areas, 0, Left), None);
+        assert_eq!(neighbor(&areas, 0, Up), Some(3));
+        assert_eq!(neighbor(&areas, 0, Down), Some(2));
+        assert_eq!(neighbor(&areas, 1, Left), Some(0));
+        assert_eq!(neighbor(&areas, 1, Down), Some(2));
+        assert_eq!(neighbor(&areas, 1, Up), Some(3));
+        assert_eq!(neighbor(&areas, 2, Up), Some(1));
+        assert_eq!(neighbor(&areas, 2, Left), Some(0));
+        assert_eq!(neighbor(&areas, 3, Down), Some(0));
+        assert_eq!(neighbor(&areas, 3, Right), Some(1));
+        assert_eq!(neighbor(&areas, 4, Right), None);
+    }
+
+    #[test]
+    fn test_neighbor_prefer_aligned() {
+        use Direction::*;
+        // [0][1]
+        //        [2]
+        // Monitor 2 is not next to 0 in row, 1 is preferred though farther
+        let areas = [
+            area(0, 0, 1000, 1000),
+            area(1000, 0, 2000, 1000),
+            area(2000, 1000, 3000, 2000),
+        ];
+        assert_eq!(neighbor(&areas, 0, Right), Some(1));
+        assert_eq!(neighbor(&areas, 1, Right), Some(2));
+        assert_eq!(neighbor(&areas, 2, Left), Some(1));
+    }
+
+    #[test]
+    fn test_spanning_rect() {
+        assert_eq!(spanning_rect(&[]), None);
+        let areas = [area(0, 0, 1920, 1080), area(-1280, -200, 0, 824)];
+        assert_eq!(spanning_rect(&areas), Some(area(-1280, -200, 1920, 1080)));
+        assert_eq!(spanning_rect(&areas[..1]), Some(areas[0]));
+    }
+
+    #[test]
+    fn test_reading_order() {
+        // L-shaped, enumerated in random order
+        let l_shaped = [
+            area(0, 1080, 1920, 2160),
+            area(1920, 0, 3840, 1080),
+            area(0, 0, 1920, 1080),
+        ];
+        assert_eq!(reading_order(&l_shaped, false), vec![2, 1, 0]);
+        assert_eq!(reading_order(&l_shaped, true), vec![2, 0, 1]);
+
+        // Stacked ones, with a portrait one beside, a laptop lower on the right
+        let stacked = [
+            area(1920, -420, 3000, 1500),
+            area(0, 1080, 1920, 2160),
+            area(3000, 300, 4920, 1380),
+            area(0, 0, 1920, 1080),
+        ];
+        assert_eq!(reading_order(&stacked, false), vec![3, 0, 2, 1]);
+        assert_eq!(reading_order(&stacked, true), vec![3, 1, 0, 2]);
+        assert!(reading_order(&[], false).is_empty());
+    }
+
+    #[test]
+    fn test_layout_changed_much() {
+        let areas = [area(0, 0, 1920, 1080), area(-1280, -200, 0, 824)];
+        assert_eq!(layout_sizes(&areas), vec!["1280x1024", "1920x1080"]);
+        let moved = [area(-1280, 0, 0, 1024), area(0, 0, 1920, 1080)];
+        assert!(!layout_changed_much(&areas, &moved));
+        assert!(layout_changed_much(&areas, &areas[..1]));
+        let phantom = [area(0, 0, 1024, 768)];
+        assert!(layout_changed_much(&areas[..1], &phantom));
+    }
+}
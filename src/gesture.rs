@@ -0,0 +1,136 @@
+// Simple gestures recognized from positions of absolute devices(pens, touch screens).
+// A stroke is a run of positions without a gap longer than GESTURE_STROKE_GAP_MS,
+// which is roughly one touch from down to up.
+
+use crate::geometry::{Corner, MonitorArea, MousePos};
+
+pub const GESTURE_STROKE_GAP_MS: u64 = 150;
+// A swipe must cross this share of monitor width, within GESTURE_SWIPE_MS
+pub const GESTURE_SWIPE_PERCENT: i64 = 70;
+pub const GESTURE_SWIPE_MS: u64 = 500;
+// Two strokes starting in the same corner within this time
+pub const GESTURE_DOUBLE_TAP_MS: u64 = 400;
+// Corner size, in percent of the shorter side of monitor
+pub const GESTURE_CORNER_PERCENT: i32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    SwipeLeft,
+    SwipeRight,
+    CornerDoubleTap,
+}
+
+struct Stroke {
+    start: MousePos,
+    start_tick: u64,
+    last_tick: u64,
+    fired: bool,
+}
+
+#[derive(Default)]
+pub struct GestureDetector {
+    stroke: Option<Stroke>,
+    last_tap: Option<(Corner, u64)>,
+}
+
+impl GestureDetector {
+    // Returns a gesture once it's recognized. Area is the monitor containing pos.
+    pub fn on_pos(&mut self, pos: MousePos, area: &MonitorArea, tick: u64) -> Option<Gesture> {
+        let width = (area.rigtbtm.x - area.lefttop.x) as i64;
+        let height = (area.rigtbtm.y - area.lefttop.y) as i64;
+
+        let stroke = match &mut self.stroke {
+            Some(s) if tick <= s.last_tick + GESTURE_STROKE_GAP_MS => s,
+            _ => {
+                self.stroke = Some(Stroke {
+                    start: pos,
+                    start_tick: tick,
+                    last_tick: tick,
+                    fired: false,
+                });
+                return self.on_stroke_start(pos, area, tick);
+            }
+        };
+        stroke.last_tick = tick;
+        if stroke.fired || tick > stroke.start_tick + GESTURE_SWIPE_MS {
+            return None;
+        }
+        let dx = (pos.x - stroke.start.x) as i64;
+        let dy = (pos.y - stroke.start.y) as i64;
+        // Mostly horizontal
+        if dx.abs() * 100 < width * GESTURE_SWIPE_PERCENT || dy.abs() * 2 > height {
+            return None;
+        }
+        stroke.fired = true;
+        self.last_tap = None;
+        if dx > 0 {
+            Some(Gesture::SwipeRight)
+        } else {
+            Some(Gesture::SwipeLeft)
+        }
+    }
+
+    fn on_stroke_start(&mut self, pos: MousePos, area: &MonitorArea, tick: u64) -> Option<Gesture> {
+        let shorter = (area.rigtbtm.x - area.lefttop.x).min(area.rigtbtm.y - area.lefttop.y);
+        let Some(corner) = area.corner_at(&pos, shorter * GESTURE_CORNER_PERCENT / 100) else {
+            self.last_tap = None;
+            return None;
+        };
+        match self.last_tap {
+            Some((last, last_tick))
+                if last == corner && tick <= last_tick + GESTURE_DOUBLE_TAP_MS =>
+            {
+                self.last_tap = None;
+                Some(Gesture::CornerDoubleTap)
+            }
+            _ => {
+                self.last_tap = Some((corner, tick));
+                None
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.stroke = None;
+        self.last_tap = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gesture_detect() {
+        let area = MonitorArea {
+            lefttop: MousePos::from(0, 0),
+            rigtbtm: MousePos::from(1920, 1080),
+            id: 0,
+        };
+        let mut g = GestureDetector::default();
+        let mut feed = |x: i32, y: i32, tick: u64| g.on_pos(MousePos::from(x, y), &area, tick);
+
+        // Swipe from left edge to right edge
+        assert_eq!(feed(100, 500, 1000), None);
+        assert_eq!(feed(900, 520, 1100), None);
+        assert_eq!(feed(1800, 540, 1200), Some(Gesture::SwipeRight));
+        assert_eq!(feed(1850, 540, 1250), None);
+
+        // Too slow
+        for (i, x) in [1800, 1600, 1400, 1200, 1000, 600, 100]
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(feed(x, 500, 2000 + i as u64 * 100), None);
+        }
+
+        // Double tap top right corner, strokes separated by gaps
+        assert_eq!(feed(1900, 10, 4000), None);
+        assert_eq!(feed(1905, 12, 4050), None);
+        assert_eq!(feed(1910, 20, 4300), Some(Gesture::CornerDoubleTap));
+
+        // Taps in different corners
+        assert_eq!(feed(10, 10, 6000), None);
+        assert_eq!(feed(10, 1070, 6200), None);
+    }
+}
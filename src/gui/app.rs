@@ -1,24 +1,117 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use monmouse::{
+    bridge::BusEvent,
+    config_sync::{self, SyncListener, SyncedSettings},
+    config_writer::{ConfigWriter, SaveState},
+    crash_report,
     errors::Error,
+    inventory::{export_devices, InventoryFormat},
+    keyboard::conflict::{known_owner, suggest_alternatives},
     message::{
-        timer_spawn, DeviceStatus, GenericDevice, Message, RoundtripData, SendData, TimerDueKind,
-        TimerOperator, UINotify, UIReactor,
+        ActiveDeviceEvent, DeviceStatus, GenericDevice, Message, Positioning, RoundtripData,
+        SendData, TrackingMode, UINotify, UIReactor,
+    },
+    paths::{view_endpoint_file, AppPaths, Artifact},
+    preset::{presets, Preset},
+    risk::{self, RiskyFeature},
+    setting::{
+        reconcile_devices, write_config, Calibration, ConfigFormat, DeviceReconcile, DeviceSetting,
+        DeviceSettingItem, ProcessorSettings, Settings, ShortcutBackend, ShortcutSettings,
+        FAILSAFE_SHORTCUT,
     },
-    setting::{write_config, DeviceSetting, DeviceSettingItem, ProcessorSettings, Settings},
+    stats::UsageStats,
+    trace::TRACE_DEFAULT_SECS,
+    update_check::{self, LatestRelease},
+    view_ipc::{ViewCall, ViewClient, ViewReply, ViewRequest, ViewServer, ViewSnapshot},
+    windows::winwrap::shell_open,
 };
 
-use crate::{components::config_panel::ConfigInputState, styles::Theme, EguiNotify};
+use crate::{
+    components::config_panel::ConfigInputState,
+    sound::{self, SoundCue},
+    styles::Theme,
+    tray::{SharedTrayOptions, TrayOptions},
+    EguiNotify,
+};
 
 pub struct App {
     pub state: AppState,
     pub last_result: StatusBarResult,
     pub alert_errors: Vec<String>,
+    pub low_battery_alerts: Vec<String>,
+    pub processor_crashed: Option<ProcessorCrash>,
+    // Shortcut failed to register for being taken, until resolved or dismissed
+    pub shortcut_conflict: Option<ShortcutConflict>,
+    // Names of shortcuts taken by other programs, registered again by mouse control
+    pub pending_shortcuts: Vec<String>,
+    // Sizes of monitors before and after, while mouse control waits for confirming
+    pub monitors_changed: Option<String>,
+    // Risky features set but not confirmed, so kept off until enabled here
+    pub unconfirmed_risks: Vec<RiskyFeature>,
+    // Not asked again in this session
+    risks_kept_off: Vec<RiskyFeature>,
+    pub active_device: Option<ActiveDeviceEvent>,
+    pub safe_mode: bool,
+    pub tracking_mode: TrackingMode,
+    pub identifying: bool,
+    // Devices not found yet may still enumerate, so not shown as disconnected
+    pub initial_scan: bool,
+    pub capturing_trace: bool,
+    // Calibrating an absolute device, one at a time
+    pub calibration: Option<CalibrationWizard>,
+    // Of monitors in jumping order, as of last calibrating
+    pub monitor_labels: Vec<String>,
+    pub presets: Vec<Preset>,
+    // Roles of a preset being mapped to devices, before applying it
+    pub preset_mapping: Option<PresetMapping>,
+    pub export_format: ConfigFormat,
+    pub inventory_format: InventoryFormat,
+    // Row of devices table with keyboard focus, for arrow key navigation
+    pub focused_device_row: Option<usize>,
+    // Today's date and stats, as of last inspecting
+    pub usage_stats: Option<(String, UsageStats)>,
+    // Config entries possibly renamed, with index of the chosen candidate
+    pub device_reconciles: Vec<(DeviceReconcile, usize)>,
+    // Of the latest config submitted to the writer
+    pub save_state: SaveState,
+    // Submitted to the writer, becomes saved settings once written
+    submitted_settings: Option<Settings>,
+    // Changed device settings not sent yet, the latest one of each device
+    pending_device_settings: BTreeMap<String, DeviceSettingItem>,
+    device_settings_due: Option<Instant>,
+    // Found by cleanup, shown until confirmed or canceled
+    pub cleanup: Option<Vec<Artifact>>,
+    // Removed after UI and mouse control exited, not to be written again by them
+    pub cleanup_on_exit: Option<Vec<Artifact>>,
+    reconcile_checked: bool,
+    // Processor settings as of last applying, None to apply all of them next time
+    applied_processor: Option<ProcessorSettings>,
+    // Of UI settings as of last applying, sent along with processor settings
+    applied_animate_jumps: bool,
+    // Parts of processor settings applied by the last applying, for status bar
+    applied_parts: Vec<&'static str>,
     config_path: Option<PathBuf>,
     should_exit: bool,
     ui_reactor: UIReactor,
-    inspect_timer: Option<TimerOperator>,
+    tray_options: SharedTrayOptions,
+    // Started again when sync settings change
+    sync_listener: Option<SyncListener>,
+    sync_notify: Option<EguiNotify>,
+    view_notify: Option<EguiNotify>,
+    config_writer: Option<ConfigWriter>,
+    // Answers view-only windows of other processes
+    view_server: Option<ViewServer>,
+    // Connected to the running instance, instead of owning mouse control
+    pub view_client: Option<ViewClient>,
+    // Result of the last update check, or error of it
+    pub latest_release: Option<Result<LatestRelease, String>>,
+    pub checking_updates: bool,
+    update_notify: Option<EguiNotify>,
 }
 
 impl App {
@@ -29,49 +122,744 @@ impl App {
             .send(Message::ScanDevices(RoundtripData::default()));
     }
 
-    pub fn trigger_inspect_devices_status(&mut self) {
+    pub fn trigger_refresh_monitors(&mut self) {
+        self.result_clear();
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::RefreshMonitors(RoundtripData::default()));
+    }
+
+    // Locks held after monitors changed are applied again, or mouse control is paused
+    pub fn confirm_monitors_changed(&mut self, apply: bool) {
+        self.monitors_changed = None;
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::ConfirmMonitorsChanged(apply));
+    }
+
+    // Confirmed ones are saved at once, other changes of settings are not
+    pub fn confirm_risks(&mut self, enable: bool) {
+        let features = std::mem::take(&mut self.unconfirmed_risks);
+        if !enable {
+            self.risks_kept_off.extend(features);
+            return;
+        }
+        let mut new_settings = self.latest_saved_settings().clone();
+        for settings in [&mut self.state.settings, &mut new_settings] {
+            let confirmed = &mut settings.processor.confirmed_risks;
+            confirmed.extend(&features);
+            confirmed.sort();
+            confirmed.dedup();
+        }
+        self.save_config(new_settings);
+        self.trigger_settings_changed();
+    }
+
+    pub fn trigger_identify_device(&mut self) {
+        self.state
+            .managed_devices
+            .iter_mut()
+            .for_each(|d| d.identified = false);
+        self.identifying = true;
+        self.result_ok("Move or click the device to identify...".to_owned());
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::IdentifyDevice(RoundtripData::default()));
+    }
+
+    // Data files are kept next to config file
+    fn paths(&self) -> Option<AppPaths> {
+        self.config_path
+            .as_ref()
+            .and_then(|p| p.parent())
+            .map(AppPaths::new)
+    }
+
+    pub fn trigger_capture_trace(&mut self) {
+        let Some(paths) = self.paths() else {
+            self.result_error_alert("No path to save trace".to_owned());
+            return;
+        };
+        let path = paths.trace_file();
+        self.capturing_trace = true;
+        self.result_ok(format!(
+            "Capturing trace for {}s, reproduce the problem now...",
+            TRACE_DEFAULT_SECS
+        ));
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::CaptureTrace(RoundtripData::new((
+                path,
+                TRACE_DEFAULT_SECS,
+            ))));
+    }
+
+    pub const CALIBRATION_SWEEP_SECS: u32 = 15;
+
+    pub fn trigger_calibrate_device(&mut self, id: String) {
+        self.result_ok(format!(
+            "Sweep the device over its whole surface, edge to edge, within {}s...",
+            Self::CALIBRATION_SWEEP_SECS
+        ));
+        self.calibration = Some(CalibrationWizard::Capturing { id: id.clone() });
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::CaptureDeviceExtent(RoundtripData::new((
+                id,
+                Self::CALIBRATION_SWEEP_SECS,
+            ))));
+    }
+
+    pub fn trigger_inspect_usage_stats(&mut self) {
         self.ui_reactor
             .mouse_control_tx
-            .send(Message::InspectDevicesStatus(RoundtripData::default()));
+            .send(Message::InspectUsageStats(RoundtripData::default()));
     }
 
+    // Quick toggles are coalesced, sent together after the debounce delay since the first
+    pub const DEVICE_SETTINGS_DEBOUNCE: Duration = Duration::from_millis(100);
+
     pub fn trigger_one_device_setting_changed(&mut self, item: DeviceSettingItem) {
+        // Mouse control drops the setting being tried for it
+        if let Some(dev) = self
+            .state
+            .managed_devices
+            .iter_mut()
+            .find(|d| d.generic.id == item.id)
+        {
+            dev.testing_until = None;
+        }
+        self.pending_device_settings.insert(item.id.clone(), item);
+        self.device_settings_due
+            .get_or_insert_with(|| Instant::now() + Self::DEVICE_SETTINGS_DEBOUNCE);
+    }
+
+    pub const DEVICE_TEST_SECS: u32 = 15;
+
+    // Lock the device for a while to try it, without changing its setting
+    pub fn trigger_device_test(&mut self, mut item: DeviceSettingItem) {
+        let Some(dev) = self
+            .state
+            .managed_devices
+            .iter_mut()
+            .find(|d| d.generic.id == item.id)
+        else {
+            return;
+        };
+        dev.testing_until =
+            Some(Instant::now() + Duration::from_secs(Self::DEVICE_TEST_SECS as u64));
+        let msg = format!(
+            "Locked {} for {}s to try",
+            dev.generic.product_name,
+            Self::DEVICE_TEST_SECS
+        );
+        self.result_ok(msg);
+        item.content.locked_in_monitor = true;
+        // Or it would overwrite the one being tried
+        self.flush_device_settings(true);
         self.ui_reactor
             .mouse_control_tx
-            .send(Message::ApplyOneDeviceSetting(SendData::new(item)));
+            .send(Message::TemporaryApply(SendData::new((
+                item,
+                Self::DEVICE_TEST_SECS,
+            ))));
+    }
+
+    // Unless forced, only sent after the debounce delay
+    pub fn flush_device_settings(&mut self, force: bool) {
+        match self.device_settings_due {
+            Some(due) if force || Instant::now() >= due => self.device_settings_due = None,
+            _ => return,
+        }
+        let items = std::mem::take(&mut self.pending_device_settings)
+            .into_values()
+            .collect();
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::ApplyDeviceSettings(SendData::new(items)));
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn add_simulated_device(&mut self, spec: monmouse::simulate::SimulatedDeviceSpec) {
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::AddSimulatedDevice(SendData::new(spec)));
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn clear_simulated_devices(&mut self) {
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::ClearSimulatedDevices);
+    }
+
+    // Move settings of a config entry onto another device, dropping the entry
+    pub fn migrate_device_setting(&mut self, from: &str, to: &str) {
+        let devs = &mut self.state.managed_devices;
+        let (Some(src), Some(dst)) = (
+            devs.iter().position(|d| d.generic.id == from),
+            devs.iter().position(|d| d.generic.id == to),
+        ) else {
+            return;
+        };
+        devs[dst].device_setting = devs[src].device_setting;
+        devs[dst].notes = std::mem::take(&mut devs[src].notes);
+        devs.remove(src);
+        self.trigger_settings_changed();
+        self.result_ok("Device settings migrated, save devices config to keep".to_owned());
+    }
+
+    pub fn remove_device_setting(&mut self, id: &str) {
+        self.state.managed_devices.retain(|d| d.generic.id != id);
+        self.trigger_settings_changed();
+        self.result_ok("Device entry removed, save devices config to keep".to_owned());
+    }
+
+    // Events of the device are dropped from then on, e.g. for one not listed by system
+    pub fn ignore_device(&mut self, id: &str) {
+        let ignored = &mut self.state.settings.processor.ignored_devices;
+        if !ignored.iter().any(|v| v == id) {
+            ignored.push(id.to_owned());
+        }
+        self.state.managed_devices.retain(|d| d.generic.id != id);
+        self.trigger_settings_changed();
+        self.result_ok("Device ignored, save config to keep".to_owned());
+    }
+
+    // Devices connected now, which roles of presets can be mapped to
+    pub fn preset_candidates(&self) -> Vec<&GenericDevice> {
+        self.state
+            .managed_devices
+            .iter()
+            .filter(|d| !matches!(d.status, DeviceStatus::Disconnected))
+            .map(|d| &d.generic)
+            .collect()
+    }
+
+    // Roles are mapped to suggested devices first, then changed by user before applying
+    pub fn start_preset(&mut self, preset: usize) {
+        let devices: Vec<GenericDevice> = self.preset_candidates().into_iter().cloned().collect();
+        self.preset_mapping = Some(PresetMapping {
+            preset,
+            devices: self.presets[preset].suggest(&devices),
+        });
+    }
+
+    // Applied as edited in config panel and devices panel, not saved yet
+    pub fn apply_preset(&mut self) {
+        let Some(mapping) = self.preset_mapping.take() else {
+            return;
+        };
+        let preset = &self.presets[mapping.preset];
+        let mut new = self.collect_processor_settings();
+        preset.apply(&mut new, &mapping.devices);
+        for item in new.devices.iter() {
+            if let Some(d) = self
+                .state
+                .managed_devices
+                .iter_mut()
+                .find(|d| d.generic.id == item.id)
+            {
+                d.device_setting = item.content;
+            }
+        }
+        let name = preset.name;
+        self.state.settings.processor = ProcessorSettings {
+            devices: std::mem::take(&mut self.state.settings.processor.devices),
+            ..new
+        };
+        self.state.config_input.set(&self.state.settings);
+        self.trigger_settings_changed();
+        self.result_ok(format!(
+            "Preset \"{}\" applied, save config and devices config to keep",
+            name
+        ));
+    }
+
+    pub fn select_all_devices(&mut self, selected: bool) {
+        self.state
+            .managed_devices
+            .iter_mut()
+            .for_each(|d| d.selected = selected);
     }
 
+    pub fn selected_devices_num(&self) -> usize {
+        self.state
+            .managed_devices
+            .iter()
+            .filter(|d| d.selected)
+            .count()
+    }
+
+    // Modify settings of all selected devices, then apply them within one roundtrip
+    pub fn batch_update_selected_devices(&mut self, f: impl Fn(&mut DeviceSetting)) {
+        let mut updated = 0;
+        self.state
+            .managed_devices
+            .iter_mut()
+            .filter(|d| d.selected)
+            .for_each(|d| {
+                f(&mut d.device_setting);
+                updated += 1;
+            });
+        if updated > 0 {
+            self.trigger_settings_changed();
+        }
+    }
+
+    // Only changed parts are sent, so processor won't reset what's not touched
     pub fn trigger_settings_changed(&mut self) {
         self.result_clear();
+        self.applied_parts.clear();
+        let new = self.collect_processor_settings();
+        // Confirmed only in the instance owning mouse control
+        if !self.safe_mode && !self.view_only() {
+            self.unconfirmed_risks = risk::unconfirmed(&new)
+                .into_iter()
+                .filter(|f| !self.risks_kept_off.contains(f))
+                .collect();
+        }
+        let tx = &self.ui_reactor.mouse_control_tx;
+        let animate_jumps = self.state.settings.ui.animate_jumps;
+        if self.applied_processor.is_none() || animate_jumps != self.applied_animate_jumps {
+            tx.send(Message::AnimateJumps(animate_jumps));
+            self.applied_animate_jumps = animate_jumps;
+        }
+        let Some(old) = self.applied_processor.replace(new.clone()) else {
+            tx.send(Message::ApplyProcessorSetting(RoundtripData::new(new)));
+            return;
+        };
+        let mut sent = false;
+        if new.shortcuts != old.shortcuts {
+            tx.send(Message::ApplyShortcuts(RoundtripData::new(
+                new.shortcuts.clone(),
+            )));
+            sent = true;
+        }
+        if Self::global_options(&new) != Self::global_options(&old) {
+            tx.send(Message::ApplyGlobalProcessorOptions(RoundtripData::new(
+                new.clone(),
+            )));
+            sent = true;
+        }
+        if new.devices != old.devices {
+            tx.send(Message::ApplyDeviceList(RoundtripData::new(new.devices)));
+            sent = true;
+        }
+        if !sent {
+            self.result_ok("New settings applyed".to_owned());
+            self.on_settings_applied();
+        }
+    }
+
+    fn global_options(s: &ProcessorSettings) -> ProcessorSettings {
+        ProcessorSettings {
+            devices: Vec::new(),
+            shortcuts: ShortcutSettings::default(),
+            ..s.clone()
+        }
+    }
+
+    fn on_part_applied(&mut self, part: &'static str, result: Result<(), Error>) {
+        match result {
+            Ok(_) => {
+                self.applied_parts.push(part);
+                self.result_ok(format!("Applied {}", self.applied_parts.join(", ")));
+                self.on_settings_applied();
+            }
+            Err(e) => {
+                self.applied_processor = None;
+                if !self.check_shortcut_conflict(&e) {
+                    self.result_error(&format!("Failed to apply {}", part), &e);
+                }
+            }
+        }
+    }
+
+    // Registered by retrying, the conflict asked about is gone by itself
+    fn on_shortcuts_pending(&mut self, names: Vec<String>) {
+        let registered: Vec<&String> = self
+            .pending_shortcuts
+            .iter()
+            .filter(|n| !names.contains(n))
+            .collect();
+        if !registered.is_empty() {
+            if self
+                .shortcut_conflict
+                .as_ref()
+                .is_some_and(|c| registered.iter().any(|n| n.as_str() == c.name))
+            {
+                self.shortcut_conflict = None;
+            }
+            self.result_ok(format!(
+                "Shortcuts registered after released by other programs: {}",
+                registered
+                    .iter()
+                    .map(|n| n.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        self.pending_shortcuts = names;
+    }
+
+    // Whether the error is a conflict of configured shortcut, to be resolved by popup
+    fn check_shortcut_conflict(&mut self, e: &Error) -> bool {
+        let Error::ShortcutConflict(shortcut) = e else {
+            return false;
+        };
+        let Some(shortcut) = shortcut.as_deref() else {
+            return false;
+        };
+        let shortcuts = &self.state.settings.processor.shortcuts;
+        let Some((name, _)) = shortcuts
+            .named()
+            .into_iter()
+            .find(|(_, s)| s.as_str() == shortcut)
+        else {
+            return false;
+        };
+        let mut taken: Vec<&str> = shortcuts
+            .named()
+            .into_iter()
+            .map(|(_, s)| s.as_str())
+            .filter(|s| !s.is_empty())
+            .collect();
+        taken.push(FAILSAFE_SHORTCUT);
+        self.shortcut_conflict = Some(ShortcutConflict {
+            name,
+            shortcut: shortcut.to_owned(),
+            owner: known_owner(shortcut),
+            suggestions: suggest_alternatives(shortcut, &taken, 3),
+        });
+        true
+    }
+
+    pub fn prepare_cleanup(&mut self) {
+        if self.view_only() {
+            self.result_error_alert("Clean up from the running instance".to_owned());
+            return;
+        }
+        let Some(paths) = self.paths() else {
+            self.result_error_alert("No config path to clean up".to_owned());
+            return;
+        };
+        self.cleanup = Some(paths.artifacts());
+    }
+
+    // Window should be closed then
+    pub fn confirm_cleanup(&mut self) {
+        self.cleanup_on_exit = self.cleanup.take();
+        self.should_exit = true;
+        self.ui_reactor.mouse_control_tx.send(Message::Exit);
+    }
+
+    // Replaced by another shortcut, or detected by keyboard hook if None
+    pub fn resolve_shortcut_conflict(&mut self, replacement: Option<String>) {
+        let Some(conflict) = self.shortcut_conflict.take() else {
+            return;
+        };
+        let shortcuts = &mut self.state.settings.processor.shortcuts;
+        match replacement {
+            Some(v) => {
+                if let Some(s) = shortcuts.named_mut(conflict.name) {
+                    *s = v;
+                }
+            }
+            None => {
+                shortcuts
+                    .backends
+                    .insert(conflict.name.to_owned(), ShortcutBackend::Hook);
+            }
+        }
+        self.state.config_input.set(&self.state.settings);
+        self.trigger_settings_changed();
+    }
+
+    pub fn restart_processor(&mut self) {
+        self.processor_crashed = None;
+        // Pending requests are gone with the crashed processor
+        self.identifying = false;
+        self.capturing_trace = false;
+        self.calibration = None;
+        self.applied_processor = None;
         self.ui_reactor
             .mouse_control_tx
-            .send(Message::ApplyProcessorSetting(RoundtripData::new(
-                self.collect_processor_settings(),
-            )));
+            .send(Message::RestartProcessor);
+        // Queued until the new eventloop is ready
+        self.trigger_scan_devices();
+        self.trigger_settings_changed();
+        self.watch_devices_status(true);
     }
 
-    pub fn setup_inspect_timer(&mut self, egui_notify: &EguiNotify) {
-        let egui_notify = egui_notify.clone();
-        let timer = timer_spawn(
-            Duration::from_millis(self.state.settings.ui.inspect_device_interval_ms),
-            self.ui_reactor.ui_tx.clone(),
-            TimerDueKind::InspectDevice,
-            Some(Box::new(move || egui_notify.notify())),
-        );
-        self.inspect_timer = Some(timer);
+    pub fn open_crashes_dir(&mut self) {
+        let Some(dir) = crash_report::crashes_dir() else {
+            return;
+        };
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            self.result_error_alert(format!("Create crashes folder failed: {}", e));
+            return;
+        }
+        if let Err(e) = shell_open(&dir.to_string_lossy()) {
+            self.result_error("Open crashes folder failed", &e);
+        }
+    }
+
+    // Statuses of devices are pushed only while watched, not to wake up for nothing when
+    // window is closed to tray
+    pub fn watch_devices_status(&mut self, watch: bool) {
+        let interval_ms = watch.then_some(self.state.settings.ui.inspect_device_interval_ms);
+        self.ui_reactor
+            .mouse_control_tx
+            .send(Message::WatchDevicesStatus(interval_ms));
+    }
+
+    pub fn setup_config_sync(&mut self, egui_notify: &EguiNotify) {
+        self.sync_notify = Some(egui_notify.clone());
+        self.restart_sync_listener();
+    }
+
+    pub fn setup_config_writer(&mut self, egui_notify: &EguiNotify) {
+        let tx = self.ui_reactor.ui_tx.clone();
+        let notify = egui_notify.clone();
+        self.config_writer = Some(ConfigWriter::start(move |state| {
+            tx.send(Message::ConfigSaveState(state));
+            notify.notify();
+        }));
+    }
+
+    pub fn setup_view_server(&mut self, egui_notify: &EguiNotify) {
+        self.view_notify = Some(egui_notify.clone());
+        self.restart_view_server();
+    }
+
+    // Started again when ui.allow_view_only changes, not listening while it's off
+    fn restart_view_server(&mut self) {
+        self.view_server = None;
+        if !self.state.settings.ui.allow_view_only {
+            return;
+        }
+        let (Some(endpoint_file), Some(notify)) = (view_endpoint_file(), self.view_notify.clone())
+        else {
+            return;
+        };
+        let tx = self.ui_reactor.ui_tx.clone();
+        match ViewServer::start(&endpoint_file, move |call| {
+            tx.send(Message::ViewCall(call));
+            notify.notify();
+        }) {
+            Ok(server) => self.view_server = Some(server),
+            Err(e) => self.result_error_silent(format!("View-only windows not served: {}", e)),
+        }
+    }
+
+    // Weekly check is left to the running instance in view-only mode
+    pub fn setup_update_check(&mut self, egui_notify: &EguiNotify) {
+        self.update_notify = Some(egui_notify.clone());
+        if self.view_only() {
+            return;
+        }
+        if self.state.settings.ui.check_updates_weekly {
+            self.check_updates(update_check::WEEKLY_CHECK_SECS);
+        }
+        // Looked at while running, even with the window closed. Whether it's on is decided
+        // when due, since it may be turned on later.
+        let Some(paths) = self.paths() else {
+            return;
+        };
+        let tx = self.ui_reactor.ui_tx.clone();
+        let notify = egui_notify.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(update_check::WEEKLY_CHECK_POLL_SECS));
+            if update_check::weekly_check_due(&paths.update_cache_file()) {
+                tx.send(Message::UpdateCheckDue);
+                notify.notify();
+            }
+        });
+    }
+
+    // Requested in background, result comes back by Message::UpdateChecked
+    pub fn check_updates(&mut self, max_age_secs: u64) {
+        if self.checking_updates {
+            return;
+        }
+        let (Some(paths), Some(notify)) = (self.paths(), self.update_notify.clone()) else {
+            return;
+        };
+        self.checking_updates = true;
+        let tx = self.ui_reactor.ui_tx.clone();
+        std::thread::spawn(move || {
+            let result = update_check::latest_release(&paths.update_cache_file(), max_age_secs);
+            tx.send(Message::UpdateChecked(result));
+            notify.notify();
+        });
+    }
+
+    pub fn view_only(&self) -> bool {
+        self.view_client.is_some()
+    }
+
+    // Waits a while for writes not finished, then stops the writer
+    pub fn stop_config_writer(&mut self) {
+        self.config_writer = None;
+    }
+
+    pub fn flush_config_writes(&mut self) {
+        let Some(writer) = &self.config_writer else {
+            return;
+        };
+        if !writer.flush(Duration::from_secs(1)) {
+            self.result_error_silent("Config is still being written".to_owned());
+        }
+    }
+
+    fn restart_sync_listener(&mut self) {
+        self.sync_listener = None;
+        let sync = &self.state.settings.ui.sync;
+        let Some(notify) = self.sync_notify.clone() else {
+            return;
+        };
+        if !sync.listen {
+            return;
+        }
+        let tx = self.ui_reactor.ui_tx.clone();
+        match SyncListener::start(&sync.listen_addr, sync.port, sync.token.clone(), move |s| {
+            tx.send(Message::SyncReceived(Box::new(s)));
+            notify.notify();
+        }) {
+            Ok(listener) => self.sync_listener = Some(listener),
+            Err(e) => self.result_error("Cannot listen for synced settings", &e),
+        }
+    }
+
+    // Pushed in background, result comes back by Message::SyncPushed
+    pub fn push_settings_to_peers(&mut self) {
+        let sync = self.state.settings.ui.sync.clone();
+        let peers: Vec<String> = sync.peer_list().into_iter().map(str::to_owned).collect();
+        if peers.is_empty() || sync.token.is_empty() {
+            self.result_error_alert("Set peers and token to push settings".to_owned());
+            return;
+        }
+        let settings = SyncedSettings::from(&self.state.settings.processor);
+        let tx = self.ui_reactor.ui_tx.clone();
+        let notify = self.sync_notify.clone();
+        self.result_ok("Pushing settings to peers".to_owned());
+        std::thread::spawn(move || {
+            let errors = peers
+                .iter()
+                .filter_map(|peer| {
+                    config_sync::push(peer, sync.port, &sync.token, &settings)
+                        .err()
+                        .map(|e| format!("{}: {}", peer, e))
+                })
+                .collect();
+            tx.send(Message::SyncPushed(errors));
+            if let Some(notify) = notify {
+                notify.notify();
+            }
+        });
+    }
+
+    fn on_sync_received(&mut self, settings: SyncedSettings) {
+        settings.apply_to(&mut self.state.settings.processor);
+        self.state.config_input.set(&self.state.settings);
+        self.trigger_settings_changed();
+        self.save_global_config();
+        self.result_ok("Settings received from a peer".to_owned());
+    }
+
+    fn on_view_call(&mut self, mut call: ViewCall) {
+        let reply = match call.take_request() {
+            ViewRequest::Snapshot => ViewReply::Snapshot(Box::new(self.view_snapshot())),
+            ViewRequest::Apply(p) => {
+                self.adopt_processor_settings(*p);
+                self.trigger_settings_changed();
+                self.result_ok("Settings applied from a view-only window".to_owned());
+                ViewReply::Done
+            }
+            ViewRequest::Save(s) => {
+                let mut s = *s;
+                self.adopt_processor_settings(s.processor.clone());
+                // Saved as adopted
+                let adopted = &self.state.settings.processor;
+                s.processor.confirmed_risks = adopted.confirmed_risks.clone();
+                s.processor.on_event_command = adopted.on_event_command.clone();
+                s.processor.device_rules = adopted.device_rules.clone();
+                let old_ui = std::mem::replace(&mut self.state.settings.ui, s.ui.clone());
+                if self.state.settings.ui.sync != old_ui.sync {
+                    self.restart_sync_listener();
+                }
+                if self.state.settings.ui.allow_view_only != old_ui.allow_view_only {
+                    self.restart_view_server();
+                }
+                *self.tray_options.lock().unwrap() = TrayOptions::from(&self.state.settings.ui);
+                self.state.config_input.set(&self.state.settings);
+                self.trigger_settings_changed();
+                self.save_config(s);
+                ViewReply::Done
+            }
+        };
+        call.reply(reply);
+    }
+
+    fn view_snapshot(&self) -> ViewSnapshot {
+        let mut settings = self.state.settings.clone();
+        settings.processor = self.collect_processor_settings();
+        ViewSnapshot {
+            settings,
+            devices: self
+                .state
+                .managed_devices
+                .iter()
+                .map(|d| (d.generic.clone(), d.status.clone()))
+                .collect(),
+        }
+    }
+
+    // Edited by a view-only window. Risky features are confirmed only here, and commands and
+    // device rules run by this instance are only edited here.
+    fn adopt_processor_settings(&mut self, mut p: ProcessorSettings) {
+        let current = &self.state.settings.processor;
+        p.confirmed_risks = current.confirmed_risks.clone();
+        p.on_event_command = current.on_event_command.clone();
+        p.device_rules = current.device_rules.clone();
+        for item in &p.devices {
+            match self
+                .state
+                .managed_devices
+                .iter_mut()
+                .find(|d| d.generic.id == item.id)
+            {
+                Some(d) => {
+                    d.device_setting = item.content;
+                    d.notes = item.notes.clone();
+                }
+                None => self
+                    .state
+                    .managed_devices
+                    .push(DeviceUIState::from_setting(item)),
+            }
+        }
+        self.state.settings.processor = p;
+        self.state.config_input.set(&self.state.settings);
     }
 
     pub fn on_settings_applied(&mut self) {
         self.state.config_input.mark_changed(false);
     }
     pub fn apply_new_settings(&mut self) {
+        let old_sync = self.state.settings.ui.sync.clone();
+        let old_allow_view_only = self.state.settings.ui.allow_view_only;
         match self.state.config_input.parse_all(&mut self.state.settings) {
             Ok(_) => {
-                let duration =
-                    Duration::from_millis(self.state.settings.ui.inspect_device_interval_ms);
-                if let Some(timer) = self.inspect_timer.as_ref() {
-                    timer.update_interval(duration);
+                if self.state.settings.ui.sync != old_sync {
+                    self.restart_sync_listener();
                 }
+                if self.state.settings.ui.allow_view_only != old_allow_view_only {
+                    self.restart_view_server();
+                }
+                self.watch_devices_status(true);
+                *self.tray_options.lock().unwrap() = TrayOptions::from(&self.state.settings.ui);
                 self.trigger_settings_changed();
             }
             Err(_) => self.result_error_alert("Not all fields contain valid value".to_owned()),
@@ -88,15 +876,56 @@ impl App {
 }
 
 impl App {
-    pub fn new(ui_reactor: UIReactor) -> Self {
+    pub fn new(ui_reactor: UIReactor, tray_options: SharedTrayOptions) -> Self {
         App {
             state: AppState::default(),
             last_result: StatusBarResult::None,
             alert_errors: Vec::new(),
+            low_battery_alerts: Vec::new(),
+            processor_crashed: None,
+            shortcut_conflict: None,
+            pending_shortcuts: Vec::new(),
+            monitors_changed: None,
+            unconfirmed_risks: Vec::new(),
+            risks_kept_off: Vec::new(),
+            active_device: None,
+            safe_mode: false,
+            tracking_mode: TrackingMode::default(),
+            identifying: false,
+            initial_scan: false,
+            capturing_trace: false,
+            calibration: None,
+            monitor_labels: Vec::new(),
+            presets: presets(),
+            preset_mapping: None,
+            export_format: ConfigFormat::Yaml,
+            inventory_format: InventoryFormat::Json,
+            focused_device_row: None,
+            usage_stats: None,
+            device_reconciles: Vec::new(),
+            save_state: SaveState::Idle,
+            submitted_settings: None,
+            pending_device_settings: BTreeMap::new(),
+            device_settings_due: None,
+            cleanup: None,
+            cleanup_on_exit: None,
+            reconcile_checked: false,
+            applied_processor: None,
+            applied_animate_jumps: false,
+            applied_parts: Vec::new(),
             config_path: None,
             should_exit: false,
             ui_reactor,
-            inspect_timer: None,
+            tray_options,
+            sync_listener: None,
+            sync_notify: None,
+            view_notify: None,
+            config_writer: None,
+            view_server: None,
+            view_client: None,
+            latest_release: None,
+            checking_updates: false,
+            update_notify: None,
         }
     }
 
@@ -112,26 +941,36 @@ impl App {
                 self.state.saved_settings = s;
             }
             Err(Error::ConfigFileNotExists(_)) => (),
-            Err(e) => {
-                self.result_error_alert(format!("Cannot load config, use default config: {}", e))
-            }
+            Err(e) => self.result_error("Cannot load config, use default config", &e),
         };
         self.state.config_input.set(&self.state.settings);
         self.config_path = config_path;
         self
     }
 
+    pub fn active_device_name(&self) -> Option<&str> {
+        let id = &self.active_device.as_ref()?.id;
+        let name = match self
+            .state
+            .managed_devices
+            .iter()
+            .find(|d| &d.generic.id == id)
+        {
+            Some(d) => d.generic.product_name.as_str(),
+            None => id.as_str(),
+        };
+        Some(name)
+    }
+
     pub fn get_theme(&self) -> Theme {
         Theme::from_string(self.state.settings.ui.theme.as_str())
     }
 
     fn init_managed_devices(&mut self, settings: &ProcessorSettings) {
         for dev in &settings.devices {
-            self.state.managed_devices.push(DeviceUIState {
-                device_setting: dev.content.clone(),
-                generic: GenericDevice::id_only(dev.id.clone()),
-                status: DeviceStatus::Disconnected,
-            })
+            self.state
+                .managed_devices
+                .push(DeviceUIState::from_setting(dev))
         }
     }
     fn merge_scanned_devices(&mut self, new_devs: Vec<GenericDevice>) {
@@ -141,6 +980,11 @@ impl App {
         }
         // Merge list
         for new_dev in new_devs.into_iter() {
+            let default_setting = self
+                .state
+                .settings
+                .processor
+                .default_for_type(new_dev.device_type);
             match self
                 .state
                 .managed_devices
@@ -152,9 +996,15 @@ impl App {
                     dev.status = DeviceStatus::Idle;
                 }
                 None => self.state.managed_devices.push(DeviceUIState {
-                    device_setting: DeviceSetting::default(),
+                    device_setting: default_setting,
                     generic: new_dev,
                     status: DeviceStatus::Idle,
+                    selected: false,
+                    battery: None,
+                    notes: String::new(),
+                    identified: false,
+                    last_positioning: None,
+                    testing_until: None,
                 }),
             }
         }
@@ -164,6 +1014,31 @@ impl App {
         // })
     }
 
+    fn update_devices_battery(&mut self, levels: Vec<(String, u8)>) {
+        let threshold = self.state.settings.ui.low_battery_alert_percent;
+        let mut alerts = Vec::new();
+        for d in &mut self.state.managed_devices {
+            let new = levels
+                .iter()
+                .find(|(id, _)| id == &d.generic.id)
+                .map(|(_, level)| *level);
+            // Alert once when falling below threshold
+            if let Some(level) = new {
+                let was_low = d.battery.is_some_and(|old| (old as u32) < threshold);
+                if (level as u32) < threshold && !was_low {
+                    let name = if d.generic.product_name.is_empty() {
+                        &d.generic.id
+                    } else {
+                        &d.generic.product_name
+                    };
+                    alerts.push(format!("{}: {}%", name, level));
+                }
+            }
+            d.battery = new;
+        }
+        self.low_battery_alerts.extend(alerts);
+    }
+
     fn update_devices_status(&mut self, devs: Vec<(String, DeviceStatus)>) {
         self.state
             .managed_devices
@@ -173,6 +1048,9 @@ impl App {
         devs.into_iter().for_each(|(id, status)| {
             for d in &mut self.state.managed_devices {
                 if d.generic.id == id {
+                    if let DeviceStatus::Active { positioning, raw } = status {
+                        d.last_positioning = Some((positioning, raw));
+                    }
                     d.status = status;
                     break;
                 }
@@ -186,12 +1064,22 @@ impl App {
                 .state
                 .managed_devices
                 .iter()
-                .map(|d| DeviceSettingItem {
-                    id: d.generic.id.clone(),
-                    content: d.device_setting,
-                })
+                .map(|d| d.clone_setting())
                 .collect(),
+            ignored_devices: self.state.settings.processor.ignored_devices.clone(),
+            switch_cycle: self.state.settings.processor.switch_cycle.clone(),
+            rawinput_usages: self.state.settings.processor.rawinput_usages.clone(),
             shortcuts: self.state.settings.processor.shortcuts.clone(),
+            hot_corners: self.state.settings.processor.hot_corners.clone(),
+            gestures: self.state.settings.processor.gestures.clone(),
+            jump_targets: self.state.settings.processor.jump_targets.clone(),
+            monitor_groups: self.state.settings.processor.monitor_groups.clone(),
+            kvm: self.state.settings.processor.kvm.clone(),
+            on_event_command: self.state.settings.processor.on_event_command.clone(),
+            device_rules: self.state.settings.processor.device_rules.clone(),
+            app_rules: self.state.settings.processor.app_rules.clone(),
+            defaults_by_type: self.state.settings.processor.defaults_by_type.clone(),
+            confirmed_risks: self.state.settings.processor.confirmed_risks.clone(),
             ..self.state.settings.processor
         }
     }
@@ -200,6 +1088,17 @@ impl App {
         if self.should_exit {
             return true;
         }
+        // No tray icon to bring UI back, closing the window exits
+        if self.view_only() {
+            self.ui_reactor.mouse_control_tx.send(Message::Exit);
+            return true;
+        }
+        // Tray icon has gone with the crashed processor, nothing can bring UI back
+        if self.processor_crashed.is_some() {
+            self.ui_reactor.mouse_control_tx.send(Message::Exit);
+            return true;
+        }
+        self.watch_devices_status(false);
         // Once clearing residual pending msg
         loop {
             match self.ui_reactor.ui_rx.try_recv() {
@@ -216,6 +1115,10 @@ impl App {
             match self.ui_reactor.ui_rx.recv() {
                 Message::Exit => return true,
                 Message::RestartUI => return false,
+                msg @ Message::Bus(BusEvent::ProcessorCrashed(_)) => {
+                    self.handle_message(msg);
+                    return false;
+                }
                 msg => {
                     // Handle others msg normally
                     self.handle_message(msg);
@@ -228,6 +1131,70 @@ impl App {
         while let Some(msg) = self.ui_reactor.ui_rx.try_recv() {
             self.handle_message(msg)
         }
+        self.flush_device_settings(false);
+    }
+
+    fn handle_bus_event(&mut self, event: BusEvent) {
+        match event {
+            BusEvent::ProcessorCrashed(reason) => {
+                self.processor_crashed = Some(ProcessorCrash {
+                    reason,
+                    dismissed: false,
+                });
+            }
+            BusEvent::UserPauseChanged(paused) => {
+                if paused {
+                    self.result_ok("Mouse control paused".to_owned());
+                } else {
+                    self.result_ok("Mouse control resumed".to_owned());
+                }
+            }
+            BusEvent::SessionChanged(paused) => {
+                if paused {
+                    self.result_error_silent(
+                        "Session disconnected, mouse control paused".to_owned(),
+                    );
+                } else {
+                    // Device handles are invalid after reconnecting
+                    self.trigger_scan_devices();
+                }
+            }
+            BusEvent::InitialScan(in_progress) => self.initial_scan = in_progress,
+            BusEvent::TrackingModeChanged(mode) => {
+                self.tracking_mode = mode;
+                if mode == TrackingMode::Polling {
+                    self.result_error_silent(
+                        "Polling cursor instead of mouse hook, locking works with reduced precision"
+                            .to_owned(),
+                    );
+                }
+            }
+            BusEvent::DevicesUpdated(devs) => self.merge_scanned_devices(devs),
+            BusEvent::BatteryUpdated(levels) => self.update_devices_battery(levels),
+            BusEvent::ActiveDeviceChanged(event) => {
+                sound::play(SoundCue::Switch, &self.state.settings.ui.sounds);
+                self.active_device = Some(event);
+            }
+            BusEvent::MonitorJumped => sound::play(SoundCue::Jump, &self.state.settings.ui.sounds),
+            BusEvent::MonitorsChanged(layout) => self.monitors_changed = Some(layout),
+            BusEvent::ShortcutsPending(names) => self.on_shortcuts_pending(names),
+            BusEvent::FailsafeTriggered => {
+                // Or settings applied next time would bring them back
+                self.state
+                    .managed_devices
+                    .iter_mut()
+                    .for_each(|d| d.device_setting = DeviceSetting::default());
+                self.result_error_alert(format!(
+                    "{} pressed, locking and switching of all devices are disabled. Save devices config to keep it.",
+                    FAILSAFE_SHORTCUT
+                ));
+            }
+            // Not subscribed, or shown by tray and overlay only
+            BusEvent::KvmRemoteChanged(_)
+            | BusEvent::PausedChanged(_)
+            | BusEvent::SettingsApplied(_)
+            | BusEvent::Relocated(_) => (),
+        }
     }
 
     pub fn handle_message(&mut self, msg: Message) {
@@ -236,6 +1203,26 @@ impl App {
                 self.should_exit = true;
             }
             Message::RestartUI => (),
+            Message::Bus(event) => self.handle_bus_event(event),
+            Message::SyncReceived(settings) => self.on_sync_received(*settings),
+            Message::SyncPushed(errors) => {
+                if errors.is_empty() {
+                    self.result_ok("Settings pushed to peers".to_owned());
+                } else {
+                    self.result_error_alert(format!("Push settings failed: {}", errors.join("; ")));
+                }
+            }
+            Message::ConfigSaveState(state) => self.on_save_state(state),
+            Message::UpdateChecked(result) => {
+                self.checking_updates = false;
+                self.latest_release = Some(result.map_err(|e| e.to_string()));
+            }
+            Message::UpdateCheckDue => {
+                if self.state.settings.ui.check_updates_weekly {
+                    self.check_updates(update_check::WEEKLY_CHECK_SECS);
+                }
+            }
+            Message::ViewCall(call) => self.on_view_call(call),
             Message::LockCurMouse(id) => {
                 let Some(dev) = self
                     .state
@@ -246,77 +1233,298 @@ impl App {
                     return;
                 };
                 dev.device_setting.locked_in_monitor = !dev.device_setting.locked_in_monitor;
-                self.ui_reactor
-                    .mouse_control_tx
-                    .send(Message::ApplyOneDeviceSetting(SendData::new(
-                        DeviceSettingItem {
-                            id,
-                            content: dev.device_setting,
-                        },
-                    )));
+                sound::play(SoundCue::Lock, &self.state.settings.ui.sounds);
+                let item = dev.clone_setting();
+                self.trigger_one_device_setting_changed(item.clone());
+                self.flush_device_settings(true);
+                if self.state.settings.processor.persist_shortcut_changes {
+                    self.save_some_devices(vec![item]);
+                }
+            }
+            Message::DeviceSettingsChanged(changes) => {
+                for (id, setting) in changes {
+                    if let Some(dev) = self
+                        .state
+                        .managed_devices
+                        .iter_mut()
+                        .find(|v| v.generic.id == id)
+                    {
+                        dev.device_setting = setting;
+                    }
+                }
+            }
+            Message::SwitchCycled(changes) => {
+                let mut items = Vec::new();
+                for (id, setting) in changes {
+                    if let Some(dev) = self
+                        .state
+                        .managed_devices
+                        .iter_mut()
+                        .find(|v| v.generic.id == id)
+                    {
+                        dev.device_setting = setting;
+                        items.push(dev.clone_setting());
+                    }
+                }
+                sound::play(SoundCue::Switch, &self.state.settings.ui.sounds);
+                if self.state.settings.processor.persist_shortcut_changes {
+                    self.save_some_devices(items);
+                }
             }
             Message::ScanDevices(data) => match data.take_rsp() {
                 Ok(devs) => {
+                    if !self.reconcile_checked {
+                        self.reconcile_checked = true;
+                        self.device_reconciles =
+                            reconcile_devices(&self.state.saved_settings.processor.devices, &devs)
+                                .into_iter()
+                                .map(|r| (r, 0))
+                                .collect();
+                    }
                     let dev_num = devs.len();
                     self.merge_scanned_devices(devs);
                     self.result_ok(format!("Scanned {} devices", dev_num))
                 }
-                Err(e) => self.result_error_alert(format!("Failed to scan devices: {}", e)),
+                Err(e) => self.result_error("Failed to scan devices", &e),
             },
-            Message::TimerDue(TimerDueKind::InspectDevice) => self.trigger_inspect_devices_status(),
+            Message::RefreshMonitors(data) => match data.take_rsp() {
+                Ok(labels) => self.result_ok(format!("Monitors refreshed: {}", labels.join(", "))),
+                Err(e) => self.result_error("Failed to refresh monitors", &e),
+            },
+            Message::IdentifyDevice(data) => {
+                self.identifying = false;
+                match data.take_rsp() {
+                    Ok(Some(id)) => self.on_device_identified(id),
+                    Ok(None) => self.result_error_silent(
+                        "No device event in 10 seconds, nothing identified".to_owned(),
+                    ),
+                    Err(e) => self.result_error("Failed to identify device", &e),
+                }
+            }
+            Message::DevicesStatusChanged(devs) => self.update_devices_status(devs),
             Message::InspectDevicesStatus(data) => match data.take_rsp() {
                 Ok(devs) => self.update_devices_status(devs),
                 Err(e) => {
                     self.result_error_silent(format!("Failed to update device status: {}", e))
                 }
             },
+            Message::InspectUsageStats(data) => match data.take_rsp() {
+                Ok(v) => self.usage_stats = Some(v),
+                Err(e) => self.result_error_silent(format!("Failed to inspect usage stats: {}", e)),
+            },
+            Message::CaptureDeviceExtent(data) => {
+                // Unless cancelled meanwhile
+                let Some(CalibrationWizard::Capturing { id }) = &self.calibration else {
+                    return;
+                };
+                let id = id.clone();
+                self.calibration = None;
+                match data.take_rsp() {
+                    Ok((extent, labels)) => {
+                        self.monitor_labels = labels;
+                        self.result_ok(format!(
+                            "Reached {}, choose where to map it and apply",
+                            extent
+                        ));
+                        self.calibration = Some(CalibrationWizard::Captured {
+                            id,
+                            calibration: Calibration::from_extent(&extent),
+                        });
+                    }
+                    Err(e) => self.result_error("Failed to calibrate device", &e),
+                }
+            }
+            Message::CaptureTrace(data) => {
+                self.capturing_trace = false;
+                match data.take_rsp() {
+                    Ok((path, records)) => self.result_ok(format!(
+                        "Trace of {} records saved to {}",
+                        records,
+                        path.display()
+                    )),
+                    Err(e) => self.result_error("Failed to capture trace", &e),
+                }
+            }
             Message::ApplyProcessorSetting(data) => match data.take_rsp() {
                 Ok(_) => {
                     self.result_ok("New settings applyed".to_owned());
                     self.on_settings_applied();
                 }
-                Err(e) => self.result_error_alert(format!("Failed to apply settings: {}", e)),
+                Err(e) => {
+                    self.applied_processor = None;
+                    if !self.check_shortcut_conflict(&e) {
+                        self.result_error("Failed to apply settings", &e)
+                    }
+                }
             },
+            Message::ApplyShortcuts(data) => self.on_part_applied("shortcuts", data.take_rsp()),
+            Message::ApplyGlobalProcessorOptions(data) => {
+                self.on_part_applied("options", data.take_rsp())
+            }
+            Message::ApplyDeviceList(data) => {
+                self.on_part_applied("device settings", data.take_rsp())
+            }
             #[allow(unreachable_patterns)]
             _ => panic!("recv unexpected msg: {:?}", msg),
         }
     }
 
+    fn on_device_identified(&mut self, id: String) {
+        match self
+            .state
+            .managed_devices
+            .iter_mut()
+            .find(|d| d.generic.id == id)
+        {
+            Some(d) => {
+                d.identified = true;
+                let msg = format!("Identified: {}", d.generic.product_name);
+                self.result_ok(msg);
+            }
+            None => self.result_error_silent(format!("Identified unlisted device: {}", id)),
+        }
+    }
+
+    // Settings being written if any, so saving one part keeps the other part submitted before
+    fn latest_saved_settings(&self) -> &Settings {
+        self.submitted_settings
+            .as_ref()
+            .unwrap_or(&self.state.saved_settings)
+    }
+
     pub fn save_global_config(&mut self) {
         let mut new_settings = self.state.settings.clone();
-        new_settings.processor.devices = self.state.saved_settings.processor.devices.clone();
+        new_settings.processor.devices = self.latest_saved_settings().processor.devices.clone();
         self.save_config(new_settings);
     }
     pub fn save_devices_config(&mut self) {
-        let mut new_settings = self.state.saved_settings.clone();
-        new_settings.processor.devices = self
-            .state
+        let mut new_settings = self.latest_saved_settings().clone();
+        new_settings.processor.devices = self.devices_to_save();
+        self.state.settings.processor.devices = new_settings.processor.devices.clone();
+        self.save_config(new_settings);
+    }
+    // Without other unsaved changes of devices, e.g. for those toggled by shortcuts
+    fn save_some_devices(&mut self, items: Vec<DeviceSettingItem>) {
+        let mut new_settings = self.latest_saved_settings().clone();
+        new_settings.processor.merge_devices(items.clone());
+        self.state.settings.processor.merge_devices(items);
+        self.save_config(new_settings);
+    }
+
+    // Devices worth keeping in config file
+    fn devices_to_save(&self) -> Vec<DeviceSettingItem> {
+        self.state
             .managed_devices
             .iter()
-            .filter(|d| d.device_setting.is_effective())
+            .filter(|d| d.device_setting.is_effective() || !d.notes.is_empty())
             .map(|d| d.clone_setting())
+            .collect()
+    }
+
+    // Write current settings next to config file, in the chosen format
+    pub fn export_config(&mut self) {
+        let Some(dir) = self.config_path.as_ref().and_then(|p| p.parent()) else {
+            self.result_error_alert("No path to export config".to_owned());
+            return;
+        };
+        let path = dir.join(format!(
+            "monmouse_export.{}",
+            self.export_format.extension()
+        ));
+        let mut settings = self.state.settings.clone();
+        settings.processor.devices = self.devices_to_save();
+        match write_config(&path, &settings) {
+            Ok(_) => self.result_ok(format!("Config exported to {}", path.display())),
+            Err(e) => self.result_error("Failed to export config", &e),
+        }
+    }
+
+    // Write devices found by the last scan next to config file, for inventory or bug reports
+    pub fn export_devices(&mut self) {
+        let Some(dir) = self.config_path.as_ref().and_then(|p| p.parent()) else {
+            self.result_error_alert("No path to export devices".to_owned());
+            return;
+        };
+        let path = dir.join(format!(
+            "monmouse_devices.{}",
+            self.inventory_format.extension()
+        ));
+        let devices: Vec<GenericDevice> = self
+            .state
+            .managed_devices
+            .iter()
+            .filter(|d| !matches!(d.status, DeviceStatus::Disconnected))
+            .map(|d| d.generic.clone())
             .collect();
-        self.state.settings.processor.devices = new_settings.processor.devices.clone();
-        self.save_config(new_settings);
+        let content = export_devices(&devices, self.inventory_format);
+        match std::fs::write(&path, content) {
+            Ok(_) => self.result_ok(format!(
+                "{} devices exported to {}",
+                devices.len(),
+                path.display()
+            )),
+            Err(e) => self.result_error_alert(format!("Failed to export devices: {}", e)),
+        }
     }
+
+    // Written in background, result comes back by Message::ConfigSaveState
     fn save_config(&mut self, new_settings: Settings) {
-        let Some(path) = &self.config_path else {
+        if let Some(client) = &self.view_client {
+            match client.save(new_settings.clone()) {
+                Ok(_) => {
+                    self.state.saved_settings = new_settings;
+                    self.result_ok("Config saved by the running instance".to_owned());
+                }
+                Err(e) => self.result_error("Failed to save config", &e),
+            }
+            return;
+        }
+        // Settings of safe mode start from defaults, and would replace the whole config file
+        if self.safe_mode {
+            self.result_error_alert(
+                "Config is not saved in safe mode, export it instead".to_owned(),
+            );
+            return;
+        }
+        let (Some(path), Some(writer)) = (&self.config_path, &self.config_writer) else {
             self.result_error_alert("No path to save config".to_owned());
             return;
         };
-        match write_config(path, &new_settings) {
-            Ok(_) => (),
-            Err(e) => {
-                self.result_error_alert(format!("Failed to write config file: {}", e));
-                return;
-            }
-        }
-        self.result_ok("Config saved".to_owned());
-        self.state.saved_settings = new_settings.clone();
+        writer.submit(path.clone(), new_settings.clone());
+        self.submitted_settings = Some(new_settings);
         // Don't write the whole new_settings into state.settings, since only one of global/devices config is to be saved.
         // self.state.settings = new_settings;
     }
 
+    fn on_save_state(&mut self, state: SaveState) {
+        // Otherwise newer settings are still being written, and reported by then
+        let all_done = self
+            .config_writer
+            .as_ref()
+            .is_none_or(|w| w.flush(Duration::ZERO));
+        match &state {
+            SaveState::Saved => {
+                if all_done {
+                    if let Some(s) = self.submitted_settings.take() {
+                        self.state.saved_settings = s;
+                    }
+                }
+                self.result_ok("Config saved".to_owned())
+            }
+            SaveState::Retrying { error, attempts: 1 } => {
+                self.result_error_silent(format!("Config not saved yet, will retry: {}", error))
+            }
+            SaveState::Failed(e) => {
+                if all_done {
+                    self.submitted_settings = None;
+                }
+                self.result_error("Failed to write config file", &e.clone().into())
+            }
+            _ => (),
+        }
+        self.save_state = state;
+    }
+
     pub fn result_ok(&mut self, msg: String) {
         self.last_result = StatusBarResult::Ok(msg);
     }
@@ -326,6 +1534,10 @@ impl App {
     pub fn result_error_alert(&mut self, msg: String) {
         self.alert_errors.push(msg);
     }
+    // With stable code of the error, to be searched or reported without the details
+    pub fn result_error(&mut self, doing: &str, e: &Error) {
+        self.result_error_alert(format!("{}: {} [{}]", doing, e, e.code()));
+    }
     pub fn result_clear(&mut self) {
         self.last_result = StatusBarResult::None;
     }
@@ -343,17 +1555,92 @@ pub struct DeviceUIState {
     pub device_setting: DeviceSetting,
     pub generic: GenericDevice,
     pub status: DeviceStatus,
+    pub selected: bool,
+    pub battery: Option<u8>, // in percent
+    pub notes: String,
+    pub identified: bool,
+    // Effective and raw positioning when last active, kept while idle
+    pub last_positioning: Option<(Positioning, Positioning)>,
+    // Locked by trying it, until then
+    pub testing_until: Option<Instant>,
 }
 
 impl DeviceUIState {
+    // Only known from settings, until scanned
+    fn from_setting(dev: &DeviceSettingItem) -> Self {
+        let mut generic = GenericDevice::id_only(dev.id.clone());
+        if !dev.product_name.is_empty() {
+            generic.product_name = dev.product_name.clone();
+        }
+        DeviceUIState {
+            device_setting: dev.content.clone(),
+            generic,
+            status: DeviceStatus::Disconnected,
+            selected: false,
+            battery: None,
+            notes: dev.notes.clone(),
+            identified: false,
+            last_positioning: None,
+            testing_until: None,
+        }
+    }
+
     pub fn clone_setting(&self) -> DeviceSettingItem {
+        // Not known yet for devices only from config
+        let product_name = if self.generic.product_name == self.generic.id {
+            String::new()
+        } else {
+            self.generic.product_name.clone()
+        };
         DeviceSettingItem {
             id: self.generic.id.clone(),
             content: self.device_setting,
+            notes: self.notes.clone(),
+            product_name,
+        }
+    }
+}
+
+// Device ids chosen for roles of a preset, None for roles left out
+pub struct PresetMapping {
+    pub preset: usize,
+    pub devices: Vec<Option<String>>,
+}
+
+// Steps of calibrating an absolute device
+pub enum CalibrationWizard {
+    // Waiting for the device to be swept over its surface
+    Capturing {
+        id: String,
+    },
+    // Extent reached, while the monitor and margins are chosen
+    Captured {
+        id: String,
+        calibration: Calibration,
+    },
+}
+
+impl CalibrationWizard {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Capturing { id } | Self::Captured { id, .. } => id,
         }
     }
 }
 
+pub struct ShortcutConflict {
+    // Field name in ShortcutSettings
+    pub name: &'static str,
+    pub shortcut: String,
+    pub owner: Option<&'static str>,
+    pub suggestions: Vec<String>,
+}
+
+pub struct ProcessorCrash {
+    pub reason: String,
+    pub dismissed: bool,
+}
+
 pub enum StatusBarResult {
     Ok(String),
     ErrMsg(String),
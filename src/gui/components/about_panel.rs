@@ -1,24 +1,12 @@
 use eframe::egui;
+use monmouse::{build_info, update_check::MANUAL_CHECK_CACHE_SECS};
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-const VERSION_ANNO: &str = env!("VERSION_ANNO");
-const VERSION_SHA: &str = env!("VERSION_SHA");
-
-fn full_ver_str() -> String {
-    let mut v = format!("v{}", VERSION);
-    if !VERSION_ANNO.is_empty() {
-        v = format!("{}-{}", v, VERSION_ANNO)
-    }
-    if !VERSION_SHA.is_empty() {
-        v = format!("{} ({})", v, VERSION_SHA)
-    }
-    v
-}
+use crate::app::App;
 
 pub struct AboutPanel {}
 
 impl AboutPanel {
-    pub fn ui(ui: &mut egui::Ui) {
+    pub fn ui(ui: &mut egui::Ui, app: &mut App) {
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new("MonMouse").strong().size(20.0));
         });
@@ -27,8 +15,13 @@ impl AboutPanel {
             .striped(false)
             .spacing([15.0, 3.0])
             .show(ui, |ui| {
+                let info = build_info();
                 ui.label("Version");
-                ui.label(full_ver_str());
+                ui.label(info.to_string());
+                ui.end_row();
+
+                ui.label("Build date");
+                ui.label(info.build_date);
                 ui.end_row();
 
                 ui.label("License");
@@ -44,6 +37,82 @@ impl AboutPanel {
                     "https://github.com/melon-masou/MonMouse",
                 ));
                 ui.end_row();
+
+                ui.label("Updates");
+                Self::updates_ui(ui, app);
+                ui.end_row();
+
+                ui.label("Data");
+                if ui
+                    .button("Clean up...")
+                    .on_hover_text("Remove config, data files and autostart of MonMouse")
+                    .clicked()
+                {
+                    app.prepare_cleanup();
+                }
+                ui.end_row();
             });
+        Self::cleanup_ui(ui, app);
+    }
+
+    fn updates_ui(ui: &mut egui::Ui, app: &mut App) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !app.checking_updates,
+                    egui::Button::new("Check for updates"),
+                )
+                .on_hover_text("Ask GitHub for the latest release, nothing else is sent")
+                .clicked()
+            {
+                app.check_updates(MANUAL_CHECK_CACHE_SECS);
+            }
+            if app.checking_updates {
+                ui.spinner();
+                return;
+            }
+            match &app.latest_release {
+                Some(Ok(r)) if r.is_newer() => {
+                    ui.add(egui::Hyperlink::from_label_and_url(
+                        format!("{} available", r.tag),
+                        &r.url,
+                    ));
+                }
+                Some(Ok(r)) => {
+                    ui.label(format!("Up to date, latest is {}", r.tag));
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                None => (),
+            }
+        });
+    }
+
+    fn cleanup_ui(ui: &mut egui::Ui, app: &mut App) {
+        let Some(artifacts) = &app.cleanup else {
+            return;
+        };
+        ui.separator();
+        if artifacts.is_empty() {
+            ui.label("Nothing to remove.");
+            if ui.button("OK").clicked() {
+                app.cleanup = None;
+            }
+            return;
+        }
+        ui.label("These will be removed after MonMouse quits:");
+        for a in artifacts.iter() {
+            ui.label(format!("  {}", a));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Remove and quit").clicked() {
+                app.confirm_cleanup();
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            if ui.button("Cancel").clicked() {
+                app.cleanup = None;
+            }
+        });
     }
 }
@@ -1,11 +1,18 @@
-use std::{cmp::Ordering, fmt::Display, str::FromStr};
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display, ops::RangeInclusive, str::FromStr};
 
 use eframe::egui::{self, RichText};
-use monmouse::setting::Settings;
+use monmouse::{
+    setting::{
+        limits, AppRule, ConfigFormat, JumpTarget, MonitorOrder, Settings, ShortcutBackend,
+        ThreadPriority, TrayAction, TrayIconStyle,
+    },
+    settings_doc::explain,
+};
 
-use crate::app::App;
-
-use super::widget::{error_color, manage_button, ShortcutChoosePopup};
+use crate::{
+    app::App,
+    components::{error_color, manage_button, text_checkbox, ShortcutChoosePopup},
+};
 
 pub struct ConfigPanel {}
 
@@ -17,14 +24,20 @@ impl ConfigPanel {
         ui.label(text)
     }
 
-    fn config_item<T: ToString, P: Parser<T>>(
+    fn config_item<T: ToString, P: Parser<T>, R: ItemResponse>(
         ui: &mut egui::Ui,
         text: &str,
         ist: &mut InputState<T, P>,
-        add_contents: impl FnOnce(&mut egui::Ui, &mut InputState<T, P>) -> bool,
+        add_contents: impl FnOnce(&mut egui::Ui, &mut InputState<T, P>) -> R,
     ) -> bool {
-        ui.label(text);
-        let changed = add_contents(ui, ist);
+        let label = ui
+            .horizontal(|ui| {
+                let label = ui.label(text);
+                Self::doc_icon(ui, ist.key);
+                label
+            })
+            .inner;
+        let changed = add_contents(ui, ist).finish(&label);
         if changed {
             ist.parse_only();
         }
@@ -35,6 +48,22 @@ impl ConfigPanel {
         changed
     }
 
+    // Shows documentation of the setting when hovered, nothing if not documented
+    fn doc_icon(ui: &mut egui::Ui, key: &str) {
+        let Some(doc) = explain(key) else {
+            return;
+        };
+        ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+            ui.set_max_width(320.0);
+            ui.label(RichText::new(doc.key).monospace());
+            ui.label(doc.summary);
+            if let Some(range) = &doc.range {
+                ui.label(format!("Range: {}", range));
+            }
+            ui.label(RichText::new(format!("Example: {}", doc.example)).monospace());
+        });
+    }
+
     #[inline]
     fn textedit(text: &mut String, char_limit: usize) -> egui::TextEdit {
         egui::TextEdit::singleline(text)
@@ -42,19 +71,255 @@ impl ConfigPanel {
             .desired_width(char_limit as f32 * 10.0)
     }
 
+    fn tray_action_combo(ui: &mut egui::Ui, id: &str, buf: &mut String) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source(id)
+            .selected_text(buf.as_str())
+            .show_ui(ui, |ui| {
+                for action in TrayAction::VARIANTS {
+                    changed |= ui
+                        .selectable_value(buf, action.to_string(), action.to_string())
+                        .changed();
+                }
+            })
+            .response;
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
+    fn tray_icon_combo(ui: &mut egui::Ui, buf: &mut String) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source("TrayIconChooser")
+            .selected_text(buf.as_str())
+            .show_ui(ui, |ui| {
+                for style in TrayIconStyle::VARIANTS {
+                    changed |= ui
+                        .selectable_value(buf, style.to_string(), style.to_string())
+                        .changed();
+                }
+            })
+            .response;
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
     pub fn advanced_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
         input.changed |= Self::config_item(
             ui,
             "Inspect device activity internal(MS)",
             &mut input.inspect_device_interval_ms,
-            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)).changed(),
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
         );
 
         input.changed |= Self::config_item(
             ui,
             "Merge unassociated events within next(MS)",
             &mut input.merge_unassociated_events_ms,
-            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)).changed(),
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Switch to another device after its consecutive events",
+            &mut input.switch_after_events,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Speed when speeding up mouse(%)",
+            &mut input.turbo_speed_percent,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Animate jumping between monitors",
+            &mut input.animate_jumps,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Keep jumping targets out of taskbar",
+            &mut input.use_work_area,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Scroll window under mouse, instead of focused one",
+            &mut input.scroll_window_under_cursor,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Query battery level interval(S), 0 to disable",
+            &mut input.battery_query_interval_secs,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Alert when battery below(%), 0 to disable",
+            &mut input.low_battery_alert_percent,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Clicking tray icon",
+            &mut input.tray_click,
+            |ui, ist| Self::tray_action_combo(ui, "TrayClickChooser", ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Double clicking tray icon",
+            &mut input.tray_double_click,
+            |ui, ist| Self::tray_action_combo(ui, "TrayDoubleClickChooser", ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Middle clicking tray icon",
+            &mut input.tray_middle_click,
+            |ui, ist| Self::tray_action_combo(ui, "TrayMiddleClickChooser", ist.buf()),
+        );
+
+        input.changed |= Self::config_item(ui, "Tray icon", &mut input.tray_icon, |ui, ist| {
+            Self::tray_icon_combo(ui, ist.buf())
+        });
+
+        input.changed |= Self::config_item(
+            ui,
+            "Check for updates weekly",
+            &mut input.check_updates_weekly,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Allow view-only windows",
+            &mut input.allow_view_only,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Update monitors at most once within(MS)",
+            &mut input.update_monitors_interval_ms,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Max messages handled per polling",
+            &mut input.poll_max_messages,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Polling timeout while busy(MS)",
+            &mut input.poll_busy_timeout_ms,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Polling timeout while idle(MS)",
+            &mut input.poll_idle_timeout_ms,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Wait for devices after starting(S)",
+            &mut input.startup_grace_secs,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Hold locks after monitors changed(S)",
+            &mut input.monitor_change_grace_secs,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Ask before applying locks after monitors changed",
+            &mut input.confirm_monitor_changes,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Save device settings toggled by shortcuts at once",
+            &mut input.persist_shortcut_changes,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Restart after crash (on next start)",
+            &mut input.restart_on_crash,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Poll cursor instead of mouse hook",
+            &mut input.cursor_polling,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Time events by high resolution counter",
+            &mut input.high_resolution_timing,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Priority of cursor correcting",
+            &mut input.thread_priority,
+            |ui, ist| Self::thread_priority_combo(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Boost cursor correcting by MMCSS",
+            &mut input.mmcss,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        let (late, all) = monmouse::late_input_events();
+        ui.label("Late input events");
+        ui.label(format!("{} of {}", late, all)).on_hover_text(
+            "Input events waiting over 32ms before processed, since MonMouse started",
+        );
+        ui.end_row();
+
+        input.changed |= Self::config_item(
+            ui,
+            "Emit ETW events for profiling(needs build feature \"tracing\")",
+            &mut input.etw_tracing,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Show locked areas and relocations on screen",
+            &mut input.debug_overlay,
+            |ui, ist| text_checkbox(ui, ist.buf()),
         );
 
         // For debugging colors Only
@@ -77,15 +342,324 @@ impl ConfigPanel {
         }
     }
 
-    pub fn shortcuts_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+    pub fn focus_mode_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(
+            ui,
+            "Dim other monitors by(%)",
+            &mut input.dim_percent,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Release after device idle for(S), 0 to never",
+            &mut input.release_after_idle_secs,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+    }
+
+    pub fn kvm_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(
+            ui,
+            "Pause while input goes to another machine",
+            &mut input.pause_when_remote,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "KVM processes, separated by ','",
+            &mut input.processes,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 30)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "State file, instead of processes",
+            &mut input.state_file,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 30)),
+        );
+    }
+
+    pub fn sync_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(
+            ui,
+            "Accept settings pushed by peers",
+            &mut input.listen,
+            |ui, ist| text_checkbox(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Listen on address",
+            &mut input.listen_addr,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 16)),
+        );
+
+        input.changed |= Self::config_item(ui, "Port", &mut input.port, |ui, ist| {
+            ui.add(Self::textedit(ist.buf(), 8))
+        });
+
+        input.changed |= Self::config_item(ui, "Shared token", &mut input.token, |ui, ist| {
+            ui.add(Self::textedit(ist.buf(), 30).password(true))
+        });
+
+        input.changed |= Self::config_item(
+            ui,
+            "Peers, separated by ','",
+            &mut input.peers,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 30)),
+        );
+    }
+
+    pub fn hot_corners_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(
+            ui,
+            "Trigger after staying in corner for(MS)",
+            &mut input.dwell_ms,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 8)),
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Only for device ID, empty for any",
+            &mut input.only_device,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 30)),
+        );
+
+        input.changed |= Self::config_item(ui, "Top left", &mut input.top_left, |ui, ist| {
+            Self::tray_action_combo(ui, "HotCornerTopLeftChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(ui, "Top right", &mut input.top_right, |ui, ist| {
+            Self::tray_action_combo(ui, "HotCornerTopRightChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(ui, "Bottom left", &mut input.bottom_left, |ui, ist| {
+            Self::tray_action_combo(ui, "HotCornerBottomLeftChooser", ist.buf())
+        });
+
+        input.changed |=
+            Self::config_item(ui, "Bottom right", &mut input.bottom_right, |ui, ist| {
+                Self::tray_action_combo(ui, "HotCornerBottomRightChooser", ist.buf())
+            });
+    }
+
+    pub fn gestures_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(ui, "Swipe left", &mut input.swipe_left, |ui, ist| {
+            Self::tray_action_combo(ui, "GestureSwipeLeftChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(ui, "Swipe right", &mut input.swipe_right, |ui, ist| {
+            Self::tray_action_combo(ui, "GestureSwipeRightChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(
+            ui,
+            "Double tap in corner",
+            &mut input.corner_double_tap,
+            |ui, ist| Self::tray_action_combo(ui, "GestureCornerDoubleTapChooser", ist.buf()),
+        );
+    }
+
+    fn jump_target_combo(ui: &mut egui::Ui, id: &str, buf: &mut String) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source(id)
+            .selected_text(buf.as_str())
+            .show_ui(ui, |ui| {
+                for target in JumpTarget::VARIANTS {
+                    changed |= ui
+                        .selectable_value(buf, target.to_string(), target.to_string())
+                        .changed();
+                }
+            })
+            .response;
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
+    fn monitor_order_combo(ui: &mut egui::Ui, buf: &mut String) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source("MonitorOrderChooser")
+            .selected_text(buf.as_str())
+            .show_ui(ui, |ui| {
+                for order in MonitorOrder::VARIANTS {
+                    changed |= ui
+                        .selectable_value(buf, order.to_string(), order.to_string())
+                        .changed();
+                }
+            })
+            .response;
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
+    fn thread_priority_combo(ui: &mut egui::Ui, buf: &mut String) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source("ThreadPriorityChooser")
+            .selected_text(buf.as_str())
+            .show_ui(ui, |ui| {
+                for priority in ThreadPriority::VARIANTS {
+                    changed |= ui
+                        .selectable_value(buf, priority.to_string(), priority.to_string())
+                        .changed();
+                }
+            })
+            .response;
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
+    pub fn jump_targets_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(
+            ui,
+            "Order of next monitor",
+            &mut input.monitor_order,
+            |ui, ist| Self::monitor_order_combo(ui, ist.buf()),
+        );
+
+        input.changed |= Self::config_item(ui, "Next monitor", &mut input.jump_next, |ui, ist| {
+            Self::jump_target_combo(ui, "JumpNextTargetChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(ui, "Left monitor", &mut input.jump_left, |ui, ist| {
+            Self::jump_target_combo(ui, "JumpLeftTargetChooser", ist.buf())
+        });
+
+        input.changed |=
+            Self::config_item(ui, "Right monitor", &mut input.jump_right, |ui, ist| {
+                Self::jump_target_combo(ui, "JumpRightTargetChooser", ist.buf())
+            });
+
+        input.changed |= Self::config_item(ui, "Upper monitor", &mut input.jump_up, |ui, ist| {
+            Self::jump_target_combo(ui, "JumpUpTargetChooser", ist.buf())
+        });
+
+        input.changed |= Self::config_item(ui, "Lower monitor", &mut input.jump_down, |ui, ist| {
+            Self::jump_target_combo(ui, "JumpDownTargetChooser", ist.buf())
+        });
+    }
+
+    pub fn sounds_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        input.changed |= Self::config_item(ui, "Lock toggled", &mut input.on_lock, |ui, ist| {
+            text_checkbox(ui, ist.buf())
+        });
+
+        input.changed |=
+            Self::config_item(ui, "Jumped to monitor", &mut input.on_jump, |ui, ist| {
+                text_checkbox(ui, ist.buf())
+            });
+
+        input.changed |=
+            Self::config_item(ui, "Device switched", &mut input.on_switch, |ui, ist| {
+                text_checkbox(ui, ist.buf())
+            });
+
+        input.changed |= Self::config_item(
+            ui,
+            "Directory of lock/jump/switch.wav, empty for system sounds",
+            &mut input.wav_dir,
+            |ui, ist| ui.add(Self::textedit(ist.buf(), 30)),
+        );
+    }
+
+    // One line per rule, e.g. "game.exe: unlock all devices"
+    fn app_rule_text(rule: &AppRule) -> String {
+        let actions: Vec<String> = rule
+            .actions
+            .iter()
+            .map(|a| {
+                let mut changes = Vec::new();
+                if let Some(v) = a.locked_in_monitor {
+                    changes.push(if v { "lock" } else { "unlock" }.to_owned());
+                }
+                if let Some(v) = a.switch {
+                    changes.push(format!("switch {:?}", v));
+                }
+                let target = if a.target.is_empty() {
+                    "all devices"
+                } else {
+                    &a.target
+                };
+                format!("{} {}", changes.join(", "), target)
+            })
+            .collect();
+        format!("{}: {}", rule.app, actions.join("; "))
+    }
+
+    pub fn app_rules_config(ui: &mut egui::Ui, input: &mut ConfigInputState) {
+        if input.app_rules.is_empty() {
+            ui.label("None, rules are added in config file as processor.app_rules");
+            return;
+        }
+        for rule in input.app_rules.iter_mut() {
+            let text = Self::app_rule_text(rule);
+            input.changed |= ui.checkbox(&mut rule.enabled, text).changed();
+        }
+    }
+
+    // Also chooses whether it's detected by keyboard hook
+    fn shortcut_chooser(
+        ui: &mut egui::Ui,
+        name: &'static str,
+        buf: &mut String,
+        backends: &mut BTreeMap<String, ShortcutBackend>,
+        positional: bool,
+        pending: &[String],
+    ) -> bool {
+        ui.horizontal(|ui| {
+            let mut changed = ShortcutChoosePopup::new(name)
+                .positional(positional)
+                .ui(ui, buf)
+                .changed;
+            let mut hook = backends.get(name) == Some(&ShortcutBackend::Hook);
+            if ui
+                .checkbox(&mut hook, "Hook")
+                .on_hover_text(
+                    "Detect by keyboard hook instead of registering. Works even if taken by \
+                     other programs, and for shortcuts of only modifiers.",
+                )
+                .changed()
+            {
+                if hook {
+                    backends.insert(name.to_owned(), ShortcutBackend::Hook);
+                } else {
+                    backends.remove(name);
+                }
+                changed = true;
+            }
+            if pending.iter().any(|n| n == name) {
+                ui.label(RichText::new("pending: conflict with other app").color(error_color(ui, false)))
+                    .on_hover_text(
+                        "Taken by another program, registered again from time to time until it's released",
+                    );
+            }
+            changed
+        })
+        .inner
+    }
+
+    pub fn shortcuts_config(ui: &mut egui::Ui, input: &mut ConfigInputState, pending: &[String]) {
+        let positional = input.shortcut_positional_keys;
         input.changed |= Self::config_item(
             ui,
             "Lock current mouse",
             &mut input.cur_mouse_lock,
             |ui, ist| {
-                ShortcutChoosePopup::new("cur_mouse_lock")
-                    .ui(ui, ist.buf())
-                    .changed
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_lock",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
             },
         );
 
@@ -94,9 +668,142 @@ impl ConfigPanel {
             "Mouse jumping to next monitor",
             &mut input.cur_mouse_jump_next,
             |ui, ist| {
-                ShortcutChoosePopup::new("cur_mouse_jump_next")
-                    .ui(ui, ist.buf())
-                    .changed
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_jump_next",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Mouse jumping to left monitor",
+            &mut input.cur_mouse_jump_left,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_jump_left",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Mouse jumping to right monitor",
+            &mut input.cur_mouse_jump_right,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_jump_right",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Mouse jumping to upper monitor",
+            &mut input.cur_mouse_jump_up,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_jump_up",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Mouse jumping to lower monitor",
+            &mut input.cur_mouse_jump_down,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_jump_down",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Move active window to monitor of mouse",
+            &mut input.move_window_here,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "move_window_here",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Dim other monitors (focus mode)",
+            &mut input.cur_mouse_focus_mode,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_focus_mode",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Move switch to next device in switch_cycle",
+            &mut input.cycle_switch_devices,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cycle_switch_devices",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
+            },
+        );
+
+        input.changed |= Self::config_item(
+            ui,
+            "Speed up mouse while holding",
+            &mut input.cur_mouse_turbo,
+            |ui, ist| {
+                Self::shortcut_chooser(
+                    ui,
+                    "cur_mouse_turbo",
+                    ist.buf(),
+                    &mut input.shortcut_backends,
+                    positional,
+                    pending,
+                )
             },
         );
     }
@@ -122,11 +829,31 @@ impl ConfigPanel {
                 app.state.config_input.mark_changed(true);
             }
             if ui
-                .add_enabled(!app.state.config_input.changed, manage_button("Save"))
+                .add_enabled(
+                    !app.state.config_input.changed && !app.safe_mode,
+                    manage_button("Save"),
+                )
+                .on_disabled_hover_text("Apply changes first. Not saved in safe mode")
                 .clicked()
             {
                 app.save_global_config();
             }
+            ui.separator();
+            egui::ComboBox::from_id_source("ExportFormatChooser")
+                .selected_text(app.export_format.to_string())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for f in ConfigFormat::VARIANTS {
+                        ui.selectable_value(&mut app.export_format, f, f.to_string());
+                    }
+                });
+            if ui
+                .add(manage_button("Export"))
+                .on_hover_text("Write current settings to monmouse_export file beside config")
+                .clicked()
+            {
+                app.export_config();
+            }
         });
 
         ui.separator();
@@ -138,7 +865,96 @@ impl ConfigPanel {
                 .spacing([40.0, 15.0])
                 .striped(false)
                 .show(ui, |ui| {
-                    Self::shortcuts_config(ui, &mut app.state.config_input);
+                    Self::shortcuts_config(ui, &mut app.state.config_input, &app.pending_shortcuts);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Where mouse lands after jumping");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("JumpTargetsPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::jump_targets_config(ui, &mut app.state.config_input);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Hot corners");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("HotCornersPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::hot_corners_config(ui, &mut app.state.config_input);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Focus mode");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("FocusModePart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::focus_mode_config(ui, &mut app.state.config_input);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Gestures of pens and touch screens");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("GesturesPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::gestures_config(ui, &mut app.state.config_input);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Overrides while programs in foreground");
+            ui.add_space(Self::SPACING);
+            Self::app_rules_config(ui, &mut app.state.config_input);
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Software KVM");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("KvmPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::kvm_config(ui, &mut app.state.config_input);
+                });
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Sync with other machines");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("SyncPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::sync_config(ui, &mut app.state.config_input);
+                });
+            if ui
+                .button("Push to peers")
+                .on_hover_text("Send shortcuts and global options as applied")
+                .clicked()
+            {
+                app.push_settings_to_peers();
+            }
+            ui.add_space(Self::SPACING);
+
+            Self::title(ui, "Sounds");
+            ui.add_space(Self::SPACING);
+            egui::Grid::new("SoundsPart")
+                .num_columns(2)
+                .spacing([40.0, 8.0])
+                .striped(false)
+                .show(ui, |ui| {
+                    Self::sounds_config(ui, &mut app.state.config_input);
                 });
             ui.add_space(Self::SPACING);
 
@@ -156,6 +972,24 @@ impl ConfigPanel {
     }
 }
 
+// What contents of a config item give back. A single widget is also labelled by the
+// item text, for screen readers.
+trait ItemResponse {
+    fn finish(self, label: &egui::Response) -> bool /* changed */;
+}
+
+impl ItemResponse for bool {
+    fn finish(self, _label: &egui::Response) -> bool {
+        self
+    }
+}
+
+impl ItemResponse for egui::Response {
+    fn finish(self, label: &egui::Response) -> bool {
+        self.labelled_by(label.id).changed()
+    }
+}
+
 trait Parser<T> {
     fn parse(&mut self, st: &str) -> Result<T, String>;
 }
@@ -167,13 +1001,58 @@ impl Parser<String> for NonCheck {
     }
 }
 
+struct BoolParser();
+impl Parser<bool> for BoolParser {
+    fn parse(&mut self, st: &str) -> Result<bool, String> {
+        bool::from_str(st).map_err(|_| "not a valid value".to_owned())
+    }
+}
+
+struct TrayActionParser();
+impl Parser<TrayAction> for TrayActionParser {
+    fn parse(&mut self, st: &str) -> Result<TrayAction, String> {
+        TrayAction::from_name(st).ok_or_else(|| "not a valid action".to_owned())
+    }
+}
+
+struct TrayIconStyleParser();
+impl Parser<TrayIconStyle> for TrayIconStyleParser {
+    fn parse(&mut self, st: &str) -> Result<TrayIconStyle, String> {
+        TrayIconStyle::from_name(st).ok_or_else(|| "not a valid style".to_owned())
+    }
+}
+
+struct JumpTargetParser();
+impl Parser<JumpTarget> for JumpTargetParser {
+    fn parse(&mut self, st: &str) -> Result<JumpTarget, String> {
+        JumpTarget::from_name(st).ok_or_else(|| "not a valid target".to_owned())
+    }
+}
+
+struct MonitorOrderParser();
+impl Parser<MonitorOrder> for MonitorOrderParser {
+    fn parse(&mut self, st: &str) -> Result<MonitorOrder, String> {
+        MonitorOrder::from_name(st).ok_or_else(|| "not a valid order".to_owned())
+    }
+}
+
+struct ThreadPriorityParser();
+impl Parser<ThreadPriority> for ThreadPriorityParser {
+    fn parse(&mut self, st: &str) -> Result<ThreadPriority, String> {
+        ThreadPriority::from_name(st).ok_or_else(|| "not a valid priority".to_owned())
+    }
+}
+
 struct OrderParser<T: Ord + FromStr + Display + Copy> {
     min: T,
     max: T,
 }
 impl<T: Ord + FromStr + Display + Copy> OrderParser<T> {
-    fn new(min: T, max: T) -> Self {
-        OrderParser { min, max }
+    fn range(r: RangeInclusive<T>) -> Self {
+        OrderParser {
+            min: *r.start(),
+            max: *r.end(),
+        }
     }
 }
 impl<T: Ord + FromStr + Display + Copy> Parser<T> for OrderParser<T> {
@@ -190,6 +1069,8 @@ impl<T: Ord + FromStr + Display + Copy> Parser<T> for OrderParser<T> {
 }
 
 struct InputState<T: ToString, P: Parser<T>> {
+    // Path of the setting in config file, for looking up its documentation
+    key: &'static str,
     buf: String,
     errmsg: Option<String>,
     p: P,
@@ -199,6 +1080,7 @@ struct InputState<T: ToString, P: Parser<T>> {
 impl<T: ToString, P: Parser<T>> InputState<T, P> {
     fn new(p: P) -> Self {
         Self {
+            key: "",
             buf: String::default(),
             errmsg: None,
             p,
@@ -224,8 +1106,87 @@ pub struct ConfigInputState {
     theme: InputState<String, NonCheck>,
     inspect_device_interval_ms: InputState<u64, OrderParser<u64>>,
     merge_unassociated_events_ms: InputState<i64, OrderParser<i64>>,
+    switch_after_events: InputState<u32, OrderParser<u32>>,
+    turbo_speed_percent: InputState<u32, OrderParser<u32>>,
+    animate_jumps: InputState<bool, BoolParser>,
+    use_work_area: InputState<bool, BoolParser>,
+    scroll_window_under_cursor: InputState<bool, BoolParser>,
+    battery_query_interval_secs: InputState<u32, OrderParser<u32>>,
+    low_battery_alert_percent: InputState<u32, OrderParser<u32>>,
+    tray_click: InputState<TrayAction, TrayActionParser>,
+    tray_double_click: InputState<TrayAction, TrayActionParser>,
+    tray_middle_click: InputState<TrayAction, TrayActionParser>,
+    tray_icon: InputState<TrayIconStyle, TrayIconStyleParser>,
+    check_updates_weekly: InputState<bool, BoolParser>,
+    allow_view_only: InputState<bool, BoolParser>,
+    update_monitors_interval_ms: InputState<u64, OrderParser<u64>>,
+    poll_max_messages: InputState<u32, OrderParser<u32>>,
+    poll_busy_timeout_ms: InputState<u32, OrderParser<u32>>,
+    poll_idle_timeout_ms: InputState<u32, OrderParser<u32>>,
+    startup_grace_secs: InputState<u32, OrderParser<u32>>,
+    monitor_change_grace_secs: InputState<u32, OrderParser<u32>>,
+    confirm_monitor_changes: InputState<bool, BoolParser>,
+    persist_shortcut_changes: InputState<bool, BoolParser>,
+    restart_on_crash: InputState<bool, BoolParser>,
+    cursor_polling: InputState<bool, BoolParser>,
+    high_resolution_timing: InputState<bool, BoolParser>,
+    thread_priority: InputState<ThreadPriority, ThreadPriorityParser>,
+    mmcss: InputState<bool, BoolParser>,
+    etw_tracing: InputState<bool, BoolParser>,
+    debug_overlay: InputState<bool, BoolParser>,
+    // Shortcuts
     cur_mouse_lock: InputState<String, NonCheck>,
     cur_mouse_jump_next: InputState<String, NonCheck>,
+    cur_mouse_turbo: InputState<String, NonCheck>,
+    cur_mouse_jump_left: InputState<String, NonCheck>,
+    cur_mouse_jump_right: InputState<String, NonCheck>,
+    cur_mouse_jump_up: InputState<String, NonCheck>,
+    cur_mouse_jump_down: InputState<String, NonCheck>,
+    move_window_here: InputState<String, NonCheck>,
+    cur_mouse_focus_mode: InputState<String, NonCheck>,
+    cycle_switch_devices: InputState<String, NonCheck>,
+    // Not validated, so kept as is
+    shortcut_backends: BTreeMap<String, ShortcutBackend>,
+    shortcut_positional_keys: bool,
+    // Where mouse lands after jumping
+    monitor_order: InputState<MonitorOrder, MonitorOrderParser>,
+    jump_next: InputState<JumpTarget, JumpTargetParser>,
+    jump_left: InputState<JumpTarget, JumpTargetParser>,
+    jump_right: InputState<JumpTarget, JumpTargetParser>,
+    jump_up: InputState<JumpTarget, JumpTargetParser>,
+    jump_down: InputState<JumpTarget, JumpTargetParser>,
+    // Hot corners
+    dwell_ms: InputState<u64, OrderParser<u64>>,
+    only_device: InputState<String, NonCheck>,
+    top_left: InputState<TrayAction, TrayActionParser>,
+    top_right: InputState<TrayAction, TrayActionParser>,
+    bottom_left: InputState<TrayAction, TrayActionParser>,
+    bottom_right: InputState<TrayAction, TrayActionParser>,
+    // Focus mode
+    dim_percent: InputState<u32, OrderParser<u32>>,
+    release_after_idle_secs: InputState<u32, OrderParser<u32>>,
+    // Gestures
+    swipe_left: InputState<TrayAction, TrayActionParser>,
+    swipe_right: InputState<TrayAction, TrayActionParser>,
+    corner_double_tap: InputState<TrayAction, TrayActionParser>,
+    // Overrides while programs in foreground
+    // Only toggled here, rules are written in config file
+    app_rules: Vec<AppRule>,
+    // Software KVM
+    pause_when_remote: InputState<bool, BoolParser>,
+    processes: InputState<String, NonCheck>,
+    state_file: InputState<String, NonCheck>,
+    // Sync with other machines
+    listen: InputState<bool, BoolParser>,
+    listen_addr: InputState<String, NonCheck>,
+    port: InputState<u16, OrderParser<u16>>,
+    token: InputState<String, NonCheck>,
+    peers: InputState<String, NonCheck>,
+    // Sounds
+    on_lock: InputState<bool, BoolParser>,
+    on_jump: InputState<bool, BoolParser>,
+    on_switch: InputState<bool, BoolParser>,
+    wav_dir: InputState<String, NonCheck>,
 }
 
 impl ConfigInputState {
@@ -239,18 +1200,99 @@ impl Default for ConfigInputState {
         Self {
             changed: false,
             theme: InputState::new(NonCheck()),
-            inspect_device_interval_ms: InputState::new(OrderParser::new(20, 1000)),
-            merge_unassociated_events_ms: InputState::new(OrderParser::new(-1, 1000)),
+            inspect_device_interval_ms: InputState::new(OrderParser::range(
+                limits::INSPECT_DEVICE_INTERVAL_MS,
+            )),
+            merge_unassociated_events_ms: InputState::new(OrderParser::range(
+                limits::MERGE_UNASSOCIATED_EVENTS_MS,
+            )),
+            switch_after_events: InputState::new(OrderParser::range(limits::SWITCH_AFTER_EVENTS)),
+            turbo_speed_percent: InputState::new(OrderParser::range(limits::TURBO_SPEED_PERCENT)),
+            animate_jumps: InputState::new(BoolParser()),
+            use_work_area: InputState::new(BoolParser()),
+            scroll_window_under_cursor: InputState::new(BoolParser()),
+            battery_query_interval_secs: InputState::new(OrderParser::range(
+                limits::BATTERY_QUERY_INTERVAL_SECS,
+            )),
+            low_battery_alert_percent: InputState::new(OrderParser::range(
+                limits::LOW_BATTERY_ALERT_PERCENT,
+            )),
+            tray_click: InputState::new(TrayActionParser()),
+            tray_double_click: InputState::new(TrayActionParser()),
+            tray_middle_click: InputState::new(TrayActionParser()),
+            tray_icon: InputState::new(TrayIconStyleParser()),
+            check_updates_weekly: InputState::new(BoolParser()),
+            allow_view_only: InputState::new(BoolParser()),
+            update_monitors_interval_ms: InputState::new(OrderParser::range(
+                limits::UPDATE_MONITORS_INTERVAL_MS,
+            )),
+            poll_max_messages: InputState::new(OrderParser::range(limits::POLL_MAX_MESSAGES)),
+            poll_busy_timeout_ms: InputState::new(OrderParser::range(limits::POLL_BUSY_TIMEOUT_MS)),
+            poll_idle_timeout_ms: InputState::new(OrderParser::range(limits::POLL_IDLE_TIMEOUT_MS)),
+            startup_grace_secs: InputState::new(OrderParser::range(limits::STARTUP_GRACE_SECS)),
+            monitor_change_grace_secs: InputState::new(OrderParser::range(
+                limits::MONITOR_CHANGE_GRACE_SECS,
+            )),
+            confirm_monitor_changes: InputState::new(BoolParser()),
+            persist_shortcut_changes: InputState::new(BoolParser()),
+            restart_on_crash: InputState::new(BoolParser()),
+            cursor_polling: InputState::new(BoolParser()),
+            high_resolution_timing: InputState::new(BoolParser()),
+            thread_priority: InputState::new(ThreadPriorityParser()),
+            mmcss: InputState::new(BoolParser()),
+            etw_tracing: InputState::new(BoolParser()),
+            debug_overlay: InputState::new(BoolParser()),
             cur_mouse_lock: InputState::new(NonCheck()),
             cur_mouse_jump_next: InputState::new(NonCheck()),
+            cur_mouse_turbo: InputState::new(NonCheck()),
+            cur_mouse_jump_left: InputState::new(NonCheck()),
+            cur_mouse_jump_right: InputState::new(NonCheck()),
+            cur_mouse_jump_up: InputState::new(NonCheck()),
+            cur_mouse_jump_down: InputState::new(NonCheck()),
+            move_window_here: InputState::new(NonCheck()),
+            cur_mouse_focus_mode: InputState::new(NonCheck()),
+            cycle_switch_devices: InputState::new(NonCheck()),
+            shortcut_backends: BTreeMap::new(),
+            shortcut_positional_keys: true,
+            monitor_order: InputState::new(MonitorOrderParser()),
+            jump_next: InputState::new(JumpTargetParser()),
+            jump_left: InputState::new(JumpTargetParser()),
+            jump_right: InputState::new(JumpTargetParser()),
+            jump_up: InputState::new(JumpTargetParser()),
+            jump_down: InputState::new(JumpTargetParser()),
+            dwell_ms: InputState::new(OrderParser::range(limits::HOT_CORNER_DWELL_MS)),
+            only_device: InputState::new(NonCheck()),
+            top_left: InputState::new(TrayActionParser()),
+            top_right: InputState::new(TrayActionParser()),
+            bottom_left: InputState::new(TrayActionParser()),
+            bottom_right: InputState::new(TrayActionParser()),
+            dim_percent: InputState::new(OrderParser::range(limits::FOCUS_DIM_PERCENT)),
+            release_after_idle_secs: InputState::new(OrderParser::range(limits::IDLE_RELEASE_SECS)),
+            swipe_left: InputState::new(TrayActionParser()),
+            swipe_right: InputState::new(TrayActionParser()),
+            corner_double_tap: InputState::new(TrayActionParser()),
+            app_rules: Vec::new(),
+            pause_when_remote: InputState::new(BoolParser()),
+            processes: InputState::new(NonCheck()),
+            state_file: InputState::new(NonCheck()),
+            listen: InputState::new(BoolParser()),
+            listen_addr: InputState::new(NonCheck()),
+            port: InputState::new(OrderParser::range(limits::SYNC_PORT)),
+            token: InputState::new(NonCheck()),
+            peers: InputState::new(NonCheck()),
+            on_lock: InputState::new(BoolParser()),
+            on_jump: InputState::new(BoolParser()),
+            on_switch: InputState::new(BoolParser()),
+            wav_dir: InputState::new(NonCheck()),
         }
     }
 }
 
 macro_rules! set_from {
-    ($dst: expr, $src: expr, $field: ident) => {
-        $dst.$field.set(&$src.$field)
-    };
+    ($dst: expr, $s: ident . $($path: ident).+, $field: ident) => {{
+        $dst.$field.key = concat!($(stringify!($path), ".",)+ stringify!($field));
+        $dst.$field.set(&$s.$($path).+.$field)
+    }};
 }
 macro_rules! parse_into {
     ($dst: expr, $src: expr, $field: ident) => {
@@ -262,16 +1304,152 @@ impl ConfigInputState {
         set_from!(self, s.ui, theme);
         set_from!(self, s.ui, inspect_device_interval_ms);
         set_from!(self, s.processor, merge_unassociated_events_ms);
+        set_from!(self, s.processor, switch_after_events);
+        set_from!(self, s.processor, turbo_speed_percent);
+        set_from!(self, s.ui, animate_jumps);
+        set_from!(self, s.processor, use_work_area);
+        set_from!(self, s.processor, scroll_window_under_cursor);
+        set_from!(self, s.processor, battery_query_interval_secs);
+        set_from!(self, s.ui, low_battery_alert_percent);
+        set_from!(self, s.ui, tray_click);
+        set_from!(self, s.ui, tray_double_click);
+        set_from!(self, s.ui, tray_middle_click);
+        set_from!(self, s.ui, tray_icon);
+        set_from!(self, s.ui, check_updates_weekly);
+        set_from!(self, s.ui, allow_view_only);
+        set_from!(self, s.processor, update_monitors_interval_ms);
+        set_from!(self, s.processor, poll_max_messages);
+        set_from!(self, s.processor, poll_busy_timeout_ms);
+        set_from!(self, s.processor, poll_idle_timeout_ms);
+        set_from!(self, s.processor, startup_grace_secs);
+        set_from!(self, s.processor, monitor_change_grace_secs);
+        set_from!(self, s.processor, confirm_monitor_changes);
+        set_from!(self, s.processor, persist_shortcut_changes);
+        set_from!(self, s.processor, restart_on_crash);
+        set_from!(self, s.processor, cursor_polling);
+        set_from!(self, s.processor, high_resolution_timing);
+        set_from!(self, s.processor, thread_priority);
+        set_from!(self, s.processor, mmcss);
+        set_from!(self, s.processor, etw_tracing);
+        set_from!(self, s.processor, debug_overlay);
         set_from!(self, s.processor.shortcuts, cur_mouse_lock);
         set_from!(self, s.processor.shortcuts, cur_mouse_jump_next);
+        set_from!(self, s.processor.shortcuts, cur_mouse_turbo);
+        set_from!(self, s.processor.shortcuts, cur_mouse_jump_left);
+        set_from!(self, s.processor.shortcuts, cur_mouse_jump_right);
+        set_from!(self, s.processor.shortcuts, cur_mouse_jump_up);
+        set_from!(self, s.processor.shortcuts, cur_mouse_jump_down);
+        set_from!(self, s.processor.shortcuts, move_window_here);
+        set_from!(self, s.processor.shortcuts, cur_mouse_focus_mode);
+        set_from!(self, s.processor.shortcuts, cycle_switch_devices);
+        self.shortcut_backends = s.processor.shortcuts.backends.clone();
+        self.shortcut_positional_keys = s.processor.shortcuts.positional_keys;
+        set_from!(self, s.processor, monitor_order);
+        set_from!(self, s.processor.jump_targets, jump_next);
+        set_from!(self, s.processor.jump_targets, jump_left);
+        set_from!(self, s.processor.jump_targets, jump_right);
+        set_from!(self, s.processor.jump_targets, jump_up);
+        set_from!(self, s.processor.jump_targets, jump_down);
+        set_from!(self, s.processor.hot_corners, dwell_ms);
+        set_from!(self, s.processor.hot_corners, only_device);
+        set_from!(self, s.processor.hot_corners, top_left);
+        set_from!(self, s.processor.hot_corners, top_right);
+        set_from!(self, s.processor.hot_corners, bottom_left);
+        set_from!(self, s.processor.hot_corners, bottom_right);
+        set_from!(self, s.processor.focus_mode, dim_percent);
+        set_from!(self, s.processor.focus_mode, release_after_idle_secs);
+        set_from!(self, s.processor.gestures, swipe_left);
+        set_from!(self, s.processor.gestures, swipe_right);
+        set_from!(self, s.processor.gestures, corner_double_tap);
+        self.app_rules = s.processor.app_rules.clone();
+        set_from!(self, s.processor.kvm, pause_when_remote);
+        set_from!(self, s.processor.kvm, processes);
+        set_from!(self, s.processor.kvm, state_file);
+        set_from!(self, s.ui.sync, listen);
+        set_from!(self, s.ui.sync, listen_addr);
+        set_from!(self, s.ui.sync, port);
+        set_from!(self, s.ui.sync, token);
+        set_from!(self, s.ui.sync, peers);
+        set_from!(self, s.ui.sounds, on_lock);
+        set_from!(self, s.ui.sounds, on_jump);
+        set_from!(self, s.ui.sounds, on_switch);
+        set_from!(self, s.ui.sounds, wav_dir);
     }
 
     pub fn parse_all(&mut self, s: &mut Settings) -> Result<(), String> {
         parse_into!(self, s.ui, theme);
         parse_into!(self, s.ui, inspect_device_interval_ms);
         parse_into!(self, s.processor, merge_unassociated_events_ms);
+        parse_into!(self, s.processor, switch_after_events);
+        parse_into!(self, s.processor, turbo_speed_percent);
+        parse_into!(self, s.ui, animate_jumps);
+        parse_into!(self, s.processor, use_work_area);
+        parse_into!(self, s.processor, scroll_window_under_cursor);
+        parse_into!(self, s.processor, battery_query_interval_secs);
+        parse_into!(self, s.ui, low_battery_alert_percent);
+        parse_into!(self, s.ui, tray_click);
+        parse_into!(self, s.ui, tray_double_click);
+        parse_into!(self, s.ui, tray_middle_click);
+        parse_into!(self, s.ui, tray_icon);
+        parse_into!(self, s.ui, check_updates_weekly);
+        parse_into!(self, s.ui, allow_view_only);
+        parse_into!(self, s.processor, update_monitors_interval_ms);
+        parse_into!(self, s.processor, poll_max_messages);
+        parse_into!(self, s.processor, poll_busy_timeout_ms);
+        parse_into!(self, s.processor, poll_idle_timeout_ms);
+        parse_into!(self, s.processor, startup_grace_secs);
+        parse_into!(self, s.processor, monitor_change_grace_secs);
+        parse_into!(self, s.processor, confirm_monitor_changes);
+        parse_into!(self, s.processor, persist_shortcut_changes);
+        parse_into!(self, s.processor, restart_on_crash);
+        parse_into!(self, s.processor, cursor_polling);
+        parse_into!(self, s.processor, high_resolution_timing);
+        parse_into!(self, s.processor, thread_priority);
+        parse_into!(self, s.processor, mmcss);
+        parse_into!(self, s.processor, etw_tracing);
+        parse_into!(self, s.processor, debug_overlay);
         parse_into!(self, s.processor.shortcuts, cur_mouse_lock);
         parse_into!(self, s.processor.shortcuts, cur_mouse_jump_next);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_turbo);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_jump_left);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_jump_right);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_jump_up);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_jump_down);
+        parse_into!(self, s.processor.shortcuts, move_window_here);
+        parse_into!(self, s.processor.shortcuts, cur_mouse_focus_mode);
+        parse_into!(self, s.processor.shortcuts, cycle_switch_devices);
+        s.processor.shortcuts.backends = self.shortcut_backends.clone();
+        s.processor.shortcuts.positional_keys = self.shortcut_positional_keys;
+        parse_into!(self, s.processor, monitor_order);
+        parse_into!(self, s.processor.jump_targets, jump_next);
+        parse_into!(self, s.processor.jump_targets, jump_left);
+        parse_into!(self, s.processor.jump_targets, jump_right);
+        parse_into!(self, s.processor.jump_targets, jump_up);
+        parse_into!(self, s.processor.jump_targets, jump_down);
+        parse_into!(self, s.processor.hot_corners, dwell_ms);
+        parse_into!(self, s.processor.hot_corners, only_device);
+        parse_into!(self, s.processor.hot_corners, top_left);
+        parse_into!(self, s.processor.hot_corners, top_right);
+        parse_into!(self, s.processor.hot_corners, bottom_left);
+        parse_into!(self, s.processor.hot_corners, bottom_right);
+        parse_into!(self, s.processor.focus_mode, dim_percent);
+        parse_into!(self, s.processor.focus_mode, release_after_idle_secs);
+        parse_into!(self, s.processor.gestures, swipe_left);
+        parse_into!(self, s.processor.gestures, swipe_right);
+        parse_into!(self, s.processor.gestures, corner_double_tap);
+        s.processor.app_rules = self.app_rules.clone();
+        parse_into!(self, s.processor.kvm, pause_when_remote);
+        parse_into!(self, s.processor.kvm, processes);
+        parse_into!(self, s.processor.kvm, state_file);
+        parse_into!(self, s.ui.sync, listen);
+        parse_into!(self, s.ui.sync, listen_addr);
+        parse_into!(self, s.ui.sync, port);
+        parse_into!(self, s.ui.sync, token);
+        parse_into!(self, s.ui.sync, peers);
+        parse_into!(self, s.ui.sounds, on_lock);
+        parse_into!(self, s.ui.sounds, on_jump);
+        parse_into!(self, s.ui.sounds, on_switch);
+        parse_into!(self, s.ui.sounds, wav_dir);
         Ok(())
     }
 }
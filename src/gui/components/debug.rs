@@ -5,6 +5,10 @@ pub struct DebugInfo {
     paint_times: u64,
     last_paint: u64,
     cur_paint: u64,
+
+    wakeups_sample_tick: u64,
+    wakeups_sample: u64,
+    wakeups_per_sec: u64,
 }
 
 impl DebugInfo {
@@ -12,10 +16,23 @@ impl DebugInfo {
         self.paint_times += 1;
         self.last_paint = self.cur_paint;
         self.cur_paint = tick;
+
+        // Sample eventloop wakeups about every second
+        let elapsed = tick - self.wakeups_sample_tick;
+        if elapsed >= 1000 {
+            let wakeups = monmouse::eventloop_wakeups();
+            self.wakeups_per_sec = (wakeups - self.wakeups_sample) * 1000 / elapsed;
+            self.wakeups_sample = wakeups;
+            self.wakeups_sample_tick = tick;
+        }
     }
 
     pub fn ui(&self, ui: &mut egui::Ui) {
         ui.label(format!("Painted: {}", self.paint_times));
         ui.label(format!("PaintCost: {}", self.cur_paint - self.last_paint));
+        ui.label(format!("Wakeups/s: {}", self.wakeups_per_sec));
+        ui.label(format!("Offscreen: {}", monmouse::offscreen_relocations()));
+        ui.label(format!("Debounced: {}", monmouse::debounced_clicks()));
+        ui.label(format!("LateInputs: {}", monmouse::late_input_events().0));
     }
 }
@@ -1,26 +1,34 @@
+use std::time::{Duration, Instant};
+
 use eframe::egui;
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 use monmouse::{
+    inventory::InventoryFormat,
     message::{DeviceStatus, GenericDevice, Positioning},
-    setting::DeviceSettingItem,
+    setting::{
+        limits, ActivationGrace, Calibration, CursorPark, CursorStyle, DeviceSettingItem,
+        ForcePositioning, ParkPosition, PosTransform, Rotation, SwitchMode,
+    },
+    trace::TRACE_DEFAULT_SECS,
 };
 
 use crate::{
-    app::DeviceUIState,
-    components::widget::{device_status_color, indicator_ui, manage_button, toggle_ui},
+    app::{CalibrationWizard, DeviceUIState},
+    components::{
+        device_status_color, indicator_ui, manage_button, toggle_ui, CommonPopup, EatInputBuffer,
+    },
     App,
 };
 
-use super::widget::{CommonPopup, EatInputBuffer};
-
 pub struct DevicesPanel {}
 
 impl DevicesPanel {
     const MIN_DEVICES_ROW: usize = 15;
 
-    fn active_str(status: &DeviceStatus) -> &str {
+    fn active_str(status: &DeviceStatus, scanning: bool) -> &str {
         match status {
-            DeviceStatus::Active(positioning) => match positioning {
+            DeviceStatus::Disconnected if scanning => "Scanning",
+            DeviceStatus::Active { positioning, .. } => match positioning {
                 Positioning::Unknown => "Active",
                 Positioning::Relative => "Relative",
                 Positioning::Absolute => "Absolute",
@@ -45,26 +53,387 @@ impl DevicesPanel {
         st
     }
 
+    fn switch_mode_ui(
+        ui: &mut egui::Ui,
+        i: usize,
+        mode: &mut SwitchMode,
+        name: &str,
+    ) -> egui::Response {
+        let mut changed = false;
+        let mut response = egui::ComboBox::from_id_source(format!("SwitchModeIdx{}", i))
+            .selected_text(mode.to_string())
+            .show_ui(ui, |ui| {
+                for m in SwitchMode::VARIANTS {
+                    changed |= ui.selectable_value(mode, m, m.to_string()).changed();
+                }
+            })
+            .response;
+        response.widget_info(|| {
+            egui::WidgetInfo::labeled(
+                egui::WidgetType::ComboBox,
+                format!("Switch mode of {}", name),
+            )
+        });
+        if changed {
+            response.mark_changed();
+        }
+        response
+    }
+
+    fn positioning_str(p: &Positioning) -> &str {
+        match p {
+            Positioning::Unknown => "unknown",
+            Positioning::Relative => "relative",
+            Positioning::Absolute => "absolute",
+        }
+    }
+
+    fn force_positioning_ui(ui: &mut egui::Ui, i: usize, force: &mut ForcePositioning) -> bool {
+        let mut changed = false;
+        egui::ComboBox::from_id_source(format!("ForcePositioningIdx{}", i))
+            .selected_text(force.to_string())
+            .show_ui(ui, |ui| {
+                for f in ForcePositioning::VARIANTS {
+                    changed |= ui.selectable_value(force, f, f.to_string()).changed();
+                }
+            })
+            .response
+            .on_hover_text("Override positioning classified from events of this device");
+        changed
+    }
+
+    fn cursor_style_ui(ui: &mut egui::Ui, i: usize, style: &mut Option<CursorStyle>) -> bool {
+        let text = |s: &Option<CursorStyle>| match s {
+            Some(v) => v.to_string(),
+            None => "system".to_owned(),
+        };
+        let mut changed = false;
+        egui::ComboBox::from_id_source(format!("CursorStyleIdx{}", i))
+            .selected_text(text(style))
+            .show_ui(ui, |ui| {
+                let choices = std::iter::once(None).chain(CursorStyle::VARIANTS.map(Some));
+                for s in choices {
+                    changed |= ui.selectable_value(style, s, text(&s)).changed();
+                }
+            })
+            .response
+            .on_hover_text("Cursor shown while this device is active");
+        changed
+    }
+
+    fn park_ui(ui: &mut egui::Ui, i: usize, park: &mut Option<CursorPark>) -> bool {
+        let text = |p: &Option<ParkPosition>| match p {
+            Some(v) => v.to_string(),
+            None => "never".to_owned(),
+        };
+        let mut at = park.map(|p| p.at);
+        let mut changed = false;
+        egui::ComboBox::from_id_source(format!("CursorParkIdx{}", i))
+            .selected_text(text(&at))
+            .show_ui(ui, |ui| {
+                let choices = std::iter::once(None).chain(ParkPosition::VARIANTS.map(Some));
+                for p in choices {
+                    changed |= ui.selectable_value(&mut at, p, text(&p)).changed();
+                }
+            })
+            .response
+            .on_hover_text("Where cursor is moved after this device is idle");
+        if changed {
+            *park = at.map(|at| CursorPark {
+                at,
+                ..park.unwrap_or_default()
+            });
+        }
+        if let Some(park) = park {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut park.after_idle_secs)
+                        .clamp_range(limits::PARK_AFTER_IDLE_SECS)
+                        .suffix("s"),
+                )
+                .on_hover_text("Idle seconds before parking")
+                .changed();
+        }
+        changed
+    }
+
+    fn activation_grace_ui(ui: &mut egui::Ui, grace: &mut Option<ActivationGrace>) -> bool {
+        let mut enabled = grace.is_some();
+        let mut changed = ui
+            .checkbox(&mut enabled, "")
+            .on_hover_text(
+                "Become active only after enough events within the time, instead of the \
+                 global switch after events, so that nudging it doesn't take cursor away",
+            )
+            .changed();
+        if changed {
+            *grace = enabled.then(ActivationGrace::default);
+        }
+        if let Some(grace) = grace {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut grace.events)
+                        .clamp_range(limits::ACTIVATION_EVENTS)
+                        .suffix(" events"),
+                )
+                .changed();
+            ui.label("within");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut grace.within_ms)
+                        .clamp_range(limits::ACTIVATION_WITHIN_MS)
+                        .suffix("ms"),
+                )
+                .changed();
+        }
+        changed
+    }
+
+    fn transform_ui(ui: &mut egui::Ui, i: usize, transform: &mut PosTransform) -> bool {
+        let mut changed = false;
+        egui::ComboBox::from_id_source(format!("RotationIdx{}", i))
+            .selected_text(transform.rotate.to_string())
+            .show_ui(ui, |ui| {
+                for r in Rotation::VARIANTS {
+                    changed |= ui
+                        .selectable_value(&mut transform.rotate, r, r.to_string())
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text("Rotate positions clockwise within the monitor, for absolute devices");
+        changed |= ui.checkbox(&mut transform.mirror_x, "Mirror X").changed();
+        changed |= ui.checkbox(&mut transform.mirror_y, "Mirror Y").changed();
+        changed
+    }
+
+    fn calibration_target_str(monitor: u32, monitor_labels: &[String]) -> String {
+        match monitor {
+            0 => "Monitor under it".to_owned(),
+            n => match monitor_labels.get(n as usize - 1) {
+                Some(label) => label.clone(),
+                None => format!("Monitor {}", n),
+            },
+        }
+    }
+
+    fn calibration_target_ui(
+        ui: &mut egui::Ui,
+        i: usize,
+        c: &mut Calibration,
+        monitor_labels: &[String],
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Map onto");
+            egui::ComboBox::from_id_source(format!("CalibrationMonitorIdx{}", i))
+                .selected_text(Self::calibration_target_str(c.monitor, monitor_labels))
+                .show_ui(ui, |ui| {
+                    for n in 0..=monitor_labels.len() as u32 {
+                        let text = Self::calibration_target_str(n, monitor_labels);
+                        ui.selectable_value(&mut c.monitor, n, text);
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Margins");
+            for (v, side) in c.margins.iter_mut().zip(["Left", "Top", "Right", "Bottom"]) {
+                ui.add(egui::DragValue::new(v).clamp_range(0..=1000).suffix("px"))
+                    .on_hover_text(format!("{} pixels left out of the monitor", side));
+            }
+        });
+    }
+
+    // Sweeping the device over its surface first, then choosing where it maps onto.
+    // Returns (changed, start), start if capturing its extent is to be started.
+    fn calibration_ui(
+        ui: &mut egui::Ui,
+        i: usize,
+        device: &mut DeviceUIState,
+        wizard: &mut Option<CalibrationWizard>,
+        monitor_labels: &[String],
+    ) -> (bool, bool) {
+        let mut changed = false;
+        let mut start = false;
+        let id = &device.generic.id;
+        match wizard.as_mut().filter(|w| w.id() == id) {
+            Some(CalibrationWizard::Capturing { .. }) => {
+                ui.horizontal(|ui| {
+                    ui.label("Calibrate");
+                    ui.spinner();
+                    ui.label("Sweep it over its whole surface...");
+                    if ui.small_button("Cancel").clicked() {
+                        *wizard = None;
+                    }
+                });
+            }
+            Some(CalibrationWizard::Captured { calibration, .. }) => {
+                ui.horizontal(|ui| {
+                    ui.label("Calibrate");
+                    ui.label(format!("Reached {}", calibration.extent()));
+                });
+                Self::calibration_target_ui(ui, i, calibration, monitor_labels);
+                let calibration = *calibration;
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        device.device_setting.calibration = Some(calibration);
+                        changed = true;
+                        *wizard = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        *wizard = None;
+                    }
+                });
+            }
+            None => {
+                let absolute = matches!(device.last_positioning, Some((Positioning::Absolute, _)));
+                ui.horizontal(|ui| {
+                    ui.label("Calibrate");
+                    match &device.device_setting.calibration {
+                        Some(c) => ui.label(format!(
+                            "{} onto {}",
+                            c.extent(),
+                            Self::calibration_target_str(c.monitor, monitor_labels)
+                        )),
+                        None => ui.label("None"),
+                    };
+                    start = ui
+                        .add_enabled(absolute && wizard.is_none(), egui::Button::new("Start"))
+                        .on_hover_text(
+                            "Map the extent an absolute device reaches onto a monitor, edge to \
+                             edge. Use the device once to enable",
+                        )
+                        .clicked();
+                    if device.device_setting.calibration.is_some() && ui.button("Clear").clicked() {
+                        device.device_setting.calibration = None;
+                        changed = true;
+                    }
+                });
+            }
+        }
+        (changed, start)
+    }
+
+    // Name read by screen readers
+    fn accessible_name(d: &GenericDevice) -> &str {
+        if d.product_name.is_empty() {
+            &d.id
+        } else {
+            &d.product_name
+        }
+    }
+
+    // Lock can be tried for a while when unlocked, returns true if clicked
+    fn lock_test_ui(ui: &mut egui::Ui, testing_until: &mut Option<Instant>, locked: bool) -> bool {
+        let left = testing_until.and_then(|t| t.checked_duration_since(Instant::now()));
+        if let Some(left) = left {
+            ui.label(format!("{}s", left.as_secs() + 1))
+                .on_hover_text("Locked for trying, restored then");
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+            return false;
+        }
+        *testing_until = None;
+        if locked {
+            return false;
+        }
+        ui.small_button("Test")
+            .on_hover_text(format!(
+                "Lock it for {}s to try, without changing settings",
+                App::DEVICE_TEST_SECS
+            ))
+            .clicked()
+    }
+
+    // Returns (changed, focused, test, calibrate, ignore), focused if any control of the row
+    // has keyboard focus, test if locking is to be tried, calibrate if calibration is started,
+    // ignore if the device is to be ignored
     fn device_line_ui(
         i: usize,
         row: &mut egui_extras::TableRow,
         device: &mut DeviceUIState,
-    ) -> bool {
+        request_focus: bool,
+        scanning: bool,
+        wizard: &mut Option<CalibrationWizard>,
+        monitor_labels: &[String],
+    ) -> (bool, bool, bool, bool, bool) {
         let d = &device.generic;
+        let name = Self::accessible_name(d).to_owned();
         let mut changed = false;
+        let mut focused = false;
+        let mut test = false;
+        let mut calibrate = false;
+        let mut ignore = false;
         row.col(|ui| {
-            indicator_ui(ui, device_status_color(ui, &device.status));
-            ui.label(Self::active_str(&device.status));
+            let rsp = ui.checkbox(&mut device.selected, "");
+            rsp.widget_info(|| {
+                egui::WidgetInfo::selected(
+                    egui::WidgetType::Checkbox,
+                    device.selected,
+                    format!("Select {}", name),
+                )
+            });
+            if request_focus {
+                rsp.request_focus();
+                rsp.scroll_to_me(None);
+            }
+            focused |= rsp.has_focus();
         });
         row.col(|ui| {
-            if toggle_ui(ui, &mut device.device_setting.switch, "switch").changed() {
-                changed = true;
+            let color = match device.status {
+                DeviceStatus::Disconnected if scanning => {
+                    device_status_color(ui, &DeviceStatus::Unknown)
+                }
+                _ => device_status_color(ui, &device.status),
+            };
+            indicator_ui(ui, color);
+            if device.identified {
+                let v = &ui.style().visuals;
+                let text = egui::RichText::new("Identified")
+                    .strong()
+                    .color(v.strong_text_color())
+                    .background_color(v.selection.bg_fill);
+                ui.label(text);
+            } else {
+                ui.label(Self::active_str(&device.status, scanning));
             }
         });
         row.col(|ui| {
-            if toggle_ui(ui, &mut device.device_setting.locked_in_monitor, "locked").changed() {
-                changed = true;
-            }
+            let rsp = Self::switch_mode_ui(ui, i, &mut device.device_setting.switch, &name);
+            changed |= rsp.changed();
+            focused |= rsp.has_focus();
+        });
+        row.col(|ui| {
+            let rsp = toggle_ui(
+                ui,
+                &mut device.device_setting.locked_in_monitor,
+                format!("Lock {} in monitor", name),
+            );
+            changed |= rsp.changed();
+            focused |= rsp.has_focus();
+            let locked = device.device_setting.locked_in_monitor;
+            test = Self::lock_test_ui(ui, &mut device.testing_until, locked);
+        });
+        row.col(|ui| {
+            let locked = device.device_setting.locked_in_monitor;
+            let rsp = ui
+                .add_enabled(
+                    locked,
+                    egui::DragValue::new(&mut device.device_setting.idle_release_secs)
+                        .clamp_range(limits::IDLE_RELEASE_SECS)
+                        .suffix("s"),
+                )
+                .on_hover_text("Release the lock after idle for such seconds, 0 for never");
+            rsp.widget_info(|| egui::WidgetInfo {
+                label: Some(format!("Idle release seconds of {}", name)),
+                ..egui::WidgetInfo::drag_value(device.device_setting.idle_release_secs as f64)
+            });
+            changed |= rsp.changed();
+            focused |= rsp.has_focus();
+        });
+        row.col(|ui| {
+            match device.battery {
+                Some(level) => ui.label(format!("{}%", level)),
+                None => ui.label("-"),
+            };
         });
         row.col(|ui| {
             ui.label(device.generic.device_type.to_string());
@@ -86,6 +455,77 @@ impl DevicesPanel {
                         ui.output_mut(|o| o.copied_text = details_text.clone());
                     }
                 });
+                if device.generic.is_orphan() {
+                    ui.horizontal(|ui| {
+                        ui.label("Sends events, but is not listed by Windows");
+                        ignore = ui
+                            .button("Ignore")
+                            .on_hover_text("Drop its events, by adding it to ignored devices")
+                            .clicked();
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Positioning");
+                    changed |= Self::force_positioning_ui(
+                        ui,
+                        i,
+                        &mut device.device_setting.force_positioning,
+                    );
+                    if let Some((effective, raw)) = &device.last_positioning {
+                        ui.label(format!(
+                            "{} (reported {})",
+                            Self::positioning_str(effective),
+                            Self::positioning_str(raw)
+                        ));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Rotate");
+                    changed |= Self::transform_ui(ui, i, &mut device.device_setting.transform);
+                });
+                let (calibrated, start) =
+                    Self::calibration_ui(ui, i, device, wizard, monitor_labels);
+                changed |= calibrated;
+                calibrate |= start;
+                ui.horizontal(|ui| {
+                    ui.label("Cursor style");
+                    changed |=
+                        Self::cursor_style_ui(ui, i, &mut device.device_setting.cursor_style);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Park cursor when idle");
+                    changed |= Self::park_ui(ui, i, &mut device.device_setting.park);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Activation grace");
+                    changed |=
+                        Self::activation_grace_ui(ui, &mut device.device_setting.activation_grace);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Debounce clicks");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut device.device_setting.debounce_click_ms)
+                                .clamp_range(limits::DEBOUNCE_CLICK_MS)
+                                .suffix("ms"),
+                        )
+                        .on_hover_text(
+                            "Drop a press coming within such milliseconds after releasing the \
+                             same button, for mice clicking twice. 0 to disable",
+                        )
+                        .changed();
+                });
+                ui.label("Notes");
+                if ui
+                    .add(
+                        egui::TextEdit::multiline(&mut device.notes)
+                            .desired_rows(2)
+                            .desired_width(f32::INFINITY),
+                    )
+                    .lost_focus()
+                {
+                    changed = true;
+                }
                 ui.add(
                     egui::TextEdit::multiline(&mut EatInputBuffer::from(&details_text))
                         .clip_text(false)
@@ -95,7 +535,29 @@ impl DevicesPanel {
             });
             ui.add_space(10.0);
         });
-        changed
+        (changed, focused, test, calibrate, ignore)
+    }
+
+    // Arrow keys move keyboard focus to the previous/next row, then Tab walks through
+    // controls of the row, and Space toggles them.
+    fn row_focus_to(ui: &mut egui::Ui, app: &App) -> Option<usize> {
+        let len = app.state.managed_devices.len();
+        // Leave arrows to the text being edited
+        if len == 0 || ui.ctx().wants_keyboard_input() {
+            return None;
+        }
+        let (up, down) = ui.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+            )
+        });
+        match (app.focused_device_row, up, down) {
+            (Some(row), true, false) => Some(row.saturating_sub(1)),
+            (Some(row), false, true) => Some((row + 1).min(len - 1)),
+            (None, false, true) => Some(0),
+            _ => None,
+        }
     }
 
     fn table_ui(ui: &mut egui::Ui, app: &mut App) {
@@ -104,12 +566,14 @@ impl DevicesPanel {
             .drag_to_scroll(true)
             .auto_shrink(false)
             .cell_layout(egui::Layout::left_to_right(egui::Align::LEFT))
+            .column(Column::exact(30.0))
             .column(Column::exact(100.0))
-            .columns(Column::auto(), 3)
+            .columns(Column::auto(), 5)
             .column(Column::remainder());
 
         table
             .header(20.0, |mut header| {
+                header.col(|_| {});
                 header.col(|ui| {
                     ui.strong("Activity");
                 });
@@ -119,6 +583,12 @@ impl DevicesPanel {
                 header.col(|ui| {
                     ui.strong("Locked");
                 });
+                header.col(|ui| {
+                    ui.strong("Idle release");
+                });
+                header.col(|ui| {
+                    ui.strong("Battery");
+                });
                 header.col(|ui| {
                     ui.strong("Type");
                 });
@@ -128,6 +598,12 @@ impl DevicesPanel {
             })
             .body(|mut body| {
                 let row_height = 20.0;
+                let focus_to = Self::row_focus_to(body.ui_mut(), app);
+                let scanning = app.initial_scan;
+                let mut focused_row = None;
+                let mut tests = Vec::new();
+                let mut calibrate = None;
+                let mut ignore = None;
                 let new_settings: Vec<DeviceSettingItem> = app
                     .state
                     .managed_devices
@@ -136,7 +612,28 @@ impl DevicesPanel {
                     .filter_map(|(i, device)| {
                         let mut changed = false;
                         body.row(row_height, |mut row| {
-                            changed = Self::device_line_ui(i, &mut row, device);
+                            let (focused, test, start, ignored);
+                            (changed, focused, test, start, ignored) = Self::device_line_ui(
+                                i,
+                                &mut row,
+                                device,
+                                focus_to == Some(i),
+                                scanning,
+                                &mut app.calibration,
+                                &app.monitor_labels,
+                            );
+                            if start {
+                                calibrate = Some(device.generic.id.clone());
+                            }
+                            if ignored {
+                                ignore = Some(device.generic.id.clone());
+                            }
+                            if focused {
+                                focused_row = Some(i);
+                            }
+                            if test {
+                                tests.push(device.clone_setting());
+                            }
                         });
                         if changed {
                             Some(device.clone_setting())
@@ -145,14 +642,30 @@ impl DevicesPanel {
                         }
                     })
                     .collect();
+                app.focused_device_row = focused_row;
+                if !new_settings.is_empty() {
+                    // To flush them even if nothing else repaints
+                    body.ui_mut()
+                        .ctx()
+                        .request_repaint_after(App::DEVICE_SETTINGS_DEBOUNCE);
+                }
                 for item in new_settings {
                     app.trigger_one_device_setting_changed(item);
                 }
+                for item in tests {
+                    app.trigger_device_test(item);
+                }
+                if let Some(id) = calibrate {
+                    app.trigger_calibrate_device(id);
+                }
+                if let Some(id) = ignore {
+                    app.ignore_device(&id);
+                }
 
                 let len = app.state.managed_devices.len() as isize;
                 for _ in 0..(Self::MIN_DEVICES_ROW as isize - len) {
                     body.row(20.0, |mut row| {
-                        for _ in 0..5 {
+                        for _ in 0..8 {
                             row.col(|_| {});
                         }
                     });
@@ -160,15 +673,117 @@ impl DevicesPanel {
             });
     }
 
+    fn batch_toolbar_ui(ui: &mut egui::Ui, app: &mut App) {
+        if ui.button("Select all").clicked() {
+            app.select_all_devices(true);
+        }
+        if ui.button("Select none").clicked() {
+            app.select_all_devices(false);
+        }
+        let selected = app.selected_devices_num();
+        ui.label(format!("{} selected", selected));
+        ui.separator();
+        ui.add_enabled_ui(selected > 0, |ui| {
+            if ui.button("Switch on").clicked() {
+                app.batch_update_selected_devices(|s| s.switch = SwitchMode::PerDevicePosition);
+            }
+            if ui.button("Switch off").clicked() {
+                app.batch_update_selected_devices(|s| s.switch = SwitchMode::Shared);
+            }
+            if ui.button("Lock on").clicked() {
+                app.batch_update_selected_devices(|s| s.locked_in_monitor = true);
+            }
+            if ui.button("Lock off").clicked() {
+                app.batch_update_selected_devices(|s| s.locked_in_monitor = false);
+            }
+        });
+    }
+
+    // Developer only, to demo devices without the hardware
+    #[cfg(debug_assertions)]
+    fn simulate_toolbar_ui(ui: &mut egui::Ui, app: &mut App) {
+        use monmouse::device_type::DeviceType;
+        use monmouse::simulate::{SimulatedDeviceSpec, SimulatedScript};
+
+        ui.label("Simulate(Debug):");
+        if ui.button("Mouse tapping").clicked() {
+            app.add_simulated_device(SimulatedDeviceSpec {
+                device_type: DeviceType::Mouse,
+                positioning: Positioning::Relative,
+                script: SimulatedScript::Tap { interval_ms: 3000 },
+            });
+        }
+        if ui.button("Touchscreen circling").clicked() {
+            app.add_simulated_device(SimulatedDeviceSpec {
+                device_type: DeviceType::TouchScreen,
+                positioning: Positioning::Absolute,
+                script: SimulatedScript::Circle {
+                    radius: 200,
+                    period_ms: 4000,
+                },
+            });
+        }
+        if ui.button("Clear").clicked() {
+            app.clear_simulated_devices();
+        }
+    }
+
     pub fn ui(ui: &mut egui::Ui, app: &mut App) {
         ui.horizontal(|ui| {
             if ui.add(manage_button("Scan")).clicked() {
                 app.trigger_scan_devices();
             }
-            if ui.add(manage_button("Save")).clicked() {
+            if ui
+                .add(manage_button("Refresh monitors"))
+                .on_hover_text("Detect monitors layout again")
+                .clicked()
+            {
+                app.trigger_refresh_monitors();
+            }
+            if ui
+                .add_enabled(!app.safe_mode, manage_button("Save"))
+                .on_disabled_hover_text("Not saved in safe mode")
+                .clicked()
+            {
                 app.save_devices_config();
             }
+            if ui
+                .add_enabled(!app.identifying, manage_button("Identify"))
+                .on_hover_text("Find the row of device which emits the next event")
+                .clicked()
+            {
+                app.trigger_identify_device();
+            }
+            if ui
+                .add_enabled(!app.capturing_trace, manage_button("Capture trace"))
+                .on_hover_text(format!(
+                    "Record raw events of the next {}s into a file, to attach to bug reports",
+                    TRACE_DEFAULT_SECS
+                ))
+                .clicked()
+            {
+                app.trigger_capture_trace();
+            }
+            ui.separator();
+            egui::ComboBox::from_id_source("InventoryFormatChooser")
+                .selected_text(app.inventory_format.to_string())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for f in InventoryFormat::VARIANTS {
+                        ui.selectable_value(&mut app.inventory_format, f, f.to_string());
+                    }
+                });
+            if ui
+                .add(manage_button("Export"))
+                .on_hover_text("Write detected devices to monmouse_devices file beside config")
+                .clicked()
+            {
+                app.export_devices();
+            }
         });
+        ui.horizontal(|ui| Self::batch_toolbar_ui(ui, app));
+        #[cfg(debug_assertions)]
+        ui.horizontal(|ui| Self::simulate_toolbar_ui(ui, app));
 
         ui.separator();
         StripBuilder::new(ui)
@@ -1,8 +1,18 @@
 pub mod about_panel;
 pub mod config_panel;
 pub mod devices_panel;
+pub mod presets_panel;
+pub mod stats_panel;
 pub mod status_bar;
-pub mod widget;
+mod widget;
 
 #[cfg(debug_assertions)]
 pub mod debug;
+
+// Widget primitives shared by panels, popups and features built on them, only used through
+// here
+pub use widget::{
+    center_anchor_area, device_status_color, error_color, indicator_ui, manage_button,
+    shortcut_input_ui, text_checkbox, toggle_ui, CommonPopup, CommonPopupResponse, EatInputBuffer,
+    NotificationPopup, PopupAction, PopupResponse, ShortcutChoosePopup, ShortcutInputResponse,
+};
@@ -0,0 +1,104 @@
+use eframe::egui;
+
+use crate::{components::manage_button, App};
+
+pub struct PresetsPanel {}
+
+impl PresetsPanel {
+    fn device_name(app: &App, id: &Option<String>) -> String {
+        let Some(id) = id else {
+            return "None".to_owned();
+        };
+        match app
+            .state
+            .managed_devices
+            .iter()
+            .find(|d| &d.generic.id == id)
+        {
+            Some(d) if !d.generic.product_name.is_empty() => d.generic.product_name.clone(),
+            _ => id.clone(),
+        }
+    }
+
+    fn gallery_ui(ui: &mut egui::Ui, app: &mut App) {
+        let mut start = None;
+        for (i, preset) in app.presets.iter().enumerate() {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(preset.name);
+                    if ui.add(manage_button("Use")).clicked() {
+                        start = Some(i);
+                    }
+                });
+                ui.label(preset.summary);
+                let roles: Vec<&str> = preset.roles.iter().map(|r| r.name).collect();
+                ui.label(format!("Roles: {}", roles.join(", ")));
+                let shortcuts: Vec<&str> = preset.shortcuts.iter().map(|s| s.1).collect();
+                ui.label(format!("Shortcuts: {}", shortcuts.join(", ")));
+            });
+            ui.add_space(5.0);
+        }
+        if let Some(i) = start {
+            app.start_preset(i);
+        }
+    }
+
+    fn mapping_ui(ui: &mut egui::Ui, app: &mut App) {
+        let Some(mapping) = &app.preset_mapping else {
+            return;
+        };
+        let preset = &app.presets[mapping.preset];
+        ui.strong(format!("Map devices to roles of \"{}\"", preset.name));
+        ui.label("Roles set to None are left out. Shortcuts already set are kept.");
+        ui.add_space(5.0);
+
+        // Chosen in a copy, since names of devices are read from app meanwhile
+        let mut chosen = mapping.devices.clone();
+        let candidates: Vec<(String, String)> = app
+            .preset_candidates()
+            .iter()
+            .map(|d| (d.id.clone(), Self::device_name(app, &Some(d.id.clone()))))
+            .collect();
+        egui::Grid::new("PresetMapping")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (i, role) in preset.roles.iter().enumerate() {
+                    ui.label(role.name);
+                    egui::ComboBox::from_id_source(("PresetRole", i))
+                        .selected_text(Self::device_name(app, &chosen[i]))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut chosen[i], None, "None");
+                            for (id, name) in candidates.iter() {
+                                ui.selectable_value(&mut chosen[i], Some(id.clone()), name);
+                            }
+                        });
+                    ui.end_row();
+                }
+            });
+
+        ui.add_space(5.0);
+        let (mut apply, mut cancel) = (false, false);
+        ui.horizontal(|ui| {
+            apply = ui.add(manage_button("Apply")).clicked();
+            cancel = ui.add(manage_button("Cancel")).clicked();
+        });
+        if let Some(mapping) = &mut app.preset_mapping {
+            mapping.devices = chosen;
+        }
+        if apply {
+            app.apply_preset();
+        } else if cancel {
+            app.preset_mapping = None;
+        }
+    }
+
+    pub fn ui(ui: &mut egui::Ui, app: &mut App) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if app.preset_mapping.is_some() {
+                Self::mapping_ui(ui, app);
+            } else {
+                Self::gallery_ui(ui, app);
+            }
+        });
+    }
+}
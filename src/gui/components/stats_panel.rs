@@ -0,0 +1,115 @@
+use eframe::egui;
+use egui_extras::{Column, TableBuilder};
+use monmouse::stats::DayUsage;
+
+use crate::{components::manage_button, App};
+
+pub struct StatsPanel {}
+
+impl StatsPanel {
+    fn duration_str(ms: u64) -> String {
+        let mins = ms / 60000;
+        if mins >= 60 {
+            format!("{}h {}m", mins / 60, mins % 60)
+        } else if mins > 0 {
+            format!("{}m", mins)
+        } else {
+            format!("{}s", ms / 1000)
+        }
+    }
+
+    fn usage_str(u: &DayUsage) -> String {
+        format!("{} / {} events", Self::duration_str(u.active_ms), u.events)
+    }
+
+    fn device_name(app: &App, id: &str) -> String {
+        match app
+            .state
+            .managed_devices
+            .iter()
+            .find(|d| d.generic.id == id)
+        {
+            Some(d) if !d.generic.product_name.is_empty() => d.generic.product_name.clone(),
+            _ => id.to_owned(),
+        }
+    }
+
+    fn table_ui(ui: &mut egui::Ui, app: &App) {
+        let Some((today, stats)) = &app.usage_stats else {
+            ui.label("No stats yet");
+            return;
+        };
+        // (name, today, total), most used first
+        let mut rows: Vec<(String, DayUsage, DayUsage)> = stats
+            .devices
+            .keys()
+            .map(|id| {
+                (
+                    Self::device_name(app, id),
+                    stats.day(id, today),
+                    stats.total(id),
+                )
+            })
+            .collect();
+        rows.sort_by_key(|r| std::cmp::Reverse(r.2.active_ms));
+        let all_active: u64 = rows.iter().map(|r| r.2.active_ms).sum();
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(false)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::LEFT))
+            .columns(Column::auto(), 3)
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Share");
+                });
+                header.col(|ui| {
+                    ui.strong("Today");
+                });
+                header.col(|ui| {
+                    ui.strong("Total");
+                });
+                header.col(|ui| {
+                    ui.strong("Device");
+                });
+            })
+            .body(|mut body| {
+                for (name, today, total) in rows {
+                    body.row(20.0, |mut row| {
+                        row.col(|ui| {
+                            let share = match all_active {
+                                0 => 0,
+                                v => total.active_ms * 100 / v,
+                            };
+                            ui.label(format!("{}%", share));
+                        });
+                        row.col(|ui| {
+                            ui.label(Self::usage_str(&today));
+                            ui.add_space(10.0);
+                        });
+                        row.col(|ui| {
+                            ui.label(Self::usage_str(&total));
+                            ui.add_space(10.0);
+                        });
+                        row.col(|ui| {
+                            ui.label(name);
+                        });
+                    });
+                }
+            });
+    }
+
+    pub fn ui(ui: &mut egui::Ui, app: &mut App) {
+        ui.horizontal(|ui| {
+            if ui.add(manage_button("Refresh")).clicked() {
+                app.trigger_inspect_usage_stats();
+            }
+            if let Some((today, _)) = &app.usage_stats {
+                ui.label(format!("Today: {}", today));
+            }
+        });
+        ui.separator();
+        Self::table_ui(ui, app);
+    }
+}
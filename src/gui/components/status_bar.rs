@@ -1,8 +1,11 @@
 use eframe::egui;
 
-use crate::app::{App, StatusBarResult};
+use monmouse::{config_writer::SaveState, message::TrackingMode, setting::FAILSAFE_SHORTCUT};
 
-use super::widget::{error_color, indicator_ui, NotificationPopup};
+use crate::{
+    app::{App, StatusBarResult},
+    components::{error_color, indicator_ui, NotificationPopup},
+};
 
 pub fn status_bar_ui(ui: &mut egui::Ui, app: &mut App) {
     let msg_with_bottons = |ui: &mut egui::Ui, ok: bool, msg: &String| {
@@ -29,6 +32,64 @@ pub fn status_bar_ui(ui: &mut egui::Ui, app: &mut App) {
         }
         StatusBarResult::None => (),
     };
+
+    let save_state = &app.save_state;
+    let active_device = app.active_device_name();
+    let polling = app.tracking_mode == TrackingMode::Polling;
+    if *save_state != SaveState::Idle || active_device.is_some() || polling {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if let Some(name) = active_device {
+                ui.label(format!("Active: {}", name));
+            }
+            if polling {
+                ui.label("Polling").on_hover_text(
+                    "Mouse hook is off or blocked by security software, cursor position is sampled instead. Cursor may leave its locked monitor for a moment.",
+                );
+            }
+            match save_state {
+                SaveState::Idle => (),
+                SaveState::Writing => {
+                    ui.label("Saving");
+                }
+                SaveState::Saved => {
+                    ui.label("Saved");
+                    indicator_ui(ui, error_color(ui, true));
+                }
+                SaveState::Retrying { error, attempts } => {
+                    ui.label("Pending save").on_hover_text(format!(
+                        "Config file is not writable, retrying ({} attempts): {}",
+                        attempts, error
+                    ));
+                    indicator_ui(ui, error_color(ui, false));
+                }
+                SaveState::Failed(error) => {
+                    ui.label("Not saved")
+                        .on_hover_text(format!("{} [{}]", error.message, error.code));
+                    indicator_ui(ui, error_color(ui, false));
+                }
+            }
+        });
+    }
+}
+
+pub fn safe_mode_banner_ui(ui: &mut egui::Ui) {
+    indicator_ui(ui, error_color(ui, false));
+    ui.label(
+        egui::RichText::from(
+            "Safe mode: config file ignored and not saved, mouse hook and shortcuts disabled",
+        )
+        .strong(),
+    );
+}
+
+pub fn view_only_banner_ui(ui: &mut egui::Ui) {
+    indicator_ui(ui, error_color(ui, true));
+    ui.label(
+        egui::RichText::from(
+            "View-only: another MonMouse controls the mouse, settings are applied and saved by it",
+        )
+        .strong(),
+    );
 }
 
 pub fn status_popup_show(ctx: &egui::Context, app: &mut App) {
@@ -43,3 +104,235 @@ pub fn status_popup_show(ctx: &egui::Context, app: &mut App) {
         }
     }
 }
+
+pub fn low_battery_popup_show(ctx: &egui::Context, app: &mut App) {
+    if !app.low_battery_alerts.is_empty() {
+        let rsp = NotificationPopup::new("LowBatteryPopup").show(ctx, "Low battery", |ui, _| {
+            for msg in &app.low_battery_alerts {
+                ui.label(msg);
+            }
+        });
+        if rsp.action.will_close() {
+            app.low_battery_alerts.clear();
+        }
+    }
+}
+
+pub fn processor_crashed_popup_show(ctx: &egui::Context, app: &mut App) {
+    let Some(crash) = &mut app.processor_crashed else {
+        return;
+    };
+    if crash.dismissed {
+        return;
+    }
+    let (mut restart, mut open_crashes) = (false, false);
+    let rsp = NotificationPopup::new("ProcessorCrashedPopup").show(
+        ctx,
+        "Mouse control crashed",
+        |ui, _| {
+            ui.label(crash.reason.as_str());
+            ui.add_space(10.0);
+            ui.label("Devices and shortcuts stop working until it is restarted.");
+            ui.horizontal(|ui| {
+                restart = ui.button("Restart").clicked();
+                open_crashes = ui.button("Open crashes folder").clicked();
+            });
+        },
+    );
+    if restart {
+        app.restart_processor();
+    } else if rsp.action.will_close() {
+        crash.dismissed = true;
+        let msg = format!("Mouse control crashed: {}", crash.reason);
+        app.result_error_silent(msg);
+    } else if open_crashes {
+        app.open_crashes_dir();
+    }
+}
+
+pub fn shortcut_conflict_popup_show(ctx: &egui::Context, app: &mut App) {
+    let Some(conflict) = &app.shortcut_conflict else {
+        return;
+    };
+    // Some(None) to detect it by keyboard hook
+    let mut resolved: Option<Option<String>> = None;
+    let rsp = NotificationPopup::new("ShortcutConflictPopup").show(
+        ctx,
+        "Shortcut taken by another program",
+        |ui, _| {
+            ui.label(format!("{} for {}", conflict.shortcut, conflict.name));
+            if let Some(owner) = conflict.owner {
+                ui.label(format!("Likely used by: {}", owner));
+            }
+            ui.add_space(10.0);
+            if !conflict.suggestions.is_empty() {
+                ui.label("Use another one:");
+                ui.horizontal(|ui| {
+                    for s in &conflict.suggestions {
+                        if ui.button(s.as_str()).clicked() {
+                            resolved = Some(Some(s.clone()));
+                        }
+                    }
+                });
+            }
+            if ui
+                .button("Force low-level")
+                .on_hover_text(
+                    "Detect it by keyboard hook, then the other program won't receive it",
+                )
+                .clicked()
+            {
+                resolved = Some(None);
+            }
+        },
+    );
+    if let Some(replacement) = resolved {
+        app.resolve_shortcut_conflict(replacement);
+    } else if rsp.action.will_close() {
+        let msg = format!(
+            "Shortcut {} conflicts with another program",
+            conflict.shortcut
+        );
+        app.shortcut_conflict = None;
+        app.result_error_silent(msg);
+    }
+}
+
+pub fn monitors_changed_popup_show(ctx: &egui::Context, app: &mut App) {
+    let Some(layout) = &app.monitors_changed else {
+        return;
+    };
+    let mut apply = None;
+    let rsp =
+        NotificationPopup::new("MonitorsChangedPopup").show(ctx, "Monitors changed", |ui, _| {
+            ui.label(layout.as_str());
+            ui.add_space(10.0);
+            ui.label(
+                "Locks are held, in case monitors are temporary while display driver restarts.",
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Apply locks").clicked() {
+                    apply = Some(true);
+                }
+                if ui.button("Pause").clicked() {
+                    apply = Some(false);
+                }
+            });
+        });
+    if let Some(apply) = apply {
+        app.confirm_monitors_changed(apply);
+    } else if rsp.action.will_close() {
+        app.confirm_monitors_changed(true);
+        app.result_ok("Locks applied on changed monitors".to_owned());
+    }
+}
+
+pub fn risky_features_popup_show(ctx: &egui::Context, app: &mut App) {
+    if app.unconfirmed_risks.is_empty() {
+        return;
+    }
+    let mut enable = None;
+    let rsp = NotificationPopup::new("RiskyFeaturesPopup").show(
+        ctx,
+        "Risky features are off",
+        |ui, _| {
+            for feature in &app.unconfirmed_risks {
+                ui.label(egui::RichText::new(feature.to_string()).strong());
+                ui.label(feature.warning());
+            }
+            ui.add_space(10.0);
+            ui.label(format!(
+                "They are never on in safe mode, and {} turns them off.",
+                FAILSAFE_SHORTCUT
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Enable").clicked() {
+                    enable = Some(true);
+                }
+                if ui.button("Keep off").clicked() {
+                    enable = Some(false);
+                }
+            });
+        },
+    );
+    if let Some(enable) = enable {
+        app.confirm_risks(enable);
+    } else if rsp.action.will_close() {
+        app.confirm_risks(false);
+    }
+}
+
+pub fn device_reconcile_popup_show(ctx: &egui::Context, app: &mut App) {
+    if app.device_reconciles.is_empty() {
+        return;
+    }
+    let name = |id: &str| match app
+        .state
+        .managed_devices
+        .iter()
+        .find(|d| d.generic.id == id)
+    {
+        Some(d) => d.generic.product_name.clone(),
+        None => id.to_owned(),
+    };
+    let names: Vec<(String, Vec<String>)> = app
+        .device_reconciles
+        .iter()
+        .map(|(r, _)| (name(&r.id), r.candidates.iter().map(|c| name(c)).collect()))
+        .collect();
+
+    // (index, migrate to candidate or remove)
+    let mut action = None;
+    let rsp = NotificationPopup::new("DeviceReconcilePopup").show(
+        ctx,
+        "Devices in config not found",
+        |ui, _| {
+            ui.label("They may be connected with new ids. Migrate their settings to:");
+            for (i, ((r, chosen), (old_name, candidates))) in app
+                .device_reconciles
+                .iter_mut()
+                .zip(names.iter())
+                .enumerate()
+            {
+                ui.add_space(10.0);
+                ui.label(egui::RichText::from(old_name).strong())
+                    .on_hover_text(r.id.as_str());
+                egui::ComboBox::from_id_source(("DeviceReconcileCandidate", i))
+                    .selected_text(candidates[*chosen].as_str())
+                    .show_ui(ui, |ui| {
+                        for (j, c) in candidates.iter().enumerate() {
+                            ui.selectable_value(chosen, j, c.as_str())
+                                .on_hover_text(r.candidates[j].as_str());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button("Migrate").clicked() {
+                        action = Some((i, true));
+                    }
+                    if ui.button("Remove").clicked() {
+                        action = Some((i, false));
+                    }
+                });
+            }
+        },
+    );
+    if let Some((i, migrate)) = action {
+        let (r, chosen) = app.device_reconciles.remove(i);
+        if migrate {
+            let to = &r.candidates[chosen];
+            app.migrate_device_setting(&r.id, to);
+            // A device can be migrated to only once
+            for (other, chosen) in app.device_reconciles.iter_mut() {
+                other.candidates.retain(|c| c != to);
+                *chosen = 0;
+            }
+            app.device_reconciles
+                .retain(|(r, _)| !r.candidates.is_empty());
+        } else {
+            app.remove_device_setting(&r.id);
+        }
+    } else if rsp.action.will_close() {
+        // Kept as they are
+        app.device_reconciles.clear();
+    }
+}
@@ -8,6 +8,7 @@ use monmouse::{
     keyboard::{
         build_modifiers,
         key_egui::{egui_to_key, egui_to_modifier},
+        key_windows::{label_to_key, shortcut_display_str},
         shortcut_to_str, META_STR,
     },
     message::DeviceStatus,
@@ -127,6 +128,16 @@ pub fn toggle_ui(ui: &mut egui::Ui, on: &mut bool, label: impl ToString) -> egui
     response
 }
 
+// Checkbox over a text buffer holding "true" or "false", e.g. of config inputs
+pub fn text_checkbox(ui: &mut egui::Ui, buf: &mut String) -> egui::Response {
+    let mut v = buf == "true";
+    let response = ui.checkbox(&mut v, "");
+    if response.changed() {
+        *buf = v.to_string();
+    }
+    response
+}
+
 #[allow(dead_code)]
 pub fn center_anchor_area<R>(
     ctx: &egui::Context,
@@ -428,9 +439,12 @@ pub fn shortcut_input_ui(
     ui: &mut egui::Ui,
     buf: &mut String,
     show_modifier: bool,
+    positional: bool,
     textinput_style: impl FnOnce(egui::TextEdit) -> egui::TextEdit,
 ) -> ShortcutInputResponse {
-    let mut b = EatInputBuffer::from(buf);
+    // Stored by key positions if positional, shown by labels
+    let label = shortcut_display_str(buf, positional);
+    let mut b = EatInputBuffer::from(&label);
     let textinput = textinput_style(egui::TextEdit::singleline(&mut b).desired_width(140.0));
 
     let inner = textinput.ui(ui);
@@ -453,7 +467,10 @@ pub fn shortcut_input_ui(
             } else {
                 None
             },
-            key.map(egui_to_key),
+            key.map(|k| match positional {
+                true => label_to_key(egui_to_key(k)),
+                false => egui_to_key(k),
+            }),
         );
         *buf = new_shortcut;
         // Had key, stop input
@@ -483,21 +500,34 @@ pub struct ShortcutChooseState {
 
 pub struct ShortcutChoosePopup {
     id_source: egui::Id,
+    positional: bool,
 }
 
 impl ShortcutChoosePopup {
     pub fn new(id_source: impl std::hash::Hash) -> Self {
         Self {
             id_source: egui::Id::new(id_source),
+            positional: true,
         }
     }
 
+    // Whether keys of shortcuts are named by positions, see ShortcutSettings::positional_keys
+    pub fn positional(mut self, positional: bool) -> Self {
+        self.positional = positional;
+        self
+    }
+
     pub fn button_ui(
         ui: &mut egui::Ui,
         action: &mut CommonPopupHeaderAction,
         text: &str,
+        positional: bool,
     ) -> egui::Response {
-        let resp = ui.add(egui::Button::new(text).min_size(egui::vec2(140.0, 10.0)));
+        let label = shortcut_display_str(text, positional);
+        let mut resp = ui.add(egui::Button::new(&label).min_size(egui::vec2(140.0, 10.0)));
+        if label != text {
+            resp = resp.on_hover_text(format!("Keys as on US layout: {}", text));
+        }
         if resp.clicked() {
             action.open_state = Some(true);
         }
@@ -520,9 +550,13 @@ impl ShortcutChoosePopup {
         changed |= ui.checkbox(&mut state.shift_checked, "Shift").clicked();
         changed |= ui.checkbox(&mut state.alt_checked, "Alt").clicked();
 
-        changed |= shortcut_input_ui(ui, &mut state.key_input, false, |textinput| {
-            textinput.desired_width(50.0)
-        })
+        changed |= shortcut_input_ui(
+            ui,
+            &mut state.key_input,
+            false,
+            self.positional,
+            |textinput| textinput.desired_width(50.0),
+        )
         .changed;
 
         if changed {
@@ -532,10 +566,6 @@ impl ShortcutChoosePopup {
     }
 
     pub fn short_cut_from_state(&mut self, state: ShortcutChooseState) -> String {
-        if state.key_input.is_empty() {
-            return "".to_owned();
-        }
-
         let modifiers = match build_modifiers(
             state.ctrl_checked,
             state.alt_checked,
@@ -546,14 +576,20 @@ impl ShortcutChoosePopup {
             None => return "".to_owned(),
         };
         let mut s = shortcut_to_str(Some(modifiers), None);
+        if state.key_input.is_empty() {
+            // Only modifiers, usable by the hook backend
+            s.pop();
+            return s;
+        }
         s.push_str(state.key_input.as_str());
         s
     }
 
     pub fn ui(mut self, ui: &mut egui::Ui, buf: &mut String) -> ShortcutInputResponse {
+        let positional = self.positional;
         let resp = CommonPopup::new(self.id_source).width(140.0).ui(
             ui,
-            |ui, action| Self::button_ui(ui, action, buf.as_str()),
+            |ui, action| Self::button_ui(ui, action, buf.as_str(), positional),
             |ui, action| self.popup_ui(ui, action),
         );
         let mut r = ShortcutInputResponse {
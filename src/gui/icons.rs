@@ -0,0 +1,53 @@
+use eframe::egui;
+use log::debug;
+use monmouse::setting::TrayIconStyle;
+use monmouse::windows::winwrap::system_uses_light_theme;
+
+const APP_ICON: &[u8] = include_bytes!("..\\..\\assets\\monmouse.ico");
+
+pub fn app_icon() -> egui::IconData {
+    let image = image::load_from_memory(APP_ICON)
+        .expect("Invalid icon data")
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    egui::IconData {
+        rgba: image.into_raw(),
+        width,
+        height,
+    }
+}
+
+// Auto is resolved to Light or Dark by theme of taskbar
+pub fn resolve_tray_style(style: TrayIconStyle) -> TrayIconStyle {
+    if style != TrayIconStyle::Auto {
+        return style;
+    }
+    match system_uses_light_theme() {
+        Ok(true) => TrayIconStyle::Light,
+        Ok(false) => TrayIconStyle::Dark,
+        Err(e) => {
+            debug!("Failed to get system theme: {}", e);
+            TrayIconStyle::Dark
+        }
+    }
+}
+
+// Monochrome variants keep shapes of the app icon by its brightness, drawn dark on light
+// taskbar and light on dark taskbar
+pub fn tray_icon(style: TrayIconStyle) -> egui::IconData {
+    let mut icon = app_icon();
+    let on_light = match resolve_tray_style(style) {
+        TrayIconStyle::Light => true,
+        TrayIconStyle::Dark => false,
+        _ => return icon,
+    };
+    for px in icon.rgba.chunks_exact_mut(4) {
+        let luma = (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000;
+        let v = match on_light {
+            true => luma / 2,
+            false => 255 - (255 - luma) / 2,
+        } as u8;
+        px[..3].fill(v);
+    }
+    icon
+}
@@ -2,11 +2,14 @@
 
 mod app;
 mod components;
-mod config;
+mod icons;
+mod sound;
 mod styles;
 mod tray;
+mod view_only;
 
-use std::panic::PanicInfo;
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, PanicInfo};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::{cell::RefCell, panic, process, rc::Rc, thread};
@@ -15,87 +18,256 @@ use app::App;
 use components::about_panel::AboutPanel;
 use components::config_panel::ConfigPanel;
 use components::devices_panel::DevicesPanel;
-use components::status_bar::{status_bar_ui, status_popup_show};
+use components::presets_panel::PresetsPanel;
+use components::stats_panel::StatsPanel;
+use components::status_bar::{
+    device_reconcile_popup_show, low_battery_popup_show, monitors_changed_popup_show,
+    processor_crashed_popup_show, risky_features_popup_show, safe_mode_banner_ui,
+    shortcut_conflict_popup_show, status_bar_ui, status_popup_show, view_only_banner_ui,
+};
 use eframe::egui;
-use log::info;
-use monmouse::message::UINotify;
-use monmouse::setting::{read_config, Settings, CONFIG_FILE_NAME};
+use log::{error, info};
+use monmouse::bridge::Topic;
+use monmouse::crash_report;
+use monmouse::message::{MouseControlReactor, TrayReactor, UINotify};
+use monmouse::paths::{
+    config_dir, deployed_config_file, remove_artifacts, view_endpoint_file, AppPaths, Artifact,
+    UI_APP_ID,
+};
+use monmouse::setting::{bootstrap_config, read_config, Settings};
+use monmouse::view_ipc::{ViewClient, ViewSnapshot};
 use monmouse::{
     errors::Error,
     message::{setup_reactors, UIReactor},
 };
-use monmouse::{SingleProcess, POLL_MSGS, POLL_TIMEOUT};
+use monmouse::{run_watchdog, spawn_watchdog, watchdog_args, SingleProcess};
 use styles::{gscale, Theme};
-use tray::Tray;
+use tray::{SharedTrayOptions, Tray, TrayOptions};
 
 #[cfg(debug_assertions)]
 use crate::components::debug::DebugInfo;
-use crate::config::get_config_dir;
-
-pub fn load_icon() -> egui::IconData {
-    let icon_data = include_bytes!("..\\..\\assets\\monmouse.ico");
-    let image = image::load_from_memory(icon_data)
-        .expect("Invalid icon data")
-        .into_rgba8();
-    let (width, height) = image.dimensions();
-    egui::IconData {
-        rgba: image.into_raw(),
-        width,
-        height,
-    }
-}
 
 fn main() {
     env_logger::builder().init();
-    set_thread_panic_process();
-    let single_process = match SingleProcess::create() {
-        Ok(v) => v,
-        Err(e) => {
-            exit_with_message(format!("Already launched: {}", e));
-            return;
+    // Spawned by another instance with restart_on_crash, no window for it
+    if let Some(args) = watchdog_args() {
+        if let Err(e) = run_watchdog(&args) {
+            error!("Watchdog failed: {}", e);
         }
+        return;
+    }
+    set_thread_panic_process();
+    let config_file = config_dir().map(|v| AppPaths::new(&v).config_file());
+    let paths = config_dir().ok().map(|v| AppPaths::new(&v));
+    // Another instance owns mouse control, it can still be viewed and configured through
+    let (single_process, view) = match SingleProcess::create() {
+        Ok(v) => (Some(v), None),
+        Err(e) => (None, Some(connect_view_only(e))),
     };
-
-    let config_file = get_config_dir().map(|v| v.join(CONFIG_FILE_NAME));
+    let stats_path = paths.as_ref().map(|v| v.stats_file());
     let config_path = config_file.as_ref().ok().cloned();
+    if let Some(paths) = &paths {
+        crash_report::set_crashes_dir(&paths.crashes_dir());
+    }
+    if let Some(path) = &config_path {
+        crash_report::set_config_file(path);
+    }
 
-    let config = config_file.and_then(|v| read_config(&v));
+    // Skip a config file which makes program crash. Nothing is saved over it, since settings
+    // start from defaults.
+    let safe_mode = std::env::args().skip(1).any(|a| a == SAFE_MODE_ARG);
+    if let (None, false, Some(path)) = (&view, safe_mode, &config_path) {
+        bootstrap_first_run(path);
+    }
+    let config = if let Some((_, snapshot)) = &view {
+        Ok(snapshot.settings.clone())
+    } else if safe_mode {
+        Ok(Settings::default())
+    } else {
+        config_file.and_then(|v| read_config(&v))
+    };
+    if let (Some(_), Ok(c)) = (&single_process, &config) {
+        if c.processor.restart_on_crash {
+            if let Err(e) = spawn_watchdog() {
+                error!("Start watchdog failed: {}", e);
+            }
+        }
+    }
+    let tray_options: SharedTrayOptions = Arc::new(Mutex::new(TrayOptions::from(
+        &config.as_ref().map(|c| c.ui.clone()).unwrap_or_default(),
+    )));
 
     let egui_notify = EguiNotify::default();
     let (tray_reactor, mouse_control_reactor, ui_reactor) =
         setup_reactors(Box::new(egui_notify.clone()), Box::new(egui_notify.clone()));
-
-    let mouse_control_thread = thread::spawn(move || {
-        let eventloop = monmouse::Eventloop::new(false, mouse_control_reactor);
-        let tray = Tray::new(tray_reactor);
-        match mouse_control_spawn(eventloop, tray) {
-            Ok(_) => info!("mouse control eventloop exited normally"),
-            Err(e) => panic!("mouse control eventloop exited for error: {}", e),
+    // Before mouse control starts, not to miss events at startup
+    ui_reactor.forward_bus_events(&[Topic::Devices, Topic::Status, Topic::Relocations]);
+
+    let view_client = view.as_ref().map(|(client, _)| client.clone());
+    let mouse_control_thread = match view {
+        Some((client, snapshot)) => thread::spawn(move || {
+            view_only::view_proxy(mouse_control_reactor, client, snapshot.settings.processor)
+        }),
+        None => {
+            let tray_options1 = tray_options.clone();
+            thread::spawn(move || {
+                mouse_control_supervise(
+                    mouse_control_reactor,
+                    tray_reactor,
+                    tray_options1,
+                    safe_mode,
+                    stats_path,
+                )
+            })
         }
-    });
+    };
 
     // winit wrapped by eframe, requires UI eventloop running inside main thread
-    let result = egui_eventloop(ui_reactor, config, config_path, egui_notify);
-    if let Err(e) = result {
-        panic!("egui eventloop exited for: {}", e);
-    }
+    let result = egui_eventloop(
+        ui_reactor,
+        config,
+        config_path,
+        egui_notify,
+        tray_options,
+        safe_mode,
+        view_client,
+    );
+    let cleanup = match result {
+        Ok(v) => v,
+        Err(e) => panic!("egui eventloop exited for: {}", e),
+    };
 
     let _ = mouse_control_thread.join();
     drop(single_process);
+    // After everything exited, which may still save config and window position
+    if let Some(artifacts) = cleanup {
+        cleanup_after_exit(&artifacts);
+    }
+}
+
+// Config deployed by administrators becomes the config file, if there is none yet
+fn bootstrap_first_run(config_path: &PathBuf) {
+    if config_path.exists() {
+        return;
+    }
+    let Some(deployed) = deployed_config_file() else {
+        return;
+    };
+    match bootstrap_config(config_path, &deployed) {
+        Ok(_) => info!("Config bootstrapped from {}", deployed.display()),
+        Err(e) => error!("Bootstrap config from {} failed: {}", deployed.display(), e),
+    }
+}
+
+const MOUSE_CONTROL_THREAD: &str = "mouse_control";
+const SAFE_MODE_ARG: &str = "--safe-mode";
+
+// Exits if the running instance doesn't answer, or user doesn't want to view it
+fn connect_view_only(launched: Error) -> (ViewClient, ViewSnapshot) {
+    use monmouse::windows::wintypes::WString;
+    use monmouse::windows::winwrap::popup_confirm_box;
+
+    let connected = view_endpoint_file()
+        .ok_or(Error::NotRunning)
+        .and_then(|f| ViewClient::connect(&f));
+    let view = match connected {
+        Ok(v) => v,
+        Err(Error::NotRunning) => exit_with_message(format!(
+            "Already launched: {}\nView-only mode unavailable, unless ui.allow_view_only is on in the running one",
+            launched
+        )),
+        Err(e) => exit_with_message(format!(
+            "Already launched: {}\nView-only mode unavailable: {}",
+            launched, e
+        )),
+    };
+    let caption = WString::encode_from_str("MonMouse");
+    let text = WString::encode_from_str(
+        "MonMouse is already running.\nOpen in view-only mode, to see its devices and edit its settings?",
+    );
+    match popup_confirm_box(caption, text) {
+        Ok(true) => view,
+        _ => process::exit(0),
+    }
+}
+
+// Run mouse control eventloop inside a child thread. If it panics, report to UI and wait
+// for user deciding whether to restart it. Child thread is respawned each time, so that
+// resources bound to the thread(window, hook, tray icon) are released together with it.
+fn mouse_control_supervise(
+    mut mouse_control_reactor: MouseControlReactor,
+    mut tray_reactor: TrayReactor,
+    tray_options: SharedTrayOptions,
+    safe_mode: bool,
+    stats_path: Option<PathBuf>,
+) {
+    loop {
+        let tray_options = tray_options.clone();
+        let stats_path = stats_path.clone();
+        let worker = thread::Builder::new()
+            .name(MOUSE_CONTROL_THREAD.to_owned())
+            .spawn(move || {
+                let mut eventloop = monmouse::Eventloop::new(false, mouse_control_reactor);
+                eventloop.set_safe_mode(safe_mode);
+                eventloop.set_stats_path(stats_path);
+                let tray = Tray::new(tray_reactor, tray_options);
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    mouse_control_spawn(&mut eventloop, &tray)
+                }));
+                (eventloop.into_reactor(), tray.into_reactor(), result)
+            })
+            .expect("Failed to spawn mouse control thread");
+        let (reactor, tray_r, result) = match worker.join() {
+            Ok(v) => v,
+            Err(_) => panic!("mouse control thread exited abnormally"),
+        };
+        match result {
+            Ok(Ok(_)) => {
+                info!("mouse control eventloop exited normally");
+                return;
+            }
+            Ok(Err(e)) => panic!("mouse control eventloop exited for error: {}", e),
+            Err(payload) => {
+                let mut reason = panic_reason(payload.as_ref());
+                if let Some(path) = crash_report::last_report() {
+                    reason = format!("{}\nCrash report: {}", reason, path.display());
+                }
+                error!("mouse control eventloop panicked: {}", reason);
+                reactor.report_crashed(reason);
+                if !reactor.wait_for_restart() {
+                    return;
+                }
+                info!("mouse control eventloop restarting");
+            }
+        }
+        mouse_control_reactor = reactor;
+        tray_reactor = tray_r;
+    }
 }
 
-fn mouse_control_spawn(mut eventloop: monmouse::Eventloop, tray: Tray) -> Result<(), Error> {
+fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+fn mouse_control_spawn(eventloop: &mut monmouse::Eventloop, tray: &Tray) -> Result<(), Error> {
     eventloop.initialize()?;
     loop {
         tray.poll_events();
-        if !eventloop.poll_wm_messages(POLL_MSGS, POLL_TIMEOUT)? {
+        let (max_events, timeout_ms) = eventloop.poll_params();
+        if !eventloop.poll_wm_messages(max_events, timeout_ms)? {
             break;
         }
         if eventloop.poll_messages() {
             break;
         };
     }
-    eventloop.terminate()?;
+    eventloop.shutdown()?;
     Ok(())
 }
 
@@ -104,10 +276,22 @@ fn egui_eventloop(
     config: Result<Settings, Error>,
     config_path: Option<PathBuf>,
     egui_notify: EguiNotify,
-) -> Result<(), eframe::Error> {
-    let mut app = App::new(ui_reactor).load_config(config, config_path);
+    tray_options: SharedTrayOptions,
+    safe_mode: bool,
+    view_client: Option<ViewClient>,
+) -> Result<Option<Vec<Artifact>> /* to cleanup */, eframe::Error> {
+    let mut app = App::new(ui_reactor, tray_options).load_config(config, config_path);
+    app.safe_mode = safe_mode;
+    app.view_client = view_client;
     app.trigger_scan_devices();
     app.trigger_settings_changed();
+    // Config is synced and written by the running instance
+    if !app.view_only() {
+        app.setup_config_sync(&egui_notify);
+        app.setup_config_writer(&egui_notify);
+        app.setup_view_server(&egui_notify);
+    }
+    app.setup_update_check(&egui_notify);
 
     let app = Rc::new(RefCell::new(app));
     loop {
@@ -118,7 +302,7 @@ fn egui_eventloop(
             ui_options_main_window(),
             Box::new(move |c| {
                 AppWrap::init_ctx(&c.egui_ctx);
-                app_ref.borrow_mut().setup_inspect_timer(&egui_notify1);
+                app_ref.borrow_mut().watch_devices_status(true);
                 egui_notify1.update_ctx(Some(c.egui_ctx.clone()));
                 Box::new(AppWrap::new(app_ref, egui_notify1))
             }),
@@ -127,7 +311,9 @@ fn egui_eventloop(
             break;
         }
     }
-    Ok(())
+    app.borrow_mut().stop_config_writer();
+    let cleanup = app.borrow_mut().cleanup_on_exit.take();
+    Ok(cleanup)
 }
 
 #[derive(Clone, Default)]
@@ -159,9 +345,9 @@ fn ui_options_main_window() -> eframe::NativeOptions {
     eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([gscale(800.0), gscale(400.0)])
-            .with_app_id("monmouse")
+            .with_app_id(UI_APP_ID)
             .with_window_level(egui::WindowLevel::Normal)
-            .with_icon(load_icon()),
+            .with_icon(icons::app_icon()),
         follow_system_theme: true,
         run_and_return: true,
         centered: true,
@@ -175,6 +361,8 @@ fn ui_options_main_window() -> eframe::NativeOptions {
 enum PanelTag {
     Devices,
     Config,
+    Presets,
+    Stats,
     About,
 }
 
@@ -233,6 +421,10 @@ impl eframe::App for AppWrap {
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.egui_notify.update_ctx(None);
+        let mut app = self.app.borrow_mut();
+        app.flush_device_settings(true);
+        // Kept retrying in background, if it's only closed to background
+        app.flush_config_writes();
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -241,10 +433,25 @@ impl eframe::App for AppWrap {
 
         // Start painting
         Self::init_visuals(ctx, app.get_theme());
+        if app.safe_mode {
+            egui::TopBottomPanel::top("SafeModeBanner").show(ctx, |ui| {
+                ui.horizontal(safe_mode_banner_ui);
+            });
+        }
+        if app.view_only() {
+            egui::TopBottomPanel::top("ViewOnlyBanner").show(ctx, |ui| {
+                ui.horizontal(view_only_banner_ui);
+            });
+        }
         egui::TopBottomPanel::bottom("StatusBar").show(ctx, |ui| {
             ui.horizontal(|ui| status_bar_ui(ui, &mut app));
         });
         status_popup_show(ctx, &mut app);
+        low_battery_popup_show(ctx, &mut app);
+        device_reconcile_popup_show(ctx, &mut app);
+        shortcut_conflict_popup_show(ctx, &mut app);
+        monitors_changed_popup_show(ctx, &mut app);
+        risky_features_popup_show(ctx, &mut app);
         egui::SidePanel::left("TabChooser")
             .resizable(false)
             .show_separator_line(true)
@@ -254,20 +461,27 @@ impl eframe::App for AppWrap {
                 let mut tab_button = |tag| {
                     let text = format!("{:?}", tag);
                     let tab = egui::RichText::from(text).heading().strong();
-                    ui.selectable_value(&mut self.cur_panel, tag, tab);
+                    ui.selectable_value(&mut self.cur_panel, tag, tab).clicked()
                 };
                 tab_button(PanelTag::Devices);
                 tab_button(PanelTag::Config);
+                tab_button(PanelTag::Presets);
+                if tab_button(PanelTag::Stats) {
+                    app.trigger_inspect_usage_stats();
+                }
                 tab_button(PanelTag::About);
 
                 #[cfg(debug_assertions)]
                 self.debug_info.ui(ui);
             });
+        processor_crashed_popup_show(ctx, &mut app);
         egui::CentralPanel::default().show(ctx, |ui| {
             match self.cur_panel {
                 PanelTag::Devices => DevicesPanel::ui(ui, &mut app),
                 PanelTag::Config => ConfigPanel::ui(ui, &mut app),
-                PanelTag::About => AboutPanel::ui(ui),
+                PanelTag::Presets => PresetsPanel::ui(ui, &mut app),
+                PanelTag::Stats => StatsPanel::ui(ui, &mut app),
+                PanelTag::About => AboutPanel::ui(ui, &mut app),
             };
         });
 
@@ -278,7 +492,7 @@ impl eframe::App for AppWrap {
 }
 
 #[cfg(target_os = "windows")]
-fn exit_with_message(text: String) {
+fn exit_with_message(text: String) -> ! {
     use monmouse::windows::wintypes::WString;
     use monmouse::windows::winwrap::popup_message_box;
 
@@ -288,12 +502,38 @@ fn exit_with_message(text: String) {
 }
 
 #[cfg(target_os = "windows")]
-fn windows_panic_hook(panic_info: &PanicInfo) {
+fn cleanup_after_exit(artifacts: &[Artifact]) {
+    use monmouse::windows::wintypes::WString;
+    use monmouse::windows::winwrap::popup_message_box;
+
+    let failed = remove_artifacts(artifacts);
+    let text = if failed.is_empty() {
+        format!(
+            "Removed {} item(s). MonMouse can be deleted now.",
+            artifacts.len()
+        )
+    } else {
+        let mut text = "Failed to remove:".to_owned();
+        for (a, e) in failed.iter() {
+            text += &format!("\n{}: {}", a, e);
+        }
+        text
+    };
+    let caption = WString::encode_from_str("MonMouse");
+    let _ = popup_message_box(caption, WString::encode_from_str(&text));
+}
+
+#[cfg(target_os = "windows")]
+fn windows_panic_hook(panic_info: &PanicInfo, report: Option<PathBuf>) {
     use monmouse::windows::wintypes::WString;
     use monmouse::windows::winwrap::popup_message_box;
 
     let caption = WString::encode_from_str("MonMouse");
-    let text = WString::encode_from_str(format!("Program panic: {}", panic_info).as_str());
+    let mut text = format!("Program panic: {}", panic_info);
+    if let Some(path) = report {
+        text += &format!("\n\nCrash report: {}", path.display());
+    }
+    let text = WString::encode_from_str(&text);
     let _ = popup_message_box(caption, text);
 }
 
@@ -301,8 +541,16 @@ fn set_thread_panic_process() {
     let orig_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
         orig_hook(panic_info);
+        let report = crash_report::write_report(panic_info);
+        // Panics of mouse control thread are caught and reported to UI by its supervisor
+        if thread::current().name() == Some(MOUSE_CONTROL_THREAD) {
+            return;
+        }
+        // Unwinding is skipped by exiting, so cursors replaced by cursor_style stay otherwise
+        #[cfg(target_os = "windows")]
+        monmouse::windows::cursor_style::restore_system_cursors();
         #[cfg(target_os = "windows")]
-        windows_panic_hook(panic_info);
+        windows_panic_hook(panic_info, report);
         process::exit(1);
     }));
 }
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use log::debug;
+use monmouse::setting::SoundSettings;
+use monmouse::windows::winwrap::{play_sound_alias, play_sound_file};
+
+#[derive(Clone, Copy, Debug)]
+pub enum SoundCue {
+    Lock,
+    Jump,
+    Switch,
+}
+
+impl SoundCue {
+    fn enabled(&self, s: &SoundSettings) -> bool {
+        match self {
+            Self::Lock => s.on_lock,
+            Self::Jump => s.on_jump,
+            Self::Switch => s.on_switch,
+        }
+    }
+
+    fn wav_name(&self) -> &'static str {
+        match self {
+            Self::Lock => "lock.wav",
+            Self::Jump => "jump.wav",
+            Self::Switch => "switch.wav",
+        }
+    }
+
+    // Played if no wav file is provided
+    fn system_alias(&self) -> &'static str {
+        match self {
+            Self::Lock => "SystemAsterisk",
+            Self::Jump => ".Default",
+            Self::Switch => "SystemNotification",
+        }
+    }
+}
+
+pub fn play(cue: SoundCue, s: &SoundSettings) {
+    if !cue.enabled(s) {
+        return;
+    }
+    if !s.wav_dir.is_empty() {
+        let path = Path::new(&s.wav_dir).join(cue.wav_name());
+        if path.is_file() && play_sound_file(&path.to_string_lossy()) {
+            return;
+        }
+    }
+    if !play_sound_alias(cue.system_alias()) {
+        debug!("Cannot play sound for {:?}", cue);
+    }
+}
@@ -1,4 +1,12 @@
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use monmouse::bridge::{BusEvent, Subscription, Topic};
 use monmouse::message::TrayReactor;
+use monmouse::setting::{TrayAction, TrayIconStyle, UISettings};
+use monmouse::TrayMiddleClick;
 use tray_icon::menu::Menu;
 use tray_icon::menu::MenuEvent;
 use tray_icon::menu::MenuItem;
@@ -8,19 +16,56 @@ use tray_icon::TrayIcon;
 use tray_icon::TrayIconBuilder;
 use tray_icon::TrayIconEvent;
 
-use crate::load_icon;
+use crate::icons;
+
+// Taskbar theme is checked at this interval, for TrayIconStyle::Auto
+const THEME_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy)]
+pub struct TrayOptions {
+    pub click: TrayAction,
+    pub double_click: TrayAction,
+    pub middle_click: TrayAction,
+    pub icon: TrayIconStyle,
+}
+
+impl From<&UISettings> for TrayOptions {
+    fn from(s: &UISettings) -> Self {
+        Self {
+            click: s.tray_click,
+            double_click: s.tray_double_click,
+            middle_click: s.tray_middle_click,
+            icon: s.tray_icon,
+        }
+    }
+}
+
+// Tray runs inside mouse control thread, while options are changed by UI
+pub type SharedTrayOptions = Arc<Mutex<TrayOptions>>;
 
 #[allow(dead_code)]
 pub struct Tray {
     open: MenuItem,
     quit: MenuItem,
+    // Dropped before the icon, whose window it subclasses
+    middle_click: Option<Box<TrayMiddleClick>>,
     trayicon: TrayIcon,
     tray_reactor: TrayReactor,
+    options: SharedTrayOptions,
+    status: Subscription,
+    // Style in options, and the one it was resolved to when shown
+    shown_icon: Cell<(TrayIconStyle, TrayIconStyle)>,
+    theme_checked: Cell<Instant>,
+    // Shown in tooltip
+    paused: Cell<bool>,
+    kvm_remote: Cell<bool>,
 }
 
 impl Tray {
-    pub fn new(tray_reactor: TrayReactor) -> Self {
-        let icon = load_icon();
+    pub fn new(tray_reactor: TrayReactor, options: SharedTrayOptions) -> Self {
+        let style = options.lock().unwrap().icon;
+        let resolved = icons::resolve_tray_style(style);
+        let icon = icons::tray_icon(resolved);
         let tray_menu = Menu::new();
 
         let open = MenuItem::new("Open", true, None);
@@ -39,20 +84,92 @@ impl Tray {
             )
             .build()
             .unwrap();
+        let middle_click = TrayMiddleClick::attach();
+        let status = tray_reactor.bus().subscribe(&[Topic::Status]);
         Self {
             open,
             quit,
+            middle_click,
             trayicon,
             tray_reactor,
+            options,
+            status,
+            shown_icon: Cell::new((style, resolved)),
+            theme_checked: Cell::new(Instant::now()),
+            paused: Cell::new(false),
+            kvm_remote: Cell::new(false),
+        }
+    }
+
+    // Regenerate icon if its style is changed, or theme of taskbar is changed for Auto
+    fn refresh_icon(&self) {
+        let style = self.options.lock().unwrap().icon;
+        let (shown_style, shown_resolved) = self.shown_icon.get();
+        if style == shown_style
+            && (style != TrayIconStyle::Auto
+                || self.theme_checked.get().elapsed() < THEME_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.theme_checked.set(Instant::now());
+        let resolved = icons::resolve_tray_style(style);
+        self.shown_icon.set((style, resolved));
+        if resolved == shown_resolved {
+            return;
+        }
+        let icon = icons::tray_icon(resolved);
+        match tray_icon::Icon::from_rgba(icon.rgba, icon.width, icon.height) {
+            Ok(v) => {
+                if let Err(e) = self.trayicon.set_icon(Some(v)) {
+                    warn!("Failed to update tray icon: {:?}", e);
+                }
+            }
+            Err(e) => warn!("Invalid tray icon: {:?}", e),
         }
     }
 
+    fn run_action(&self, action: TrayAction) {
+        match action {
+            TrayAction::None => (),
+            TrayAction::OpenUI => self.tray_reactor.restart_ui(),
+            TrayAction::TogglePause => self.tray_reactor.toggle_pause(),
+            TrayAction::JumpNext => self.tray_reactor.jump_next_monitor(),
+        }
+    }
+
+    pub fn into_reactor(self) -> TrayReactor {
+        self.tray_reactor
+    }
+
     pub fn poll_events(&self) {
+        self.refresh_icon();
+        while let Some(event) = self.status.try_recv() {
+            match event {
+                BusEvent::PausedChanged(paused) => self.paused.set(paused),
+                BusEvent::KvmRemoteChanged(remote) => self.kvm_remote.set(remote),
+                _ => continue,
+            }
+            let tooltip = match (self.paused.get(), self.kvm_remote.get()) {
+                (false, _) => "MonMouse",
+                (true, false) => "MonMouse (paused)",
+                (true, true) => "MonMouse (paused, input on another machine)",
+            };
+            let _ = self.trayicon.set_tooltip(Some(tooltip));
+        }
+
         if let Ok(event) = TrayIconEvent::receiver().try_recv() {
-            if event.click_type == ClickType::Double {
-                self.tray_reactor.restart_ui();
+            let actions = *self.options.lock().unwrap();
+            match event.click_type {
+                ClickType::Left => self.run_action(actions.click),
+                ClickType::Double => self.run_action(actions.double_click),
+                // Reserved for menu
+                ClickType::Right => (),
             }
         }
+        if self.middle_click.as_ref().is_some_and(|m| m.take()) {
+            let actions = *self.options.lock().unwrap();
+            self.run_action(actions.middle_click);
+        }
 
         if let Ok(event) = MenuEvent::receiver().try_recv() {
             if event.id == self.quit.id() {
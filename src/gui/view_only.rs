@@ -0,0 +1,126 @@
+// Takes the place of mouse control when another instance owns it. Messages of UI are answered
+// by the running instance through view_ipc, or refused if only mouse control can do them.
+
+use std::time::Duration;
+
+use log::debug;
+use monmouse::errors::Error;
+use monmouse::message::{DeviceStatus, Message, MouseControlReactor};
+use monmouse::setting::ProcessorSettings;
+use monmouse::view_ipc::ViewClient;
+
+// Parts of settings come one by one, the whole of them is sent each time
+pub fn view_proxy(
+    reactor: MouseControlReactor,
+    client: ViewClient,
+    mut settings: ProcessorSettings,
+) {
+    // Snapshots are taken only while UI watches statuses, which are pushed when changed
+    let mut watch: Option<Duration> = None;
+    let mut last_status: Option<Vec<(String, DeviceStatus)>> = None;
+    loop {
+        let mut msg = match watch {
+            Some(interval) => match reactor.mouse_control_rx.recv_timeout(interval) {
+                Some(msg) => msg,
+                None => {
+                    if let Ok(status) = snapshot_status(&client) {
+                        if last_status.as_ref() != Some(&status) {
+                            last_status = Some(status.clone());
+                            reactor.report_devices_status(status);
+                        }
+                    }
+                    continue;
+                }
+            },
+            None => reactor.mouse_control_rx.recv(),
+        };
+        match &mut msg {
+            Message::Exit => return,
+            Message::ScanDevices(data) => {
+                data.set_result(
+                    client
+                        .snapshot()
+                        .map(|s| s.devices.into_iter().map(|(generic, _)| generic).collect()),
+                );
+            }
+            Message::InspectDevicesStatus(data) => data.set_result(snapshot_status(&client)),
+            Message::WatchDevicesStatus(interval_ms) => {
+                watch = interval_ms.map(Duration::from_millis);
+                last_status = None;
+                continue;
+            }
+            Message::ApplyProcessorSetting(data) => {
+                let new = data.req().clone();
+                data.set_result(apply(&client, &mut settings, new));
+            }
+            Message::ApplyShortcuts(data) => {
+                let new = ProcessorSettings {
+                    shortcuts: data.req().clone(),
+                    ..settings.clone()
+                };
+                data.set_result(apply(&client, &mut settings, new));
+            }
+            Message::ApplyGlobalProcessorOptions(data) => {
+                let new = ProcessorSettings {
+                    devices: settings.devices.clone(),
+                    shortcuts: settings.shortcuts.clone(),
+                    ..data.req().clone()
+                };
+                data.set_result(apply(&client, &mut settings, new));
+            }
+            Message::ApplyDeviceList(data) => {
+                let new = ProcessorSettings {
+                    devices: data.req().clone(),
+                    ..settings.clone()
+                };
+                data.set_result(apply(&client, &mut settings, new));
+            }
+            Message::ApplyDeviceSettings(data) => {
+                let mut new = settings.clone();
+                for item in data.take() {
+                    match new.devices.iter_mut().find(|d| d.id == item.id) {
+                        Some(d) => *d = item,
+                        None => new.devices.push(item),
+                    }
+                }
+                if let Err(e) = apply(&client, &mut settings, new) {
+                    debug!("Device settings not applied by running instance: {}", e);
+                }
+                continue;
+            }
+            Message::RefreshMonitors(data) => data.set_error(Error::ViewOnly),
+            Message::IdentifyDevice(data) => data.set_error(Error::ViewOnly),
+            Message::InspectUsageStats(data) => data.set_error(Error::ViewOnly),
+            Message::CaptureTrace(data) => data.set_error(Error::ViewOnly),
+            Message::CaptureDeviceExtent(data) => data.set_error(Error::ViewOnly),
+            _ => {
+                debug!("Ignored in view-only mode: {:?}", msg);
+                continue;
+            }
+        }
+        reactor.return_msg(msg);
+    }
+}
+
+fn snapshot_status(client: &ViewClient) -> Result<Vec<(String, DeviceStatus)>, Error> {
+    client.snapshot().map(|s| {
+        s.devices
+            .into_iter()
+            .map(|(generic, status)| (generic.id, status))
+            .collect()
+    })
+}
+
+// Unchanged settings are not sent, e.g. the first applying of those from the snapshot
+fn apply(
+    client: &ViewClient,
+    settings: &mut ProcessorSettings,
+    new: ProcessorSettings,
+) -> Result<(), Error> {
+    if new == *settings {
+        return Ok(());
+    }
+    client.apply(new.clone())?;
+    *settings = new;
+    Ok(())
+}
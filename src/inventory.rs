@@ -0,0 +1,164 @@
+// Machine-readable list of detected devices, for inventory tooling and bug reports
+
+use std::fmt::Display;
+
+use crate::{message::GenericDevice, utils::json_str};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InventoryFormat {
+    Json,
+    // One column for each key of platform specific infos among all devices
+    Csv,
+}
+
+impl InventoryFormat {
+    pub const VARIANTS: [InventoryFormat; 2] = [Self::Json, Self::Csv];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .into_iter()
+            .find(|f| f.extension().eq_ignore_ascii_case(name))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl Display for InventoryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Json => "JSON",
+            Self::Csv => "CSV",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+pub fn export_devices(devices: &[GenericDevice], format: InventoryFormat) -> String {
+    match format {
+        InventoryFormat::Json => to_json(devices),
+        InventoryFormat::Csv => to_csv(devices),
+    }
+}
+
+fn to_json(devices: &[GenericDevice]) -> String {
+    let items: Vec<String> = devices
+        .iter()
+        .map(|d| {
+            let infos: Vec<String> = d
+                .platform_specific_infos
+                .iter()
+                .map(|(k, v)| format!("{}:{}", json_str(k), json_str(v)))
+                .collect();
+            format!(
+                "  {{\"id\":{},\"type\":{},\"product\":{},\"platform_specific_infos\":{{{}}}}}",
+                json_str(&d.id),
+                json_str(&d.device_type.to_string()),
+                json_str(&d.product_name),
+                infos.join(",")
+            )
+        })
+        .collect();
+    if items.is_empty() {
+        return "[]\n".to_owned();
+    }
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+// Quoted only if needed, by RFC 4180
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn to_csv(devices: &[GenericDevice]) -> String {
+    // In the order first seen
+    let mut keys: Vec<&str> = Vec::new();
+    for d in devices {
+        for (k, _) in &d.platform_specific_infos {
+            if !keys.contains(&k.as_str()) {
+                keys.push(k);
+            }
+        }
+    }
+    let mut header = vec!["id", "type", "product"];
+    header.extend(keys.iter());
+    let mut lines = vec![header
+        .iter()
+        .map(|h| csv_field(h))
+        .collect::<Vec<_>>()
+        .join(",")];
+    for d in devices {
+        let mut fields = vec![
+            csv_field(&d.id),
+            csv_field(&d.device_type.to_string()),
+            csv_field(&d.product_name),
+        ];
+        fields.extend(keys.iter().map(|key| {
+            d.platform_specific_infos
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(String::new(), |(_, v)| csv_field(v))
+        }));
+        lines.push(fields.join(","));
+    }
+    lines.join("\r\n") + "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device_type::DeviceType;
+
+    #[test]
+    fn test_export_devices() {
+        let devices = vec![
+            GenericDevice {
+                id: r"\\?\HID#VID_046D".to_owned(),
+                device_type: DeviceType::Mouse,
+                product_name: "Mouse \"M1\", wireless".to_owned(),
+                platform_specific_infos: vec![("Handle".to_owned(), "0x1".to_owned())],
+            },
+            GenericDevice {
+                id: "pen".to_owned(),
+                device_type: DeviceType::Pen,
+                product_name: "Pen".to_owned(),
+                platform_specific_infos: vec![
+                    ("Interface".to_owned(), "usb".to_owned()),
+                    ("Handle".to_owned(), "0x2".to_owned()),
+                ],
+            },
+        ];
+        assert_eq!(
+            export_devices(&devices, InventoryFormat::Json),
+            concat!(
+                "[\n",
+                r#"  {"id":"\\\\?\\HID#VID_046D","type":"Mouse","product":"Mouse \"M1\", wireless","platform_specific_infos":{"Handle":"0x1"}},"#,
+                "\n",
+                r#"  {"id":"pen","type":"Pen","product":"Pen","platform_specific_infos":{"Interface":"usb","Handle":"0x2"}}"#,
+                "\n]\n"
+            )
+        );
+        assert_eq!(
+            export_devices(&devices, InventoryFormat::Csv),
+            concat!(
+                "id,type,product,Handle,Interface\r\n",
+                r#"\\?\HID#VID_046D,Mouse,"Mouse ""M1"", wireless",0x1,"#,
+                "\r\n",
+                "pen,Pen,Pen,0x2,usb\r\n"
+            )
+        );
+        assert_eq!(export_devices(&[], InventoryFormat::Json), "[]\n");
+        assert_eq!(
+            InventoryFormat::from_name("JSON"),
+            Some(InventoryFormat::Json)
+        );
+    }
+}
@@ -0,0 +1,109 @@
+// Shortcuts likely taken by Windows or popular apps, to explain why registering one failed
+// and to suggest free ones instead. RegisterHotKey only tells a conflict, not who owns it.
+
+use keyboard_types::{Code, Modifiers};
+
+use super::{shortcut_from_str, shortcut_to_str};
+
+const WELL_KNOWN: &[(&str, &str)] = &[
+    ("Win+L", "Windows, lock screen"),
+    ("Win+D", "Windows, show desktop"),
+    ("Win+E", "Windows, file explorer"),
+    ("Win+R", "Windows, run dialog"),
+    ("Win+P", "Windows, project to display"),
+    ("Win+Tab", "Windows, task view"),
+    ("Win+Left", "Windows, snap window"),
+    ("Win+Right", "Windows, snap window"),
+    ("Win+Up", "Windows, maximize window"),
+    ("Win+Down", "Windows, minimize window"),
+    ("Win+Shift+Left", "Windows, move window to other monitor"),
+    ("Win+Shift+Right", "Windows, move window to other monitor"),
+    ("Ctrl+Win+Left", "Windows, switch virtual desktop"),
+    ("Ctrl+Win+Right", "Windows, switch virtual desktop"),
+    ("Ctrl+Win+D", "Windows, new virtual desktop"),
+    ("Win+Shift+S", "Windows, snipping tool"),
+    ("Ctrl+Shift+Escape", "Windows, task manager"),
+    ("Alt+Tab", "Windows, switch windows"),
+    ("Alt+F4", "Windows, close window"),
+    ("Ctrl+Alt+Up", "Graphics drivers, rotate screen"),
+    ("Ctrl+Alt+Down", "Graphics drivers, rotate screen"),
+    ("Ctrl+Alt+Left", "Graphics drivers, rotate screen"),
+    ("Ctrl+Alt+Right", "Graphics drivers, rotate screen"),
+    ("Alt+Shift+Tab", "Windows, switch windows"),
+    ("Ctrl+Alt+Delete", "Windows, security screen"),
+    ("Ctrl+Shift+M", "Teams, toggle mute"),
+    ("Ctrl+Alt+A", "Chat apps, screenshot"),
+    ("Alt+Z", "NVIDIA GeForce Experience, overlay"),
+    ("Alt+F9", "NVIDIA GeForce Experience, recording"),
+    ("Alt+F10", "NVIDIA GeForce Experience, instant replay"),
+    ("Win+Alt+R", "Xbox Game Bar, recording"),
+    ("Win+Alt+G", "Xbox Game Bar, record that"),
+];
+
+// Tried in order for suggestions, keeping the key
+const MODIFIER_CHOICES: [Modifiers; 6] = [
+    Modifiers::CONTROL.union(Modifiers::ALT),
+    Modifiers::CONTROL.union(Modifiers::SHIFT),
+    Modifiers::ALT.union(Modifiers::SHIFT),
+    Modifiers::CONTROL
+        .union(Modifiers::ALT)
+        .union(Modifiers::SHIFT),
+    Modifiers::META.union(Modifiers::ALT),
+    Modifiers::CONTROL
+        .union(Modifiers::META)
+        .union(Modifiers::ALT),
+];
+
+fn same_shortcut(a: &str, b: (Modifiers, Code)) -> bool {
+    shortcut_from_str(a) == Some(b)
+}
+
+// Who likely owns the shortcut, regardless of order of modifiers
+pub fn known_owner(shortcut: &str) -> Option<&'static str> {
+    let parsed = shortcut_from_str(shortcut)?;
+    WELL_KNOWN
+        .iter()
+        .find(|(s, _)| same_shortcut(s, parsed))
+        .map(|(_, owner)| *owner)
+}
+
+// Shortcuts of the same key with other modifiers, neither well known nor in taken
+pub fn suggest_alternatives(shortcut: &str, taken: &[&str], max: usize) -> Vec<String> {
+    let Some((modifiers, key)) = shortcut_from_str(shortcut) else {
+        return Vec::new();
+    };
+    MODIFIER_CHOICES
+        .iter()
+        .filter(|&&m| m != modifiers)
+        .filter(|&&m| {
+            !WELL_KNOWN
+                .iter()
+                .map(|(s, _)| *s)
+                .chain(taken.iter().copied())
+                .any(|s| same_shortcut(s, (m, key)))
+        })
+        .take(max)
+        .map(|&m| shortcut_to_str(Some(m), Some(key)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_suggestions() {
+        assert_eq!(
+            known_owner("Alt+Ctrl+Up"),
+            Some("Graphics drivers, rotate screen")
+        );
+        assert_eq!(known_owner("Ctrl+Alt+F9"), None);
+
+        // Ctrl+Shift+M is taken by Teams, Ctrl+Alt+M by another shortcut
+        assert_eq!(
+            suggest_alternatives("Alt+M", &["Ctrl+Alt+M"], 2),
+            vec!["Alt+Shift+M".to_owned(), "Ctrl+Alt+Shift+M".to_owned()]
+        );
+        assert!(suggest_alternatives("Ctrl+Shift", &[], 3).is_empty());
+    }
+}
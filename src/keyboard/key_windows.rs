@@ -1,10 +1,26 @@
 use keyboard_types::{Code, Modifiers};
 use windows::Win32::UI::Input::KeyboardAndMouse::*;
 
-use super::shortcut_from_str;
+use super::{
+    code_to_scancode, key_to_str, modifiers_from_str, scancode_to_code, shortcut_from_str,
+    str_to_key,
+};
 
-pub fn shortcut_str_to_win(st: &str) -> Option<(HOT_KEY_MODIFIERS, VIRTUAL_KEY)> {
-    shortcut_from_str(st).and_then(|(m, code)| key_to_win(code).map(|c| (modifier_to_win(m), c)))
+// Keys are named by positions on US layout if positional, or else by labels on current layout
+pub fn shortcut_str_to_win(st: &str, positional: bool) -> Option<(HOT_KEY_MODIFIERS, VIRTUAL_KEY)> {
+    shortcut_from_str(st)
+        .and_then(|(m, code)| key_to_win(code, positional).map(|c| (modifier_to_win(m), c)))
+}
+
+// Key is None for shortcuts of only modifiers, which only the hook backend supports
+pub fn shortcut_str_to_hook(
+    st: &str,
+    positional: bool,
+) -> Option<(HOT_KEY_MODIFIERS, Option<VIRTUAL_KEY>)> {
+    match shortcut_str_to_win(st, positional) {
+        Some((m, key)) => Some((m, Some(key))),
+        None => modifiers_from_str(st).map(|m| (modifier_to_win(m), None)),
+    }
 }
 
 pub fn modifier_to_win(m: Modifiers) -> HOT_KEY_MODIFIERS {
@@ -24,7 +40,64 @@ pub fn modifier_to_win(m: Modifiers) -> HOT_KEY_MODIFIERS {
     r
 }
 
-pub fn key_to_win(key: Code) -> Option<VIRTUAL_KEY> {
+// Virtual key of the key at the position of code, on the keyboard layout of current thread.
+// Not positional for keys named by labels, as configs did before.
+pub fn key_to_win(key: Code, positional: bool) -> Option<VIRTUAL_KEY> {
+    if !positional {
+        return key_to_win_us(key);
+    }
+    if let Some(sc) = code_to_scancode(key) {
+        let vk = unsafe { MapVirtualKeyW(sc, MAPVK_VSC_TO_VK) };
+        if vk != 0 {
+            return Some(VIRTUAL_KEY(vk as u16));
+        }
+    }
+    key_to_win_us(key)
+}
+
+// Label of the key on current layout, e.g. "Q" for KeyA on AZERTY
+pub fn key_label(key: Code) -> String {
+    let label = code_to_scancode(key)
+        .and_then(|_| key_to_win(key, true))
+        .map(|vk| unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_CHAR) })
+        // High bit set for dead keys
+        .and_then(|c| char::from_u32(c & 0x7FFF_FFFF))
+        .filter(|c| !c.is_control() && !c.is_whitespace());
+    match label {
+        Some(c) => c.to_uppercase().to_string(),
+        None => key_to_str(key).to_owned(),
+    }
+}
+
+// As labeled on current layout, e.g. "Ctrl+Q" for "Ctrl+A" on AZERTY. Other parts are kept, and
+// shortcuts not positional are already by labels.
+pub fn shortcut_display_str(st: &str, positional: bool) -> String {
+    if !positional {
+        return st.to_owned();
+    }
+    st.split('+')
+        .map(|part| match str_to_key(part) {
+            Some(code) => key_label(code),
+            None => part.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+// Key at the position of the one labeled as code on current layout, for recording what users
+// press. E.g. KeyQ for KeyA pressed on AZERTY.
+pub fn label_to_key(label: Code) -> Code {
+    if code_to_scancode(label).is_none() {
+        return label;
+    }
+    key_to_win_us(label)
+        .map(|vk| unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC) })
+        .and_then(scancode_to_code)
+        .unwrap_or(label)
+}
+
+// Virtual keys of US layout
+fn key_to_win_us(key: Code) -> Option<VIRTUAL_KEY> {
     Some(match key {
         Code::ArrowDown => VK_DOWN,
         Code::ArrowLeft => VK_LEFT,
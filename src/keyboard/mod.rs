@@ -1,3 +1,4 @@
+pub mod conflict;
 pub mod key_egui;
 #[cfg(target_os = "windows")]
 pub mod key_windows;
@@ -102,6 +103,91 @@ pub fn shortcut_from_str(s: &str) -> Option<(Modifiers, Code)> {
     }
 }
 
+// Shortcut of only modifiers, e.g. "Ctrl+Shift"
+pub fn modifiers_from_str(s: &str) -> Option<Modifiers> {
+    let mut m: Option<Modifiers> = None;
+    for sub in s.split('+') {
+        m = match sub {
+            "Ctrl" => modifier_or(m, Modifiers::CONTROL),
+            META_STR => modifier_or(m, Modifiers::META),
+            "Alt" => modifier_or(m, Modifiers::ALT),
+            "Shift" => modifier_or(m, Modifiers::SHIFT),
+            _ => return None,
+        };
+    }
+    m
+}
+
+// Whether both name the same keys, regardless of order of modifiers
+pub fn shortcuts_equal(a: &str, b: &str) -> bool {
+    if let (Some(x), Some(y)) = (shortcut_from_str(a), shortcut_from_str(b)) {
+        return x == y;
+    }
+    if let (Some(x), Some(y)) = (modifiers_from_str(a), modifiers_from_str(b)) {
+        return x == y;
+    }
+    a == b
+}
+
+// Keys labeled differently by keyboard layouts, with their scancodes. Shortcuts name them as
+// on US layout, meaning the key at that position, so "Ctrl+Alt+Q" is the same key on AZERTY
+// or QWERTZ keyboards however it's labeled.
+const POSITIONAL_KEYS: [(Code, u32); 38] = [
+    (Code::Digit1, 0x02),
+    (Code::Digit2, 0x03),
+    (Code::Digit3, 0x04),
+    (Code::Digit4, 0x05),
+    (Code::Digit5, 0x06),
+    (Code::Digit6, 0x07),
+    (Code::Digit7, 0x08),
+    (Code::Digit8, 0x09),
+    (Code::Digit9, 0x0A),
+    (Code::Digit0, 0x0B),
+    (Code::Minus, 0x0C),
+    (Code::Equal, 0x0D),
+    (Code::KeyQ, 0x10),
+    (Code::KeyW, 0x11),
+    (Code::KeyE, 0x12),
+    (Code::KeyR, 0x13),
+    (Code::KeyT, 0x14),
+    (Code::KeyY, 0x15),
+    (Code::KeyU, 0x16),
+    (Code::KeyI, 0x17),
+    (Code::KeyO, 0x18),
+    (Code::KeyP, 0x19),
+    (Code::KeyA, 0x1E),
+    (Code::KeyS, 0x1F),
+    (Code::KeyD, 0x20),
+    (Code::KeyF, 0x21),
+    (Code::KeyG, 0x22),
+    (Code::KeyH, 0x23),
+    (Code::KeyJ, 0x24),
+    (Code::KeyK, 0x25),
+    (Code::KeyL, 0x26),
+    (Code::KeyZ, 0x2C),
+    (Code::KeyX, 0x2D),
+    (Code::KeyC, 0x2E),
+    (Code::KeyV, 0x2F),
+    (Code::KeyB, 0x30),
+    (Code::KeyN, 0x31),
+    (Code::KeyM, 0x32),
+];
+
+// None for keys labeled the same on all layouts, e.g. F1 or Home
+pub fn code_to_scancode(code: Code) -> Option<u32> {
+    POSITIONAL_KEYS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|&(_, sc)| sc)
+}
+
+pub fn scancode_to_code(scancode: u32) -> Option<Code> {
+    POSITIONAL_KEYS
+        .iter()
+        .find(|(_, sc)| *sc == scancode)
+        .map(|&(c, _)| c)
+}
+
 pub fn key_to_str(key: Code) -> &'static str {
     match key {
         Code::ArrowDown => "Down",
@@ -307,5 +393,31 @@ mod tests {
         assert_eq!(shortcut_from_str("Ctrl+Shift+A+D"), None);
         // No modifier
         assert_eq!(shortcut_from_str("A"), None);
+
+        assert_eq!(
+            modifiers_from_str("Ctrl+Shift"),
+            Some(Modifiers::CONTROL | Modifiers::SHIFT)
+        );
+        assert_eq!(modifiers_from_str("Ctrl+Shift+A"), None);
+        assert_eq!(modifiers_from_str("Ctrl+"), None);
+
+        assert!(shortcuts_equal(
+            "Ctrl+Alt+Shift+Escape",
+            "Shift+Ctrl+Alt+Escape"
+        ));
+        assert!(shortcuts_equal("Ctrl+Shift", "Shift+Ctrl"));
+        assert!(!shortcuts_equal("Ctrl+Alt+F9", "Ctrl+F9"));
+        assert!(!shortcuts_equal("Ctrl+Shift", "Ctrl+Shift+A"));
+    }
+
+    #[test]
+    fn test_positional_keys() {
+        assert_eq!(code_to_scancode(Code::KeyA), Some(0x1E));
+        assert_eq!(scancode_to_code(0x10), Some(Code::KeyQ));
+        assert_eq!(code_to_scancode(Code::F1), None);
+        for (code, sc) in POSITIONAL_KEYS {
+            assert_eq!(scancode_to_code(sc), Some(code));
+            assert!(str_to_key(key_to_str(code)) == Some(code));
+        }
     }
 }
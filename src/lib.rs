@@ -1,10 +1,34 @@
+#[cfg(feature = "async")]
+pub mod async_api;
+pub mod bridge;
+pub mod config_sync;
+pub mod config_writer;
+pub mod crash_report;
 pub mod device_type;
 pub mod errors;
+pub mod event_command;
+pub mod geometry;
+pub mod gesture;
+pub mod inventory;
 pub mod keyboard;
 pub mod message;
 pub mod mouse_control;
+pub mod paths;
+pub mod preset;
+pub mod remote_action;
+pub mod risk;
 pub mod setting;
+pub mod settings_doc;
+#[cfg(any(debug_assertions, feature = "devsim"))]
+pub mod simulate;
+pub mod stats;
+pub mod trace;
+pub mod update_check;
 pub mod utils;
+pub mod version;
+pub mod view_ipc;
+
+pub use version::build_info;
 
 pub use platform::*;
 
@@ -17,6 +41,29 @@ pub mod platform {
     use super::windows;
     pub type Eventloop = windows::win_processor::WinEventLoop;
     pub type SingleProcess = windows::SingleProcess;
-    pub const POLL_MSGS: u32 = windows::constants::WIN_EVENTLOOP_POLL_MAX_MESSAGES;
-    pub const POLL_TIMEOUT: u32 = windows::constants::WIN_EVENTLOOP_POLL_WAIT_TIMEOUT_MS;
+    pub use windows::tray_click::TrayMiddleClick;
+    pub use windows::watchdog::{run_watchdog, spawn_watchdog, watchdog_args};
+    pub use windows::win_processor::send_remote_action;
+    #[cfg(feature = "devsim")]
+    pub use windows::winwrap::send_mouse_move;
+    pub use windows::winwrap::{
+        delete_registry_value, https_get, registry_string_value, registry_value_exists,
+    };
+    pub fn eventloop_wakeups() -> u64 {
+        windows::win_processor::EVENTLOOP_WAKEUPS.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    pub fn offscreen_relocations() -> u64 {
+        windows::win_processor::OFFSCREEN_RELOCATIONS.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    pub fn debounced_clicks() -> u64 {
+        windows::win_processor::DEBOUNCED_CLICKS.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    // Late ones and all of raw input events processed
+    pub fn late_input_events() -> (u64, u64) {
+        use std::sync::atomic::Ordering::Relaxed;
+        (
+            windows::win_processor::LATE_INPUT_EVENTS.load(Relaxed),
+            windows::win_processor::INPUT_EVENTS.load(Relaxed),
+        )
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
-    fmt::Debug,
+    fmt::{Debug, Display},
+    path::PathBuf,
     sync::mpsc::{
         channel, sync_channel, Receiver, RecvError, RecvTimeoutError, Sender, SyncSender,
         TryRecvError,
@@ -7,28 +8,69 @@ use std::{
     time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
+    bridge::{BusEvent, EventBus, Topic},
+    config_sync::SyncedSettings,
+    config_writer::SaveState,
     device_type::DeviceType,
     errors::Error,
-    setting::{DeviceSettingItem, ProcessorSettings},
+    geometry::MonitorArea,
+    setting::{DeviceSetting, DeviceSettingItem, ProcessorSettings, ShortcutSettings},
+    stats::UsageStats,
+    update_check::LatestRelease,
+    view_ipc::ViewCall,
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Positioning {
     Unknown,
     Relative,
     Absolute,
 }
 
-#[derive(Debug)]
+// How cursor position is tracked, the hook may be blocked by security software
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackingMode {
+    #[default]
+    Hook,
+    // Sampled periodically, with reduced fidelity
+    Polling,
+}
+
+impl Display for TrackingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackingMode::Hook => write!(f, "mouse hook"),
+            TrackingMode::Polling => write!(f, "polling"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceStatus {
-    Active(Positioning),
+    // Raw is classified from events, before force_positioning of device setting applied
+    Active {
+        positioning: Positioning,
+        raw: Positioning,
+    },
     Idle,
     Disconnected,
     Unknown,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct ActiveDeviceEvent {
+    pub id: String,
+    pub device_type: DeviceType,
+    pub positioning: Positioning,
+}
+
+// Ids of placeholders for handles sending events, but not listed by system
+pub const ORPHAN_DEVICE_ID_PREFIX: &str = "Orphan#";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenericDevice {
     pub id: String,
     pub device_type: DeviceType,
@@ -45,6 +87,34 @@ impl GenericDevice {
             platform_specific_infos: Vec::new(),
         }
     }
+
+    pub fn is_orphan(&self) -> bool {
+        self.id.starts_with(ORPHAN_DEVICE_ID_PREFIX)
+    }
+}
+
+// One check of self-test diagnostics
+#[derive(Debug)]
+pub struct DiagnosticCheck {
+    pub name: &'static str,
+    // Details if passed, or the error
+    pub result: std::result::Result<String, String>,
+    // How to fix it, if failed
+    pub hint: &'static str,
+}
+
+impl DiagnosticCheck {
+    pub fn new<T: ToString, E: ToString>(
+        name: &'static str,
+        result: std::result::Result<T, E>,
+        hint: &'static str,
+    ) -> Self {
+        DiagnosticCheck {
+            name,
+            result: result.map(|v| v.to_string()).map_err(|e| e.to_string()),
+            hint,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -112,6 +182,8 @@ impl<TReq, TRsp> RoundtripData<TReq, TRsp> {
     }
 }
 
+// Not sent by the UI any more, which is pushed device statuses, see WatchDevicesStatus. Kept
+// with timer_spawn for apps embedding the crate.
 #[derive(Clone, Copy, Debug)]
 pub enum TimerDueKind {
     InspectDevice,
@@ -121,12 +193,65 @@ pub enum TimerDueKind {
 pub enum Message {
     Exit,
     RestartUI,
+    RestartProcessor,
+    // Published by mouse control, forwarded by UIReactor::forward_bus_events()
+    Bus(BusEvent),
+    // Apply locks again, or pause mouse control
+    ConfirmMonitorsChanged(bool /* apply */),
+    // Pushed by another machine, see config_sync
+    SyncReceived(Box<SyncedSettings>),
+    // Errors of pushing to each peer, empty if all succeeded
+    SyncPushed(Vec<String>),
+    // Reported by the config writer thread
+    ConfigSaveState(SaveState),
+    // Latest release found by update_check, or error of checking
+    UpdateChecked(Result<LatestRelease>),
+    // Cache of the last check got a week old, see update_check::weekly_check_due
+    UpdateCheckDue,
+    // From a view-only window of another process, answered by the call
+    ViewCall(ViewCall),
     TimerDue(TimerDueKind),
     LockCurMouse(String),
+    // Already applied by mouse control, e.g. by device rules
+    DeviceSettingsChanged(Vec<(String, DeviceSetting)>),
+    // Already applied by mouse control, by shortcut cycle_switch_devices
+    SwitchCycled(Vec<(String, DeviceSetting)>),
     ScanDevices(RoundtripData<(), Vec<GenericDevice>>),
+    // Responded with labels of monitors, in jumping order
+    RefreshMonitors(RoundtripData<(), Vec<String>>),
+    // Responded with the first device emitting event, or None if timed out
+    IdentifyDevice(RoundtripData<(), Option<String>>),
     InspectDevicesStatus(RoundtripData<(), Vec<(String, DeviceStatus)>>),
+    // Statuses are pushed on changes while watched, at most once in the interval. None to
+    // stop, e.g. when UI window is closed to tray.
+    WatchDevicesStatus(Option<u64 /* interval ms */>),
+    DevicesStatusChanged(Vec<(String, DeviceStatus)>),
+    // Responded with today's date, and stats until now
+    InspectUsageStats(RoundtripData<(), (String, UsageStats)>),
+    // Requested with trace file and seconds to capture, responded when finished with the
+    // file and number of records
+    CaptureTrace(RoundtripData<(PathBuf, u32), (PathBuf, u64)>),
+    // Requested with device id and seconds to sweep it over its surface, responded with the
+    // extent its positions reached and labels of monitors, for calibrating absolute devices
+    CaptureDeviceExtent(RoundtripData<(String, u32), (MonitorArea, Vec<String>)>),
     ApplyProcessorSetting(RoundtripData<ProcessorSettings, ()>),
-    ApplyOneDeviceSetting(SendData<DeviceSettingItem>),
+    // Parts of processor settings, each applied without touching the others
+    ApplyShortcuts(RoundtripData<ShortcutSettings, ()>),
+    // All but devices and shortcuts, which are ignored
+    ApplyGlobalProcessorOptions(RoundtripData<ProcessorSettings, ()>),
+    ApplyDeviceList(RoundtripData<Vec<DeviceSettingItem>, ()>),
+    // Changed settings of some devices, the others are untouched
+    ApplyDeviceSettings(SendData<Vec<DeviceSettingItem>>),
+    // Applied for some seconds to try it, then the device setting before is restored
+    TemporaryApply(SendData<(DeviceSettingItem, u32 /* secs */)>),
+    #[cfg(debug_assertions)]
+    AddSimulatedDevice(SendData<crate::simulate::SimulatedDeviceSpec>),
+    #[cfg(debug_assertions)]
+    ClearSimulatedDevices,
+    TogglePause,
+    JumpNextMonitor,
+    // Of UI settings, since the overlay is drawn by mouse control. Off without UI.
+    AnimateJumps(bool),
 }
 
 #[repr(i32)]
@@ -134,6 +259,16 @@ pub enum Message {
 pub enum ShortcutID {
     CurMouseLock = 1000,
     CurMouseJumpNext = 1001,
+    CurMouseTurbo = 1002,
+    CurMouseJumpLeft = 1003,
+    CurMouseJumpRight = 1004,
+    CurMouseJumpUp = 1005,
+    CurMouseJumpDown = 1006,
+    MoveWindowHere = 1007,
+    // Not configurable, see FAILSAFE_SHORTCUT
+    Failsafe = 1008,
+    CurMouseFocusMode = 1009,
+    CycleSwitchDevices = 1010,
 }
 
 pub struct SignalSender(SyncSender<()>);
@@ -167,21 +302,25 @@ pub fn setup_reactors(
 ) -> (TrayReactor, MouseControlReactor, UIReactor) {
     let (ui_tx, ui_rx) = channel::<Message>();
     let (mouse_control_tx, mouse_control_rx) = channel::<Message>();
+    let bus = EventBus::new();
 
     let tray = TrayReactor {
         ui_tx: MessageSender::from(&ui_tx),
         mouse_control_tx: MessageSender::from(&mouse_control_tx),
         ui_notify: ui_notify1,
+        bus: bus.clone(),
     };
     let mouse_ctrl = MouseControlReactor {
         ui_tx: MessageSender::from(&ui_tx),
         mouse_control_rx: MessageReceiver::from(mouse_control_rx),
         ui_notify: ui_notify2,
+        bus: bus.clone(),
     };
     let ui = UIReactor {
         ui_rx: MessageReceiver::from(ui_rx),
         ui_tx: MessageSender::from(&ui_tx),
         mouse_control_tx: MessageSender::from(&mouse_control_tx),
+        bus,
     };
 
     (tray, mouse_ctrl, ui)
@@ -191,9 +330,13 @@ pub struct TrayReactor {
     ui_tx: MessageSender,
     mouse_control_tx: MessageSender,
     ui_notify: Box<dyn UINotify>,
+    bus: EventBus,
 }
 
 impl TrayReactor {
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
     pub fn exit(&self) {
         self.ui_notify.notify_close();
         self.ui_tx.send(Message::Exit);
@@ -202,21 +345,42 @@ impl TrayReactor {
     pub fn restart_ui(&self) {
         self.ui_tx.send(Message::RestartUI);
     }
+    pub fn toggle_pause(&self) {
+        self.mouse_control_tx.send(Message::TogglePause);
+    }
+    pub fn jump_next_monitor(&self) {
+        self.mouse_control_tx.send(Message::JumpNextMonitor);
+    }
 }
 
 pub struct UIReactor {
     pub ui_rx: MessageReceiver,
     pub ui_tx: MessageSender,
     pub mouse_control_tx: MessageSender,
+    pub bus: EventBus,
+}
+
+impl UIReactor {
+    // Events of topics are received along with other messages, as Message::Bus
+    pub fn forward_bus_events(&self, topics: &[Topic]) {
+        let tx = self.ui_tx.0.clone();
+        self.bus
+            .forward(topics, move |event| tx.send(Message::Bus(event)).is_ok());
+    }
 }
 
 pub struct MouseControlReactor {
     pub ui_tx: MessageSender,
     pub mouse_control_rx: MessageReceiver,
     ui_notify: Box<dyn UINotify>,
+    bus: EventBus,
 }
 
 impl MouseControlReactor {
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
     #[inline]
     pub fn return_msg(&self, msg: Message) {
         match msg {
@@ -228,13 +392,122 @@ impl MouseControlReactor {
                 self.ui_tx.send(msg);
                 self.ui_notify.notify();
             }
+            Message::InspectUsageStats(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
+            Message::CaptureTrace(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
+            Message::RefreshMonitors(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
+            Message::IdentifyDevice(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
             Message::ApplyProcessorSetting(_) => {
                 self.ui_tx.send(msg);
                 self.ui_notify.notify();
             }
+            Message::ApplyShortcuts(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
+            Message::ApplyGlobalProcessorOptions(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
+            Message::ApplyDeviceList(_) => {
+                self.ui_tx.send(msg);
+                self.ui_notify.notify();
+            }
             _ => panic!("MouseControl should not return msg: {:?}", msg),
         }
     }
+
+    pub fn restart_ui(&self) {
+        self.ui_tx.send(Message::RestartUI);
+        self.ui_notify.notify();
+    }
+
+    pub fn report_crashed(&self, reason: String) {
+        self.bus.publish(BusEvent::ProcessorCrashed(reason));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_session_changed(&self, paused: bool) {
+        self.bus.publish(BusEvent::SessionChanged(paused));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_initial_scan(&self, in_progress: bool) {
+        self.bus.publish(BusEvent::InitialScan(in_progress));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_user_pause_changed(&self, paused: bool) {
+        self.bus.publish(BusEvent::UserPauseChanged(paused));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_tracking_mode_changed(&self, mode: TrackingMode) {
+        self.bus.publish(BusEvent::TrackingModeChanged(mode));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_active_device_changed(&self, event: ActiveDeviceEvent) {
+        self.bus.publish(BusEvent::ActiveDeviceChanged(event));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_monitor_jumped(&self) {
+        self.bus.publish(BusEvent::MonitorJumped);
+        self.ui_notify.notify();
+    }
+
+    pub fn report_monitors_changed(&self, layout: String) {
+        self.bus.publish(BusEvent::MonitorsChanged(layout));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_failsafe_triggered(&self) {
+        self.bus.publish(BusEvent::FailsafeTriggered);
+        self.ui_notify.notify();
+    }
+
+    pub fn report_shortcuts_pending(&self, names: Vec<String>) {
+        self.bus.publish(BusEvent::ShortcutsPending(names));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_devices_updated(&self, devices: Vec<GenericDevice>) {
+        self.bus.publish(BusEvent::DevicesUpdated(devices));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_devices_status(&self, status: Vec<(String, DeviceStatus)>) {
+        self.ui_tx.send(Message::DevicesStatusChanged(status));
+        self.ui_notify.notify();
+    }
+
+    pub fn report_battery_updated(&self, levels: Vec<(String, u8)>) {
+        self.bus.publish(BusEvent::BatteryUpdated(levels));
+        self.ui_notify.notify();
+    }
+
+    pub fn wait_for_restart(&self) -> bool /* restart? */ {
+        loop {
+            match self.mouse_control_rx.recv() {
+                Message::Exit => return false,
+                Message::RestartProcessor => return true,
+                // Requests are dropped while processor is down
+                _ => (),
+            }
+        }
+    }
 }
 
 pub struct MessageReceiver(Receiver<Message>);
@@ -315,6 +588,7 @@ impl TimerOperator {
     }
 }
 
+// Sends Message::TimerDue of kind every interval, until stopped
 pub fn timer_spawn(
     mut interval: Duration,
     tx: MessageSender,
@@ -1,26 +1,13 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
 
+use crate::geometry::{self, Corner, Direction};
+pub use crate::geometry::{MonitorArea, MonitorId, MousePos};
 use crate::message::Positioning;
-use crate::setting::DeviceSetting;
-use crate::utils::vec_ensure_get_mut;
-
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
-pub struct MousePos {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl MousePos {
-    pub fn from(x: i32, y: i32) -> Self {
-        MousePos { x, y }
-    }
-}
-
-impl Display for MousePos {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", self.x, self.y)
-    }
-}
+use crate::setting::{
+    ActivationGrace, DeviceSetting, JumpTarget, MonitorOrder, ParkPosition, SwitchMode,
+};
+use crate::utils::wildcard_match;
 
 #[derive(Debug)]
 pub struct DeviceController {
@@ -30,8 +17,14 @@ pub struct DeviceController {
     last_active_tick: u64, // in ms
     last_active_pos: MousePos,
 
+    // As classified from events, and after setting.force_positioning applied
+    raw_positioning: Positioning,
     positioning: Positioning,
     locked_area: Option<MonitorArea>,
+    // Cursor has been parked since the last event
+    parked: bool,
+    debounce: ClickDebounce,
+    activation: ActivationCounter,
 }
 
 impl DeviceController {
@@ -41,18 +34,41 @@ impl DeviceController {
             setting,
             last_active_tick: 0,
             last_active_pos: MousePos::default(),
-            positioning: Positioning::Unknown,
+            raw_positioning: Positioning::Unknown,
+            positioning: setting.force_positioning.apply(Positioning::Unknown),
             locked_area: None,
+            parked: false,
+            debounce: ClickDebounce::default(),
+            activation: ActivationCounter::default(),
         }
     }
 
     pub fn update_settings(&mut self, new_setting: &DeviceSetting) {
         self.locked_area = None;
+        self.activation = ActivationCounter::default();
         self.setting = *new_setting;
+        self.positioning = self.setting.force_positioning.apply(self.raw_positioning);
+    }
+
+    pub fn setting(&self) -> &DeviceSetting {
+        &self.setting
     }
 
     pub fn update_positioning(&mut self, p: Positioning) {
-        self.positioning = p;
+        self.raw_positioning = p;
+        self.positioning = self.setting.force_positioning.apply(p);
+    }
+
+    pub fn positioning(&self) -> Positioning {
+        self.positioning
+    }
+
+    pub fn locked_area(&self) -> Option<&MonitorArea> {
+        self.locked_area.as_ref()
+    }
+
+    pub fn raw_positioning(&self) -> Positioning {
+        self.raw_positioning
     }
 
     pub fn reset(&mut self) {
@@ -60,9 +76,37 @@ impl DeviceController {
         self.last_active_tick = 0;
     }
 
+    // Release the locked area if device has been idle for long enough.
+    // Returns true if released.
+    pub fn release_lock_if_idle(&mut self, tick: u64) -> bool {
+        let idle_ms = self.setting.idle_release_secs as u64 * 1000;
+        if idle_ms == 0 || self.locked_area.is_none() || self.last_active_tick == 0 {
+            return false;
+        }
+        if tick < self.last_active_tick + idle_ms {
+            return false;
+        }
+        self.locked_area = None;
+        true
+    }
+
     fn update_pos(&mut self, p: &MousePos, tick: u64) {
         self.last_active_pos = *p;
         self.last_active_tick = tick;
+        self.parked = false;
+    }
+
+    // Returns true if the button event should be dropped
+    pub fn debounce_click(&mut self, button: usize, down: bool, tick: u64) -> bool {
+        let window_ms = self.setting.debounce_click_ms as u64;
+        self.debounce.on_button(button, down, tick, window_ms)
+    }
+
+    // Counts an event while another device is active. Returns whether it is to become
+    // active, or None if it has no activation grace set.
+    pub fn count_activation(&mut self, tick: u64) -> Option<bool> {
+        let grace = self.setting.activation_grace?;
+        Some(self.activation.on_event(tick, &grace))
     }
 
     pub fn get_last_pos(&self) -> Option<(u64, MousePos, Positioning)> {
@@ -78,6 +122,56 @@ impl DeviceController {
     }
 }
 
+pub const DEBOUNCE_BUTTONS: usize = 5;
+
+// Drops a press of a button arriving too soon after its release, and the release of it
+#[derive(Debug, Default)]
+pub struct ClickDebounce {
+    last_up_tick: [u64; DEBOUNCE_BUTTONS],
+    dropping: [bool; DEBOUNCE_BUTTONS],
+}
+
+impl ClickDebounce {
+    pub fn on_button(&mut self, button: usize, down: bool, tick: u64, window_ms: u64) -> bool {
+        if button >= DEBOUNCE_BUTTONS || window_ms == 0 {
+            return false;
+        }
+        if down {
+            let last_up = self.last_up_tick[button];
+            self.dropping[button] = last_up > 0 && tick.saturating_sub(last_up) < window_ms;
+            self.dropping[button]
+        } else if std::mem::take(&mut self.dropping[button]) {
+            true
+        } else {
+            self.last_up_tick[button] = tick;
+            false
+        }
+    }
+}
+
+// Events of a device not active, since the first one within the window of its grace
+#[derive(Debug, Default)]
+pub struct ActivationCounter {
+    since: u64,
+    events: u32,
+}
+
+impl ActivationCounter {
+    // Returns true once enough events came within the window, then counts from zero again
+    pub fn on_event(&mut self, tick: u64, grace: &ActivationGrace) -> bool {
+        if self.events == 0 || tick.saturating_sub(self.since) > grace.within_ms as u64 {
+            self.since = tick;
+            self.events = 0;
+        }
+        self.events += 1;
+        if self.events < grace.events {
+            return false;
+        }
+        self.events = 0;
+        true
+    }
+}
+
 pub struct RelocatePos(pub MousePos);
 
 impl RelocatePos {
@@ -93,9 +187,31 @@ pub struct MouseRelocator {
     cur_pos: MousePos,
     relocate_pos: Option<RelocatePos>,
     to_update_monitors: bool,
-    last_jump_pos: Vec<Option<MousePos>>,
+    // Keyed by monitor identity, since monitors may be enumerated again in another order
+    last_jump_pos: BTreeMap<MonitorId, MousePos>,
+    corner_dwell: Option<CornerDwell>,
+    use_work_area: bool,
+    monitor_order: MonitorOrder,
+    monitor_groups: Vec<Vec<String>>,
+    // As updated, before grouped and sorted
+    enumerated: Vec<MonitorArea>,
+    // Device being calibrated, and the extent of its positions so far
+    extent_capture: Option<(u64, Option<MonitorArea>)>,
 }
 
+// Cursor staying in a corner of monitor since tick
+#[derive(Clone, Copy)]
+struct CornerDwell {
+    corner: Corner,
+    since: u64,
+    fired: bool,
+}
+
+// Pixels from monitor edges, which are still counted as in the corner
+const HOT_CORNER_MARGIN: i32 = 3;
+// Parked cursor keeps off hot corners
+const PARK_MARGIN: i32 = HOT_CORNER_MARGIN * 3;
+
 impl Default for MouseRelocator {
     fn default() -> Self {
         Self::new()
@@ -110,32 +226,120 @@ impl MouseRelocator {
             cur_pos: MousePos::default(),
             relocate_pos: None,
             to_update_monitors: false,
-            last_jump_pos: Vec::new(),
+            last_jump_pos: BTreeMap::new(),
+            corner_dwell: None,
+            use_work_area: false,
+            monitor_order: MonitorOrder::default(),
+            monitor_groups: Vec::new(),
+            enumerated: Vec::new(),
+            extent_capture: None,
+        }
+    }
+
+    // Jumping and centering avoid taskbar, by targeting work areas of monitors
+    pub fn set_use_work_area(&mut self, v: bool) {
+        self.use_work_area = v;
+    }
+
+    // Monitors are sorted again at next update, since enumeration order is lost once sorted
+    pub fn set_monitor_order(&mut self, order: MonitorOrder) {
+        if self.monitor_order != order {
+            self.monitor_order = order;
+            self.to_update_monitors = true;
+        }
+    }
+
+    // Monitors are merged again at next update, like order
+    pub fn set_monitor_groups(&mut self, groups: &[Vec<String>]) {
+        if self.monitor_groups != groups {
+            self.monitor_groups = groups.to_vec();
+            self.to_update_monitors = true;
+        }
+    }
+
+    // Area where jumping or centering lands
+    fn target_area(&self, id: usize) -> Option<&MonitorArea> {
+        if self.use_work_area {
+            self.monitors.get_work_area(id)
+        } else {
+            self.monitors.get_area(id)
         }
     }
 
     pub fn update_monitors(&mut self, monitors: MonitorAreasList) {
-        self.monitors = monitors;
-        // clear previous state
-        self.last_jump_pos.fill(None);
+        // Remembered positions follow their monitors, unless those are moved
+        self.last_jump_pos.retain(|id, pos| {
+            monitors
+                .list
+                .iter()
+                .any(|area| area.id == *id && area.contains(pos))
+        });
+        self.enumerated = monitors.list.clone();
+        self.monitors = monitors
+            .grouped(&self.monitor_groups)
+            .sorted(self.monitor_order);
         self.relocate_pos = None
     }
 
-    pub fn jump_to_next_monitor(&mut self, ctrl: Option<&mut DeviceController>) {
+    pub fn jump_to_next_monitor(
+        &mut self,
+        ctrl: Option<&mut DeviceController>,
+        target: JumpTarget,
+    ) {
         if self.monitors.is_empty() {
             return;
         }
         let next_id = if let Some(cur_id) = self.monitors.locate_id(&self.cur_pos) {
-            *vec_ensure_get_mut(&mut self.last_jump_pos, cur_id) = Some(self.cur_pos);
+            self.remember_jump_pos(cur_id);
             self.monitors.next_id(cur_id)
         } else {
             0 // maybe go to primary monitor?
         };
+        self.jump_to_monitor(ctrl, next_id, target);
+    }
 
-        let Some(area) = self.monitors.get_area(next_id) else {
+    pub fn jump_to_direction(
+        &mut self,
+        ctrl: Option<&mut DeviceController>,
+        dir: Direction,
+        target: JumpTarget,
+    ) {
+        let Some(cur_id) = self.monitors.nearest_id(&self.cur_pos) else {
             return;
         };
-        let mut new_pos = area.center();
+        let Some(next_id) = self.monitors.neighbor_id(cur_id, dir) else {
+            return;
+        };
+        if self.monitors.locate_id(&self.cur_pos).is_some() {
+            self.remember_jump_pos(cur_id);
+        }
+        self.jump_to_monitor(ctrl, next_id, target);
+    }
+
+    fn remember_jump_pos(&mut self, cur_id: usize) {
+        if let Some(area) = self.monitors.get_area(cur_id) {
+            self.last_jump_pos.insert(area.id, self.cur_pos);
+        }
+    }
+
+    fn jump_to_monitor(
+        &mut self,
+        ctrl: Option<&mut DeviceController>,
+        next_id: usize,
+        target: JumpTarget,
+    ) {
+        let Some(area) = self.target_area(next_id).copied() else {
+            return;
+        };
+        let mut new_pos = match target {
+            JumpTarget::Remembered | JumpTarget::Center => area.center(),
+            JumpTarget::Relative => self
+                .monitors
+                .nearest_id(&self.cur_pos)
+                .and_then(|id| self.target_area(id))
+                .map_or(area.center(), |cur| cur.map_relative(&self.cur_pos, &area)),
+            JumpTarget::NearestEdge => area.clamp(&self.cur_pos),
+        };
         if let Some(ctrl) = ctrl {
             if ctrl.setting.locked_in_monitor {
                 // Clear and find new one in next mouse event. In case user requests
@@ -143,16 +347,61 @@ impl MouseRelocator {
                 // which monitor.
                 ctrl.locked_area = None;
             }
-            if let Some(Some(pos)) = self.last_jump_pos.get(next_id) {
-                new_pos = *pos;
+            if let (JumpTarget::Remembered, Some(pos)) = (target, self.last_jump_pos.get(&area.id))
+            {
+                new_pos = match self.use_work_area {
+                    true => area.clamp(pos),
+                    false => *pos,
+                };
             }
         }
         self.cur_pos = new_pos;
         self.relocate_pos = RelocatePos::from(new_pos);
     }
 
-    pub fn on_pos_update(&mut self, optc: Option<&mut DeviceController>, pos: MousePos) {
+    // Positions of the device are collected as they are reported, before calibrated
+    pub fn start_extent_capture(&mut self, ctrl_id: u64) {
+        self.extent_capture = Some((ctrl_id, None));
+    }
+
+    // Stops collecting, and returns the extent if any position came
+    pub fn finish_extent_capture(&mut self) -> Option<MonitorArea> {
+        self.extent_capture.take().and_then(|(_, extent)| extent)
+    }
+
+    fn capture_extent(&mut self, ctrl_id: u64, raw: &MousePos) {
+        let Some((id, extent)) = &mut self.extent_capture else {
+            return;
+        };
+        if *id != ctrl_id {
+            return;
+        }
+        let point = MonitorArea {
+            lefttop: *raw,
+            rigtbtm: *raw,
+            id: 0,
+        };
+        *extent = Some(extent.map_or(point, |e| e.union(&point)));
+    }
+
+    // Position reported by moving the device, which transform and calibration apply to
+    pub fn on_pos_update(&mut self, optc: Option<&mut DeviceController>, raw: MousePos) {
+        self.update_pos(optc, raw, true);
+    }
+
+    // Position of cursor not moved by the device itself, e.g. polled, or of clicks. It may be
+    // where cursor was relocated to already, so it is not transformed again.
+    pub fn on_cursor_update(&mut self, optc: Option<&mut DeviceController>, pos: MousePos) {
+        self.update_pos(optc, pos, false);
+    }
+
+    fn update_pos(&mut self, optc: Option<&mut DeviceController>, raw: MousePos, moved: bool) {
+        let mut pos = raw;
         if let Some(ctrl) = optc {
+            if moved {
+                self.capture_extent(ctrl.id, &raw);
+                pos = self.transform_pos(ctrl, raw);
+            }
             if ctrl.setting.locked_in_monitor {
                 // Has been locked into one area
                 if let Some(area) = &ctrl.locked_area {
@@ -174,33 +423,184 @@ impl MouseRelocator {
                 }
             }
         }
+        if pos != raw {
+            self.relocate_pos = RelocatePos::from(pos);
+        }
         self.cur_pos = pos;
     }
 
+    fn transform_pos(&self, ctrl: &DeviceController, mut pos: MousePos) -> MousePos {
+        if !matches!(ctrl.positioning, Positioning::Absolute) {
+            return pos;
+        }
+        if let Some(c) = &ctrl.setting.calibration {
+            let target = match c.monitor {
+                0 => self.monitors.locate(&c.extent().center()),
+                n => self.monitors.list.get(n as usize - 1),
+            };
+            if let Some(target) = target {
+                pos = c.apply(target, &pos);
+            }
+        }
+        let transform = ctrl.setting.transform;
+        if transform.is_identity() {
+            return pos;
+        }
+        match self.monitors.locate(&pos) {
+            Some(area) => transform.apply(area, &pos),
+            None => pos,
+        }
+    }
+
     pub fn on_mouse_update(&mut self, c: &mut DeviceController, tick: u64) {
         if self.cur_mouse != c.id {
             self.cur_mouse = c.id;
 
-            if c.setting.switch {
-                // Has rememberd position
-                if let Some((_, old_pos, _)) = c.get_last_pos() {
-                    self.cur_pos = old_pos;
-                    self.relocate_pos = RelocatePos::from(old_pos);
-                    // Find area to go
-                    // if let Some(area) = self.monitors.locate(&old_pos) {
-                    //     self.cur_pos = old_pos;
-                    //     self.relocate_pos = RelocatePos::from(old_pos, area);
-                    //     return;
-                    // } else {
-                    //     self.to_update_monitors = true;
-                    //     return;
-                    // }
+            // Has rememberd position
+            if let Some((_, old_pos, _)) = c.get_last_pos() {
+                let new_pos = match c.setting.switch {
+                    SwitchMode::Shared => None,
+                    SwitchMode::PerDevicePosition => Some(old_pos),
+                    SwitchMode::PerDeviceMonitor => self
+                        .monitors
+                        .nearest_id(&old_pos)
+                        .and_then(|id| self.target_area(id))
+                        .map(|area| area.center()),
+                };
+                if let Some(new_pos) = new_pos {
+                    self.cur_pos = new_pos;
+                    self.relocate_pos = RelocatePos::from(new_pos);
                 }
             }
         }
         c.update_pos(&self.cur_pos, tick);
     }
 
+    // Move cursor aside once, if device c moved it last and has been idle for long enough.
+    // Returns true if parked.
+    pub fn park_if_idle(&mut self, c: &mut DeviceController, tick: u64) -> bool {
+        let Some(park) = c.setting.park else {
+            return false;
+        };
+        if self.cur_mouse != c.id || c.parked || c.last_active_tick == 0 {
+            return false;
+        }
+        if tick < c.last_active_tick + park.after_idle_secs as u64 * 1000 {
+            return false;
+        }
+        c.parked = true;
+        let area = match c.locked_area {
+            Some(area) => area,
+            None => match self
+                .monitors
+                .nearest_id(&self.cur_pos)
+                .and_then(|id| self.target_area(id))
+            {
+                Some(area) => *area,
+                None => return false,
+            },
+        };
+        let m = PARK_MARGIN;
+        let (lt, rb) = (area.lefttop, area.rigtbtm);
+        let new_pos = match park.at {
+            ParkPosition::TopLeft => MousePos::from(lt.x + m, lt.y + m),
+            ParkPosition::TopRight => MousePos::from(rb.x - m, lt.y + m),
+            ParkPosition::BottomLeft => MousePos::from(lt.x + m, rb.y - m),
+            ParkPosition::BottomRight => MousePos::from(rb.x - m, rb.y - m),
+            ParkPosition::Center => area.center(),
+        };
+        self.cur_pos = new_pos;
+        self.relocate_pos = RelocatePos::from(new_pos);
+        true
+    }
+
+    // Scale the motion from current position to pos
+    pub fn scale_motion(&self, pos: &MousePos, percent: u32) -> MousePos {
+        let scale = |from: i32, to: i32| from + ((to - from) as i64 * percent as i64 / 100) as i32;
+        MousePos::from(scale(self.cur_pos.x, pos.x), scale(self.cur_pos.y, pos.y))
+    }
+
+    pub fn first_monitor_center(&self) -> Option<MousePos> {
+        if self.monitors.is_empty() {
+            return None;
+        }
+        self.target_area(0).map(|a| a.center())
+    }
+
+    // Returns the corner once, after cursor has stayed in it for dwell_ms
+    pub fn poll_corner_dwell(&mut self, dwell_ms: u64, tick: u64) -> Option<Corner> {
+        let cur_pos = self.cur_pos;
+        let Some(corner) = self
+            .monitors
+            .locate(&cur_pos)
+            .and_then(|area| area.corner_at(&cur_pos, HOT_CORNER_MARGIN))
+        else {
+            self.corner_dwell = None;
+            return None;
+        };
+        match &mut self.corner_dwell {
+            Some(d) if d.corner == corner => {
+                if !d.fired && tick >= d.since + dwell_ms {
+                    d.fired = true;
+                    return Some(corner);
+                }
+            }
+            _ => {
+                self.corner_dwell = Some(CornerDwell {
+                    corner,
+                    since: tick,
+                    fired: false,
+                })
+            }
+        }
+        None
+    }
+
+    // Cursor is in a corner, but has not stayed long enough
+    pub fn is_corner_dwelling(&self) -> bool {
+        self.corner_dwell.is_some_and(|d| !d.fired)
+    }
+
+    // Nearest position on monitors, if p is outside all of them. None if p is fine, or
+    // no monitor is known to check against.
+    pub fn clamp_offscreen(&self, p: &MousePos) -> Option<MousePos> {
+        if self.monitors.locate(p).is_some() {
+            return None;
+        }
+        let id = self.monitors.nearest_id(p)?;
+        self.monitors.get_area(id).map(|area| area.clamp(p))
+    }
+
+    // Monitor containing p
+    pub fn locate_area(&self, p: &MousePos) -> Option<MonitorArea> {
+        self.monitors.locate(p).copied()
+    }
+
+    // Rect covering all monitors
+    pub fn spanning_area(&self) -> Option<MonitorArea> {
+        self.monitors.spanning_area()
+    }
+
+    pub fn monitor_areas(&self) -> &[MonitorArea] {
+        &self.monitors.list
+    }
+
+    // In the same order as monitor_areas()
+    pub fn monitor_labels(&self) -> Vec<String> {
+        (0..self.monitors.list.len())
+            .map(|i| self.monitors.get_label(i))
+            .collect()
+    }
+
+    // Compared with monitors enumerated again, to tell whether they changed
+    pub fn enumerated_areas(&self) -> &[MonitorArea] {
+        &self.enumerated
+    }
+
+    pub fn peek_relocate_pos(&self) -> Option<MousePos> {
+        self.relocate_pos.as_ref().map(|p| p.0)
+    }
+
     pub fn pop_relocate_pos(&mut self) -> Option<RelocatePos> {
         self.relocate_pos.take()
     }
@@ -211,13 +611,119 @@ impl MouseRelocator {
     }
 }
 
+#[derive(Clone, Default)]
 pub struct MonitorAreasList {
     list: Vec<MonitorArea>,
+    // Work areas of the same monitors, excluding taskbar. Empty if unknown.
+    work: Vec<MonitorArea>,
+    // Names of the same monitors, for matching groups. Empty if unknown.
+    names: Vec<String>,
+    // Readable names of the same monitors from EDID, e.g. "DELL U2720Q", or empty strings for
+    // those without. Empty if unknown.
+    labels: Vec<String>,
 }
 
 impl MonitorAreasList {
     pub fn from(list: Vec<MonitorArea>) -> Self {
-        MonitorAreasList { list }
+        MonitorAreasList {
+            list,
+            work: Vec::new(),
+            names: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+    pub fn with_work_areas(mut self, work: Vec<MonitorArea>) -> Self {
+        self.work = work;
+        self
+    }
+    pub fn with_names(mut self, names: Vec<String>) -> Self {
+        self.names = names;
+        self
+    }
+    pub fn with_labels(mut self, labels: Vec<String>) -> Self {
+        self.labels = labels;
+        self
+    }
+    // Monitors matching patterns of one group, by name or label, are merged into the rectangle
+    // around them, which takes the place and identity of the first one. A monitor joins only
+    // its first group.
+    pub fn grouped(mut self, groups: &[Vec<String>]) -> Self {
+        if self.names.len() != self.list.len() {
+            return self;
+        }
+        let has_labels = self.labels.len() == self.list.len();
+        let mut joined = vec![false; self.list.len()];
+        let mut merged_away = vec![false; self.list.len()];
+        for group in groups {
+            let members: Vec<usize> = (0..self.list.len())
+                .filter(|&i| !joined[i])
+                .filter(|&i| {
+                    group.iter().any(|p| {
+                        wildcard_match(p, &self.names[i])
+                            || self
+                                .labels
+                                .get(i)
+                                .is_some_and(|l| !l.is_empty() && wildcard_match(p, l))
+                    })
+                })
+                .collect();
+            let Some((&first, rest)) = members.split_first() else {
+                continue;
+            };
+            for &i in rest {
+                let id = self.list[first].id;
+                self.list[first] = MonitorArea {
+                    id,
+                    ..self.list[first].union(&self.list[i])
+                };
+                if self.work.len() == self.list.len() {
+                    self.work[first] = MonitorArea {
+                        id,
+                        ..self.work[first].union(&self.work[i])
+                    };
+                }
+                if has_labels && !self.labels[i].is_empty() {
+                    let label = match self.labels[first].is_empty() {
+                        true => self.labels[i].clone(),
+                        false => format!("{} + {}", self.labels[first], self.labels[i]),
+                    };
+                    self.labels[first] = label;
+                }
+                merged_away[i] = true;
+            }
+            members.iter().for_each(|&i| joined[i] = true);
+        }
+        let ids: Vec<usize> = (0..self.list.len()).filter(|&i| !merged_away[i]).collect();
+        let pick = |areas: &[MonitorArea]| ids.iter().map(|&i| areas[i]).collect();
+        if self.work.len() == self.list.len() {
+            self.work = pick(&self.work);
+        }
+        self.names = ids.iter().map(|&i| self.names[i].clone()).collect();
+        if has_labels {
+            self.labels = ids.iter().map(|&i| self.labels[i].clone()).collect();
+        }
+        self.list = pick(&self.list);
+        self
+    }
+    // Next monitor to jump follows the list order
+    pub fn sorted(mut self, order: MonitorOrder) -> Self {
+        let ids = match order {
+            MonitorOrder::Enumeration => return self,
+            MonitorOrder::Rows => geometry::reading_order(&self.list, false),
+            MonitorOrder::Columns => geometry::reading_order(&self.list, true),
+        };
+        let pick = |areas: &[MonitorArea]| ids.iter().map(|&i| areas[i]).collect();
+        if self.work.len() == self.list.len() {
+            self.work = pick(&self.work);
+        }
+        if self.names.len() == self.list.len() {
+            self.names = ids.iter().map(|&i| self.names[i].clone()).collect();
+        }
+        if self.labels.len() == self.list.len() {
+            self.labels = ids.iter().map(|&i| self.labels[i].clone()).collect();
+        }
+        self.list = pick(&self.list);
+        self
     }
     pub fn locate(&self, p: &MousePos) -> Option<&MonitorArea> {
         self.list.iter().find(|&ma| ma.contains(p))
@@ -230,6 +736,17 @@ impl MonitorAreasList {
         }
     }
 
+    pub fn nearest_id(&self, p: &MousePos) -> Option<usize> {
+        geometry::nearest(&self.list, p)
+    }
+    pub fn neighbor_id(&self, id: usize, dir: Direction) -> Option<usize> {
+        geometry::neighbor(&self.list, id, dir)
+    }
+
+    pub fn spanning_area(&self) -> Option<MonitorArea> {
+        geometry::spanning_rect(&self.list)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.list.is_empty()
     }
@@ -240,79 +757,271 @@ impl MonitorAreasList {
     pub fn get_area(&self, round_id: usize) -> Option<&MonitorArea> {
         self.list.get(round_id % self.list.len())
     }
+    // For showing to users, falls back to its position in list if without label
+    pub fn get_label(&self, round_id: usize) -> String {
+        match self.labels.get(round_id) {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => format!("Monitor {}", round_id + 1),
+        }
+    }
+    // Falls back to the whole monitor, if work area is unknown
+    pub fn get_work_area(&self, round_id: usize) -> Option<&MonitorArea> {
+        if self.work.len() != self.list.len() {
+            return self.get_area(round_id);
+        }
+        self.work.get(round_id % self.work.len())
+    }
 }
 
 impl Display for MonitorAreasList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "[")?;
-        for m in self.list.iter() {
-            write!(f, "{} ", m)?;
+        for (i, m) in self.list.iter().enumerate() {
+            match self.labels.get(i) {
+                Some(label) if !label.is_empty() => write!(f, "{}({}) ", m, label)?,
+                _ => write!(f, "{} ", m)?,
+            }
         }
         write!(f, "]")
     }
 }
 
-#[derive(Default, Clone, Copy, Debug)]
-pub struct MonitorArea {
-    pub lefttop: MousePos,
-    pub rigtbtm: MousePos,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::{Calibration, CursorPark, ForcePositioning, JumpTarget};
 
-impl MonitorArea {
-    pub fn contains(&self, p: &MousePos) -> bool {
-        (self.lefttop.x <= p.x && p.x <= self.rigtbtm.x)
-            && (self.lefttop.y <= p.y && p.y <= self.rigtbtm.y)
-    }
-    const RESERVE_PIXEL: i32 = 3;
-    pub fn capture_pos(&self, p: &MousePos) -> MousePos {
-        let rp = Self::RESERVE_PIXEL;
-        let x1 = match (p.x < self.lefttop.x, p.x > self.rigtbtm.x - rp) {
-            (true, _) => self.lefttop.x,
-            (_, true) => self.rigtbtm.x - rp,
-            _ => p.x,
+    #[test]
+    fn test_force_positioning() {
+        let mut ctrl = DeviceController::new(1, DeviceSetting::default());
+        ctrl.update_positioning(Positioning::Absolute);
+        assert!(matches!(ctrl.positioning(), Positioning::Absolute));
+
+        let forced = DeviceSetting {
+            force_positioning: ForcePositioning::Relative,
+            ..Default::default()
         };
-        let y1 = match (p.y < self.lefttop.y, p.y > self.rigtbtm.y - rp) {
-            (true, _) => self.lefttop.y,
-            (_, true) => self.rigtbtm.y - rp,
-            _ => p.y,
+        ctrl.update_settings(&forced);
+        assert!(matches!(ctrl.positioning(), Positioning::Relative));
+        assert!(matches!(ctrl.raw_positioning(), Positioning::Absolute));
+
+        ctrl.update_settings(&DeviceSetting::default());
+        assert!(matches!(ctrl.positioning(), Positioning::Absolute));
+    }
+
+    #[test]
+    fn test_click_debounce() {
+        let mut d = ClickDebounce::default();
+        assert!(!d.on_button(0, true, 1000, 50));
+        assert!(!d.on_button(0, false, 1080, 50));
+        // Bounce of the left button, with its release
+        assert!(d.on_button(0, true, 1100, 50));
+        assert!(d.on_button(0, false, 1110, 50));
+        // Other buttons are not affected
+        assert!(!d.on_button(1, true, 1115, 50));
+        assert!(!d.on_button(0, true, 1200, 50));
+        assert!(!d.on_button(0, false, 1220, 50));
+        assert!(!d.on_button(0, true, 1230, 0));
+    }
+
+    #[test]
+    fn test_activation_grace() {
+        let grace = ActivationGrace {
+            events: 3,
+            within_ms: 100,
         };
-        MousePos::from(x1, y1)
+        let mut c = ActivationCounter::default();
+        // A nudge, then too slow
+        assert!(!c.on_event(1000, &grace));
+        assert!(!c.on_event(1050, &grace));
+        assert!(!c.on_event(1200, &grace));
+        assert!(!c.on_event(1250, &grace));
+        assert!(c.on_event(1290, &grace));
+        // Counted from zero again
+        assert!(!c.on_event(1300, &grace));
+
+        let mut ctrl = DeviceController::new(1, DeviceSetting::default());
+        assert_eq!(ctrl.count_activation(1000), None);
+        ctrl.update_settings(&DeviceSetting {
+            activation_grace: Some(ActivationGrace {
+                events: 1,
+                within_ms: 100,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(ctrl.count_activation(1000), Some(true));
     }
-    pub fn center(&self) -> MousePos {
-        MousePos::from(
-            (self.lefttop.x + self.rigtbtm.x) / 2,
-            (self.lefttop.y + self.rigtbtm.y) / 2,
-        )
+
+    #[test]
+    fn test_park_if_idle() {
+        let mut relocator = MouseRelocator::new();
+        let area = MonitorArea {
+            lefttop: MousePos::from(0, 0),
+            rigtbtm: MousePos::from(1920, 1080),
+            id: 1,
+        };
+        relocator.update_monitors(MonitorAreasList::from(vec![area]));
+        let setting = DeviceSetting {
+            park: Some(CursorPark::default()),
+            ..Default::default()
+        };
+        let mut ctrl = DeviceController::new(1, setting);
+        relocator.on_pos_update(Some(&mut ctrl), MousePos::from(500, 500));
+        relocator.on_mouse_update(&mut ctrl, 1000);
+
+        assert!(!relocator.park_if_idle(&mut ctrl, 5000));
+        assert!(relocator.park_if_idle(&mut ctrl, 11000));
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(MousePos::from(1920 - PARK_MARGIN, 1080 - PARK_MARGIN))
+        );
+        // Only once until device moves again
+        assert!(!relocator.park_if_idle(&mut ctrl, 20000));
+        relocator.on_mouse_update(&mut ctrl, 21000);
+        assert!(relocator.park_if_idle(&mut ctrl, 31000));
     }
-}
 
-impl Display for MonitorArea {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{{{}.{}-{}.{}}}",
-            self.lefttop.x, self.lefttop.y, self.rigtbtm.x, self.rigtbtm.y,
-        )
+    #[test]
+    fn test_calibration() {
+        let area = |l: i32, r: i32, id: MonitorId| MonitorArea {
+            lefttop: MousePos::from(l, 0),
+            rigtbtm: MousePos::from(r, 1080),
+            id,
+        };
+        let mut relocator = MouseRelocator::new();
+        relocator.update_monitors(MonitorAreasList::from(vec![
+            area(0, 1920, 11),
+            area(1920, 3840, 22),
+        ]));
+        let mut pen = DeviceController::new(1, DeviceSetting::default());
+        pen.update_positioning(Positioning::Absolute);
+        let mut mouse = DeviceController::new(2, DeviceSetting::default());
+
+        relocator.start_extent_capture(1);
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(900, 500));
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(100, 50));
+        relocator.on_pos_update(Some(&mut mouse), MousePos::from(3000, 10));
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(1800, 1000));
+        let extent = relocator.finish_extent_capture().unwrap();
+        assert_eq!(extent.lefttop, MousePos::from(100, 50));
+        assert_eq!(extent.rigtbtm, MousePos::from(1800, 1000));
+        assert_eq!(relocator.finish_extent_capture(), None);
+
+        // Onto the second monitor, edge to edge
+        let mut c = Calibration::from_extent(&extent);
+        c.monitor = 2;
+        pen.update_settings(&DeviceSetting {
+            calibration: Some(c),
+            ..Default::default()
+        });
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(100, 50));
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(MousePos::from(1920, 0))
+        );
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(1800, 1000));
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(MousePos::from(3840, 1080))
+        );
+        // Where it was relocated to, not calibrated again
+        relocator.on_cursor_update(Some(&mut pen), MousePos::from(3840, 1080));
+        assert!(relocator.pop_relocate_pos().is_none());
+
+        // Relative positions are never calibrated
+        pen.update_positioning(Positioning::Relative);
+        relocator.on_pos_update(Some(&mut pen), MousePos::from(100, 50));
+        assert!(relocator.pop_relocate_pos().is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_remembered_jump_reordered_monitors() {
+        let area = |l: i32, r: i32, id: MonitorId| MonitorArea {
+            lefttop: MousePos::from(l, 0),
+            rigtbtm: MousePos::from(r, 1080),
+            id,
+        };
+        let (a, b) = (area(0, 1920, 11), area(1920, 3840, 22));
+        let mut relocator = MouseRelocator::new();
+        let mut ctrl = DeviceController::new(1, DeviceSetting::default());
+        relocator.update_monitors(MonitorAreasList::from(vec![a, b]));
+
+        relocator.on_pos_update(Some(&mut ctrl), MousePos::from(100, 200));
+        relocator.jump_to_next_monitor(Some(&mut ctrl), JumpTarget::Remembered);
+        assert_eq!(relocator.pop_relocate_pos().map(|p| p.0), Some(b.center()));
+
+        relocator.on_pos_update(Some(&mut ctrl), MousePos::from(2500, 300));
+        // Enumerated again in another order
+        relocator.update_monitors(MonitorAreasList::from(vec![b, a]));
+        relocator.jump_to_next_monitor(Some(&mut ctrl), JumpTarget::Remembered);
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(MousePos::from(100, 200))
+        );
+        relocator.jump_to_next_monitor(Some(&mut ctrl), JumpTarget::Remembered);
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(MousePos::from(2500, 300))
+        );
+
+        // Forgotten once its monitor moves away
+        let moved = area(-1920, 0, 11);
+        relocator.update_monitors(MonitorAreasList::from(vec![b, moved]));
+        relocator.jump_to_next_monitor(Some(&mut ctrl), JumpTarget::Remembered);
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(moved.center())
+        );
+    }
 
     #[test]
-    fn test_monitor_area_capture_pos() {
-        let pt = MousePos::from;
-        let m = MonitorArea {
-            lefttop: pt(-100, 500),
-            rigtbtm: pt(300, 1500),
+    fn test_monitor_groups() {
+        let area = |l: i32, r: i32, id: MonitorId| MonitorArea {
+            lefttop: MousePos::from(l, 0),
+            rigtbtm: MousePos::from(r, 1080),
+            id,
         };
-        assert_eq!(m.capture_pos(&pt(50, 700)), pt(50, 700));
-        assert_eq!(m.capture_pos(&pt(-150, 1500)), pt(-100, 1500));
-        assert_eq!(m.capture_pos(&pt(350, 500)), pt(300, 500));
-        assert_eq!(m.capture_pos(&pt(-100, 490)), pt(-100, 500));
-        assert_eq!(m.capture_pos(&pt(300, 3000)), pt(300, 1500));
-        assert_eq!(m.capture_pos(&pt(-120, 1300)), pt(-100, 1300));
-        assert_eq!(m.capture_pos(&pt(-200, 1800)), pt(-100, 1500));
+        let areas = vec![
+            area(0, 1920, 11),
+            area(1920, 3840, 22),
+            area(3840, 5760, 33),
+        ];
+        let names = (1..=3).map(|i| format!("\\\\.\\DISPLAY{}", i)).collect();
+        let mut relocator = MouseRelocator::new();
+        let mut ctrl = DeviceController::new(1, DeviceSetting::default());
+        relocator.set_monitor_groups(&[vec!["*display3".to_owned(), "*DISPLAY2".to_owned()]]);
+        assert!(relocator.pop_need_update_monitors());
+        relocator.update_monitors(MonitorAreasList::from(areas.clone()).with_names(names));
+
+        assert_eq!(relocator.enumerated_areas(), areas.as_slice());
+        let span = area(1920, 5760, 22);
+        assert_eq!(relocator.monitor_areas(), &[areas[0], span]);
+        relocator.on_pos_update(Some(&mut ctrl), MousePos::from(100, 200));
+        relocator.jump_to_next_monitor(Some(&mut ctrl), JumpTarget::Center);
+        assert_eq!(
+            relocator.pop_relocate_pos().map(|p| p.0),
+            Some(span.center())
+        );
+
+        // Without names, monitors are kept apart
+        relocator.update_monitors(MonitorAreasList::from(areas.clone()));
+        assert_eq!(relocator.monitor_areas(), areas.as_slice());
+
+        // Labels are matched as well, and joined for the group
+        let names = (1..=3).map(|i| format!("\\\\.\\DISPLAY{}", i)).collect();
+        let labels = ["DELL U2720Q", "LG 27GL850", "LG 27GL850"]
+            .map(str::to_owned)
+            .to_vec();
+        relocator.set_monitor_groups(&[vec!["lg *".to_owned()]]);
+        relocator.update_monitors(
+            MonitorAreasList::from(areas.clone())
+                .with_names(names)
+                .with_labels(labels),
+        );
+        assert_eq!(relocator.monitor_areas(), &[areas[0], span]);
+        assert_eq!(
+            relocator.monitor_labels(),
+            vec!["DELL U2720Q", "LG 27GL850 + LG 27GL850"]
+        );
     }
 }
@@ -0,0 +1,245 @@
+// Locations of all files and registry values MonMouse reads or writes, kept in one place so
+// that cleanup can find everything left on the machine.
+
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::errors::Error;
+
+pub const CONFIG_FILE_NAME: &str = "monmouse.yml";
+pub const CONFIG_FILE_NAME_TOML: &str = "monmouse.toml";
+pub const STATS_FILE_NAME: &str = "monmouse_stats.yml";
+pub const TRACE_FILE_NAME: &str = "monmouse_trace.jsonl.gz";
+pub const CRASHES_DIR: &str = "crashes";
+// Port and token of the running instance, for view-only windows. Removed when it exits. Kept
+// in the data dir of the user, not beside config, which other users may be able to read.
+pub const VIEW_ENDPOINT_FILE_NAME: &str = "monmouse_view.yml";
+// Latest release answered by the last update check
+pub const UPDATE_CACHE_FILE_NAME: &str = "monmouse_update.yml";
+
+// Deployed by administrators for all users, see deployed_config_file()
+pub const DEPLOYED_CONFIG_DIR: &str = "monmouse";
+pub const DEPLOYED_CONFIG_FILE_NAME: &str = "default.yml";
+
+// Config and data files are beside the program, or inside "debug" for debug builds
+#[cfg(not(debug_assertions))]
+pub const DEFAULT_CONFIG_DIR: &str = ".";
+#[cfg(debug_assertions)]
+pub const DEFAULT_CONFIG_DIR: &str = "debug";
+
+// Window position is saved by eframe under this name, in the data dir of the user
+pub const UI_APP_ID: &str = "monmouse";
+
+// Registry keys are under HKEY_CURRENT_USER
+pub const AUTOSTART_REGISTRY_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+pub const AUTOSTART_REGISTRY_VALUE: &str = "MonMouse";
+pub const THEME_REGISTRY_KEY: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+
+pub fn config_dir() -> Result<PathBuf, Error> {
+    if cfg!(debug_assertions) {
+        return Ok(PathBuf::from(DEFAULT_CONFIG_DIR));
+    }
+    std::env::current_dir().map_err(|_| Error::ConfigFileNotExists("None".to_owned()))
+}
+
+// Config file inside dir. monmouse.toml is used only if it exists, and monmouse.yml not.
+pub fn config_file_in(dir: &Path) -> PathBuf {
+    let yaml = dir.join(CONFIG_FILE_NAME);
+    let toml = dir.join(CONFIG_FILE_NAME_TOML);
+    if !yaml.exists() && toml.exists() {
+        toml
+    } else {
+        yaml
+    }
+}
+
+// Under %ProgramData%, taken as base of config file on first run. None if not deployed.
+pub fn deployed_config_file() -> Option<PathBuf> {
+    let dir = std::env::var_os("ProgramData")?;
+    let file = PathBuf::from(dir)
+        .join(DEPLOYED_CONFIG_DIR)
+        .join(DEPLOYED_CONFIG_FILE_NAME);
+    file.is_file().then_some(file)
+}
+
+pub fn ui_storage_dir() -> Option<PathBuf> {
+    eframe::storage_dir(UI_APP_ID)
+}
+
+// Inside ui_storage_dir(), removed along with it
+pub fn view_endpoint_file() -> Option<PathBuf> {
+    ui_storage_dir().map(|d| d.join(VIEW_ENDPOINT_FILE_NAME))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Artifact {
+    File(PathBuf),
+    Dir(PathBuf),
+    Registry(&'static str /* key */, &'static str /* value */),
+}
+
+impl Display for Artifact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Artifact::File(p) | Artifact::Dir(p) => write!(f, "{}", p.display()),
+            Artifact::Registry(key, value) => write!(f, r"HKCU\{}\{}", key, value),
+        }
+    }
+}
+
+impl Artifact {
+    fn exists(&self) -> bool {
+        match self {
+            Artifact::File(p) => p.is_file(),
+            Artifact::Dir(p) => p.is_dir(),
+            Artifact::Registry(key, value) => crate::platform::registry_value_exists(key, value),
+        }
+    }
+
+    pub fn remove(&self) -> Result<(), Error> {
+        match self {
+            Artifact::File(p) => fs::remove_file(p).map_err(Error::IO),
+            Artifact::Dir(p) => fs::remove_dir_all(p).map_err(Error::IO),
+            Artifact::Registry(key, value) => crate::platform::delete_registry_value(key, value),
+        }
+    }
+}
+
+// Files beside config file
+pub struct AppPaths {
+    dir: PathBuf,
+}
+
+impl AppPaths {
+    pub fn new(config_dir: &Path) -> Self {
+        AppPaths {
+            dir: config_dir.to_path_buf(),
+        }
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        config_file_in(&self.dir)
+    }
+
+    pub fn stats_file(&self) -> PathBuf {
+        self.dir.join(STATS_FILE_NAME)
+    }
+
+    pub fn trace_file(&self) -> PathBuf {
+        self.dir.join(TRACE_FILE_NAME)
+    }
+
+    pub fn crashes_dir(&self) -> PathBuf {
+        self.dir.join(CRASHES_DIR)
+    }
+
+    pub fn update_cache_file(&self) -> PathBuf {
+        self.dir.join(UPDATE_CACHE_FILE_NAME)
+    }
+
+    fn file_artifacts(&self) -> Vec<Artifact> {
+        let mut all = vec![
+            Artifact::File(self.dir.join(CONFIG_FILE_NAME)),
+            Artifact::File(self.dir.join(CONFIG_FILE_NAME_TOML)),
+            Artifact::File(self.stats_file()),
+            Artifact::File(self.trace_file()),
+            Artifact::Dir(self.crashes_dir()),
+            Artifact::File(self.update_cache_file()),
+        ];
+        all.retain(Artifact::exists);
+        all
+    }
+
+    // Existing ones only. Files included by config file are not listed, they may be shared.
+    pub fn artifacts(&self) -> Vec<Artifact> {
+        let mut all = self.file_artifacts();
+        if let Some(dir) = ui_storage_dir().filter(|d| d.is_dir()) {
+            all.push(Artifact::Dir(dir));
+        }
+        // Set by another copy of MonMouse otherwise, which is left alone
+        let command = crate::platform::registry_string_value(
+            AUTOSTART_REGISTRY_KEY,
+            AUTOSTART_REGISTRY_VALUE,
+        );
+        if let (Some(command), Ok(exe)) = (command, std::env::current_exe()) {
+            if command_runs_exe(&command, &exe) {
+                all.push(Artifact::Registry(
+                    AUTOSTART_REGISTRY_KEY,
+                    AUTOSTART_REGISTRY_VALUE,
+                ));
+            }
+        }
+        all
+    }
+}
+
+// Whether a command line, e.g. of autostart, starts the executable. Program path may be
+// quoted, and is compared case-insensitively as Windows does.
+fn command_runs_exe(command: &str, exe: &Path) -> bool {
+    let command = command.trim_start();
+    let program = match command.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(rest),
+        None => command.split_whitespace().next().unwrap_or(command),
+    };
+    program.eq_ignore_ascii_case(&exe.to_string_lossy())
+}
+
+// Goes on after failures, returning the failed ones
+pub fn remove_artifacts(artifacts: &[Artifact]) -> Vec<(Artifact, Error)> {
+    artifacts
+        .iter()
+        .filter_map(|a| a.remove().err().map(|e| (a.clone(), e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_temp_dir;
+
+    #[test]
+    fn test_file_artifacts() {
+        let dir = test_temp_dir("paths");
+        fs::create_dir_all(dir.join(CRASHES_DIR)).unwrap();
+        fs::write(dir.join(CONFIG_FILE_NAME), "").unwrap();
+        fs::write(dir.join(STATS_FILE_NAME), "").unwrap();
+        fs::write(dir.join("base.yml"), "").unwrap();
+
+        let paths = AppPaths::new(&dir);
+        let artifacts = paths.file_artifacts();
+        assert_eq!(
+            artifacts,
+            vec![
+                Artifact::File(dir.join(CONFIG_FILE_NAME)),
+                Artifact::File(paths.stats_file()),
+                Artifact::Dir(paths.crashes_dir()),
+            ]
+        );
+        assert!(remove_artifacts(&artifacts).is_empty());
+        assert!(paths.file_artifacts().is_empty());
+        assert!(dir.join("base.yml").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_command_runs_exe() {
+        let exe = Path::new(r"C:\Program Files\MonMouse\monmouse.exe");
+        assert!(command_runs_exe(
+            r#""C:\Program Files\MonMouse\monmouse.exe" --minimized"#,
+            exe
+        ));
+        assert!(command_runs_exe(
+            r"c:\program files\monmouse\MONMOUSE.EXE",
+            exe
+        ));
+        assert!(!command_runs_exe(
+            r#""D:\Tools\MonMouse\monmouse.exe""#,
+            exe
+        ));
+        assert!(!command_runs_exe("", exe));
+    }
+}
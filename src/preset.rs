@@ -0,0 +1,257 @@
+// Templates of settings for common setups, offered in Presets panel of UI. Applying one fills
+// in defaults of device types, shortcuts and some options user has not set yet, then changes
+// devices user mapped to its roles.
+
+use crate::device_type::DeviceType;
+use crate::keyboard::shortcuts_equal;
+use crate::message::GenericDevice;
+use crate::setting::{
+    DeviceRuleAction, DeviceSetting, MonitorOrder, ProcessorSettings, SwitchMode,
+};
+
+// A part a device plays in the setup, e.g. the drawing tablet
+pub struct PresetRole {
+    pub name: &'static str,
+    // Devices of accepted types are suggested for it
+    pub accepts: fn(DeviceType) -> bool,
+    pub action: DeviceRuleAction,
+}
+
+pub struct Preset {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub roles: Vec<PresetRole>,
+    // For devices not mapped to any role, by device type, only filled in if not set yet
+    pub defaults_by_type: Vec<(DeviceType, DeviceSetting)>,
+    // Shortcut names and values, only filled in if not set yet
+    pub shortcuts: Vec<(&'static str, &'static str)>,
+    // Given settings and their defaults, fills in options by fill()
+    options: fn(&mut ProcessorSettings, &ProcessorSettings),
+}
+
+// Option is changed only if user left it as default
+fn fill<T: PartialEq>(option: &mut T, default: T, value: T) {
+    if *option == default {
+        *option = value;
+    }
+}
+
+impl Preset {
+    // Device ids for each role, the first device of accepted types not taken by roles before
+    pub fn suggest(&self, devices: &[GenericDevice]) -> Vec<Option<String>> {
+        let mut taken: Vec<&str> = Vec::new();
+        self.roles
+            .iter()
+            .map(|r| {
+                let d = devices
+                    .iter()
+                    .find(|d| (r.accepts)(d.device_type) && !taken.contains(&d.id.as_str()))?;
+                taken.push(&d.id);
+                Some(d.id.clone())
+            })
+            .collect()
+    }
+
+    // Devices in mapping are by role, those None are skipped
+    pub fn apply(&self, p: &mut ProcessorSettings, mapping: &[Option<String>]) {
+        for (t, s) in self.defaults_by_type.iter() {
+            p.defaults_by_type.entry(t.to_string()).or_insert(*s);
+        }
+        for (name, value) in self.shortcuts.iter() {
+            // Neither replaced, nor taken from another shortcut
+            let used = p
+                .shortcuts
+                .named()
+                .iter()
+                .any(|(_, v)| shortcuts_equal(v, value));
+            match p.shortcuts.named_mut(name) {
+                Some(v) if v.is_empty() && !used => *v = (*value).to_owned(),
+                _ => (),
+            }
+        }
+        (self.options)(p, &ProcessorSettings::default());
+        for (role, id) in self.roles.iter().zip(mapping) {
+            if let Some(id) = id {
+                p.ensure_mut_device(id, |d| role.action.apply(d));
+            }
+        }
+    }
+}
+
+fn role(
+    name: &'static str,
+    accepts: fn(DeviceType) -> bool,
+    locked: bool,
+    switch: SwitchMode,
+) -> PresetRole {
+    PresetRole {
+        name,
+        accepts,
+        action: DeviceRuleAction {
+            target: String::new(),
+            locked_in_monitor: Some(locked),
+            switch: Some(switch),
+        },
+    }
+}
+
+fn locked() -> DeviceSetting {
+    DeviceSetting {
+        locked_in_monitor: true,
+        ..Default::default()
+    }
+}
+
+pub fn presets() -> Vec<Preset> {
+    vec![
+        Preset {
+            name: "Laptop + external touchscreen",
+            summary: "The touchscreen stays on its monitor, and the touchpad returns to where it \
+                      left the cursor.",
+            roles: vec![
+                role(
+                    "Touchpad",
+                    |t| matches!(t, DeviceType::TouchPad | DeviceType::Mouse),
+                    false,
+                    SwitchMode::PerDevicePosition,
+                ),
+                role(
+                    "Touchscreen",
+                    |t| matches!(t, DeviceType::TouchScreen),
+                    true,
+                    SwitchMode::Shared,
+                ),
+            ],
+            defaults_by_type: vec![(DeviceType::TouchScreen, locked())],
+            shortcuts: vec![
+                ("cur_mouse_lock", "Ctrl+Alt+L"),
+                ("cur_mouse_jump_next", "Ctrl+Alt+N"),
+            ],
+            options: |p, d| fill(&mut p.use_work_area, d.use_work_area, true),
+        },
+        Preset {
+            name: "Dual monitor + drawing tablet",
+            summary: "The pen stays on the monitor it is mapped to, and the mouse returns to \
+                      where it left the cursor.",
+            roles: vec![
+                role(
+                    "Mouse",
+                    |t| matches!(t, DeviceType::Mouse | DeviceType::TouchPad),
+                    false,
+                    SwitchMode::PerDevicePosition,
+                ),
+                role(
+                    "Drawing tablet",
+                    |t| matches!(t, DeviceType::Pen | DeviceType::Digitizer),
+                    true,
+                    SwitchMode::Shared,
+                ),
+            ],
+            defaults_by_type: vec![(DeviceType::Pen, locked())],
+            shortcuts: vec![
+                ("cur_mouse_lock", "Ctrl+Alt+L"),
+                ("cur_mouse_jump_next", "Ctrl+Alt+N"),
+            ],
+            options: |p, d| fill(&mut p.monitor_order, d.monitor_order, MonitorOrder::Rows),
+        },
+        Preset {
+            name: "Triple monitor KVM",
+            summary: "The mouse jumps between monitors by shortcuts, and pauses while a software \
+                      KVM sends input to another machine.",
+            roles: vec![role(
+                "Mouse",
+                |t| matches!(t, DeviceType::Mouse | DeviceType::TouchPad),
+                false,
+                SwitchMode::PerDeviceMonitor,
+            )],
+            defaults_by_type: Vec::new(),
+            shortcuts: vec![
+                ("cur_mouse_jump_left", "Ctrl+Alt+Left"),
+                ("cur_mouse_jump_right", "Ctrl+Alt+Right"),
+                ("cur_mouse_jump_next", "Ctrl+Alt+N"),
+                ("move_window_here", "Ctrl+Alt+M"),
+            ],
+            options: |p, d| {
+                fill(&mut p.monitor_order, d.monitor_order, MonitorOrder::Rows);
+                fill(&mut p.kvm.pause_when_remote, d.kvm.pause_when_remote, true);
+            },
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::Settings;
+
+    fn device(id: &str, device_type: DeviceType) -> GenericDevice {
+        GenericDevice {
+            device_type,
+            ..GenericDevice::id_only(id.to_owned())
+        }
+    }
+
+    #[test]
+    fn test_presets_valid() {
+        for preset in presets() {
+            let mut s = Settings::default();
+            let mapping = vec![Some("dev".to_owned()); preset.roles.len()];
+            preset.apply(&mut s.processor, &mapping);
+            assert!(s.validate().is_empty(), "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn test_preset_apply() {
+        let preset = presets().remove(1);
+        let devices = vec![
+            device("pen", DeviceType::Pen),
+            device("touchpad", DeviceType::TouchPad),
+            device("mouse", DeviceType::Mouse),
+        ];
+        let mapping = preset.suggest(&devices);
+        assert_eq!(
+            mapping,
+            vec![Some("touchpad".to_owned()), Some("pen".to_owned())]
+        );
+
+        let mut p = ProcessorSettings::default();
+        p.shortcuts.cur_mouse_lock = "Ctrl+Alt+F9".to_owned();
+        p.shortcuts.cur_mouse_turbo = "Alt+Ctrl+N".to_owned();
+        p.ensure_mut_device("pen", |d| d.idle_release_secs = 30);
+        preset.apply(&mut p, &[Some("mouse".to_owned()), Some("pen".to_owned())]);
+        // Shortcuts set or taken are kept
+        assert_eq!(p.shortcuts.cur_mouse_lock, "Ctrl+Alt+F9");
+        assert!(p.shortcuts.cur_mouse_jump_next.is_empty());
+        let pen = p.devices.iter().find(|d| d.id == "pen").unwrap();
+        assert!(pen.content.locked_in_monitor);
+        assert_eq!(pen.content.idle_release_secs, 30);
+        let mouse = p.devices.iter().find(|d| d.id == "mouse").unwrap();
+        assert_eq!(mouse.content.switch, SwitchMode::PerDevicePosition);
+        assert!(p.default_for_type(DeviceType::Pen).locked_in_monitor);
+        assert_eq!(p.monitor_order, MonitorOrder::Rows);
+    }
+
+    #[test]
+    fn test_preset_keeps_user_options() {
+        let preset = presets().remove(2);
+        let mut p = ProcessorSettings::default();
+        p.monitor_order = MonitorOrder::Columns;
+        p.defaults_by_type
+            .insert(DeviceType::Mouse.to_string(), DeviceSetting::default());
+        let mut defaults = preset.defaults_by_type.clone();
+        defaults.push((DeviceType::Mouse, locked()));
+        let preset = Preset {
+            defaults_by_type: defaults,
+            ..preset
+        };
+        preset.apply(&mut p, &[None]);
+        assert_eq!(p.monitor_order, MonitorOrder::Columns);
+        assert!(p.kvm.pause_when_remote);
+        assert!(!p.default_for_type(DeviceType::Mouse).locked_in_monitor);
+
+        let mut p = ProcessorSettings::default();
+        presets().remove(0).apply(&mut p, &[None, None]);
+        assert!(p.use_work_area);
+    }
+}
@@ -0,0 +1,73 @@
+// Actions triggered from other processes, e.g. by `monmouse-cli action jump_next` in
+// AutoHotkey scripts, without registering hotkeys of their own.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteAction {
+    JumpNext,
+    JumpLeft,
+    JumpRight,
+    JumpUp,
+    JumpDown,
+    LockToggle,
+    Pause,
+    MoveWindowHere,
+    OpenUI,
+    FocusMode,
+}
+
+impl RemoteAction {
+    pub const VARIANTS: [RemoteAction; 10] = [
+        Self::JumpNext,
+        Self::JumpLeft,
+        Self::JumpRight,
+        Self::JumpUp,
+        Self::JumpDown,
+        Self::LockToggle,
+        Self::Pause,
+        Self::MoveWindowHere,
+        Self::OpenUI,
+        Self::FocusMode,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::JumpNext => "jump_next",
+            Self::JumpLeft => "jump_left",
+            Self::JumpRight => "jump_right",
+            Self::JumpUp => "jump_up",
+            Self::JumpDown => "jump_down",
+            Self::LockToggle => "lock_toggle",
+            Self::Pause => "pause",
+            Self::MoveWindowHere => "move_window_here",
+            Self::OpenUI => "open_ui",
+            Self::FocusMode => "focus_mode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|a| a.name() == name)
+    }
+
+    // Carried in messages between processes
+    pub fn id(&self) -> usize {
+        Self::VARIANTS.iter().position(|a| a == self).unwrap()
+    }
+
+    pub fn from_id(id: usize) -> Option<Self> {
+        Self::VARIANTS.get(id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_action_id() {
+        for a in RemoteAction::VARIANTS {
+            assert_eq!(RemoteAction::from_id(a.id()), Some(a));
+            assert_eq!(RemoteAction::from_name(a.name()), Some(a));
+        }
+        assert!(RemoteAction::from_id(RemoteAction::VARIANTS.len()).is_none());
+    }
+}
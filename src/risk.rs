@@ -0,0 +1,185 @@
+// Features able to lock users out of their keyboard or mouse, e.g. by swallowing keys or
+// dropping clicks. They take effect only after confirmed by user, never in safe mode, and are
+// all turned off by the failsafe shortcut. New risky features only need a variant here.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::setting::{DeviceSetting, ProcessorSettings, ShortcutBackend};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskyFeature {
+    // Keys of shortcuts detected by keyboard hook are not passed to other programs
+    HookShortcuts,
+    // Presses of buttons after release are dropped
+    ClickDebounce,
+    // Wheel events are sent to the window under cursor, instead of the focused one
+    ScrollRedirect,
+}
+
+impl RiskyFeature {
+    pub const VARIANTS: [RiskyFeature; 3] = [
+        Self::HookShortcuts,
+        Self::ClickDebounce,
+        Self::ScrollRedirect,
+    ];
+
+    pub fn warning(&self) -> &'static str {
+        match self {
+            Self::HookShortcuts => {
+                "Keys of hooked shortcuts are taken from all programs, a wrong one may take keys you need."
+            }
+            Self::ClickDebounce => {
+                "Clicks shortly after a release are dropped, a large value may make buttons unusable."
+            }
+            Self::ScrollRedirect => {
+                "Wheel events go to the window under cursor, which some programs don't expect."
+            }
+        }
+    }
+
+    // Used by a single device, or by defaults of device types
+    fn in_device(&self, d: &DeviceSetting) -> bool {
+        match self {
+            Self::ClickDebounce => d.debounce_click_ms > 0,
+            Self::HookShortcuts | Self::ScrollRedirect => false,
+        }
+    }
+
+    fn disable_in_device(&self, d: &mut DeviceSetting) {
+        if let Self::ClickDebounce = self {
+            d.debounce_click_ms = 0;
+        }
+    }
+
+    pub fn in_use(&self, p: &ProcessorSettings) -> bool {
+        let global = match self {
+            Self::HookShortcuts => p.shortcuts.named().iter().any(|(name, s)| {
+                !s.is_empty() && p.shortcuts.backend(name) == ShortcutBackend::Hook
+            }),
+            Self::ScrollRedirect => p.scroll_window_under_cursor,
+            Self::ClickDebounce => false,
+        };
+        global
+            || p.devices.iter().any(|d| self.in_device(&d.content))
+            || p.defaults_by_type.values().any(|d| self.in_device(d))
+    }
+
+    fn disable(&self, p: &mut ProcessorSettings) {
+        match self {
+            Self::HookShortcuts => p
+                .shortcuts
+                .backends
+                .retain(|_, b| *b != ShortcutBackend::Hook),
+            Self::ScrollRedirect => p.scroll_window_under_cursor = false,
+            Self::ClickDebounce => (),
+        }
+        p.devices
+            .iter_mut()
+            .for_each(|d| self.disable_in_device(&mut d.content));
+        p.defaults_by_type
+            .values_mut()
+            .for_each(|d| self.disable_in_device(d));
+    }
+
+    fn allowed(&self, confirmed: &[RiskyFeature], safe_mode: bool) -> bool {
+        !safe_mode && confirmed.contains(self)
+    }
+}
+
+impl Display for RiskyFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::HookShortcuts => "Shortcuts by keyboard hook",
+            Self::ClickDebounce => "Click debouncing",
+            Self::ScrollRedirect => "Scrolling window under cursor",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Risky features in use, but not confirmed yet
+pub fn unconfirmed(p: &ProcessorSettings) -> Vec<RiskyFeature> {
+    RiskyFeature::VARIANTS
+        .into_iter()
+        .filter(|f| f.in_use(p) && !p.confirmed_risks.contains(f))
+        .collect()
+}
+
+// Turns off risky features not allowed, returning them
+pub fn gate(p: &mut ProcessorSettings, safe_mode: bool) -> Vec<RiskyFeature> {
+    let denied: Vec<RiskyFeature> = RiskyFeature::VARIANTS
+        .into_iter()
+        .filter(|f| f.in_use(p) && !f.allowed(&p.confirmed_risks, safe_mode))
+        .collect();
+    denied.iter().for_each(|f| f.disable(p));
+    denied
+}
+
+// For settings of a single device applied alone
+pub fn gate_device(d: &mut DeviceSetting, confirmed: &[RiskyFeature], safe_mode: bool) {
+    for f in RiskyFeature::VARIANTS {
+        if f.in_device(d) && !f.allowed(confirmed, safe_mode) {
+            f.disable_in_device(d);
+        }
+    }
+}
+
+// By the failsafe shortcut, regardless of confirming
+pub fn disable_all(p: &mut ProcessorSettings) -> Vec<RiskyFeature> {
+    gate(p, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::DeviceSettingItem;
+
+    #[test]
+    fn test_gate_risky_features() {
+        let mut p = ProcessorSettings {
+            scroll_window_under_cursor: true,
+            devices: vec![DeviceSettingItem {
+                id: "mouse".to_owned(),
+                content: DeviceSetting {
+                    locked_in_monitor: true,
+                    debounce_click_ms: 30,
+                    ..Default::default()
+                },
+                notes: String::new(),
+                product_name: String::new(),
+            }],
+            ..Default::default()
+        };
+        p.shortcuts.cur_mouse_lock = "Ctrl+Shift".to_owned();
+        p.shortcuts
+            .backends
+            .insert("cur_mouse_lock".to_owned(), ShortcutBackend::Hook);
+        assert_eq!(unconfirmed(&p), RiskyFeature::VARIANTS.to_vec());
+
+        p.confirmed_risks = vec![RiskyFeature::ClickDebounce];
+        let mut gated = p.clone();
+        assert_eq!(
+            gate(&mut gated, false),
+            vec![RiskyFeature::HookShortcuts, RiskyFeature::ScrollRedirect]
+        );
+        assert!(!gated.scroll_window_under_cursor);
+        assert!(gated.shortcuts.backends.is_empty());
+        assert_eq!(gated.shortcuts.cur_mouse_lock, "Ctrl+Shift");
+        assert_eq!(gated.devices[0].content.debounce_click_ms, 30);
+        assert!(unconfirmed(&gated).is_empty());
+
+        let mut gated = p.clone();
+        assert_eq!(gate(&mut gated, true).len(), 3);
+        assert_eq!(gated.devices[0].content.debounce_click_ms, 0);
+        assert!(gated.devices[0].content.locked_in_monitor);
+
+        let mut d = p.devices[0].content;
+        gate_device(&mut d, &p.confirmed_risks, false);
+        assert_eq!(d.debounce_click_ms, 30);
+        gate_device(&mut d, &[], false);
+        assert_eq!(d.debounce_click_ms, 0);
+    }
+}
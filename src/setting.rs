@@ -1,11 +1,76 @@
+use crate::device_type::{DeviceType, WindowsRawinput};
 use crate::errors::Error;
+use crate::event_command::DeviceEvent;
+use crate::geometry::{Corner, Direction, MonitorArea, MousePos};
+use crate::gesture::Gesture;
+use crate::keyboard::{modifiers_from_str, shortcut_from_str, shortcuts_equal};
+use crate::message::{GenericDevice, Positioning};
+use crate::risk::RiskyFeature;
+use crate::utils::wildcard_match;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::io;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 
-pub const CONFIG_FILE_NAME: &str = "monmouse.yml";
+pub const CONFIG_INCLUDE_KEY: &str = "include";
 
-pub fn read_config(file: &PathBuf) -> Result<Settings, Error> {
+// Always registered, even in safe mode, to get the cursor back from a broken config
+pub const FAILSAFE_SHORTCUT: &str = "Ctrl+Alt+Shift+Escape";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub const VARIANTS: [ConfigFormat; 2] = [Self::Yaml, Self::Toml];
+
+    // Decided by extension, YAML if unknown
+    pub fn from_path(file: &Path) -> Self {
+        match file.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Yaml => "yml",
+            Self::Toml => "toml",
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Read config file, merging files listed in `include:` key.
+// Included files are merged in order, later ones override earlier ones,
+// and the including file overrides all of them.
+pub fn read_config(file: &Path) -> Result<Settings, Error> {
+    let mut visiting = Vec::new();
+    let (value, includes) = read_config_value(file, &mut visiting)?;
+    match serde_yaml::from_value::<Settings>(value) {
+        Ok(mut v) => {
+            // Keep includes of top file, so that writing config back preserves them
+            v.include = includes;
+            Ok(v)
+        }
+        Err(e) => Err(Error::InvalidConfigFile(e.to_string())),
+    }
+}
+
+fn read_config_text(file: &Path) -> Result<serde_yaml::Value, Error> {
     match std::fs::read_to_string(file) {
         Ok(v) => Ok(v),
         Err(e) => match e.kind() {
@@ -15,119 +80,2047 @@ pub fn read_config(file: &PathBuf) -> Result<Settings, Error> {
             _ => Err(Error::IO(e)),
         },
     }
-    .and_then(|content| match serde_yaml::from_str::<Settings>(&content) {
-        Ok(v) => Ok(v),
-        Err(e) => Err(Error::InvalidConfigFile(e.to_string())),
-    })
+    .and_then(|content| {
+        // Contents of both formats are merged as YAML values
+        let value = match ConfigFormat::from_path(file) {
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(&content)
+                .map_err(|e| Error::InvalidConfigFile(e.to_string())),
+            ConfigFormat::Toml => toml::from_str::<serde_yaml::Value>(&content)
+                .map_err(|e| Error::InvalidConfigFile(e.to_string())),
+        };
+        match value {
+            Ok(serde_yaml::Value::Null) => Ok(serde_yaml::Value::Mapping(Default::default())),
+            v => v,
+        }
+    })
+}
+
+fn take_includes(value: &mut serde_yaml::Value) -> Result<Vec<String>, Error> {
+    let include = match value.as_mapping_mut() {
+        Some(m) => m.remove(CONFIG_INCLUDE_KEY),
+        None => return Err(Error::InvalidConfigFile("not a mapping".to_owned())),
+    };
+    match include {
+        None | Some(serde_yaml::Value::Null) => Ok(Vec::new()),
+        Some(serde_yaml::Value::String(s)) => Ok(vec![s]),
+        Some(v) => serde_yaml::from_value::<Vec<String>>(v)
+            .map_err(|e| Error::InvalidConfigFile(format!("{}: {}", CONFIG_INCLUDE_KEY, e))),
+    }
+}
+
+// Returns merged content of file, and includes listed in it
+fn read_config_value(
+    file: &Path,
+    visiting: &mut Vec<PathBuf>,
+) -> Result<(serde_yaml::Value, Vec<String>), Error> {
+    let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    if visiting.contains(&canonical) {
+        return Err(Error::ConfigIncludeCycle(format!("{}", file.display())));
+    }
+    let mut value = read_config_text(file)?;
+    let includes = take_includes(&mut value)?;
+    if includes.is_empty() {
+        return Ok((value, includes));
+    }
+
+    visiting.push(canonical);
+    let base_dir = file.parent().unwrap_or(Path::new(""));
+    let mut merged = serde_yaml::Value::Mapping(Default::default());
+    for include in includes.iter() {
+        let (included, _) = read_config_value(&base_dir.join(include), visiting)?;
+        merge_config_value(&mut merged, included);
+    }
+    visiting.pop();
+
+    merge_config_value(&mut merged, value);
+    Ok((merged, includes))
+}
+
+// Mappings are merged recursively, lists of items with `id` (e.g. devices)
+// are merged by id, other values are replaced.
+fn merge_config_value(base: &mut serde_yaml::Value, over: serde_yaml::Value) {
+    use serde_yaml::Value;
+    match (base, over) {
+        (Value::Mapping(base), Value::Mapping(over)) => {
+            for (k, v) in over {
+                match base.get_mut(&k) {
+                    Some(b) => merge_config_value(b, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(over))
+            if base
+                .iter()
+                .chain(over.iter())
+                .all(|v| v.get("id").is_some()) =>
+        {
+            for v in over {
+                match base.iter_mut().find(|b| b.get("id") == v.get("id")) {
+                    Some(b) => merge_config_value(b, v),
+                    None => base.push(v),
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+// Config deployed by administrators is taken as base, and values of the config file are kept
+// over it. Devices are merged by id, so deployed ones are added. The config file is created
+// if not existing. Returns whether it's written.
+pub fn bootstrap_config(file: &PathBuf, deployed: &Path) -> Result<bool, Error> {
+    let (mut merged, _) = read_config_value(deployed, &mut Vec::new())?;
+    let (own, includes) = match read_config_text(file) {
+        Ok(mut v) => {
+            let includes = take_includes(&mut v)?;
+            (Some(v), includes)
+        }
+        Err(Error::ConfigFileNotExists(_)) => (None, Vec::new()),
+        Err(e) => return Err(e),
+    };
+    if let Some(own) = own.clone() {
+        merge_config_value(&mut merged, own);
+    }
+    let mut settings = serde_yaml::from_value::<Settings>(merged)
+        .map_err(|e| Error::InvalidConfigFile(e.to_string()))?;
+    settings.include = includes;
+    let issues = settings.validate();
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        return Err(Error::InvalidConfigFile(issues.join("; ")));
+    }
+
+    if let Some(own) = own {
+        let mut current = serde_yaml::from_value::<Settings>(own)
+            .map_err(|e| Error::InvalidConfigFile(e.to_string()))?;
+        current.include = settings.include.clone();
+        if serde_yaml::to_value(&current).ok() == serde_yaml::to_value(&settings).ok() {
+            return Ok(false);
+        }
+    }
+    write_config(file, &settings)?;
+    Ok(true)
+}
+
+// Values equal to those merged from included files are removed from value, the reverse of
+// merge_config_value. Returns whether nothing is left of value.
+fn strip_config_value(value: &mut serde_yaml::Value, base: &serde_yaml::Value) -> bool {
+    use serde_yaml::Value;
+    match (value, base) {
+        (Value::Mapping(value), Value::Mapping(base)) => {
+            value.retain(|k, v| match base.get(k) {
+                Some(b) => !strip_config_value(v, b),
+                None => true,
+            });
+            value.is_empty()
+        }
+        (Value::Sequence(value), Value::Sequence(base))
+            if value
+                .iter()
+                .chain(base.iter())
+                .all(|v| v.get("id").is_some()) =>
+        {
+            value.retain_mut(|v| {
+                let Some(b) = base.iter().find(|b| b.get("id") == v.get("id")) else {
+                    return true;
+                };
+                let id = v.get("id").cloned();
+                if strip_config_value(v, b) {
+                    return false;
+                }
+                // Kept for merging by it
+                if let (Some(m), Some(id)) = (v.as_mapping_mut(), id) {
+                    m.insert(Value::from("id"), id);
+                }
+                true
+            });
+            value.is_empty()
+        }
+        (value, base) => value == base,
+    }
+}
+
+fn remove_null_values(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(m) => {
+            m.retain(|_, v| !v.is_null());
+            m.values_mut().for_each(remove_null_values);
+        }
+        serde_yaml::Value::Sequence(s) => s.iter_mut().for_each(remove_null_values),
+        _ => (),
+    }
+}
+
+// Settings of a config with includes are written without values coming from included files
+// or left default, so that they are not copied into it and still follow changes of those
+// files. Devices only listed by included files cannot be removed this way.
+fn own_config_value(file: &Path, settings: &Settings) -> Result<serde_yaml::Value, Error> {
+    let mut value =
+        serde_yaml::to_value(settings).map_err(|e| Error::InvalidConfigFile(e.to_string()))?;
+    let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let mut visiting = vec![canonical];
+    let base_dir = file.parent().unwrap_or(Path::new(""));
+    let mut included = serde_yaml::Value::Mapping(Default::default());
+    for include in settings.include.iter() {
+        let (v, _) = read_config_value(&base_dir.join(include), &mut visiting)?;
+        merge_config_value(&mut included, v);
+    }
+    // With defaults filled in, as settings are
+    let included = serde_yaml::from_value::<Settings>(included)
+        .and_then(serde_yaml::to_value)
+        .map_err(|e| Error::InvalidConfigFile(e.to_string()))?;
+    strip_config_value(&mut value, &included);
+    Ok(value)
+}
+
+pub fn write_config(file: &PathBuf, settings: &Settings) -> Result<(), Error> {
+    let own = if settings.include.is_empty() {
+        None
+    } else {
+        Some(own_config_value(file, settings)?)
+    };
+    match (ConfigFormat::from_path(file), own) {
+        (ConfigFormat::Yaml, None) => {
+            serde_yaml::to_string(settings).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+        }
+        (ConfigFormat::Toml, None) => {
+            toml::to_string(settings).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+        }
+        (ConfigFormat::Yaml, Some(v)) => {
+            serde_yaml::to_string(&v).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+        }
+        // Nothing stands for null in TOML, absent keys are read as None
+        (ConfigFormat::Toml, Some(mut v)) => {
+            remove_null_values(&mut v);
+            toml::to_string(&v).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+        }
+    }
+    .and_then(|content| match std::fs::write(file, content) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::IO(e)),
+    })
+}
+
+// Valid ranges of numeric settings
+pub mod limits {
+    use std::ops::RangeInclusive;
+
+    pub const INSPECT_DEVICE_INTERVAL_MS: RangeInclusive<u64> = 20..=1000;
+    pub const MERGE_UNASSOCIATED_EVENTS_MS: RangeInclusive<i64> = -1..=1000;
+    pub const SWITCH_AFTER_EVENTS: RangeInclusive<u32> = 1..=100;
+    pub const TURBO_SPEED_PERCENT: RangeInclusive<u32> = 100..=1000;
+    pub const POLL_MAX_MESSAGES: RangeInclusive<u32> = 1..=1000;
+    pub const POLL_BUSY_TIMEOUT_MS: RangeInclusive<u32> = 1..=1000;
+    pub const POLL_IDLE_TIMEOUT_MS: RangeInclusive<u32> = 1..=5000;
+    pub const UPDATE_MONITORS_INTERVAL_MS: RangeInclusive<u64> = 100..=60000;
+    pub const IDLE_RELEASE_SECS: RangeInclusive<u32> = 0..=3600;
+    pub const BATTERY_QUERY_INTERVAL_SECS: RangeInclusive<u32> = 0..=86400;
+    pub const LOW_BATTERY_ALERT_PERCENT: RangeInclusive<u32> = 0..=100;
+    pub const HOT_CORNER_DWELL_MS: RangeInclusive<u64> = 50..=10000;
+    pub const FOCUS_DIM_PERCENT: RangeInclusive<u32> = 10..=100;
+    pub const PARK_AFTER_IDLE_SECS: RangeInclusive<u32> = 1..=3600;
+    pub const DEBOUNCE_CLICK_MS: RangeInclusive<u32> = 0..=500;
+    pub const ACTIVATION_EVENTS: RangeInclusive<u32> = 1..=100;
+    pub const ACTIVATION_WITHIN_MS: RangeInclusive<u32> = 10..=5000;
+    pub const STARTUP_GRACE_SECS: RangeInclusive<u32> = 0..=120;
+    pub const MONITOR_CHANGE_GRACE_SECS: RangeInclusive<u32> = 0..=120;
+    pub const SYNC_PORT: RangeInclusive<u16> = 1024..=65535;
+    pub const SYNC_TOKEN_MIN_LEN: usize = 8;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub ui: UISettings,
+    #[serde(default)]
+    pub processor: ProcessorSettings,
+}
+
+impl Settings {
+    // Check values which can be parsed, but not accepted
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |field: &str, message: String| {
+            issues.push(ValidationIssue {
+                field: field.to_owned(),
+                message,
+            })
+        };
+        macro_rules! check_range {
+            ($v: expr, $field: expr, $range: expr) => {
+                if !$range.contains(&$v) {
+                    issue(
+                        $field,
+                        format!(
+                            "value {} should among {}-{}",
+                            $v,
+                            $range.start(),
+                            $range.end()
+                        ),
+                    );
+                }
+            };
+        }
+
+        let ui = &self.ui;
+        let p = &self.processor;
+        check_range!(
+            ui.inspect_device_interval_ms,
+            "ui.inspect_device_interval_ms",
+            limits::INSPECT_DEVICE_INTERVAL_MS
+        );
+        if ui.sync.listen_addr.parse::<IpAddr>().is_err() {
+            issue(
+                "ui.sync.listen_addr",
+                format!("{} is not an IP address", ui.sync.listen_addr),
+            );
+        }
+        check_range!(ui.sync.port, "ui.sync.port", limits::SYNC_PORT);
+        if ui.sync.listen && ui.sync.token.len() < limits::SYNC_TOKEN_MIN_LEN {
+            issue(
+                "ui.sync.token",
+                format!(
+                    "should have at least {} characters to listen",
+                    limits::SYNC_TOKEN_MIN_LEN
+                ),
+            );
+        }
+        check_range!(
+            p.merge_unassociated_events_ms,
+            "processor.merge_unassociated_events_ms",
+            limits::MERGE_UNASSOCIATED_EVENTS_MS
+        );
+        check_range!(
+            p.switch_after_events,
+            "processor.switch_after_events",
+            limits::SWITCH_AFTER_EVENTS
+        );
+        check_range!(
+            p.turbo_speed_percent,
+            "processor.turbo_speed_percent",
+            limits::TURBO_SPEED_PERCENT
+        );
+        check_range!(
+            p.update_monitors_interval_ms,
+            "processor.update_monitors_interval_ms",
+            limits::UPDATE_MONITORS_INTERVAL_MS
+        );
+        check_range!(
+            p.poll_max_messages,
+            "processor.poll_max_messages",
+            limits::POLL_MAX_MESSAGES
+        );
+        check_range!(
+            p.poll_busy_timeout_ms,
+            "processor.poll_busy_timeout_ms",
+            limits::POLL_BUSY_TIMEOUT_MS
+        );
+        check_range!(
+            p.poll_idle_timeout_ms,
+            "processor.poll_idle_timeout_ms",
+            limits::POLL_IDLE_TIMEOUT_MS
+        );
+        check_range!(
+            p.startup_grace_secs,
+            "processor.startup_grace_secs",
+            limits::STARTUP_GRACE_SECS
+        );
+        check_range!(
+            p.monitor_change_grace_secs,
+            "processor.monitor_change_grace_secs",
+            limits::MONITOR_CHANGE_GRACE_SECS
+        );
+        check_range!(
+            p.hot_corners.dwell_ms,
+            "processor.hot_corners.dwell_ms",
+            limits::HOT_CORNER_DWELL_MS
+        );
+        check_range!(
+            p.focus_mode.dim_percent,
+            "processor.focus_mode.dim_percent",
+            limits::FOCUS_DIM_PERCENT
+        );
+        check_range!(
+            p.focus_mode.release_after_idle_secs,
+            "processor.focus_mode.release_after_idle_secs",
+            limits::IDLE_RELEASE_SECS
+        );
+        check_range!(
+            p.battery_query_interval_secs,
+            "processor.battery_query_interval_secs",
+            limits::BATTERY_QUERY_INTERVAL_SECS
+        );
+        check_range!(
+            ui.low_battery_alert_percent,
+            "ui.low_battery_alert_percent",
+            limits::LOW_BATTERY_ALERT_PERCENT
+        );
+
+        let shortcuts = p.shortcuts.named();
+        for (i, (name, value)) in shortcuts.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            let field = format!("processor.shortcuts.{}", name);
+            // Only the hook backend detects shortcuts of only modifiers
+            let modifiers_only = p.shortcuts.backend(name) == ShortcutBackend::Hook
+                && modifiers_from_str(value).is_some();
+            if shortcut_from_str(value).is_none() && !modifiers_only {
+                issue(&field, format!("invalid shortcut \"{}\"", value));
+            } else if shortcuts_equal(value, FAILSAFE_SHORTCUT) {
+                issue(
+                    &field,
+                    format!("shortcut \"{}\" is reserved for failsafe", value),
+                );
+            } else if let Some((other, _)) = shortcuts[..i]
+                .iter()
+                .find(|(_, v)| shortcuts_equal(v, value))
+            {
+                issue(
+                    &field,
+                    format!("shortcut \"{}\" is also used by {}", value, other),
+                );
+            }
+        }
+        for name in p.shortcuts.backends.keys() {
+            if !shortcuts.iter().any(|(n, _)| n == name) {
+                issue(
+                    "processor.shortcuts.backends",
+                    format!("unknown shortcut {}", name),
+                );
+            }
+        }
+
+        for (name, d) in p.defaults_by_type.iter() {
+            if DeviceType::from_name(name).is_none() {
+                issue(
+                    &format!("processor.defaults_by_type.{}", name),
+                    "unknown device type".to_owned(),
+                );
+            }
+            if let Some(v) = d.merge_unassociated_events_ms {
+                check_range!(
+                    v,
+                    &format!(
+                        "processor.defaults_by_type.{}.merge_unassociated_events_ms",
+                        name
+                    ),
+                    limits::MERGE_UNASSOCIATED_EVENTS_MS
+                );
+            }
+        }
+
+        for (name, argv) in p.on_event_command.iter() {
+            let field = format!("processor.on_event_command.{}", name);
+            if DeviceEvent::from_name(name).is_none() {
+                issue(&field, "unknown event".to_owned());
+            } else if argv.first().filter(|v| !v.is_empty()).is_none() {
+                issue(&field, "empty command".to_owned());
+            }
+        }
+
+        for (i, r) in p.device_rules.iter().enumerate() {
+            let field = format!("processor.device_rules[{}]", i);
+            if r.device.is_empty() {
+                issue(&field, "empty device".to_owned());
+            }
+            if r.on_connect
+                .iter()
+                .chain(&r.on_disconnect)
+                .any(|a| a.is_empty())
+            {
+                issue(&field, "action changing nothing".to_owned());
+            }
+        }
+
+        for (i, r) in p.app_rules.iter().enumerate() {
+            let field = format!("processor.app_rules[{}]", i);
+            if r.app.is_empty() {
+                issue(&field, "empty app".to_owned());
+            }
+            if r.actions.iter().any(|a| a.is_empty()) {
+                issue(&field, "action changing nothing".to_owned());
+            }
+        }
+
+        if p.rawinput_usages.is_empty() {
+            issue(
+                "processor.rawinput_usages",
+                "empty, leave it out for the defaults".to_owned(),
+            );
+        }
+        for (i, u) in p.rawinput_usages.iter().enumerate() {
+            let field = format!("processor.rawinput_usages[{}]", i);
+            if u.page == 0 {
+                issue(&field, "usage page 0 is undefined".to_owned());
+            } else if u.covers_keyboards() {
+                issue(&field, "keyboards are not managed".to_owned());
+            } else if p.rawinput_usages[..i].contains(u) {
+                issue(&field, format!("duplicated usage {}", u));
+            }
+        }
+        if !p.rawinput_usages.is_empty()
+            && !p
+                .rawinput_usages
+                .iter()
+                .any(|u| u.matches(WindowsRawinput::MOUSE))
+        {
+            issue(
+                "processor.rawinput_usages",
+                "Generic Desktop Mouse(page 0x01, usage 0x02) must be registered".to_owned(),
+            );
+        }
+
+        for (i, d) in p.devices.iter().enumerate() {
+            if d.id.is_empty() {
+                issue(&format!("processor.devices[{}]", i), "empty id".to_owned());
+            } else if p.devices[..i].iter().any(|v| v.id == d.id) {
+                issue(
+                    &format!("processor.devices[{}]", i),
+                    format!("duplicated id \"{}\"", d.id),
+                );
+            }
+            check_range!(
+                d.content.idle_release_secs,
+                &format!("processor.devices[{}].idle_release_secs", i),
+                limits::IDLE_RELEASE_SECS
+            );
+            check_range!(
+                d.content.debounce_click_ms,
+                &format!("processor.devices[{}].debounce_click_ms", i),
+                limits::DEBOUNCE_CLICK_MS
+            );
+            if let Some(park) = d.content.park {
+                check_range!(
+                    park.after_idle_secs,
+                    &format!("processor.devices[{}].park.after_idle_secs", i),
+                    limits::PARK_AFTER_IDLE_SECS
+                );
+            }
+            if let Some(v) = d.content.merge_unassociated_events_ms {
+                check_range!(
+                    v,
+                    &format!("processor.devices[{}].merge_unassociated_events_ms", i),
+                    limits::MERGE_UNASSOCIATED_EVENTS_MS
+                );
+            }
+            if let Some(g) = d.content.activation_grace {
+                check_range!(
+                    g.events,
+                    &format!("processor.devices[{}].activation_grace.events", i),
+                    limits::ACTIVATION_EVENTS
+                );
+                check_range!(
+                    g.within_ms,
+                    &format!("processor.devices[{}].activation_grace.within_ms", i),
+                    limits::ACTIVATION_WITHIN_MS
+                );
+            }
+            if let Some(c) = d.content.calibration {
+                let [l, t, r, b] = c.extent;
+                if r <= l || b <= t {
+                    issue(
+                        &format!("processor.devices[{}].calibration.extent", i),
+                        "empty extent".to_owned(),
+                    );
+                }
+            }
+        }
+        issues
+    }
+}
+
+// Settings for single device
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSetting {
+    #[serde(default = "bool_const::<false>")]
+    pub locked_in_monitor: bool,
+    #[serde(default)]
+    pub switch: SwitchMode,
+    // Release the locked monitor after idle for such seconds, 0 for never
+    #[serde(default)]
+    pub idle_release_secs: u32,
+    // Overrides processor.merge_unassociated_events_ms while this device is active
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_unassociated_events_ms: Option<i64>,
+    // System cursor is replaced while this device is active, and restored after
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_style: Option<CursorStyle>,
+    // For devices reporting absolute flags incorrectly
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ForcePositioning::is_auto")]
+    pub force_positioning: ForcePositioning,
+    // Cursor is moved aside after this device is idle, e.g. not to hover over slides
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub park: Option<CursorPark>,
+    // A button press within such milliseconds after its release is dropped, for worn
+    // switches clicking twice. 0 to disable
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub debounce_click_ms: u32,
+    // Only for positions of absolute devices
+    #[serde(default)]
+    #[serde(skip_serializing_if = "PosTransform::is_identity")]
+    pub transform: PosTransform,
+    // Only for positions of absolute devices, applied before transform
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+    // Overrides processor.switch_after_events for this device becoming active
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation_grace: Option<ActivationGrace>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSettingItem {
+    pub id: String,
+    #[serde(flatten)]
+    pub content: DeviceSetting,
+    // Free text from user, e.g. where the device is plugged
+    #[serde(default = "empty_string")]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    // Product name when last seen, to recognize the device if its id changes
+    #[serde(default = "empty_string")]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub product_name: String,
+}
+
+// Device settings changed when a device connects or disconnects, e.g. locking the tablet
+// when it connects and unlocking the mouse, then reverting both when it disconnects.
+// Devices connected at startup are counted as connecting.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRule {
+    // Pattern of id of the device triggering it, where '*' matches any
+    pub device: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_connect: Vec<DeviceRuleAction>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_disconnect: Vec<DeviceRuleAction>,
+}
+
+impl DeviceRule {
+    pub fn actions(&self, connected: bool) -> &[DeviceRuleAction] {
+        match connected {
+            true => &self.on_connect,
+            false => &self.on_disconnect,
+        }
+    }
+}
+
+// Fields not given are kept
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRuleAction {
+    // Pattern of ids of connected devices to change, the triggering device if empty
+    #[serde(default = "empty_string")]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub target: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_in_monitor: Option<bool>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub switch: Option<SwitchMode>,
+}
+
+impl DeviceRuleAction {
+    pub fn is_empty(&self) -> bool {
+        self.locked_in_monitor.is_none() && self.switch.is_none()
+    }
+
+    pub fn apply(&self, s: &mut DeviceSetting) {
+        if let Some(v) = self.locked_in_monitor {
+            s.locked_in_monitor = v;
+        }
+        if let Some(v) = self.switch {
+            s.switch = v;
+        }
+    }
+
+    // Values of s for the fields it sets, which undo it when applied. Other fields are kept,
+    // as they may be changed meanwhile.
+    pub fn revert_of(&self, s: &DeviceSetting) -> DeviceRuleAction {
+        DeviceRuleAction {
+            target: String::new(),
+            locked_in_monitor: self.locked_in_monitor.map(|_| s.locked_in_monitor),
+            switch: self.switch.map(|_| s.switch),
+        }
+    }
+}
+
+// Device settings overridden while a program is in foreground, e.g. unlocking the touch
+// screen while a whiteboard app is used. Settings of devices come back once another program
+// is in foreground, or the rule is disabled.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppRule {
+    // Pattern of executable name of the foreground program, e.g. "whiteboard*.exe"
+    pub app: String,
+    #[serde(default = "bool_const::<true>")]
+    pub enabled: bool,
+    // Actions without target change all connected devices
+    #[serde(default)]
+    pub actions: Vec<DeviceRuleAction>,
+}
+
+// A device entry in config without any connected device of the same id, and the
+// connected devices which may be it under a new id, best matched first
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeviceReconcile {
+    pub id: String,
+    pub candidates: Vec<String>,
+}
+
+// Hardware ids in a device path, like VID_046D, PID_C52B, MI_01 and COL02
+fn hardware_ids(id: &str) -> Vec<String> {
+    id.to_ascii_uppercase()
+        .split(['#', '&', '\\'])
+        .filter(|s| {
+            ["VID_", "PID_", "MI_", "COL"]
+                .iter()
+                .any(|p| s.starts_with(p))
+        })
+        .map(str::to_owned)
+        .collect()
+}
+
+fn device_match_score(item: &DeviceSettingItem, dev: &GenericDevice) -> u32 {
+    let (old, new) = (hardware_ids(&item.id), hardware_ids(&dev.id));
+    let same = |prefix: &str| {
+        let find = |ids: &Vec<String>| ids.iter().find(|s| s.starts_with(prefix)).cloned();
+        find(&old).is_some_and(|v| Some(v) == find(&new))
+    };
+    let mut score = 0;
+    if same("VID_") && same("PID_") {
+        score += 4;
+        score += ["MI_", "COL"].iter().filter(|p| same(p)).count() as u32;
+    }
+    if !item.product_name.is_empty() && item.product_name.eq_ignore_ascii_case(&dev.product_name) {
+        score += 3;
+    }
+    score
+}
+
+// Entries without candidates are left out, since they may be just unplugged
+pub fn reconcile_devices(
+    configured: &[DeviceSettingItem],
+    scanned: &[GenericDevice],
+) -> Vec<DeviceReconcile> {
+    const MIN_SCORE: u32 = 3;
+    let unmatched: Vec<&GenericDevice> = scanned
+        .iter()
+        .filter(|d| !configured.iter().any(|c| c.id == d.id))
+        .collect();
+    configured
+        .iter()
+        .filter(|c| !scanned.iter().any(|d| d.id == c.id))
+        .filter_map(|c| {
+            let mut scored: Vec<(u32, &GenericDevice)> = unmatched
+                .iter()
+                .map(|d| (device_match_score(c, d), *d))
+                .filter(|(score, _)| *score >= MIN_SCORE)
+                .collect();
+            if scored.is_empty() {
+                return None;
+            }
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            Some(DeviceReconcile {
+                id: c.id.clone(),
+                candidates: scored.into_iter().map(|(_, d)| d.id.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+impl DeviceSetting {
+    // Any field changed from default, so that it is worth saving
+    pub fn is_effective(&self) -> bool {
+        *self != DeviceSetting::default()
+    }
+}
+
+// Where cursor goes when switching back to a device
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "SwitchModeCompat")]
+pub enum SwitchMode {
+    // Cursor is shared, stays where the last device left it
+    #[default]
+    Shared,
+    // Restore the position device left
+    PerDevicePosition,
+    // Restore only the monitor device left, cursor placed at its center
+    PerDeviceMonitor,
+}
+
+// Where cursor lands when jumping to another monitor
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpTarget {
+    // Position cursor left that monitor last time, or its center
+    #[default]
+    Remembered,
+    Center,
+    // Same relative position as in current monitor, scaled to the new one
+    Relative,
+    // Point of new monitor nearest to cursor
+    NearestEdge,
+}
+
+impl JumpTarget {
+    pub const VARIANTS: [JumpTarget; 4] = [
+        Self::Remembered,
+        Self::Center,
+        Self::Relative,
+        Self::NearestEdge,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for JumpTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Remembered => "remembered",
+            Self::Center => "center",
+            Self::Relative => "relative",
+            Self::NearestEdge => "nearest_edge",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Order of monitors when jumping to the next one
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorOrder {
+    // As enumerated by system, which may look random
+    #[default]
+    Enumeration,
+    // Rows from top to bottom, each from left to right
+    Rows,
+    // Columns from left to right, each from top to bottom
+    Columns,
+}
+
+impl MonitorOrder {
+    pub const VARIANTS: [MonitorOrder; 3] = [Self::Enumeration, Self::Rows, Self::Columns];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for MonitorOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Enumeration => "enumeration",
+            Self::Rows => "rows",
+            Self::Columns => "columns",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Priority of the thread correcting cursor. Higher ones keep up under CPU load, but take
+// CPU from other programs when events flood.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadPriority {
+    #[default]
+    Normal,
+    AboveNormal,
+    Highest,
+}
+
+impl ThreadPriority {
+    pub const VARIANTS: [ThreadPriority; 3] = [Self::Normal, Self::AboveNormal, Self::Highest];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for ThreadPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Normal => "normal",
+            Self::AboveNormal => "above_normal",
+            Self::Highest => "highest",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// HID usage registered for raw input, devices of other usages are neither seen nor managed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawinputUsage {
+    pub page: u16,
+    // 0 for all usages of the page
+    #[serde(default)]
+    pub usage: u16,
+}
+
+impl RawinputUsage {
+    pub fn defaults() -> Vec<Self> {
+        WindowsRawinput::DEFAULT_USAGE_SET
+            .iter()
+            .map(|&(page, usage)| RawinputUsage { page, usage })
+            .collect()
+    }
+
+    pub fn is_page_only(&self) -> bool {
+        self.usage == WindowsRawinput::ALL
+    }
+
+    pub fn matches(&self, (page, usage): (u16, u16)) -> bool {
+        self.page == page && (self.is_page_only() || self.usage == usage)
+    }
+
+    // Keyboards are watched by shortcuts, not managed as devices
+    fn covers_keyboards(&self) -> bool {
+        self.matches(WindowsRawinput::KEYBOARD) || self.matches(WindowsRawinput::KEYPAD)
+    }
+}
+
+impl Display for RawinputUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.is_page_only() {
+            true => write!(f, "0x{:02X}/*", self.page),
+            false => write!(f, "0x{:02X}/0x{:02X}", self.page, self.usage),
+        }
+    }
+}
+
+// Overrides positioning classified from events of a device
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForcePositioning {
+    // Classified by flags of each event
+    #[default]
+    Auto,
+    Relative,
+    Absolute,
+}
+
+impl ForcePositioning {
+    pub const VARIANTS: [ForcePositioning; 3] = [Self::Auto, Self::Relative, Self::Absolute];
+
+    pub fn is_auto(&self) -> bool {
+        *self == Self::Auto
+    }
+
+    pub fn apply(&self, raw: Positioning) -> Positioning {
+        match self {
+            Self::Auto => raw,
+            Self::Relative => Positioning::Relative,
+            Self::Absolute => Positioning::Absolute,
+        }
+    }
+}
+
+impl Display for ForcePositioning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Auto => "auto",
+            Self::Relative => "relative",
+            Self::Absolute => "absolute",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Replacement of system cursor while a device is active
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorStyle {
+    // 1.5 times of system size
+    Large,
+    // Twice of system size
+    ExtraLarge,
+    // Crosshair, e.g. for pen
+    Precision,
+}
+
+impl CursorStyle {
+    pub const VARIANTS: [CursorStyle; 3] = [Self::Large, Self::ExtraLarge, Self::Precision];
+}
+
+impl Display for CursorStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Large => "large",
+            Self::ExtraLarge => "extra_large",
+            Self::Precision => "precision",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Where cursor is parked, on the monitor of its device
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl ParkPosition {
+    pub const VARIANTS: [ParkPosition; 5] = [
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+        Self::Center,
+    ];
+}
+
+impl Display for ParkPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::TopLeft => "top_left",
+            Self::TopRight => "top_right",
+            Self::BottomLeft => "bottom_left",
+            Self::BottomRight => "bottom_right",
+            Self::Center => "center",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorPark {
+    #[serde(default = "CursorPark::default_after_idle_secs")]
+    pub after_idle_secs: u32,
+    #[serde(default)]
+    pub at: ParkPosition,
+}
+
+impl CursorPark {
+    fn default_after_idle_secs() -> u32 {
+        10
+    }
+}
+
+impl Default for CursorPark {
+    fn default() -> Self {
+        Self {
+            after_idle_secs: Self::default_after_idle_secs(),
+            at: ParkPosition::default(),
+        }
+    }
+}
+
+// Events a device must send within a time to become active, so that nudging it doesn't
+// take cursor away from the device in use
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivationGrace {
+    #[serde(default = "ActivationGrace::default_events")]
+    pub events: u32,
+    #[serde(default = "ActivationGrace::default_within_ms")]
+    pub within_ms: u32,
+}
+
+impl ActivationGrace {
+    fn default_events() -> u32 {
+        5
+    }
+
+    fn default_within_ms() -> u32 {
+        200
+    }
+}
+
+impl Default for ActivationGrace {
+    fn default() -> Self {
+        Self {
+            events: Self::default_events(),
+            within_ms: Self::default_within_ms(),
+        }
+    }
+}
+
+// Clockwise, in degrees in config file
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const VARIANTS: [Rotation; 4] = [Self::None, Self::Cw90, Self::Cw180, Self::Cw270];
+
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Cw90 => 90,
+            Self::Cw180 => 180,
+            Self::Cw270 => 270,
+        }
+    }
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = String;
+    fn try_from(degrees: u32) -> std::result::Result<Self, Self::Error> {
+        Self::VARIANTS
+            .into_iter()
+            .find(|r| r.degrees() == degrees)
+            .ok_or(format!("rotation must be 0, 90, 180 or 270: {}", degrees))
+    }
+}
+
+impl From<Rotation> for u32 {
+    fn from(r: Rotation) -> Self {
+        r.degrees()
+    }
+}
+
+impl Display for Rotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}°", self.degrees())
+    }
+}
+
+// Positions of an absolute device within the monitor are rotated then mirrored, before
+// locking and relocating. For pen displays mounted rotated, or mapped by drivers wrongly.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PosTransform {
+    #[serde(default)]
+    pub rotate: Rotation,
+    #[serde(default)]
+    pub mirror_x: bool,
+    #[serde(default)]
+    pub mirror_y: bool,
+}
+
+impl PosTransform {
+    // Fraction of monitor size, fine enough for any resolution
+    const SCALE: i64 = 1 << 16;
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, area: &MonitorArea, p: &MousePos) -> MousePos {
+        let (l, t) = (area.lefttop.x, area.lefttop.y);
+        let (w, h) = ((area.rigtbtm.x - l) as i64, (area.rigtbtm.y - t) as i64);
+        if self.is_identity() || w <= 0 || h <= 0 {
+            return *p;
+        }
+        let s = Self::SCALE;
+        let (u, v) = ((p.x - l) as i64 * s / w, (p.y - t) as i64 * s / h);
+        let (u, v) = match self.rotate {
+            Rotation::None => (u, v),
+            Rotation::Cw90 => (s - v, u),
+            Rotation::Cw180 => (s - u, s - v),
+            Rotation::Cw270 => (v, s - u),
+        };
+        let u = if self.mirror_x { s - u } else { u };
+        let v = if self.mirror_y { s - v } else { v };
+        // Rounded, not to drift by a pixel on axes kept
+        let back = |f: i64, size: i64| ((f * size + s / 2) / s) as i32;
+        MousePos::from(l + back(u, w), t + back(v, h))
+    }
+}
+
+// Maps the extent an absolute device reaches onto a monitor, so a pen whose driver maps it
+// short of the edges, or onto the wrong monitor, covers the monitor edge to edge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Calibration {
+    // Desktop pixels reached by the device as left, top, right, bottom. Captured by sweeping
+    // it over its whole surface.
+    pub extent: [i32; 4],
+    // Number of the monitor in jumping order from 1, 0 for the monitor the extent lies on
+    #[serde(default)]
+    pub monitor: u32,
+    // Pixels left out of the monitor as left, top, right, bottom
+    #[serde(default)]
+    pub margins: [u32; 4],
+}
+
+impl Calibration {
+    pub fn from_extent(extent: &MonitorArea) -> Self {
+        Calibration {
+            extent: [
+                extent.lefttop.x,
+                extent.lefttop.y,
+                extent.rigtbtm.x,
+                extent.rigtbtm.y,
+            ],
+            monitor: 0,
+            margins: [0; 4],
+        }
+    }
+
+    pub fn extent(&self) -> MonitorArea {
+        let [l, t, r, b] = self.extent;
+        MonitorArea {
+            lefttop: MousePos::from(l, t),
+            rigtbtm: MousePos::from(r, b),
+            id: 0,
+        }
+    }
+
+    // Position within the extent, scaled into target with margins left out. Positions
+    // beyond the extent stay on the edges.
+    pub fn apply(&self, target: &MonitorArea, p: &MousePos) -> MousePos {
+        let [l, t, r, b] = self.extent;
+        let [ml, mt, mr, mb] = self.margins.map(|v| v as i32);
+        let (tl, tt) = (target.lefttop.x + ml, target.lefttop.y + mt);
+        let (tr, tb) = (target.rigtbtm.x - mr, target.rigtbtm.y - mb);
+        if r <= l || b <= t || tr < tl || tb < tt {
+            return *p;
+        }
+        let scale = |v: i32, from: (i32, i32), to: (i32, i32)| {
+            let v = v.clamp(from.0, from.1) as i64 - from.0 as i64;
+            let (span, tspan) = ((from.1 - from.0) as i64, (to.1 - to.0) as i64);
+            to.0 + ((v * tspan + span / 2) / span) as i32
+        };
+        MousePos::from(scale(p.x, (l, r), (tl, tr)), scale(p.y, (t, b), (tt, tb)))
+    }
+}
+
+// Older config files have "switch" as a bool
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SwitchModeCompat {
+    Legacy(bool),
+    Name(String),
+}
+
+impl TryFrom<SwitchModeCompat> for SwitchMode {
+    type Error = String;
+    fn try_from(v: SwitchModeCompat) -> std::result::Result<Self, Self::Error> {
+        match v {
+            SwitchModeCompat::Legacy(true) => Ok(Self::PerDevicePosition),
+            SwitchModeCompat::Legacy(false) => Ok(Self::Shared),
+            SwitchModeCompat::Name(name) => {
+                Self::from_name(&name).ok_or(format!("unknown switch mode: {}", name))
+            }
+        }
+    }
+}
+
+// Older config files have "low_level", names of shortcuts detected by keyboard hook
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShortcutBackendsCompat {
+    Backends(BTreeMap<String, ShortcutBackend>),
+    LowLevel(Vec<String>),
+}
+
+fn deserialize_backends<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<BTreeMap<String, ShortcutBackend>, D::Error> {
+    Ok(match ShortcutBackendsCompat::deserialize(deserializer)? {
+        ShortcutBackendsCompat::Backends(backends) => backends,
+        ShortcutBackendsCompat::LowLevel(names) => names
+            .into_iter()
+            .map(|name| (name, ShortcutBackend::Hook))
+            .collect(),
+    })
+}
+
+impl SwitchMode {
+    pub const VARIANTS: [SwitchMode; 3] = [
+        Self::Shared,
+        Self::PerDevicePosition,
+        Self::PerDeviceMonitor,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for SwitchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Shared => "shared",
+            Self::PerDevicePosition => "per_device_position",
+            Self::PerDeviceMonitor => "per_device_monitor",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Settings for processor
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessorSettings {
+    #[serde(default = "ProcessorSettings::default_merge_unassociated_events_ms")]
+    pub merge_unassociated_events_ms: i64,
+
+    // Consecutive events of another device needed to make it active. More than 1 keeps
+    // two people using own mouse on the same machine from switching each other.
+    #[serde(default = "ProcessorSettings::default_switch_after_events")]
+    pub switch_after_events: u32,
+
+    #[serde(default = "ProcessorSettings::default_devices")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceSettingItem>,
+
+    // Patterns of device ids, where '*' matches any. Matched devices are not managed at all.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_devices: Vec<String>,
+
+    // Device ids, in order, among which shortcut cycle_switch_devices moves switch. Only one
+    // of them has switch enabled at a time.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub switch_cycle: Vec<String>,
+
+    #[serde(default = "ShortcutSettings::default")]
+    pub shortcuts: ShortcutSettings,
+
+    #[serde(default = "HotCornerSettings::default")]
+    pub hot_corners: HotCornerSettings,
+
+    #[serde(default)]
+    pub gestures: GestureSettings,
+
+    #[serde(default)]
+    pub jump_targets: JumpTargetSettings,
+
+    #[serde(default)]
+    pub monitor_order: MonitorOrder,
+
+    // Monitors of a group are treated as one, for locking and jumping. Each group lists
+    // patterns of monitor names, where '*' matches any sequence.
+    #[serde(default)]
+    pub monitor_groups: Vec<Vec<String>>,
+
+    #[serde(default)]
+    pub focus_mode: FocusModeSettings,
+
+    #[serde(default)]
+    pub kvm: KvmSettings,
+
+    // External command for each event name(e.g. "locked"), as program and its arguments.
+    // Or a named pipe, written with a line of the event.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub on_event_command: BTreeMap<String, Vec<String>>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub device_rules: Vec<DeviceRule>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub app_rules: Vec<AppRule>,
+
+    #[serde(default = "ProcessorSettings::default_turbo_speed_percent")]
+    pub turbo_speed_percent: u32,
+
+    #[serde(default = "bool_const::<false>")]
+    pub log_active_device: bool,
+
+    // Jumping and centering land inside work area of monitor, not under taskbar
+    #[serde(default = "bool_const::<false>")]
+    pub use_work_area: bool,
+
+    // Wheel events go to the window under cursor, instead of the focused one
+    #[serde(default = "bool_const::<false>")]
+    pub scroll_window_under_cursor: bool,
+
+    // Updating monitors triggered by system events happens at most once within it
+    #[serde(default = "ProcessorSettings::default_update_monitors_interval_ms")]
+    pub update_monitors_interval_ms: u64,
+
+    #[serde(default = "ProcessorSettings::default_poll_max_messages")]
+    pub poll_max_messages: u32,
+
+    #[serde(default = "ProcessorSettings::default_poll_busy_timeout_ms")]
+    pub poll_busy_timeout_ms: u32,
+
+    #[serde(default = "ProcessorSettings::default_poll_idle_timeout_ms")]
+    pub poll_idle_timeout_ms: u32,
+
+    // Usages registered for raw input, e.g. without digitizers for pens handled by their
+    // own drivers. Generic Desktop Mouse must be kept.
+    #[serde(default = "RawinputUsage::defaults")]
+    pub rawinput_usages: Vec<RawinputUsage>,
+
+    // Devices missing from scans after startup are not shown disconnected until then, since
+    // they may enumerate late after login
+    #[serde(default = "ProcessorSettings::default_startup_grace_secs")]
+    pub startup_grace_secs: u32,
+
+    // After monitors added, removed or resized, locks are held for this long, since display
+    // driver restarts may show temporary monitors. 0 to apply at once.
+    #[serde(default = "ProcessorSettings::default_monitor_change_grace_secs")]
+    pub monitor_change_grace_secs: u32,
+
+    // Ask user before applying locks again after monitors changed much
+    #[serde(default)]
+    pub confirm_monitor_changes: bool,
+
+    // Device settings toggled by shortcuts are written to config file at once, instead of
+    // only lasting until restart
+    #[serde(default)]
+    pub persist_shortcut_changes: bool,
+
+    // A watchdog process starts MonMouse again if it crashes, e.g. for kiosks relying on
+    // touch screens locked. Takes effect on next start.
+    #[serde(default)]
+    pub restart_on_crash: bool,
+
+    // Interval of querying battery level of devices, 0 to disable
+    #[serde(default)]
+    pub battery_query_interval_secs: u32,
+
+    // Sample cursor position instead of installing the mouse hook, which some security
+    // software blocks. Also used if the hook fails to install.
+    #[serde(default)]
+    pub cursor_polling: bool,
+
+    // Time events by the high resolution performance counter, instead of system ticks of
+    // about 15ms, which make merging windows shorter than that meaningless
+    #[serde(default)]
+    pub high_resolution_timing: bool,
+
+    #[serde(default)]
+    pub thread_priority: ThreadPriority,
+
+    // Register thread correcting cursor with Multimedia Class Scheduler Service as a game task,
+    // which boosts it above normal priorities while it has events to process
+    #[serde(default = "bool_const::<false>")]
+    pub mmcss: bool,
+
+    // Emit ETW events for profiling, only works if built with feature "tracing"
+    #[serde(default = "bool_const::<false>")]
+    pub etw_tracing: bool,
+
+    // Show locked areas and relocations on screen, always shown in debug builds
+    #[serde(default = "bool_const::<false>")]
+    pub debug_overlay: bool,
+
+    // Settings for devices not configured yet, keyed by device type(e.g. "TouchScreen")
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults_by_type: BTreeMap<String, DeviceSetting>,
+
+    // Risky features turned on knowingly, others are kept off even if set
+    #[serde(default)]
+    pub confirmed_risks: Vec<RiskyFeature>,
+}
+
+impl Default for ProcessorSettings {
+    fn default() -> Self {
+        Self {
+            merge_unassociated_events_ms: Self::default_merge_unassociated_events_ms(),
+            switch_after_events: Self::default_switch_after_events(),
+            devices: Self::default_devices(),
+            ignored_devices: Vec::new(),
+            switch_cycle: Vec::new(),
+            shortcuts: ShortcutSettings::default(),
+            hot_corners: HotCornerSettings::default(),
+            gestures: GestureSettings::default(),
+            jump_targets: JumpTargetSettings::default(),
+            monitor_order: MonitorOrder::default(),
+            monitor_groups: Vec::new(),
+            focus_mode: FocusModeSettings::default(),
+            kvm: KvmSettings::default(),
+            on_event_command: BTreeMap::new(),
+            device_rules: Vec::new(),
+            app_rules: Vec::new(),
+            turbo_speed_percent: Self::default_turbo_speed_percent(),
+            log_active_device: false,
+            use_work_area: false,
+            scroll_window_under_cursor: false,
+            update_monitors_interval_ms: Self::default_update_monitors_interval_ms(),
+            poll_max_messages: Self::default_poll_max_messages(),
+            poll_busy_timeout_ms: Self::default_poll_busy_timeout_ms(),
+            poll_idle_timeout_ms: Self::default_poll_idle_timeout_ms(),
+            rawinput_usages: RawinputUsage::defaults(),
+            startup_grace_secs: Self::default_startup_grace_secs(),
+            monitor_change_grace_secs: Self::default_monitor_change_grace_secs(),
+            confirm_monitor_changes: false,
+            persist_shortcut_changes: false,
+            restart_on_crash: false,
+            battery_query_interval_secs: 0,
+            cursor_polling: false,
+            high_resolution_timing: false,
+            thread_priority: ThreadPriority::default(),
+            mmcss: false,
+            etw_tracing: false,
+            debug_overlay: false,
+            defaults_by_type: BTreeMap::new(),
+            confirmed_risks: Vec::new(),
+        }
+    }
+}
+
+impl ProcessorSettings {
+    fn default_merge_unassociated_events_ms() -> i64 {
+        5
+    }
+
+    fn default_switch_after_events() -> u32 {
+        1
+    }
+
+    fn default_devices() -> Vec<DeviceSettingItem> {
+        Vec::new()
+    }
+
+    fn default_turbo_speed_percent() -> u32 {
+        300
+    }
+
+    fn default_update_monitors_interval_ms() -> u64 {
+        1000
+    }
+
+    pub const fn default_poll_max_messages() -> u32 {
+        20
+    }
+
+    pub const fn default_poll_busy_timeout_ms() -> u32 {
+        20
+    }
+
+    fn default_poll_idle_timeout_ms() -> u32 {
+        200
+    }
+
+    fn default_startup_grace_secs() -> u32 {
+        15
+    }
+
+    fn default_monitor_change_grace_secs() -> u32 {
+        0
+    }
+
+    // Usages registered for raw input. The defaults if mice would not be registered, e.g. by
+    // settings applied without validating, since nothing could be managed then.
+    pub fn usages_to_register(&self) -> Vec<RawinputUsage> {
+        let has_mouse = self
+            .rawinput_usages
+            .iter()
+            .any(|u| u.matches(WindowsRawinput::MOUSE));
+        match has_mouse {
+            true => self.rawinput_usages.clone(),
+            false => RawinputUsage::defaults(),
+        }
+    }
+
+    pub fn is_device_ignored(&self, id: &str) -> bool {
+        self.ignored_devices
+            .iter()
+            .any(|pattern| wildcard_match(pattern, id))
+    }
+
+    // Actions of enabled rules for the program in foreground, in order of rules
+    pub fn app_actions(&self, app: &str) -> Vec<DeviceRuleAction> {
+        self.app_rules
+            .iter()
+            .filter(|r| r.enabled && wildcard_match(&r.app, app))
+            .flat_map(|r| r.actions.iter().cloned())
+            .collect()
+    }
+
+    pub fn default_for_type(&self, device_type: DeviceType) -> DeviceSetting {
+        self.defaults_by_type
+            .get(&device_type.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn mut_device<R>(
+        &mut self,
+        id: &str,
+        mut f: impl FnMut(&mut DeviceSetting) -> R,
+    ) -> Option<R> {
+        self.devices
+            .iter_mut()
+            .find(|d| d.id.as_str() == id)
+            .map(|d| f(&mut d.content))
+    }
+    pub fn ensure_mut_device<R>(
+        &mut self,
+        id: &str,
+        mut f: impl FnMut(&mut DeviceSetting) -> R,
+    ) -> R {
+        if let Some(r) = self.mut_device(id, &mut f) {
+            return r;
+        }
+        self.devices.push(DeviceSettingItem {
+            id: id.to_owned(),
+            content: DeviceSetting::default(),
+            notes: String::new(),
+            product_name: String::new(),
+        });
+        f(self.devices.last_mut().map(|d| &mut d.content).unwrap())
+    }
+
+    // Replaces entries of the same devices. New ones are added only if worth keeping.
+    pub fn merge_devices(&mut self, items: Vec<DeviceSettingItem>) {
+        for item in items {
+            match self.devices.iter_mut().find(|d| d.id == item.id) {
+                Some(d) => *d = item,
+                None if item.content.is_effective() || !item.notes.is_empty() => {
+                    self.devices.push(item)
+                }
+                None => (),
+            }
+        }
+    }
+
+    // Switch moves from the first enabled device in switch_cycle to the next one, keeping its
+    // mode. Others in switch_cycle are disabled. Returns ids of devices changed.
+    pub fn cycle_switch(&mut self) -> Vec<String> {
+        let cycle = self.switch_cycle.clone();
+        if cycle.is_empty() {
+            return Vec::new();
+        }
+        let mode_of = |p: &Self, id: &str| {
+            p.devices
+                .iter()
+                .find(|d| d.id == id)
+                .map(|d| d.content.switch)
+                .unwrap_or_default()
+        };
+        let (next, mode) = match cycle
+            .iter()
+            .position(|id| mode_of(self, id) != SwitchMode::Shared)
+        {
+            Some(i) => ((i + 1) % cycle.len(), mode_of(self, &cycle[i])),
+            None => (0, SwitchMode::PerDevicePosition),
+        };
+        let mut changed = Vec::new();
+        for (i, id) in cycle.iter().enumerate() {
+            let want = if i == next { mode } else { SwitchMode::Shared };
+            if mode_of(self, id) != want {
+                self.ensure_mut_device(id, |d| d.switch = want);
+                changed.push(id.clone());
+            }
+        }
+        changed
+    }
+}
+
+// How a shortcut is detected
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutBackend {
+    // Registered by RegisterHotKey, fails if taken by other programs
+    #[default]
+    Hotkey,
+    // Detected by keyboard hook, then not passed to other programs. Also supports shortcuts
+    // of only modifiers(e.g. "Ctrl+Shift"), triggered when released without other keys.
+    Hook,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutSettings {
+    #[serde(default = "empty_string")]
+    pub cur_mouse_lock: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_jump_next: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_turbo: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_jump_left: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_jump_right: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_jump_up: String,
+
+    #[serde(default = "empty_string")]
+    pub cur_mouse_jump_down: String,
+
+    // Move foreground window onto the monitor of cursor
+    #[serde(default = "empty_string")]
+    pub move_window_here: String,
+
+    // Dim monitors other than the one of current mouse, see FocusModeSettings
+    #[serde(default = "empty_string")]
+    pub cur_mouse_focus_mode: String,
+
+    // Move switch to the next device in processor.switch_cycle
+    #[serde(default = "empty_string")]
+    pub cycle_switch_devices: String,
+
+    // Backend of each shortcut above by its name, hotkey if not listed
+    #[serde(
+        default,
+        alias = "low_level",
+        deserialize_with = "deserialize_backends"
+    )]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub backends: BTreeMap<String, ShortcutBackend>,
+
+    // Letter and digit keys above name the key at that position on US layout. Configs written
+    // before it was introduced miss it, and keep naming keys by labels of current layout.
+    #[serde(default)]
+    pub positional_keys: bool,
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            cur_mouse_lock: empty_string(),
+            cur_mouse_jump_next: empty_string(),
+            cur_mouse_turbo: empty_string(),
+            cur_mouse_jump_left: empty_string(),
+            cur_mouse_jump_right: empty_string(),
+            cur_mouse_jump_up: empty_string(),
+            cur_mouse_jump_down: empty_string(),
+            move_window_here: empty_string(),
+            cur_mouse_focus_mode: empty_string(),
+            cycle_switch_devices: empty_string(),
+            backends: BTreeMap::new(),
+            positional_keys: true,
+        }
+    }
+}
+
+impl ShortcutSettings {
+    pub fn named(&self) -> [(&'static str, &String); 10] {
+        [
+            ("cur_mouse_lock", &self.cur_mouse_lock),
+            ("cur_mouse_jump_next", &self.cur_mouse_jump_next),
+            ("cur_mouse_turbo", &self.cur_mouse_turbo),
+            ("cur_mouse_jump_left", &self.cur_mouse_jump_left),
+            ("cur_mouse_jump_right", &self.cur_mouse_jump_right),
+            ("cur_mouse_jump_up", &self.cur_mouse_jump_up),
+            ("cur_mouse_jump_down", &self.cur_mouse_jump_down),
+            ("move_window_here", &self.move_window_here),
+            ("cur_mouse_focus_mode", &self.cur_mouse_focus_mode),
+            ("cycle_switch_devices", &self.cycle_switch_devices),
+        ]
+    }
+
+    pub fn named_mut(&mut self, name: &str) -> Option<&mut String> {
+        Some(match name {
+            "cur_mouse_lock" => &mut self.cur_mouse_lock,
+            "cur_mouse_jump_next" => &mut self.cur_mouse_jump_next,
+            "cur_mouse_turbo" => &mut self.cur_mouse_turbo,
+            "cur_mouse_jump_left" => &mut self.cur_mouse_jump_left,
+            "cur_mouse_jump_right" => &mut self.cur_mouse_jump_right,
+            "cur_mouse_jump_up" => &mut self.cur_mouse_jump_up,
+            "cur_mouse_jump_down" => &mut self.cur_mouse_jump_down,
+            "move_window_here" => &mut self.move_window_here,
+            "cur_mouse_focus_mode" => &mut self.cur_mouse_focus_mode,
+            "cycle_switch_devices" => &mut self.cycle_switch_devices,
+            _ => return None,
+        })
+    }
+
+    pub fn backend(&self, name: &str) -> ShortcutBackend {
+        self.backends.get(name).copied().unwrap_or_default()
+    }
+}
+
+// Actions triggered by gestures of absolute devices, e.g. pens and touch screens
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GestureSettings {
+    // Fast horizontal movement across most of monitor, in one stroke
+    #[serde(default)]
+    pub swipe_left: TrayAction,
+
+    #[serde(default)]
+    pub swipe_right: TrayAction,
+
+    // Two quick taps in the same corner of monitor
+    #[serde(default)]
+    pub corner_double_tap: TrayAction,
+}
+
+impl GestureSettings {
+    pub fn action(&self, gesture: Gesture) -> TrayAction {
+        match gesture {
+            Gesture::SwipeLeft => self.swipe_left,
+            Gesture::SwipeRight => self.swipe_right,
+            Gesture::CornerDoubleTap => self.corner_double_tap,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        [self.swipe_left, self.swipe_right, self.corner_double_tap]
+            .iter()
+            .any(|&a| a != TrayAction::None)
+    }
 }
 
-pub fn write_config(file: &PathBuf, settings: &Settings) -> Result<(), Error> {
-    match serde_yaml::to_string(settings) {
-        Ok(v) => Ok(v),
-        Err(e) => Err(Error::InvalidConfigFile(e.to_string())),
+// Focus mode dims all monitors except the one current mouse is locked to, or is on
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FocusModeSettings {
+    // Opacity of black covering other monitors, 100 for fully black
+    #[serde(default = "FocusModeSettings::default_dim_percent")]
+    pub dim_percent: u32,
+
+    // Released after the device is idle for such seconds, 0 for never
+    #[serde(default = "FocusModeSettings::default_release_after_idle_secs")]
+    pub release_after_idle_secs: u32,
+}
+
+impl Default for FocusModeSettings {
+    fn default() -> Self {
+        Self {
+            dim_percent: Self::default_dim_percent(),
+            release_after_idle_secs: Self::default_release_after_idle_secs(),
+        }
     }
-    .and_then(|content| match std::fs::write(file, content) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(Error::IO(e)),
-    })
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct Settings {
-    #[serde(default)]
-    pub ui: UISettings,
-    #[serde(default)]
-    pub processor: ProcessorSettings,
+impl FocusModeSettings {
+    fn default_dim_percent() -> u32 {
+        70
+    }
+
+    fn default_release_after_idle_secs() -> u32 {
+        60
+    }
 }
 
-// Settings for single device
-#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
-pub struct DeviceSetting {
-    #[serde(default = "bool_const::<false>")]
-    pub locked_in_monitor: bool,
+// Software KVMs(e.g. Mouse Without Borders, Synergy, Barrier) share one keyboard and mouse
+// between machines. While input goes to another machine, cursor here is hidden and left at
+// the edge, so relocating is paused.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KvmSettings {
     #[serde(default = "bool_const::<false>")]
-    pub switch: bool,
+    pub pause_when_remote: bool,
+
+    // Executable names of KVM processes, separated by ','. Control is remote if one of them is
+    // running and cursor is hidden.
+    #[serde(default = "KvmSettings::default_processes")]
+    pub processes: String,
+
+    // File written by KVM scripts, containing "remote" while control is remote. Checked
+    // instead of processes if set.
+    #[serde(default = "empty_string")]
+    pub state_file: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct DeviceSettingItem {
-    pub id: String,
-    #[serde(flatten)]
-    pub content: DeviceSetting,
+impl Default for KvmSettings {
+    fn default() -> Self {
+        Self {
+            pause_when_remote: false,
+            processes: Self::default_processes(),
+            state_file: empty_string(),
+        }
+    }
 }
 
-impl DeviceSetting {
-    pub fn is_effective(&self) -> bool {
-        self.locked_in_monitor || self.switch
+impl KvmSettings {
+    fn default_processes() -> String {
+        "MouseWithoutBorders.exe, PowerToys.MouseWithoutBorders.exe, barriers.exe, \
+         synergys.exe, synergy-core.exe, input-leaps.exe"
+            .to_owned()
+    }
+
+    pub fn process_list(&self) -> Vec<&str> {
+        self.processes
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
     }
 }
 
-// Settings for processor
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ProcessorSettings {
-    #[serde(default = "ProcessorSettings::default_merge_unassociated_events_ms")]
-    pub merge_unassociated_events_ms: i64,
+// Where cursor lands for each jumping shortcut. Tray and hot corner actions of JumpNext
+// follow jump_next.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JumpTargetSettings {
+    #[serde(default)]
+    pub jump_next: JumpTarget,
 
-    #[serde(default = "ProcessorSettings::default_devices")]
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub devices: Vec<DeviceSettingItem>,
+    #[serde(default)]
+    pub jump_left: JumpTarget,
 
-    #[serde(default = "ShortcutSettings::default")]
-    pub shortcuts: ShortcutSettings,
+    #[serde(default)]
+    pub jump_right: JumpTarget,
+
+    #[serde(default)]
+    pub jump_up: JumpTarget,
+
+    #[serde(default)]
+    pub jump_down: JumpTarget,
 }
 
-impl Default for ProcessorSettings {
+impl JumpTargetSettings {
+    pub fn direction(&self, dir: Direction) -> JumpTarget {
+        match dir {
+            Direction::Left => self.jump_left,
+            Direction::Right => self.jump_right,
+            Direction::Up => self.jump_up,
+            Direction::Down => self.jump_down,
+        }
+    }
+}
+
+// Audio cues confirming actions, mostly for those triggered by shortcuts
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SoundSettings {
+    #[serde(default = "bool_const::<false>")]
+    pub on_lock: bool,
+
+    #[serde(default = "bool_const::<false>")]
+    pub on_jump: bool,
+
+    #[serde(default = "bool_const::<false>")]
+    pub on_switch: bool,
+
+    // Directory of lock.wav, jump.wav and switch.wav. System sounds are played for
+    // missing ones, or if empty.
+    #[serde(default = "empty_string")]
+    pub wav_dir: String,
+}
+
+// Shortcuts and global options pushed between machines sharing one keyboard and mouse,
+// e.g. by a KVM. Only pushes with the same token are accepted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncSettings {
+    #[serde(default = "bool_const::<false>")]
+    pub listen: bool,
+
+    // Address of the interface listened on. Only this machine can push by default, set to
+    // one of LAN, or 0.0.0.0 for all, to accept pushes from other machines.
+    #[serde(default = "SyncSettings::default_listen_addr")]
+    pub listen_addr: String,
+
+    #[serde(default = "SyncSettings::default_port")]
+    pub port: u16,
+
+    #[serde(default = "empty_string")]
+    pub token: String,
+
+    // Hosts pushed to, separated by ','. Each may be "host" or "host:port".
+    #[serde(default = "empty_string")]
+    pub peers: String,
+}
+
+impl Default for SyncSettings {
     fn default() -> Self {
         Self {
-            merge_unassociated_events_ms: Self::default_merge_unassociated_events_ms(),
-            devices: Self::default_devices(),
-            shortcuts: ShortcutSettings::default(),
+            listen: false,
+            listen_addr: Self::default_listen_addr(),
+            port: Self::default_port(),
+            token: empty_string(),
+            peers: empty_string(),
         }
     }
 }
 
-impl ProcessorSettings {
-    fn default_merge_unassociated_events_ms() -> i64 {
-        5
+impl SyncSettings {
+    fn default_listen_addr() -> String {
+        "127.0.0.1".to_owned()
     }
 
-    fn default_devices() -> Vec<DeviceSettingItem> {
-        Vec::new()
+    fn default_port() -> u16 {
+        47251
     }
 
-    pub fn mut_device<R>(
-        &mut self,
-        id: &str,
-        mut f: impl FnMut(&mut DeviceSetting) -> R,
-    ) -> Option<R> {
-        self.devices
-            .iter_mut()
-            .find(|d| d.id.as_str() == id)
-            .map(|d| f(&mut d.content))
+    pub fn peer_list(&self) -> Vec<&str> {
+        self.peers
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
     }
-    pub fn ensure_mut_device<R>(
-        &mut self,
-        id: &str,
-        mut f: impl FnMut(&mut DeviceSetting) -> R,
-    ) -> R {
-        if let Some(r) = self.mut_device(id, &mut f) {
-            return r;
+}
+
+// Actions triggered by cursor staying in a corner of monitor
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HotCornerSettings {
+    #[serde(default = "HotCornerSettings::default_dwell_ms")]
+    pub dwell_ms: u64,
+
+    // Only cursor moved by this device triggers actions, empty for any device
+    #[serde(default = "empty_string")]
+    pub only_device: String,
+
+    #[serde(default)]
+    pub top_left: TrayAction,
+
+    #[serde(default)]
+    pub top_right: TrayAction,
+
+    #[serde(default)]
+    pub bottom_left: TrayAction,
+
+    #[serde(default)]
+    pub bottom_right: TrayAction,
+}
+
+impl Default for HotCornerSettings {
+    fn default() -> Self {
+        Self {
+            dwell_ms: Self::default_dwell_ms(),
+            only_device: empty_string(),
+            top_left: TrayAction::None,
+            top_right: TrayAction::None,
+            bottom_left: TrayAction::None,
+            bottom_right: TrayAction::None,
         }
-        self.devices.push(DeviceSettingItem {
-            id: id.to_owned(),
-            content: DeviceSetting::default(),
-        });
-        f(self.devices.last_mut().map(|d| &mut d.content).unwrap())
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct ShortcutSettings {
-    #[serde(default = "empty_string")]
-    pub cur_mouse_lock: String,
+impl HotCornerSettings {
+    fn default_dwell_ms() -> u64 {
+        500
+    }
 
-    #[serde(default = "empty_string")]
-    pub cur_mouse_jump_next: String,
+    pub fn action(&self, corner: Corner) -> TrayAction {
+        match corner {
+            Corner::TopLeft => self.top_left,
+            Corner::TopRight => self.top_right,
+            Corner::BottomLeft => self.bottom_left,
+            Corner::BottomRight => self.bottom_right,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        [
+            self.top_left,
+            self.top_right,
+            self.bottom_left,
+            self.bottom_right,
+        ]
+        .iter()
+        .any(|&a| a != TrayAction::None)
+    }
 }
 
 // Settings for UI
@@ -140,6 +2133,41 @@ pub struct UISettings {
 
     #[serde(default = "UISettings::default_inspect_device_interval_ms")]
     pub inspect_device_interval_ms: u64,
+
+    // Alert when battery of device falls below, 0 to disable
+    #[serde(default = "UISettings::default_low_battery_alert_percent")]
+    pub low_battery_alert_percent: u32,
+
+    #[serde(default = "UISettings::default_tray_click")]
+    pub tray_click: TrayAction,
+
+    #[serde(default = "UISettings::default_tray_double_click")]
+    pub tray_double_click: TrayAction,
+
+    #[serde(default = "UISettings::default_tray_middle_click")]
+    pub tray_middle_click: TrayAction,
+
+    #[serde(default)]
+    pub tray_icon: TrayIconStyle,
+
+    // Draw a short trail on screen when jumping between monitors, drawn by mouse control
+    #[serde(default)]
+    pub animate_jumps: bool,
+
+    // Opt-in, see update_check
+    #[serde(default)]
+    pub check_updates_weekly: bool,
+
+    // Answer windows opened in view-only mode while this instance runs, see view_ipc. Opt-in,
+    // no port is listened on otherwise.
+    #[serde(default)]
+    pub allow_view_only: bool,
+
+    #[serde(default = "SoundSettings::default")]
+    pub sounds: SoundSettings,
+
+    #[serde(default = "SyncSettings::default")]
+    pub sync: SyncSettings,
 }
 
 impl Default for UISettings {
@@ -147,6 +2175,16 @@ impl Default for UISettings {
         Self {
             theme: Self::default_theme(),
             inspect_device_interval_ms: Self::default_inspect_device_interval_ms(),
+            low_battery_alert_percent: Self::default_low_battery_alert_percent(),
+            tray_click: Self::default_tray_click(),
+            tray_double_click: Self::default_tray_double_click(),
+            tray_middle_click: Self::default_tray_middle_click(),
+            tray_icon: TrayIconStyle::default(),
+            animate_jumps: false,
+            check_updates_weekly: false,
+            allow_view_only: false,
+            sounds: SoundSettings::default(),
+            sync: SyncSettings::default(),
         }
     }
 }
@@ -158,6 +2196,80 @@ impl UISettings {
     fn default_inspect_device_interval_ms() -> u64 {
         100
     }
+    fn default_low_battery_alert_percent() -> u32 {
+        15
+    }
+    fn default_tray_click() -> TrayAction {
+        TrayAction::None
+    }
+    fn default_tray_double_click() -> TrayAction {
+        TrayAction::OpenUI
+    }
+    fn default_tray_middle_click() -> TrayAction {
+        TrayAction::None
+    }
+}
+
+// Actions could be bound to clicking tray icon, or hot corners
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrayAction {
+    #[default]
+    None,
+    OpenUI,
+    TogglePause,
+    JumpNext,
+}
+
+impl TrayAction {
+    pub const VARIANTS: [TrayAction; 4] =
+        [Self::None, Self::OpenUI, Self::TogglePause, Self::JumpNext];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for TrayAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::None => "None",
+            Self::OpenUI => "OpenUI",
+            Self::TogglePause => "TogglePause",
+            Self::JumpNext => "JumpNext",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Monochrome styles are drawn in contrast with the taskbar, which is light or dark
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrayIconStyle {
+    #[default]
+    Colored,
+    // Light or Dark, following theme of system
+    Auto,
+    Light,
+    Dark,
+}
+
+impl TrayIconStyle {
+    pub const VARIANTS: [TrayIconStyle; 4] = [Self::Colored, Self::Auto, Self::Light, Self::Dark];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS.into_iter().find(|t| t.to_string() == name)
+    }
+}
+
+impl Display for TrayIconStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Colored => "Colored",
+            Self::Auto => "Auto",
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 // Some helper functions for serde_derive default
@@ -177,3 +2289,591 @@ const fn bool_const<const V: bool>() -> bool {
 fn empty_string() -> String {
     "".to_owned()
 }
+fn is_zero(v: &u32) -> bool {
+    *v == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::{config_file_in, CONFIG_FILE_NAME, CONFIG_FILE_NAME_TOML};
+    use crate::utils::test_temp_dir;
+
+    fn write_temp(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_config_include() {
+        let dir = test_temp_dir("include");
+        write_temp(
+            &dir,
+            "base.yml",
+            "processor:\n  merge_unassociated_events_ms: 10\n  turbo_speed_percent: 200\n  devices:\n  - id: A\n    switch: true\n  - id: B\n    switch: true\n",
+        );
+        write_temp(
+            &dir,
+            "machine.yml",
+            "processor:\n  devices:\n  - id: B\n    locked_in_monitor: true\n  - id: C\n    switch: true\n",
+        );
+        let top = write_temp(
+            &dir,
+            "monmouse.yml",
+            "include: [base.yml, machine.yml]\nprocessor:\n  turbo_speed_percent: 400\n",
+        );
+
+        let s = read_config(&top).unwrap();
+        assert_eq!(s.include, vec!["base.yml", "machine.yml"]);
+        assert_eq!(s.processor.merge_unassociated_events_ms, 10);
+        assert_eq!(s.processor.turbo_speed_percent, 400);
+        let ids: Vec<&str> = s.processor.devices.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["A", "B", "C"]);
+        let b = &s.processor.devices[1].content;
+        assert!(b.switch == SwitchMode::PerDevicePosition && b.locked_in_monitor);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_config_include() {
+        let dir = test_temp_dir("write_include");
+        write_temp(
+            &dir,
+            "base.yml",
+            "processor:\n  merge_unassociated_events_ms: 10\n  devices:\n  - id: A\n    switch: true\n",
+        );
+        let top = write_temp(&dir, "monmouse.yml", "include: base.yml\n");
+
+        let mut s = read_config(&top).unwrap();
+        s.processor.turbo_speed_percent = 400;
+        s.processor
+            .ensure_mut_device("B", |d| d.locked_in_monitor = true);
+        write_config(&top, &s).unwrap();
+
+        let content = std::fs::read_to_string(&top).unwrap();
+        assert!(content.contains("include"));
+        assert!(!content.contains("merge_unassociated_events_ms"));
+        assert!(!content.contains("id: A"));
+        let r = read_config(&top).unwrap();
+        assert_eq!(
+            serde_yaml::to_value(&r).unwrap(),
+            serde_yaml::to_value(&s).unwrap()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bootstrap_config() {
+        let dir = test_temp_dir("bootstrap");
+        let deployed = write_temp(
+            &dir,
+            "default.yml",
+            "processor:\n  turbo_speed_percent: 300\n  devices:\n  - id: Touch\n    locked_in_monitor: true\n",
+        );
+        let file = dir.join("monmouse.yml");
+
+        // Copied on first run
+        assert!(bootstrap_config(&file, &deployed).unwrap());
+        let s = read_config(&file).unwrap();
+        assert_eq!(s.processor.turbo_speed_percent, 300);
+        assert!(s.processor.devices.iter().any(|d| d.id == "Touch"));
+        assert!(!bootstrap_config(&file, &deployed).unwrap());
+
+        // Values of user are kept, deployed devices added
+        write_temp(
+            &dir,
+            "monmouse.yml",
+            "processor:\n  turbo_speed_percent: 500\n  devices:\n  - id: Pen\n    switch: true\n",
+        );
+        assert!(bootstrap_config(&file, &deployed).unwrap());
+        let s = read_config(&file).unwrap();
+        assert_eq!(s.processor.turbo_speed_percent, 500);
+        let ids: Vec<&str> = s.processor.devices.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["Touch", "Pen"]);
+
+        // Invalid deployed config is not written
+        let broken = write_temp(&dir, "broken.yml", "processor:\n  turbo_speed_percent: 5\n");
+        let other = dir.join("other.yml");
+        assert!(bootstrap_config(&other, &broken).is_err());
+        assert!(!other.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_is_device_ignored() {
+        let s = ProcessorSettings {
+            ignored_devices: vec!["\\\\?\\HID#VID_0E0F*".to_owned(), "*rdp_mou*".to_owned()],
+            ..Default::default()
+        };
+        assert!(s.is_device_ignored("\\\\?\\HID#VID_0E0F&PID_0003&MI_01#7&1a2b"));
+        assert!(s.is_device_ignored("\\\\?\\Root#RDP_MOU#0000#{378de44c}"));
+        assert!(!s.is_device_ignored("\\\\?\\HID#VID_046D&PID_C52B#7&3c4d"));
+        assert!(!ProcessorSettings::default().is_device_ignored("any"));
+    }
+
+    #[test]
+    fn test_switch_mode_compat() {
+        let parse = |s: &str| serde_yaml::from_str::<DeviceSetting>(s).map(|d| d.switch);
+        assert_eq!(
+            parse("switch: true").unwrap(),
+            SwitchMode::PerDevicePosition
+        );
+        assert_eq!(parse("switch: false").unwrap(), SwitchMode::Shared);
+        assert_eq!(parse("{}").unwrap(), SwitchMode::Shared);
+        assert_eq!(
+            parse("switch: per_device_monitor").unwrap(),
+            SwitchMode::PerDeviceMonitor
+        );
+        assert!(parse("switch: nowhere").is_err());
+
+        let s = serde_yaml::to_string(&DeviceSetting {
+            switch: SwitchMode::PerDeviceMonitor,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(s.contains("switch: per_device_monitor"));
+    }
+
+    #[test]
+    fn test_positional_keys_compat() {
+        let parse = |s: &str| serde_yaml::from_str::<ShortcutSettings>(s).unwrap();
+        // Written before keys were positional
+        assert!(!parse("cur_mouse_lock: Ctrl+Alt+A").positional_keys);
+        assert!(parse("positional_keys: true").positional_keys);
+        assert!(ShortcutSettings::default().positional_keys);
+
+        let s = serde_yaml::to_string(&Settings::default()).unwrap();
+        let s: Settings = serde_yaml::from_str(&s).unwrap();
+        assert!(s.processor.shortcuts.positional_keys);
+    }
+
+    #[test]
+    fn test_shortcut_backends_compat() {
+        let parse = |s: &str| serde_yaml::from_str::<ShortcutSettings>(s).unwrap();
+        let s = parse("low_level: [cur_mouse_lock]");
+        assert_eq!(s.backend("cur_mouse_lock"), ShortcutBackend::Hook);
+        assert_eq!(s.backend("cur_mouse_turbo"), ShortcutBackend::Hotkey);
+        let s = parse("backends: {cur_mouse_turbo: hook}");
+        assert_eq!(s.backend("cur_mouse_turbo"), ShortcutBackend::Hook);
+
+        let yaml = serde_yaml::to_string(&parse("low_level: [cur_mouse_lock]")).unwrap();
+        assert!(yaml.contains("backends:") && !yaml.contains("low_level"));
+    }
+
+    #[test]
+    fn test_pos_transform() {
+        let t: PosTransform = serde_yaml::from_str("rotate: 90\nmirror_y: true").unwrap();
+        assert_eq!(t.rotate, Rotation::Cw90);
+        assert!(serde_yaml::from_str::<PosTransform>("rotate: 45").is_err());
+        assert!(serde_yaml::to_string(&t).unwrap().contains("rotate: 90"));
+
+        let area = MonitorArea {
+            lefttop: MousePos::from(1000, 0),
+            rigtbtm: MousePos::from(2000, 500),
+            id: 0,
+        };
+        let p = MousePos::from(1000, 0);
+        let rotate = |r| PosTransform {
+            rotate: r,
+            ..Default::default()
+        };
+        assert_eq!(
+            rotate(Rotation::Cw90).apply(&area, &p),
+            MousePos::from(2000, 0)
+        );
+        assert_eq!(
+            rotate(Rotation::Cw180).apply(&area, &p),
+            MousePos::from(2000, 500)
+        );
+        assert_eq!(
+            rotate(Rotation::Cw270).apply(&area, &p),
+            MousePos::from(1000, 500)
+        );
+        assert_eq!(t.apply(&area, &p), MousePos::from(2000, 500));
+        let mirror_x = PosTransform {
+            mirror_x: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            mirror_x.apply(&area, &MousePos::from(1250, 100)),
+            MousePos::from(1750, 100)
+        );
+        assert_eq!(PosTransform::default().apply(&area, &p), p);
+    }
+
+    #[test]
+    fn test_calibration() {
+        let c: Calibration =
+            serde_yaml::from_str("extent: [100, 50, 900, 450]\nmargins: [10, 0, 10, 0]").unwrap();
+        assert_eq!(c.monitor, 0);
+        let target = MonitorArea {
+            lefttop: MousePos::from(1000, 0),
+            rigtbtm: MousePos::from(2000, 500),
+            id: 0,
+        };
+        // Corners of the extent land on corners within margins
+        assert_eq!(
+            c.apply(&target, &MousePos::from(100, 50)),
+            MousePos::from(1010, 0)
+        );
+        assert_eq!(
+            c.apply(&target, &MousePos::from(900, 450)),
+            MousePos::from(1990, 500)
+        );
+        assert_eq!(
+            c.apply(&target, &MousePos::from(500, 250)),
+            MousePos::from(1500, 250)
+        );
+        // Beyond the extent
+        assert_eq!(
+            c.apply(&target, &MousePos::from(0, 1000)),
+            MousePos::from(1010, 500)
+        );
+
+        let empty = Calibration::from_extent(&MonitorArea {
+            lefttop: MousePos::from(5, 5),
+            rigtbtm: MousePos::from(5, 9),
+            id: 0,
+        });
+        let p = MousePos::from(7, 7);
+        assert_eq!(empty.apply(&target, &p), p);
+
+        let mut s = Settings::default();
+        s.processor.devices.push(DeviceSettingItem {
+            id: "pen".to_owned(),
+            content: DeviceSetting {
+                calibration: Some(empty),
+                ..Default::default()
+            },
+            notes: String::new(),
+            product_name: String::new(),
+        });
+        assert_eq!(s.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_settings_validate() {
+        let mut s = Settings::default();
+        assert!(s.validate().is_empty());
+
+        s.processor.turbo_speed_percent = 50;
+        s.processor.shortcuts.cur_mouse_lock = "Ctrl+Alt+F9".to_owned();
+        s.processor.shortcuts.cur_mouse_jump_next = "Ctrl+Alt+F9".to_owned();
+        s.processor.shortcuts.cur_mouse_turbo = "Ctrl+Nothing".to_owned();
+        // Same keys in another order
+        s.processor.shortcuts.cur_mouse_jump_left = "Shift+Ctrl+Alt+Escape".to_owned();
+        s.processor.shortcuts.cur_mouse_jump_right = "Alt+Ctrl+F9".to_owned();
+        s.processor.shortcuts.move_window_here = "Alt+Shift".to_owned();
+        s.processor.shortcuts.cur_mouse_focus_mode = "Ctrl+Shift".to_owned();
+        s.processor.shortcuts.backends = BTreeMap::from([
+            ("cur_mouse_focus_mode".to_owned(), ShortcutBackend::Hook),
+            ("no_such_shortcut".to_owned(), ShortcutBackend::Hook),
+        ]);
+        s.processor.devices = ["A", "B", "A"]
+            .into_iter()
+            .map(|id| DeviceSettingItem {
+                id: id.to_owned(),
+                content: DeviceSetting::default(),
+                notes: String::new(),
+                product_name: String::new(),
+            })
+            .collect();
+        s.processor.devices[1].content.merge_unassociated_events_ms = Some(5000);
+        s.processor.device_rules = vec![DeviceRule {
+            device: "*VID_056A*".to_owned(),
+            on_connect: vec![DeviceRuleAction::default()],
+            ..Default::default()
+        }];
+        let fields: Vec<String> = s.validate().into_iter().map(|v| v.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "processor.turbo_speed_percent",
+                "processor.shortcuts.cur_mouse_jump_next",
+                "processor.shortcuts.cur_mouse_turbo",
+                "processor.shortcuts.cur_mouse_jump_left",
+                "processor.shortcuts.cur_mouse_jump_right",
+                "processor.shortcuts.move_window_here",
+                "processor.shortcuts.backends",
+                "processor.device_rules[0]",
+                "processor.devices[1].merge_unassociated_events_ms",
+                "processor.devices[2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rawinput_usages() {
+        let s: ProcessorSettings = serde_yaml::from_str("poll_max_messages: 20").unwrap();
+        assert_eq!(s.rawinput_usages, RawinputUsage::defaults());
+
+        let usages: Vec<RawinputUsage> =
+            serde_yaml::from_str("[{page: 0x0D}, {page: 0x01, usage: 0x02}]").unwrap();
+        assert!(usages[0].matches((0x0D, 0x05)));
+        assert!(!usages[1].matches((0x01, 0x01)));
+        assert_eq!(usages[0].to_string(), "0x0D/*");
+        assert_eq!(usages[1].to_string(), "0x01/0x02");
+
+        let mut s = Settings::default();
+        s.processor.rawinput_usages = usages;
+        assert!(s.validate().is_empty());
+
+        s.processor.rawinput_usages = vec![
+            RawinputUsage { page: 0, usage: 1 },
+            RawinputUsage { page: 1, usage: 0 },
+            RawinputUsage { page: 13, usage: 4 },
+            RawinputUsage { page: 13, usage: 4 },
+        ];
+        let fields: Vec<String> = s.validate().into_iter().map(|v| v.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "processor.rawinput_usages[0]",
+                "processor.rawinput_usages[1]",
+                "processor.rawinput_usages[3]",
+            ]
+        );
+
+        s.processor.rawinput_usages.clear();
+        assert_eq!(s.validate().len(), 1);
+        assert_eq!(s.processor.usages_to_register(), RawinputUsage::defaults());
+
+        // Only pens, no mice
+        s.processor.rawinput_usages = vec![RawinputUsage { page: 13, usage: 2 }];
+        let fields: Vec<String> = s.validate().into_iter().map(|v| v.field).collect();
+        assert_eq!(fields, vec!["processor.rawinput_usages"]);
+        assert_eq!(s.processor.usages_to_register(), RawinputUsage::defaults());
+    }
+
+    #[test]
+    fn test_app_actions() {
+        let p: ProcessorSettings = serde_yaml::from_str(
+            r#"
+app_rules:
+  - app: "whiteboard*.exe"
+    actions:
+      - target: "*VID_056A*"
+        locked_in_monitor: false
+  - app: "*.exe"
+    enabled: false
+    actions:
+      - switch: per_device_position
+"#,
+        )
+        .unwrap();
+        assert!(p.app_rules[0].enabled);
+        let actions = p.app_actions("Whiteboard2.EXE");
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].locked_in_monitor, Some(false));
+        assert!(p.app_actions("notepad.exe").is_empty());
+
+        let mut s = DeviceSetting {
+            locked_in_monitor: true,
+            switch: SwitchMode::PerDeviceMonitor,
+            ..Default::default()
+        };
+        let revert = actions[0].revert_of(&s);
+        actions[0].apply(&mut s);
+        assert!(!s.locked_in_monitor);
+        // Changed meanwhile, not reverted
+        s.switch = SwitchMode::Shared;
+        revert.apply(&mut s);
+        assert!(s.locked_in_monitor);
+        assert_eq!(s.switch, SwitchMode::Shared);
+    }
+
+    #[test]
+    fn test_merge_devices() {
+        let item = |id: &str, locked_in_monitor: bool| DeviceSettingItem {
+            id: id.to_owned(),
+            content: DeviceSetting {
+                locked_in_monitor,
+                ..Default::default()
+            },
+            notes: String::new(),
+            product_name: String::new(),
+        };
+        let mut p = ProcessorSettings {
+            devices: vec![item("pen", true), item("mouse", true)],
+            ..Default::default()
+        };
+        p.merge_devices(vec![
+            item("mouse", false),
+            item("touchpad", false),
+            item("trackball", true),
+        ]);
+        assert_eq!(
+            p.devices,
+            vec![
+                item("pen", true),
+                item("mouse", false),
+                item("trackball", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_device_setting_round_trip() {
+        assert!(!DeviceSetting::default().is_effective());
+        // Not locked or switched, but still configured
+        let debounced = DeviceSettingItem {
+            id: "mouse".to_owned(),
+            content: DeviceSetting {
+                debounce_click_ms: 30,
+                ..Default::default()
+            },
+            notes: String::new(),
+            product_name: String::new(),
+        };
+        assert!(debounced.content.is_effective());
+
+        let mut p = ProcessorSettings::default();
+        p.merge_devices(vec![debounced.clone()]);
+        let s = serde_yaml::to_string(&p).unwrap();
+        let read: ProcessorSettings = serde_yaml::from_str(&s).unwrap();
+        assert_eq!(read.devices, vec![debounced]);
+    }
+
+    #[test]
+    fn test_cycle_switch() {
+        let mut p = ProcessorSettings {
+            devices: Vec::new(),
+            switch_cycle: vec!["mouse".to_owned(), "pen".to_owned(), "touch".to_owned()],
+            ..Default::default()
+        };
+        use SwitchMode::*;
+        let switch_of = |p: &ProcessorSettings| -> Vec<SwitchMode> {
+            p.switch_cycle
+                .iter()
+                .map(|id| {
+                    p.devices
+                        .iter()
+                        .find(|d| &d.id == id)
+                        .map_or(Shared, |d| d.content.switch)
+                })
+                .collect()
+        };
+
+        // None enabled, the first one is
+        assert_eq!(p.cycle_switch(), vec!["mouse".to_owned()]);
+        assert_eq!(switch_of(&p), vec![PerDevicePosition, Shared, Shared]);
+
+        p.mut_device("mouse", |d| d.switch = PerDeviceMonitor);
+        assert_eq!(p.cycle_switch(), vec!["mouse".to_owned(), "pen".to_owned()]);
+        assert_eq!(switch_of(&p), vec![Shared, PerDeviceMonitor, Shared]);
+        p.cycle_switch();
+        assert_eq!(
+            p.cycle_switch(),
+            vec!["mouse".to_owned(), "touch".to_owned()]
+        );
+        assert_eq!(switch_of(&p), vec![PerDeviceMonitor, Shared, Shared]);
+
+        // Only one is left enabled
+        p.mut_device("touch", |d| d.switch = PerDevicePosition);
+        p.cycle_switch();
+        assert_eq!(switch_of(&p), vec![Shared, PerDeviceMonitor, Shared]);
+    }
+
+    #[test]
+    fn test_reconcile_devices() {
+        let item = |id: &str, product_name: &str| DeviceSettingItem {
+            id: id.to_owned(),
+            content: DeviceSetting::default(),
+            notes: String::new(),
+            product_name: product_name.to_owned(),
+        };
+        let dev = |id: &str, product_name: &str| GenericDevice {
+            product_name: product_name.to_owned(),
+            ..GenericDevice::id_only(id.to_owned())
+        };
+        let configured = [
+            item(
+                "\\\\?\\HID#VID_046D&PID_C52B&MI_01&Col02#7&3c4d",
+                "Logi Receiver",
+            ),
+            item("\\\\?\\HID#VID_0E0F&PID_0003#7&1a2b", ""),
+            item("\\\\?\\HID#VID_1234&PID_0001#7&9f9f", "Pen"),
+            item("\\\\?\\HID#VID_5678&PID_0002#7&0000", ""),
+        ];
+        let scanned = [
+            dev("\\\\?\\HID#VID_0E0F&PID_0003#7&1a2b", "VMware"),
+            dev("\\\\?\\HID#VID_046D&PID_C52B&MI_00#8&aaaa", "Logi Receiver"),
+            dev(
+                "\\\\?\\HID#VID_046D&PID_C52B&MI_01&Col02#8&bbbb",
+                "Logi Receiver",
+            ),
+            dev("\\\\?\\HID#VID_9999&PID_0001#8&cccc", "Pen"),
+        ];
+        assert_eq!(
+            reconcile_devices(&configured, &scanned),
+            vec![
+                DeviceReconcile {
+                    id: configured[0].id.clone(),
+                    candidates: vec![scanned[2].id.clone(), scanned[1].id.clone()],
+                },
+                DeviceReconcile {
+                    id: configured[2].id.clone(),
+                    candidates: vec![scanned[3].id.clone()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_toml() {
+        let dir = test_temp_dir("toml");
+        let mut s = Settings::default();
+        s.processor.turbo_speed_percent = 400;
+        s.processor.devices.push(DeviceSettingItem {
+            id: "A".to_owned(),
+            content: DeviceSetting {
+                switch: SwitchMode::PerDeviceMonitor,
+                ..Default::default()
+            },
+            notes: String::new(),
+            product_name: String::new(),
+        });
+        let path = dir.join(CONFIG_FILE_NAME_TOML);
+        write_config(&path, &s).unwrap();
+        assert_eq!(config_file_in(&dir), path);
+
+        let r = read_config(&path).unwrap();
+        assert_eq!(r.processor.turbo_speed_percent, 400);
+        assert_eq!(
+            r.processor.devices[0].content.switch,
+            SwitchMode::PerDeviceMonitor
+        );
+
+        // Formats can be mixed by including
+        let top = write_temp(
+            &dir,
+            CONFIG_FILE_NAME,
+            "include: monmouse.toml\nprocessor:\n  log_active_device: true\n",
+        );
+        assert_eq!(config_file_in(&dir), top);
+        let r = read_config(&top).unwrap();
+        assert_eq!(r.processor.turbo_speed_percent, 400);
+        assert!(r.processor.log_active_device);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_config_include_cycle() {
+        let dir = test_temp_dir("cycle");
+        write_temp(&dir, "a.yml", "include: b.yml\n");
+        write_temp(&dir, "b.yml", "include: a.yml\n");
+        let top = write_temp(&dir, "monmouse.yml", "include: a.yml\n");
+
+        assert!(matches!(
+            read_config(&top),
+            Err(Error::ConfigIncludeCycle(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,525 @@
+// Documentation of settings, shown beside config items in GUI and by `--explain` of CLI.
+// Keys are paths in config file, e.g. "processor.hot_corners.dwell_ms".
+
+use std::{fmt::Display, ops::RangeInclusive, sync::OnceLock};
+
+use crate::setting::limits;
+
+pub struct SettingDoc {
+    pub key: &'static str,
+    pub summary: &'static str,
+    // Valid values, None if any value of the type is accepted
+    pub range: Option<String>,
+    pub example: &'static str,
+}
+
+impl Display for SettingDoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.key)?;
+        writeln!(f, "  {}", self.summary)?;
+        if let Some(range) = &self.range {
+            writeln!(f, "  Range: {}", range)?;
+        }
+        write!(f, "  Example: {}", self.example)
+    }
+}
+
+fn range<T: Display>(r: RangeInclusive<T>) -> Option<String> {
+    Some(format!("{}-{}", r.start(), r.end()))
+}
+
+fn choices(names: &[&str]) -> Option<String> {
+    Some(names.join(", "))
+}
+
+const TRAY_ACTIONS: [&str; 4] = ["None", "OpenUI", "TogglePause", "JumpNext"];
+const TRAY_ICON_STYLES: [&str; 4] = ["Colored", "Auto", "Light", "Dark"];
+const JUMP_TARGETS: [&str; 4] = ["remembered", "center", "relative", "nearest_edge"];
+const MONITOR_ORDERS: [&str; 3] = ["enumeration", "rows", "columns"];
+const THREAD_PRIORITIES: [&str; 3] = ["normal", "above_normal", "highest"];
+const RISKY_FEATURES: [&str; 3] = ["hook_shortcuts", "click_debounce", "scroll_redirect"];
+const SHORTCUT_RANGE: &str =
+    "modifiers and a key joined by '+', empty to disable. Letter and digit keys are named by their position on US layout, see processor.shortcuts.positional_keys";
+
+// Built once, as it's looked up on every frame config panel is shown
+pub fn settings_doc() -> &'static [SettingDoc] {
+    static DOCS: OnceLock<Vec<SettingDoc>> = OnceLock::new();
+    DOCS.get_or_init(build_docs)
+}
+
+fn build_docs() -> Vec<SettingDoc> {
+    let doc = |key, summary, range, example| SettingDoc {
+        key,
+        summary,
+        range,
+        example,
+    };
+    let shortcut = |key, summary, example| SettingDoc {
+        key,
+        summary,
+        range: Some(SHORTCUT_RANGE.to_owned()),
+        example,
+    };
+    let tray_action = |key, summary, example| SettingDoc {
+        key,
+        summary,
+        range: choices(&TRAY_ACTIONS),
+        example,
+    };
+    let jump_target = |key, summary, example| SettingDoc {
+        key,
+        summary,
+        range: choices(&JUMP_TARGETS),
+        example,
+    };
+    let flag = |key, summary, example| SettingDoc {
+        key,
+        summary,
+        range: choices(&["true", "false"]),
+        example,
+    };
+    vec![
+        doc(
+            "ui.inspect_device_interval_ms",
+            "Shortest interval between refreshes of device activity in the devices panel, in milliseconds. Activity is only sent when it changes, and not while the window is closed to tray.",
+            range(limits::INSPECT_DEVICE_INTERVAL_MS),
+            "inspect_device_interval_ms: 100",
+        ),
+        doc(
+            "ui.low_battery_alert_percent",
+            "Alert when battery of a device falls below this percent. 0 to disable.",
+            range(limits::LOW_BATTERY_ALERT_PERCENT),
+            "low_battery_alert_percent: 15",
+        ),
+        tray_action(
+            "ui.tray_click",
+            "Action when the tray icon is clicked.",
+            "tray_click: TogglePause",
+        ),
+        tray_action(
+            "ui.tray_double_click",
+            "Action when the tray icon is double clicked.",
+            "tray_double_click: OpenUI",
+        ),
+        tray_action(
+            "ui.tray_middle_click",
+            "Action when the tray icon is clicked by the middle button.",
+            "tray_middle_click: TogglePause",
+        ),
+        doc(
+            "ui.tray_icon",
+            "Style of the tray icon. Light and Dark are monochrome for light or dark taskbar, and Auto picks one by theme of system.",
+            choices(&TRAY_ICON_STYLES),
+            "tray_icon: Auto",
+        ),
+        flag(
+            "ui.animate_jumps",
+            "Draw a short trail on screen when jumping between monitors.",
+            "animate_jumps: true",
+        ),
+        flag(
+            "ui.check_updates_weekly",
+            "Ask GitHub for the latest release once a week, and show it in the About panel if newer. Nothing else is sent.",
+            "check_updates_weekly: true",
+        ),
+        flag(
+            "ui.allow_view_only",
+            "Let MonMouse launched again open in view-only mode, showing devices of this instance and editing its settings. Answered on a localhost port only while on.",
+            "allow_view_only: true",
+        ),
+        flag(
+            "ui.sounds.on_lock",
+            "Play a sound when locking of current mouse is toggled.",
+            "on_lock: true",
+        ),
+        flag(
+            "ui.sounds.on_jump",
+            "Play a sound when cursor jumps to another monitor.",
+            "on_jump: true",
+        ),
+        flag(
+            "ui.sounds.on_switch",
+            "Play a sound when another device takes over the cursor.",
+            "on_switch: true",
+        ),
+        doc(
+            "ui.sounds.wav_dir",
+            "Directory of lock.wav, jump.wav and switch.wav. System sounds are played for missing ones, or if empty.",
+            None,
+            "wav_dir: C:\\Sounds",
+        ),
+        flag(
+            "ui.sync.listen",
+            "Accept shortcuts and global options pushed by other machines with the same token.",
+            "listen: true",
+        ),
+        doc(
+            "ui.sync.listen_addr",
+            "IP address of the interface listened on. Only pushes from this machine are accepted by default. Set to an address in the LAN, or 0.0.0.0 for all interfaces, to accept other machines.",
+            None,
+            "listen_addr: 192.168.1.10",
+        ),
+        doc(
+            "ui.sync.port",
+            "TCP port listened on, and pushed to if a peer has no port.",
+            range(limits::SYNC_PORT),
+            "port: 47251",
+        ),
+        doc(
+            "ui.sync.token",
+            "Shared by machines pushing settings to each other. Sent as is, so only use it in trusted networks.",
+            Some(format!(
+                "at least {} characters to listen",
+                limits::SYNC_TOKEN_MIN_LEN
+            )),
+            "token: my-desk-2024",
+        ),
+        doc(
+            "ui.sync.peers",
+            "Machines settings are pushed to, separated by ','. Each is a host, optionally with a port.",
+            None,
+            "peers: 192.168.1.20, laptop:47300",
+        ),
+        doc(
+            "processor.merge_unassociated_events_ms",
+            "Events not associated with any device, within such milliseconds after the last event of active device, are taken as from it. -1 to never merge. Devices may override it with their own merge_unassociated_events_ms.",
+            range(limits::MERGE_UNASSOCIATED_EVENTS_MS),
+            "merge_unassociated_events_ms: 5",
+        ),
+        doc(
+            "processor.switch_after_events",
+            "Consecutive events needed before another device takes over the cursor. Larger values ignore jitters of idle devices.",
+            range(limits::SWITCH_AFTER_EVENTS),
+            "switch_after_events: 3",
+        ),
+        doc(
+            "processor.turbo_speed_percent",
+            "Cursor speed while the turbo shortcut is held, in percent of normal speed.",
+            range(limits::TURBO_SPEED_PERCENT),
+            "turbo_speed_percent: 300",
+        ),
+        flag(
+            "processor.log_active_device",
+            "Log every switch of active device, for debugging.",
+            "log_active_device: true",
+        ),
+        flag(
+            "processor.use_work_area",
+            "Jumping and centering land inside work area of monitor, not under taskbar.",
+            "use_work_area: true",
+        ),
+        flag(
+            "processor.scroll_window_under_cursor",
+            "Send wheel scrolling to the window under cursor, even if it's not focused.",
+            "scroll_window_under_cursor: true",
+        ),
+        doc(
+            "processor.update_monitors_interval_ms",
+            "Updating monitors triggered by system events happens at most once within such milliseconds.",
+            range(limits::UPDATE_MONITORS_INTERVAL_MS),
+            "update_monitors_interval_ms: 1000",
+        ),
+        doc(
+            "processor.poll_max_messages",
+            "Most messages handled in one round of the event loop.",
+            range(limits::POLL_MAX_MESSAGES),
+            "poll_max_messages: 20",
+        ),
+        doc(
+            "processor.poll_busy_timeout_ms",
+            "Wait time of the event loop while events keep coming, in milliseconds.",
+            range(limits::POLL_BUSY_TIMEOUT_MS),
+            "poll_busy_timeout_ms: 20",
+        ),
+        doc(
+            "processor.poll_idle_timeout_ms",
+            "Wait time of the event loop when idle, in milliseconds.",
+            range(limits::POLL_IDLE_TIMEOUT_MS),
+            "poll_idle_timeout_ms: 200",
+        ),
+        doc(
+            "processor.rawinput_usages",
+            "HID usages registered for raw input, as usage page and usage, where usage 0 takes the whole page. Devices of other usages are neither listed nor managed, e.g. drop the Digitizer page for pens handled by their own drivers. Generic Desktop Mouse must be kept, or the defaults are registered instead, and keyboards cannot be added.",
+            None,
+            "rawinput_usages: [{page: 0x01, usage: 0x01}, {page: 0x01, usage: 0x02}]",
+        ),
+        doc(
+            "processor.startup_grace_secs",
+            "After starting, devices not found yet are shown as scanning instead of disconnected for this long, in seconds. Devices and monitors are also scanned again a few times within the first minute.",
+            range(limits::STARTUP_GRACE_SECS),
+            "startup_grace_secs: 30",
+        ),
+        doc(
+            "processor.monitor_change_grace_secs",
+            "After monitors are added, removed or change resolution, locks are held for this long, in seconds, since monitors may come and go while display driver restarts. Changes within it start the wait again. 0 to apply locks at once.",
+            range(limits::MONITOR_CHANGE_GRACE_SECS),
+            "monitor_change_grace_secs: 10",
+        ),
+        flag(
+            "processor.confirm_monitor_changes",
+            "After monitors changed and monitor_change_grace_secs passed, ask before applying locks again, or pausing mouse control.",
+            "confirm_monitor_changes: true",
+        ),
+        flag(
+            "processor.persist_shortcut_changes",
+            "Write device settings toggled by shortcuts, e.g. locking by cur_mouse_lock, to config file at once. Otherwise they are lost on restart, unless devices config is saved.",
+            "persist_shortcut_changes: true",
+        ),
+        flag(
+            "processor.restart_on_crash",
+            "Start a watchdog process along with MonMouse, which starts it again if it crashes. It is not started again if it crashes within 30 seconds, not to loop on a bad config. Takes effect on next start.",
+            "restart_on_crash: true",
+        ),
+        doc(
+            "processor.battery_query_interval_secs",
+            "How often battery levels of devices are queried, in seconds. 0 to disable.",
+            range(limits::BATTERY_QUERY_INTERVAL_SECS),
+            "battery_query_interval_secs: 600",
+        ),
+        flag(
+            "processor.cursor_polling",
+            "Sample cursor position instead of installing the low-level mouse hook, which some security software blocks. Locking and jumping still work, but cursor may leave its monitor for a moment, and turbo, gestures and click debouncing are unavailable. Also used automatically while the hook fails to install, which is retried meanwhile.",
+            "cursor_polling: true",
+        ),
+        flag(
+            "processor.high_resolution_timing",
+            "Time mouse events by the high resolution performance counter. Without it, events are timed by system ticks of about 15ms, so merge_unassociated_events_ms shorter than that works unreliably.",
+            "high_resolution_timing: true",
+        ),
+        doc(
+            "processor.thread_priority",
+            "Priority of the thread correcting cursor. Raise it if cursor escapes locked monitors while CPU is busy, and check whether late input events in Advanced stop growing.",
+            choices(&THREAD_PRIORITIES),
+            "thread_priority: above_normal",
+        ),
+        flag(
+            "processor.mmcss",
+            "Register the thread correcting cursor with Multimedia Class Scheduler Service as a game task, boosting it while it has events to process. Works together with thread_priority.",
+            "mmcss: true",
+        ),
+        flag(
+            "processor.etw_tracing",
+            "Emit ETW events for profiling. Only works if built with feature \"tracing\".",
+            "etw_tracing: true",
+        ),
+        flag(
+            "processor.debug_overlay",
+            "Shade areas devices are locked to, and flash a dot where the cursor was relocated. Always shown in debug builds, without changing the config.",
+            "debug_overlay: true",
+        ),
+        doc(
+            "processor.confirmed_risks",
+            "Risky features allowed to take effect: shortcuts by keyboard hook, click debouncing and scrolling the window under cursor. Others set are kept off, and UI asks for confirming them. None of them is on in safe mode, and the failsafe shortcut turns all of them off.",
+            choices(&RISKY_FEATURES),
+            "confirmed_risks: [hook_shortcuts]",
+        ),
+        doc(
+            "processor.focus_mode.dim_percent",
+            "How dark other monitors become in focus mode, 100 for black.",
+            range(limits::FOCUS_DIM_PERCENT),
+            "dim_percent: 70",
+        ),
+        doc(
+            "processor.focus_mode.release_after_idle_secs",
+            "Focus mode ends after its mouse is idle for this long, in seconds. 0 to keep it until toggled.",
+            range(limits::IDLE_RELEASE_SECS),
+            "release_after_idle_secs: 60",
+        ),
+        flag(
+            "processor.kvm.pause_when_remote",
+            "Pause relocating while a software KVM(e.g. Mouse Without Borders, Barrier) sends input to another machine.",
+            "pause_when_remote: true",
+        ),
+        doc(
+            "processor.kvm.processes",
+            "Executable names of software KVMs, separated by ','. Input is taken as remote while one of them runs and the cursor is hidden.",
+            None,
+            "processes: MouseWithoutBorders.exe, barriers.exe",
+        ),
+        doc(
+            "processor.kvm.state_file",
+            "File containing \"remote\" while input goes to another machine, written by scripts of the KVM. Processes are not checked if set.",
+            None,
+            "state_file: C:\\Users\\me\\kvm_state.txt",
+        ),
+        doc(
+            "processor.hot_corners.dwell_ms",
+            "How long cursor stays in a corner before its action is triggered, in milliseconds.",
+            range(limits::HOT_CORNER_DWELL_MS),
+            "dwell_ms: 500",
+        ),
+        doc(
+            "processor.hot_corners.only_device",
+            "Only cursor moved by this device triggers hot corners. Empty for any device.",
+            None,
+            "only_device: \\\\?\\HID#VID_046D&PID_C52B&MI_01&COL01#...",
+        ),
+        tray_action(
+            "processor.hot_corners.top_left",
+            "Action when cursor stays in the top left corner of a monitor.",
+            "top_left: OpenUI",
+        ),
+        tray_action(
+            "processor.hot_corners.top_right",
+            "Action when cursor stays in the top right corner of a monitor.",
+            "top_right: TogglePause",
+        ),
+        tray_action(
+            "processor.hot_corners.bottom_left",
+            "Action when cursor stays in the bottom left corner of a monitor.",
+            "bottom_left: JumpNext",
+        ),
+        tray_action(
+            "processor.hot_corners.bottom_right",
+            "Action when cursor stays in the bottom right corner of a monitor.",
+            "bottom_right: None",
+        ),
+        tray_action(
+            "processor.gestures.swipe_left",
+            "Action when a pen or touch screen swipes left across most of a monitor.",
+            "swipe_left: JumpNext",
+        ),
+        tray_action(
+            "processor.gestures.swipe_right",
+            "Action when a pen or touch screen swipes right across most of a monitor.",
+            "swipe_right: JumpNext",
+        ),
+        tray_action(
+            "processor.gestures.corner_double_tap",
+            "Action when a pen or touch screen taps the same corner twice quickly.",
+            "corner_double_tap: OpenUI",
+        ),
+        doc(
+            "processor.monitor_order",
+            "Order of jumping to the next monitor: as enumerated by system, rows from top to bottom each from left to right, or columns from left to right each from top to bottom. Portrait or offset monitors beside others count in the same row.",
+            choices(&MONITOR_ORDERS),
+            "monitor_order: columns",
+        ),
+        doc(
+            "processor.monitor_groups",
+            "Monitors merged into one screen, for locking and jumping. Each group lists patterns of monitor names like DELL U2720Q, device paths or display names like \\\\.\\DISPLAY1, where '*' matches any sequence. A group spans the rectangle around its monitors.",
+            None,
+            "monitor_groups: [[\"*DISPLAY1\", \"*DISPLAY2\"]]",
+        ),
+        jump_target(
+            "processor.jump_targets.jump_next",
+            "Where cursor lands when jumping to the next monitor: position it left there, center, the same relative position, or the nearest point.",
+            "jump_next: relative",
+        ),
+        jump_target(
+            "processor.jump_targets.jump_left",
+            "Where cursor lands when jumping to the monitor on the left.",
+            "jump_left: nearest_edge",
+        ),
+        jump_target(
+            "processor.jump_targets.jump_right",
+            "Where cursor lands when jumping to the monitor on the right.",
+            "jump_right: nearest_edge",
+        ),
+        jump_target(
+            "processor.jump_targets.jump_up",
+            "Where cursor lands when jumping to the monitor above.",
+            "jump_up: center",
+        ),
+        jump_target(
+            "processor.jump_targets.jump_down",
+            "Where cursor lands when jumping to the monitor below.",
+            "jump_down: remembered",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_lock",
+            "Toggle locking current mouse in its monitor.",
+            "cur_mouse_lock: Ctrl+Alt+L",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_jump_next",
+            "Move cursor of current mouse to the next monitor.",
+            "cur_mouse_jump_next: Ctrl+Alt+N",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_turbo",
+            "Hold to speed up cursor of current mouse.",
+            "cur_mouse_turbo: Ctrl+Alt+T",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_jump_left",
+            "Move cursor of current mouse to the monitor on the left.",
+            "cur_mouse_jump_left: Ctrl+Alt+Left",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_jump_right",
+            "Move cursor of current mouse to the monitor on the right.",
+            "cur_mouse_jump_right: Ctrl+Alt+Right",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_jump_up",
+            "Move cursor of current mouse to the monitor above.",
+            "cur_mouse_jump_up: Ctrl+Alt+Up",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_jump_down",
+            "Move cursor of current mouse to the monitor below.",
+            "cur_mouse_jump_down: Ctrl+Alt+Down",
+        ),
+        shortcut(
+            "processor.shortcuts.move_window_here",
+            "Move the foreground window onto the monitor of cursor.",
+            "move_window_here: Ctrl+Alt+M",
+        ),
+        shortcut(
+            "processor.shortcuts.cur_mouse_focus_mode",
+            "Toggle dimming monitors other than the one current mouse is on.",
+            "cur_mouse_focus_mode: Ctrl+Alt+F",
+        ),
+        shortcut(
+            "processor.shortcuts.cycle_switch_devices",
+            "Move switch to the next device listed in processor.switch_cycle, as ids of devices in order. Only one of them has switch enabled at a time.",
+            "cycle_switch_devices: Ctrl+Alt+S",
+        ),
+        flag(
+            "processor.shortcuts.positional_keys",
+            "Letter and digit keys of shortcuts are named by their position on US layout. Missing in configs written by older versions, whose shortcuts keep using labels of current layout.",
+            "positional_keys: false",
+        ),
+    ]
+}
+
+// Key may omit its sections, if the rest is unique, e.g. "dwell_ms"
+pub fn explain(key: &str) -> Option<&'static SettingDoc> {
+    let docs = settings_doc();
+    if let Some(d) = docs.iter().find(|d| d.key == key) {
+        return Some(d);
+    }
+    let suffix = format!(".{}", key);
+    let mut found = docs.iter().filter(|d| d.key.ends_with(&suffix));
+    match (found.next(), found.next()) {
+        (Some(d), None) => Some(d),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::Settings;
+
+    #[test]
+    fn test_settings_doc_keys() {
+        let value = serde_yaml::to_value(Settings::default()).unwrap();
+        for doc in settings_doc() {
+            let mut v = &value;
+            for part in doc.key.split('.') {
+                v = v
+                    .get(part)
+                    .unwrap_or_else(|| panic!("no such setting {}", doc.key));
+            }
+            let field = doc.key.rsplit('.').next().unwrap();
+            assert!(doc.example.starts_with(&format!("{}: ", field)));
+        }
+        assert_eq!(
+            explain("dwell_ms").unwrap().key,
+            "processor.hot_corners.dwell_ms"
+        );
+        assert!(explain("no_such_key").is_none());
+    }
+}
@@ -0,0 +1,210 @@
+// Fake devices with scripted activity, for developing and demoing without real hardware.
+// Only built into debug builds, and patterns of injected input only with feature "devsim".
+
+#[cfg(debug_assertions)]
+use std::f64::consts::PI;
+
+#[cfg(debug_assertions)]
+use crate::device_type::DeviceType;
+#[cfg(debug_assertions)]
+use crate::geometry::MousePos;
+#[cfg(debug_assertions)]
+use crate::message::Positioning;
+
+#[cfg(debug_assertions)]
+pub const SIMULATED_DEVICE_ID_PREFIX: &str = "Simulated#";
+
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatedScript {
+    // Briefly touch the center of first monitor once every interval
+    Tap { interval_ms: u64 },
+    // Move around the center of first monitor for one period, then idle for one period
+    Circle { radius: i32, period_ms: u64 },
+}
+
+// How long a tap lasts
+#[cfg(debug_assertions)]
+const TAP_LAST_FOR_MS: u64 = 100;
+
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedDeviceSpec {
+    pub device_type: DeviceType,
+    pub positioning: Positioning,
+    pub script: SimulatedScript,
+}
+
+#[cfg(debug_assertions)]
+impl SimulatedDeviceSpec {
+    // Position of device at the moment, None if it is idle.
+    // Idle phases leave the cursor to real devices.
+    pub fn pos_at(&self, center: MousePos, elapsed_ms: u64) -> Option<MousePos> {
+        match self.script {
+            SimulatedScript::Tap { interval_ms } => {
+                (elapsed_ms % interval_ms.max(1) < TAP_LAST_FOR_MS).then_some(center)
+            }
+            SimulatedScript::Circle { radius, period_ms } => {
+                let period_ms = period_ms.max(1);
+                if (elapsed_ms / period_ms) % 2 == 1 {
+                    return None;
+                }
+                let angle = (elapsed_ms % period_ms) as f64 / period_ms as f64 * 2.0 * PI;
+                Some(MousePos::from(
+                    center.x + (radius as f64 * angle.cos()).round() as i32,
+                    center.y + (radius as f64 * angle.sin()).round() as i32,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+pub fn simulated_device_id(seq: usize) -> String {
+    format!("{}{}", SIMULATED_DEVICE_ID_PREFIX, seq)
+}
+
+// Input injected by SendInput for monmouse-devsim. Injected events carry no device handle,
+// so they land in the merge window of unassociated events like some real devices do.
+#[cfg(feature = "devsim")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedMove {
+    Relative { dx: i32, dy: i32 },
+    // Normalized to the virtual desktop, 0 to 65535 on both axes
+    Absolute { x: i32, y: i32 },
+}
+
+#[cfg(feature = "devsim")]
+#[derive(Debug, Clone, Copy)]
+pub struct InjectPattern {
+    pub rate_hz: u32,
+    // Chance of each burst being absolute
+    pub absolute_percent: u32,
+    // Events in a row of the same kind, 1 for alternating as fast as possible
+    pub burst: u32,
+    pub seed: u64,
+}
+
+#[cfg(feature = "devsim")]
+impl InjectPattern {
+    // Absolute events stay in the middle of the desktop, not to trigger hot corners
+    const ABSOLUTE_MIN: i32 = 16384;
+    const ABSOLUTE_MAX: i32 = 49152;
+
+    // Events with milliseconds since start. The same seed gives the same events, to
+    // reproduce a race again.
+    pub fn schedule(&self, duration_ms: u64) -> Vec<(u64, InjectedMove)> {
+        let mut rng = XorShift(self.seed.max(1));
+        let interval_ms = 1000.0 / self.rate_hz.max(1) as f64;
+        let count = (duration_ms as f64 / interval_ms) as u64;
+        let burst = self.burst.max(1) as u64;
+        let mut absolute = false;
+        (0..count)
+            .map(|i| {
+                if i % burst == 0 {
+                    absolute = rng.next() % 100 < self.absolute_percent as u64;
+                }
+                let at_ms = (i as f64 * interval_ms) as u64;
+                let mv = if absolute {
+                    let span = (Self::ABSOLUTE_MAX - Self::ABSOLUTE_MIN) as u64;
+                    InjectedMove::Absolute {
+                        x: Self::ABSOLUTE_MIN + (rng.next() % span) as i32,
+                        y: Self::ABSOLUTE_MIN + (rng.next() % span) as i32,
+                    }
+                } else {
+                    InjectedMove::Relative {
+                        dx: (rng.next() % 11) as i32 - 5,
+                        dy: (rng.next() % 11) as i32 - 5,
+                    }
+                };
+                (at_ms, mv)
+            })
+            .collect()
+    }
+}
+
+// Not for anything but repeatable patterns
+#[cfg(feature = "devsim")]
+struct XorShift(u64);
+
+#[cfg(feature = "devsim")]
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_simulated_circle() {
+        let spec = SimulatedDeviceSpec {
+            device_type: DeviceType::TouchScreen,
+            positioning: Positioning::Absolute,
+            script: SimulatedScript::Circle {
+                radius: 100,
+                period_ms: 1000,
+            },
+        };
+        let c = MousePos::from(500, 500);
+        assert_eq!(spec.pos_at(c, 0), Some(MousePos::from(600, 500)));
+        assert_eq!(spec.pos_at(c, 250), Some(MousePos::from(500, 600)));
+        assert_eq!(spec.pos_at(c, 500), Some(MousePos::from(400, 500)));
+        assert_eq!(spec.pos_at(c, 1500), None);
+        assert_eq!(spec.pos_at(c, 2000), Some(MousePos::from(600, 500)));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_simulated_tap() {
+        let spec = SimulatedDeviceSpec {
+            device_type: DeviceType::Mouse,
+            positioning: Positioning::Relative,
+            script: SimulatedScript::Tap { interval_ms: 1000 },
+        };
+        let c = MousePos::from(500, 500);
+        assert_eq!(spec.pos_at(c, 50), Some(c));
+        assert_eq!(spec.pos_at(c, 500), None);
+        assert_eq!(spec.pos_at(c, 2020), Some(c));
+    }
+
+    #[cfg(feature = "devsim")]
+    #[test]
+    fn test_inject_pattern() {
+        let pattern = InjectPattern {
+            rate_hz: 100,
+            absolute_percent: 50,
+            burst: 4,
+            seed: 7,
+        };
+        let events = pattern.schedule(1000);
+        assert_eq!(events.len(), 100);
+        assert_eq!(events[1].0, 10);
+        assert_eq!(events, pattern.schedule(1000));
+        for chunk in events.chunks(4) {
+            let absolute =
+                |(_, m): &(u64, InjectedMove)| matches!(m, InjectedMove::Absolute { .. });
+            assert!(chunk.iter().all(|e| absolute(e) == absolute(&chunk[0])));
+        }
+        let absolute = events
+            .iter()
+            .filter(|(_, m)| matches!(m, InjectedMove::Absolute { .. }))
+            .count();
+        assert!(absolute > 0 && absolute < 100);
+
+        let relative_only = InjectPattern {
+            absolute_percent: 0,
+            ..pattern
+        };
+        assert!(relative_only
+            .schedule(1000)
+            .iter()
+            .all(|(_, m)| matches!(m, InjectedMove::Relative { .. })));
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Sender},
+    thread::JoinHandle,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Error;
+
+// Events closer than this are counted as continuous use
+pub const STATS_ACTIVE_GAP_MS: u64 = 1000;
+pub const STATS_FLUSH_INTERVAL_MS: u64 = 60000;
+// Older days are dropped, keeping the state file small
+pub const STATS_KEEP_DAYS: usize = 90;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DayUsage {
+    pub active_ms: u64,
+    pub events: u64,
+}
+
+impl DayUsage {
+    fn add(&mut self, other: &DayUsage) {
+        self.active_ms += other.active_ms;
+        self.events += other.events;
+    }
+}
+
+// Usage of each device id, by local date as "YYYY-MM-DD"
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UsageStats {
+    pub devices: BTreeMap<String, BTreeMap<String, DayUsage>>,
+}
+
+impl UsageStats {
+    pub fn day(&self, id: &str, day: &str) -> DayUsage {
+        self.devices
+            .get(id)
+            .and_then(|days| days.get(day))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total(&self, id: &str) -> DayUsage {
+        let mut total = DayUsage::default();
+        if let Some(days) = self.devices.get(id) {
+            days.values().for_each(|d| total.add(d));
+        }
+        total
+    }
+
+    fn merge(&mut self, day: &str, id: String, usage: &DayUsage) {
+        let days = self.devices.entry(id).or_default();
+        days.entry(day.to_owned()).or_default().add(usage);
+        while days.len() > STATS_KEEP_DAYS {
+            days.pop_first();
+        }
+    }
+}
+
+pub fn read_stats(file: &Path) -> Result<UsageStats, Error> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(v) => v,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(UsageStats::default()),
+        Err(e) => return Err(Error::IO(e)),
+    };
+    serde_yaml::from_str(&content).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+}
+
+pub fn write_stats(file: &Path, stats: &UsageStats) -> Result<(), Error> {
+    let content =
+        serde_yaml::to_string(stats).map_err(|e| Error::InvalidConfigFile(e.to_string()))?;
+    std::fs::write(file, content).map_err(Error::IO)
+}
+
+// Writes stats file in a thread of its own, since stats are flushed by the eventloop, which
+// also runs the mouse hook. Only the latest stats sent are written. Dropping it waits for
+// the last write.
+struct StatsWriter {
+    tx: Option<Sender<UsageStats>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatsWriter {
+    fn start(path: PathBuf) -> io::Result<Self> {
+        let (tx, rx) = channel::<UsageStats>();
+        let thread = std::thread::Builder::new()
+            .name("stats_writer".to_owned())
+            .spawn(move || {
+                while let Ok(mut stats) = rx.recv() {
+                    while let Ok(v) = rx.try_recv() {
+                        stats = v;
+                    }
+                    if let Err(e) = write_stats(&path, &stats) {
+                        log::warn!("Write usage stats failed: {}", e);
+                    }
+                }
+            })?;
+        Ok(StatsWriter {
+            tx: Some(tx),
+            thread: Some(thread),
+        })
+    }
+
+    fn submit(&self, stats: UsageStats) -> Result<(), Error> {
+        match self.tx.as_ref().map(|tx| tx.send(stats)) {
+            Some(Ok(_)) => Ok(()),
+            _ => Err(Error::IO(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "stats writer stopped",
+            ))),
+        }
+    }
+}
+
+impl Drop for StatsWriter {
+    fn drop(&mut self) {
+        self.tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Default)]
+struct PendingUsage {
+    last_tick: u64,
+    usage: DayUsage,
+}
+
+// Counts events cheaply in memory, which are merged into stats and written to file
+// by flush(). So that a day switched between two flushes is counted into the later day.
+#[derive(Default)]
+pub struct StatsAggregator {
+    stats: UsageStats,
+    pending: HashMap<String, PendingUsage>,
+    writer: Option<StatsWriter>,
+    last_flush: u64,
+}
+
+impl StatsAggregator {
+    // Without a path, stats are only kept in memory
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let stats = match path.as_ref().map(|p| read_stats(p)) {
+            Some(Ok(v)) => v,
+            Some(Err(e)) => {
+                log::warn!("Cannot load usage stats, start from empty: {}", e);
+                UsageStats::default()
+            }
+            None => UsageStats::default(),
+        };
+        let writer = match path.map(StatsWriter::start) {
+            Some(Ok(v)) => Some(v),
+            Some(Err(e)) => {
+                log::warn!(
+                    "Cannot start writing usage stats, only kept in memory: {}",
+                    e
+                );
+                None
+            }
+            None => None,
+        };
+        StatsAggregator {
+            stats,
+            writer,
+            ..Default::default()
+        }
+    }
+
+    pub fn on_event(&mut self, id: &str, tick: u64) {
+        if !self.pending.contains_key(id) {
+            self.pending.insert(id.to_owned(), PendingUsage::default());
+        }
+        let p = self.pending.get_mut(id).unwrap();
+        if p.last_tick > 0 && tick >= p.last_tick && tick - p.last_tick < STATS_ACTIVE_GAP_MS {
+            p.usage.active_ms += tick - p.last_tick;
+        }
+        p.usage.events += 1;
+        p.last_tick = tick;
+    }
+
+    pub fn should_flush(&self, tick: u64) -> bool {
+        tick >= self.last_flush + STATS_FLUSH_INTERVAL_MS
+    }
+
+    // Merge pending usage into the day, and write stats file in background if anything changed
+    pub fn flush(&mut self, day: &str, tick: u64) -> Result<(), Error> {
+        self.last_flush = tick;
+        let mut changed = false;
+        for (id, p) in self.pending.iter_mut() {
+            if p.usage.events == 0 {
+                continue;
+            }
+            self.stats.merge(day, id.clone(), &p.usage);
+            // Keep last_tick, so that use across flushing is still continuous
+            p.usage = DayUsage::default();
+            changed = true;
+        }
+        match &self.writer {
+            Some(writer) if changed => writer.submit(self.stats.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn stats(&self) -> &UsageStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_aggregate() {
+        let mut agg = StatsAggregator::load(None);
+        agg.on_event("mouse", 1000);
+        agg.on_event("mouse", 1200);
+        agg.on_event("mouse", 1500);
+        // Gap too long, not counted as active
+        agg.on_event("mouse", 5000);
+        agg.on_event("pen", 5000);
+        agg.flush("2024-01-01", 6000).unwrap();
+        agg.on_event("mouse", 5500);
+        agg.flush("2024-01-02", 7000).unwrap();
+
+        let s = agg.stats();
+        assert_eq!(
+            s.day("mouse", "2024-01-01"),
+            DayUsage {
+                active_ms: 500,
+                events: 4
+            }
+        );
+        assert_eq!(
+            s.day("mouse", "2024-01-02"),
+            DayUsage {
+                active_ms: 500,
+                events: 1
+            }
+        );
+        assert_eq!(s.total("mouse").events, 5);
+        assert_eq!(s.total("pen").active_ms, 0);
+        assert_eq!(s.total("unknown"), DayUsage::default());
+    }
+
+    #[test]
+    fn test_stats_written_in_background() {
+        let path = std::env::temp_dir().join(format!("monmouse_stats_{}.yml", std::process::id()));
+        let mut agg = StatsAggregator::load(Some(path.clone()));
+        agg.on_event("mouse", 1000);
+        agg.flush("2024-01-01", 2000).unwrap();
+        // Waits for the writer
+        drop(agg);
+
+        let stats = read_stats(&path).unwrap();
+        assert_eq!(stats.day("mouse", "2024-01-01").events, 1);
+        let _ = std::fs::remove_file(&path);
+    }
+}
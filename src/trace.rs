@@ -0,0 +1,162 @@
+// Raw event tracing for bug reports. Device ids may contain serial numbers, so they
+// are written as "dev1", "dev2"... in order of appearance.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::errors::Error;
+
+pub const TRACE_DEFAULT_SECS: u32 = 10;
+pub const TRACE_MAX_SECS: u32 = 300;
+
+pub struct EventTracer {
+    writer: GzEncoder<BufWriter<File>>,
+    path: PathBuf,
+    until: u64,
+    devices: HashMap<String, usize>,
+    records: u64,
+    // First failure of writing, reported by finish()
+    error: Option<io::Error>,
+}
+
+impl EventTracer {
+    pub fn start(path: &Path, secs: u32, tick: u64) -> Result<Self, Error> {
+        let file = File::create(path).map_err(Error::IO)?;
+        let secs = secs.clamp(1, TRACE_MAX_SECS) as u64;
+        Ok(EventTracer {
+            writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            path: path.to_owned(),
+            until: tick + secs * 1000,
+            devices: HashMap::new(),
+            records: 0,
+            error: None,
+        })
+    }
+
+    pub fn is_due(&self, tick: u64) -> bool {
+        tick >= self.until
+    }
+
+    fn device_name(&mut self, id: Option<&str>) -> String {
+        let Some(id) = id else {
+            return "null".to_owned();
+        };
+        let next = self.devices.len() + 1;
+        let n = *self.devices.entry(id.to_owned()).or_insert(next);
+        format!("\"dev{}\"", n)
+    }
+
+    fn write_line(&mut self, line: String) {
+        if self.error.is_some() {
+            return;
+        }
+        match writeln!(self.writer, "{}", line) {
+            Ok(_) => self.records += 1,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    // Outcome is what processor did with the event, e.g. "device", "merged" or "ignored"
+    pub fn input(
+        &mut self,
+        tick: u64,
+        handle: isize,
+        id: Option<&str>,
+        flags: Option<u16>,
+        positioning: &str,
+        outcome: &str,
+    ) {
+        let device = self.device_name(id);
+        let flags = flags.map_or("null".to_owned(), |v| v.to_string());
+        self.write_line(format!(
+            "{{\"tick\":{},\"event\":\"input\",\"handle\":{},\"device\":{},\"flags\":{},\"positioning\":\"{}\",\"outcome\":\"{}\"}}",
+            tick, handle, device, flags, positioning, outcome
+        ));
+    }
+
+    pub fn relocate(&mut self, tick: u64, x: i32, y: i32, clamped: bool) {
+        self.write_line(format!(
+            "{{\"tick\":{},\"event\":\"relocate\",\"x\":{},\"y\":{},\"clamped\":{}}}",
+            tick, x, y, clamped
+        ));
+    }
+
+    // Returns the file written, and number of records in it
+    pub fn finish(mut self) -> Result<(PathBuf, u64), Error> {
+        if let Some(e) = self.error.take() {
+            return Err(Error::IO(e));
+        }
+        self.writer
+            .finish()
+            .and_then(|mut w| w.flush())
+            .map_err(Error::IO)?;
+        Ok((self.path, self.records))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::TRACE_FILE_NAME;
+    use crate::utils::test_temp_dir;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_trace_anonymized() {
+        let dir = test_temp_dir("trace");
+        let path = dir.join(TRACE_FILE_NAME);
+
+        let mut t = EventTracer::start(&path, 5, 1000).unwrap();
+        assert!(!t.is_due(5999));
+        assert!(t.is_due(6000));
+        t.input(
+            1001,
+            11,
+            Some("HID#VID_046D#SERIAL"),
+            Some(0),
+            "Relative",
+            "device",
+        );
+        t.input(
+            1002,
+            22,
+            Some("HID#VID_0E0F#OTHER"),
+            Some(1),
+            "Absolute",
+            "device",
+        );
+        t.input(
+            1003,
+            0,
+            Some("HID#VID_046D#SERIAL"),
+            None,
+            "Unknown",
+            "merged",
+        );
+        t.relocate(1004, -10, 20, true);
+        assert_eq!(t.finish().unwrap(), (path.clone(), 4));
+
+        let mut content = String::new();
+        GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(!content.contains("SERIAL"));
+        assert!(lines[0].contains("\"device\":\"dev1\""));
+        assert!(lines[1].contains("\"device\":\"dev2\""));
+        assert!(lines[2].contains("\"device\":\"dev1\",\"flags\":null"));
+        assert_eq!(
+            lines[3],
+            "{\"tick\":1004,\"event\":\"relocate\",\"x\":-10,\"y\":20,\"clamped\":true}"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,186 @@
+// Whether a newer release is published on GitHub. Checked only when asked, by the About panel
+// or CLI, or weekly if ui.check_updates_weekly is on. Nothing is sent but the request for the
+// latest release, and its answer is cached beside config.
+
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{build_info, errors::Error};
+
+const RELEASES_HOST: &str = "api.github.com";
+const LATEST_RELEASE_PATH: &str = "/repos/melon-masou/MonMouse/releases/latest";
+const RELEASE_PAGE_PREFIX: &str = "https://github.com/melon-masou/MonMouse/releases/tag/";
+const TIMEOUT_MS: i32 = 10000;
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+// Checks asked again within it are answered by the cache
+pub const MANUAL_CHECK_CACHE_SECS: u64 = 3600;
+pub const WEEKLY_CHECK_SECS: u64 = 7 * 24 * 3600;
+// How often a running instance looks whether the weekly check is due, as it may run for weeks
+pub const WEEKLY_CHECK_POLL_SECS: u64 = 3600;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatestRelease {
+    pub tag: String,
+    pub url: String,
+    // Seconds since unix epoch
+    pub checked_at: u64,
+}
+
+impl LatestRelease {
+    // Tags not like "v1.2.3" are never newer
+    pub fn is_newer_than(&self, version: &str) -> bool {
+        match (parse_version(&self.tag), parse_version(version)) {
+            (Some(latest), Some(cur)) => latest > cur,
+            _ => false,
+        }
+    }
+
+    pub fn is_newer(&self) -> bool {
+        self.is_newer_than(build_info().version)
+    }
+}
+
+// "v1.2.3" or "1.2", suffixes like "-beta" ignored
+fn parse_version(s: &str) -> Option<(u64, u64, u64)> {
+    let s = s.trim();
+    let s = s.strip_prefix('v').unwrap_or(s);
+    let core = s.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+// Good enough for tag_name of a release, a field at top level and never escaped
+fn json_string_field(body: &str, key: &str) -> Option<String> {
+    let pattern = format!("\"{}\"", key);
+    let rest = &body[body.find(&pattern)? + pattern.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_owned())
+}
+
+fn parse_release(body: &str, checked_at: u64) -> Result<LatestRelease, Error> {
+    let tag = json_string_field(body, "tag_name")
+        .filter(|t| parse_version(t).is_some())
+        .ok_or_else(|| Error::UpdateCheck("no release tag in response".to_owned()))?;
+    Ok(LatestRelease {
+        url: format!("{}{}", RELEASE_PAGE_PREFIX, tag),
+        tag,
+        checked_at,
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn read_cache(cache_file: &Path) -> Option<LatestRelease> {
+    let content = fs::read_to_string(cache_file).ok()?;
+    serde_yaml::from_str(&content).ok()
+}
+
+fn write_cache(cache_file: &Path, release: &LatestRelease) -> Result<(), Error> {
+    let content = serde_yaml::to_string(release)
+        .map_err(|e| Error::InvalidParam("release".to_owned(), e.to_string()))?;
+    fs::write(cache_file, content).map_err(Error::IO)
+}
+
+fn fresh(release: &LatestRelease, now: u64, max_age_secs: u64) -> bool {
+    release.checked_at <= now && now - release.checked_at < max_age_secs
+}
+
+// Whether the weekly check should ask GitHub again
+pub fn weekly_check_due(cache_file: &Path) -> bool {
+    !read_cache(cache_file).is_some_and(|c| fresh(&c, now_secs(), WEEKLY_CHECK_SECS))
+}
+
+// Answered by the cache if checked within max_age_secs, blocks for the request otherwise
+pub fn latest_release(cache_file: &Path, max_age_secs: u64) -> Result<LatestRelease, Error> {
+    let now = now_secs();
+    if let Some(cached) = read_cache(cache_file).filter(|c| fresh(c, now, max_age_secs)) {
+        return Ok(cached);
+    }
+    let user_agent = format!("MonMouse/{}", build_info().version);
+    let body = crate::platform::https_get(
+        RELEASES_HOST,
+        LATEST_RELEASE_PATH,
+        &user_agent,
+        TIMEOUT_MS,
+        MAX_RESPONSE_BYTES,
+    )?;
+    let release = parse_release(&body, now)?;
+    if let Err(e) = write_cache(cache_file, &release) {
+        warn!("Cannot cache latest release: {}", e);
+    }
+    Ok(release)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_temp_dir;
+
+    #[test]
+    fn test_parse_release() {
+        assert_eq!(parse_version("v0.1.2"), Some((0, 1, 2)));
+        assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("v1.0.0-beta"), Some((1, 0, 0)));
+        assert_eq!(parse_version("nightly"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+
+        let body = r#"{"url":"x","html_url":"y","id":1,"author":{"login":"a"},
+            "tag_name" : "v0.2.0","name":"MonMouse v0.2.0"}"#;
+        let release = parse_release(body, 100).unwrap();
+        assert_eq!(release.tag, "v0.2.0");
+        assert_eq!(
+            release.url,
+            "https://github.com/melon-masou/MonMouse/releases/tag/v0.2.0"
+        );
+        assert!(release.is_newer_than("0.1.9"));
+        assert!(!release.is_newer_than("0.2.0"));
+        assert!(!release.is_newer_than("garbage"));
+        assert!(matches!(
+            parse_release(r#"{"message":"Not Found"}"#, 100),
+            Err(Error::UpdateCheck(_))
+        ));
+
+        assert!(fresh(&release, 150, 100));
+        assert!(!fresh(&release, 200, 100));
+        // Clock turned back
+        assert!(!fresh(&release, 50, 100));
+    }
+
+    #[test]
+    fn test_weekly_check_due() {
+        let dir = test_temp_dir("update_cache");
+        let file = dir.join("update_cache.yml");
+        assert!(weekly_check_due(&file));
+
+        let mut release = LatestRelease {
+            tag: "v0.2.0".to_owned(),
+            url: "y".to_owned(),
+            checked_at: now_secs(),
+        };
+        write_cache(&file, &release).unwrap();
+        assert!(!weekly_check_due(&file));
+        release.checked_at -= WEEKLY_CHECK_SECS;
+        write_cache(&file, &release).unwrap();
+        assert!(weekly_check_due(&file));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,3 +1,6 @@
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant, SystemTime};
 
 pub struct SimpleRatelimit {
@@ -31,6 +34,74 @@ impl SimpleRatelimit {
     }
 }
 
+// Milliseconds of a monotonic clock. In high resolution mode it is counted by Instant, which
+// is backed by QueryPerformanceCounter on Windows, instead of the coarse source whose
+// resolution may be about 15ms(e.g. GetTickCount64). Ticks never go backwards when switching.
+// Read lock-free from any thread, since it is read for every input event.
+pub struct TickClock {
+    coarse: fn() -> u64,
+    // Instants are counted from it, taken at the first use
+    epoch: OnceLock<Instant>,
+    // Offset added to the source in use, shifted left by one, with the lowest bit set in
+    // high resolution mode. Kept in one atomic, so that both are always read together.
+    state: AtomicI64,
+}
+
+impl TickClock {
+    pub const fn new(coarse: fn() -> u64) -> Self {
+        TickClock {
+            coarse,
+            epoch: OnceLock::new(),
+            state: AtomicI64::new(0),
+        }
+    }
+
+    fn source(&self, precise: bool) -> u64 {
+        if precise {
+            self.epoch.get_or_init(Instant::now).elapsed().as_millis() as u64
+        } else {
+            (self.coarse)()
+        }
+    }
+
+    fn now_in(&self, state: i64) -> u64 {
+        (self.source(state & 1 == 1) as i64 + (state >> 1)) as u64
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now_in(self.state.load(Ordering::Acquire))
+    }
+
+    pub fn is_precise(&self) -> bool {
+        self.state.load(Ordering::Acquire) & 1 == 1
+    }
+
+    // A time of the coarse source, e.g. stamped on a message, as a tick of this clock. None in
+    // high resolution mode, where such stamps are too coarse and events are timed when seen.
+    pub fn from_coarse(&self, coarse: u64) -> Option<u64> {
+        let state = self.state.load(Ordering::Acquire);
+        (state & 1 == 0).then(|| (coarse as i64 + (state >> 1)) as u64)
+    }
+
+    // Expected to be called from one thread at a time
+    pub fn set_precise(&self, on: bool) {
+        let state = self.state.load(Ordering::Acquire);
+        if on == (state & 1 == 1) {
+            return;
+        }
+        let now = self.now_in(state) as i64;
+        let source = self.source(on) as i64;
+        // Coarse ticks may fall behind, never ahead of where they were
+        let offset = if on {
+            now - source
+        } else {
+            (now - source).max(0)
+        };
+        self.state
+            .store((offset << 1) | on as i64, Ordering::Release);
+    }
+}
+
 pub struct ArrayVec<T: Copy, const N: usize> {
     arr: [Option<T>; N],
 }
@@ -60,9 +131,105 @@ pub fn delay_panic(seconds: u64) {
     }
 }
 
+// Match text against pattern, where '*' matches any sequence. Case insensitive.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of last '*' in pattern, and where it begins to match in text
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            // Let the last '*' eat one more char
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
 pub fn vec_ensure_get_mut<T: Default>(v: &mut Vec<T>, id: usize) -> &mut T {
     if id >= v.len() {
         v.resize_with(id + 1, T::default);
     }
     v.get_mut(id).unwrap()
 }
+
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// JSON is also YAML, so this takes replies of either, e.g. Error::to_json() or a YAML document
+pub(crate) fn from_json_or_yaml<T: DeserializeOwned>(s: &str) -> Result<T, serde_yaml::Error> {
+    serde_yaml::from_str(s)
+}
+
+// Unique to the test and process, not to collide with parallel runs. Removed by the test.
+#[cfg(test)]
+pub fn test_temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("monmouse_test_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    static COARSE: AtomicU64 = AtomicU64::new(1000);
+
+    #[test]
+    fn test_tick_clock_switching() {
+        let clock = TickClock::new(|| COARSE.load(Ordering::Relaxed));
+        assert_eq!(clock.now(), 1000);
+
+        clock.set_precise(true);
+        std::thread::sleep(Duration::from_millis(20));
+        let precise = clock.now();
+        assert!(precise >= 1020);
+
+        // Coarse source lags behind, ticks still go on from where they were
+        COARSE.store(1010, Ordering::Relaxed);
+        clock.set_precise(false);
+        assert!(clock.now() >= precise);
+        COARSE.store(1050, Ordering::Relaxed);
+        assert_eq!(clock.now(), 1050 + precise - 1010);
+        assert_eq!(clock.from_coarse(1040), Some(1040 + precise - 1010));
+        clock.set_precise(true);
+        assert_eq!(clock.from_coarse(1040), None);
+    }
+
+    #[test]
+    fn test_json_or_yaml() {
+        let line = format!("{{\"a\":{},\"b\":2}}", json_str("x\"\n\u{1}"));
+        assert_eq!(line, "{\"a\":\"x\\\"\\n\\u0001\",\"b\":2}");
+        let map: std::collections::BTreeMap<String, String> = from_json_or_yaml(&line).unwrap();
+        assert_eq!(map["a"], "x\"\n\u{1}");
+        let map: std::collections::BTreeMap<String, String> = from_json_or_yaml("a: y\n").unwrap();
+        assert_eq!(map["a"], "y");
+    }
+}
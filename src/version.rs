@@ -0,0 +1,72 @@
+use std::fmt::Display;
+
+// Set by build.rs
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const VERSION_ANNO: &str = env!("VERSION_ANNO");
+const VERSION_SHA: &str = env!("VERSION_SHA");
+const VERSION_BUILD_DATE: &str = env!("VERSION_BUILD_DATE");
+
+#[derive(Clone, Debug)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    // Empty for release builds, otherwise e.g. "dev"
+    pub anno: &'static str,
+    // Short git sha, empty if unknown
+    pub git_sha: &'static str,
+    // "YYYY-MM-DD" in UTC
+    pub build_date: &'static str,
+    pub features: Vec<&'static str>,
+    pub debug: bool,
+}
+
+pub fn build_info() -> BuildInfo {
+    let mut features = Vec::new();
+    if cfg!(feature = "tracing") {
+        features.push("tracing");
+    }
+    if cfg!(feature = "dep-only") {
+        features.push("dep-only");
+    }
+    BuildInfo {
+        version: VERSION,
+        anno: VERSION_ANNO,
+        git_sha: VERSION_SHA,
+        build_date: VERSION_BUILD_DATE,
+        features,
+        debug: cfg!(debug_assertions),
+    }
+}
+
+impl BuildInfo {
+    // Multiple lines of "key: value", for bug reports
+    pub fn verbose(&self) -> String {
+        let features = if self.features.is_empty() {
+            "none".to_owned()
+        } else {
+            self.features.join(",")
+        };
+        format!(
+            "version: {}\nanno: {}\ngit_sha: {}\nbuild_date: {}\nfeatures: {}\nprofile: {}",
+            self.version,
+            self.anno,
+            self.git_sha,
+            self.build_date,
+            features,
+            if self.debug { "debug" } else { "release" }
+        )
+    }
+}
+
+// Like "v0.1.1-dev (1a2b3c4)"
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.version)?;
+        if !self.anno.is_empty() {
+            write!(f, "-{}", self.anno)?;
+        }
+        if !self.git_sha.is_empty() {
+            write!(f, " ({})", self.git_sha)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,421 @@
+// A second GUI started while another instance owns mouse control, may open in view-only mode.
+// It shows devices of the running instance, and edits its settings through it. A request is a
+// YAML document over TCP on localhost, answered by another one. Port and a random token are
+// written to a file in the data dir of the user, so other users can't connect.
+
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{Error, ErrorReport},
+    message::{DeviceStatus, GenericDevice},
+    setting::{ProcessorSettings, Settings},
+    utils::from_json_or_yaml,
+};
+
+const PROTOCOL_VERSION: u32 = 1;
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+const MAX_REPLY_BYTES: u64 = 4 * 1024 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(3);
+// Running instance may be busy, e.g. scanning devices
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+// How often the server checks whether it is stopped, while no request comes
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewEndpoint {
+    pub port: u16,
+    pub token: String,
+}
+
+impl ViewEndpoint {
+    // NotRunning if no instance has written it
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).map_err(|_| Error::NotRunning)?;
+        serde_yaml::from_str(&content).map_err(|e| Error::InvalidConfigFile(e.to_string()))
+    }
+
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let content = serde_yaml::to_string(self)
+            .map_err(|e| Error::InvalidParam("endpoint".to_owned(), e.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::IO)?;
+        }
+        fs::write(path, content).map_err(Error::IO)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ViewRequest {
+    Snapshot,
+    // Applied by the running instance, not saved
+    Apply(Box<ProcessorSettings>),
+    // Applied and saved by the running instance
+    Save(Box<Settings>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ViewSnapshot {
+    pub settings: Settings,
+    pub devices: Vec<(GenericDevice, DeviceStatus)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ViewReply {
+    Snapshot(Box<ViewSnapshot>),
+    Done,
+    // With code and category, mapped back to an Error by the client
+    Error(ErrorReport),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ViewEnvelope {
+    version: u32,
+    token: String,
+    request: ViewRequest,
+}
+
+// Request received by the server, to be answered by the owner of settings
+#[derive(Debug)]
+pub struct ViewCall {
+    request: Option<ViewRequest>,
+    tx: Sender<ViewReply>,
+}
+
+impl ViewCall {
+    pub fn take_request(&mut self) -> ViewRequest {
+        self.request.take().unwrap()
+    }
+
+    pub fn reply(self, reply: ViewReply) {
+        let _ = self.tx.send(reply);
+    }
+}
+
+// Seeded randomly by std for each process, enough for a token only kept in the endpoint file
+fn random_token() -> String {
+    (0..2u32)
+        .map(|i| {
+            let mut h = RandomState::new().build_hasher();
+            h.write_u32(i);
+            format!("{:016x}", h.finish())
+        })
+        .collect()
+}
+
+// Not to tell how much of the token matched by timing
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn receive(stream: &mut TcpStream, token: &str) -> Result<ViewRequest, Error> {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let mut content = String::new();
+    stream
+        .take(MAX_REQUEST_BYTES)
+        .read_to_string(&mut content)
+        .map_err(Error::IO)?;
+    let envelope: ViewEnvelope =
+        serde_yaml::from_str(&content).map_err(|e| Error::ViewRejected(e.to_string()))?;
+    if envelope.version != PROTOCOL_VERSION {
+        return Err(Error::ViewRejected(format!(
+            "unsupported version {}",
+            envelope.version
+        )));
+    }
+    if !token_eq(&envelope.token, token) {
+        return Err(Error::ViewRejected("wrong token".to_owned()));
+    }
+    // Checked the same way as a local config, applied settings over defaults of the rest
+    let issues = match &envelope.request {
+        ViewRequest::Snapshot => Vec::new(),
+        ViewRequest::Apply(p) => Settings {
+            processor: (**p).clone(),
+            ..Default::default()
+        }
+        .validate(),
+        ViewRequest::Save(s) => s.validate(),
+    };
+    if let Some(issue) = issues.into_iter().next() {
+        return Err(Error::InvalidParam(issue.field, issue.message));
+    }
+    Ok(envelope.request)
+}
+
+// Answers a connection on its own thread, returns within IO_TIMEOUT and ANSWER_TIMEOUT
+fn serve(mut stream: TcpStream, token: &str, on_call: &dyn Fn(ViewCall)) {
+    if let Err(e) = stream.set_nonblocking(false) {
+        warn!("View request dropped: {}", e);
+        return;
+    }
+    let reply = match receive(&mut stream, token) {
+        Ok(request) => {
+            let (tx, rx) = channel();
+            on_call(ViewCall {
+                request: Some(request),
+                tx,
+            });
+            rx.recv_timeout(ANSWER_TIMEOUT).unwrap_or_else(|_| {
+                let e = Error::ViewRejected("not answered".to_owned());
+                ViewReply::Error(ErrorReport::from(&e))
+            })
+        }
+        Err(e) => {
+            warn!("View request rejected: {}", e);
+            ViewReply::Error(ErrorReport::from(&e))
+        }
+    };
+    match serde_yaml::to_string(&reply) {
+        Ok(content) => {
+            let _ = stream.write_all(content.as_bytes());
+        }
+        Err(e) => warn!("Cannot serialize view reply: {}", e),
+    }
+}
+
+// Answers view-only windows in a background thread, until dropped
+pub struct ViewServer {
+    endpoint_file: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ViewServer {
+    // Each connection is served by its own thread, so one stalled client doesn't hold up others
+    pub fn start(
+        endpoint_file: &Path,
+        on_call: impl Fn(ViewCall) + Send + Sync + 'static,
+    ) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(Error::IO)?;
+        // Polled, so that stopping doesn't depend on waking a blocking accept
+        listener.set_nonblocking(true).map_err(Error::IO)?;
+        let endpoint = ViewEndpoint {
+            port: listener.local_addr().map_err(Error::IO)?.port(),
+            token: random_token(),
+        };
+        endpoint.write(endpoint_file)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop1 = stop.clone();
+        let token = endpoint.token;
+        let on_call = Arc::new(on_call);
+        let spawned = std::thread::Builder::new()
+            .name("view_server".to_owned())
+            .spawn(move || {
+                while !stop1.load(Ordering::Relaxed) {
+                    let stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("Accept view request failed: {}", e);
+                            std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                            continue;
+                        }
+                    };
+                    let token = token.clone();
+                    let on_call = on_call.clone();
+                    let spawned = std::thread::Builder::new()
+                        .name("view_call".to_owned())
+                        .spawn(move || serve(stream, &token, &*on_call));
+                    if let Err(e) = spawned {
+                        warn!("View request dropped: {}", e);
+                    }
+                }
+            });
+        let thread = match spawned {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = fs::remove_file(endpoint_file);
+                return Err(Error::IO(e));
+            }
+        };
+        Ok(ViewServer {
+            endpoint_file: endpoint_file.to_path_buf(),
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ViewServer {
+    // Returns within ACCEPT_POLL_INTERVAL, calls being answered are left to finish
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.endpoint_file);
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ViewClient {
+    endpoint: ViewEndpoint,
+}
+
+impl ViewClient {
+    // Returns with the first snapshot, as a check that the running instance answers
+    pub fn connect(endpoint_file: &Path) -> Result<(Self, ViewSnapshot), Error> {
+        let client = ViewClient {
+            endpoint: ViewEndpoint::read(endpoint_file)?,
+        };
+        let snapshot = client.snapshot()?;
+        Ok((client, snapshot))
+    }
+
+    pub fn snapshot(&self) -> Result<ViewSnapshot, Error> {
+        match self.call(ViewRequest::Snapshot)? {
+            ViewReply::Snapshot(s) => Ok(*s),
+            r => Err(Error::ViewRejected(format!("unexpected reply {:?}", r))),
+        }
+    }
+
+    pub fn apply(&self, settings: ProcessorSettings) -> Result<(), Error> {
+        self.call_done(ViewRequest::Apply(Box::new(settings)))
+    }
+
+    pub fn save(&self, settings: Settings) -> Result<(), Error> {
+        self.call_done(ViewRequest::Save(Box::new(settings)))
+    }
+
+    fn call_done(&self, request: ViewRequest) -> Result<(), Error> {
+        match self.call(request)? {
+            ViewReply::Done => Ok(()),
+            r => Err(Error::ViewRejected(format!("unexpected reply {:?}", r))),
+        }
+    }
+
+    fn call(&self, request: ViewRequest) -> Result<ViewReply, Error> {
+        let content = serde_yaml::to_string(&ViewEnvelope {
+            version: PROTOCOL_VERSION,
+            token: self.endpoint.token.clone(),
+            request,
+        })
+        .map_err(|e| Error::InvalidParam("request".to_owned(), e.to_string()))?;
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], self.endpoint.port));
+        let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT).map_err(Error::IO)?;
+        stream
+            .set_read_timeout(Some(ANSWER_TIMEOUT + IO_TIMEOUT))
+            .map_err(Error::IO)?;
+        stream
+            .set_write_timeout(Some(IO_TIMEOUT))
+            .map_err(Error::IO)?;
+        stream.write_all(content.as_bytes()).map_err(Error::IO)?;
+        stream.shutdown(Shutdown::Write).map_err(Error::IO)?;
+
+        let mut reply = String::new();
+        stream
+            .take(MAX_REPLY_BYTES)
+            .read_to_string(&mut reply)
+            .map_err(Error::IO)?;
+        match from_json_or_yaml(&reply) {
+            Ok(ViewReply::Error(e)) => Err(e.into()),
+            Ok(r) => Ok(r),
+            Err(e) => Err(Error::ViewRejected(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{device_type::DeviceType, errors::ErrorCategory};
+
+    #[test]
+    fn test_view_call() {
+        let dir = crate::utils::test_temp_dir("view_ipc");
+        let endpoint_file = dir.join("monmouse_view.yml");
+        assert!(matches!(
+            ViewClient::connect(&endpoint_file),
+            Err(Error::NotRunning)
+        ));
+
+        let (tx, rx) = channel();
+        let server = ViewServer::start(&endpoint_file, move |mut call| {
+            let reply = match call.take_request() {
+                ViewRequest::Snapshot => ViewReply::Snapshot(Box::new(ViewSnapshot {
+                    settings: Settings::default(),
+                    devices: vec![(GenericDevice::id_only("pen".to_owned()), DeviceStatus::Idle)],
+                })),
+                ViewRequest::Apply(p) => {
+                    let _ = tx.send(p.turbo_speed_percent);
+                    ViewReply::Done
+                }
+                ViewRequest::Save(_) => {
+                    let e = Error::IO(std::io::Error::other("read-only disk"));
+                    ViewReply::Error(ErrorReport::from(&e))
+                }
+            };
+            call.reply(reply);
+        })
+        .unwrap();
+
+        // Not holding up others while sending nothing
+        let port = ViewEndpoint::read(&endpoint_file).unwrap().port;
+        let _stalled = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let started = std::time::Instant::now();
+        let (client, snapshot) = ViewClient::connect(&endpoint_file).unwrap();
+        assert!(started.elapsed() < IO_TIMEOUT);
+        assert_eq!(snapshot.devices.len(), 1);
+        assert!(matches!(
+            snapshot.devices[0].0.device_type,
+            DeviceType::Unknown
+        ));
+        client
+            .apply(ProcessorSettings {
+                turbo_speed_percent: 300,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(rx.recv_timeout(IO_TIMEOUT).unwrap(), 300);
+        // Never passed to the owner
+        let e = client
+            .apply(ProcessorSettings {
+                turbo_speed_percent: 5,
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert_eq!(e.code(), "invalid-param");
+        assert!(rx.try_recv().is_err());
+        let e = client.save(Settings::default()).unwrap_err();
+        assert_eq!(e.code(), "io");
+        assert_eq!(e.category(), ErrorCategory::IO);
+        assert!(e.to_string().contains("read-only disk"));
+
+        let wrong = ViewClient {
+            endpoint: ViewEndpoint {
+                token: "wrong".to_owned(),
+                ..client.endpoint.clone()
+            },
+        };
+        assert_eq!(wrong.snapshot().unwrap_err().code(), "view-rejected");
+
+        drop(server);
+        assert!(!endpoint_file.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,162 @@
+// Battery levels of wireless devices. Queried periodically apart from device scanning, in a
+// thread of its own, since reading HID reports from some devices may be slow and must not
+// hold the hook of the eventloop.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+use log::debug;
+
+use crate::errors::Result;
+
+use super::win_processor::WinDevice;
+use super::wintypes::WString;
+use super::winwrap::device_query_hid_battery;
+
+// Failed queries in a row before a device is taken as not reporting battery, since reads may
+// fail for a while, e.g. when it wakes up
+const MAX_FAILURES: u32 = 3;
+
+type QueryResults = Vec<(isize, Result<Option<u8>>)>;
+
+pub struct BatteryProvider {
+    interval_ms: u64,
+    last_tick: u64,
+    levels: HashMap<isize, u8>,
+    failures: HashMap<isize, u32>,
+    // Devices not reporting battery, skipped in later queries
+    unsupported: HashSet<isize>,
+    // Results of the query running, one at a time
+    querying: Option<Receiver<QueryResults>>,
+}
+
+impl Default for BatteryProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatteryProvider {
+    pub fn new() -> Self {
+        BatteryProvider {
+            interval_ms: 0,
+            last_tick: 0,
+            levels: HashMap::new(),
+            failures: HashMap::new(),
+            unsupported: HashSet::new(),
+            querying: None,
+        }
+    }
+
+    // 0 to disable
+    pub fn set_interval_secs(&mut self, secs: u32) {
+        self.interval_ms = secs as u64 * 1000;
+        self.last_tick = 0;
+    }
+
+    // Handles may be reused by new devices after devices changed
+    pub fn reset(&mut self) {
+        self.levels.clear();
+        self.failures.clear();
+        self.unsupported.clear();
+        self.last_tick = 0;
+        // Results of the running one are for old devices
+        self.querying = None;
+    }
+
+    pub fn forget(&mut self, handle: isize) {
+        self.levels.remove(&handle);
+        self.failures.remove(&handle);
+        self.unsupported.remove(&handle);
+    }
+
+    // Returns levels of all devices by id, if a query finished and anything changed. Queries
+    // are started when due, and never waited for.
+    pub fn poll<'a>(
+        &mut self,
+        devices: impl Iterator<Item = &'a WinDevice>,
+        tick: u64,
+    ) -> Option<Vec<(String, u8)>> {
+        if self.interval_ms == 0 {
+            self.querying = None;
+            return None;
+        }
+        if let Some(rx) = &self.querying {
+            match rx.try_recv() {
+                Ok(results) => {
+                    self.querying = None;
+                    return self.update(results, devices);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => self.querying = None,
+            }
+        }
+        if self.last_tick > 0 && tick < self.last_tick + self.interval_ms {
+            return None;
+        }
+        self.last_tick = tick;
+
+        let targets: Vec<(isize, WString)> = devices
+            .filter(|dev| dev.id.is_some() && !self.unsupported.contains(&dev.handle.0))
+            .filter_map(|dev| Some((dev.handle.0, dev.rawinput.as_ref()?.iface.clone())))
+            .collect();
+        if targets.is_empty() {
+            return None;
+        }
+        let (tx, rx) = channel();
+        let spawned = std::thread::Builder::new()
+            .name("battery_query".to_owned())
+            .spawn(move || {
+                let results = targets
+                    .into_iter()
+                    .map(|(handle, iface)| (handle, device_query_hid_battery(&iface)))
+                    .collect();
+                let _ = tx.send(results);
+            });
+        match spawned {
+            Ok(_) => self.querying = Some(rx),
+            Err(e) => debug!("Start querying battery failed: {}", e),
+        }
+        None
+    }
+
+    fn update<'a>(
+        &mut self,
+        results: QueryResults,
+        devices: impl Iterator<Item = &'a WinDevice>,
+    ) -> Option<Vec<(String, u8)>> {
+        // Devices may have changed while querying
+        let ids: Vec<(isize, &String)> = devices
+            .filter_map(|dev| Some((dev.handle.0, dev.id.as_ref()?)))
+            .collect();
+        let mut changed = false;
+        for (handle, result) in results {
+            let Some((_, id)) = ids.iter().find(|(h, _)| *h == handle) else {
+                continue;
+            };
+            match result {
+                Ok(Some(level)) => {
+                    self.failures.remove(&handle);
+                    changed |= self.levels.insert(handle, level) != Some(level);
+                }
+                Ok(None) => {
+                    self.unsupported.insert(handle);
+                }
+                Err(e) => {
+                    debug!("Query battery of device {} failed: {}", id, e);
+                    let failures = self.failures.entry(handle).or_insert(0);
+                    *failures += 1;
+                    if *failures >= MAX_FAILURES {
+                        self.unsupported.insert(handle);
+                        changed |= self.levels.remove(&handle).is_some();
+                    }
+                }
+            }
+        }
+        changed.then(|| {
+            ids.iter()
+                .filter_map(|(handle, id)| Some(((*id).clone(), *self.levels.get(handle)?)))
+                .collect()
+        })
+    }
+}
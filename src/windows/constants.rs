@@ -1,15 +1,52 @@
 use windows::core::HRESULT;
+use windows::Win32::UI::WindowsAndMessaging::WM_APP;
+
+use crate::setting::ProcessorSettings;
 
 pub const STR_INVALID_WIN_WIDE_OS_STR: &str = "InvalidWinWideOsStr";
 
-pub const RATELIMIT_UPDATE_MONITOR_ONCE_MS: u64 = 1000;
 pub const RATELIMIT_UPDATE_DEVICE_ONCE_MS: u64 = 1000;
 pub const MOUSE_EVENT_ACTIVE_LAST_FOR_MS: u64 = 100;
+pub const DEVICE_INFO_RETRY_BASE_MS: u64 = 200;
+pub const DEVICE_INFO_RETRY_MAX_TIMES: u32 = 5;
 
-pub const WIN_EVENTLOOP_POLL_MAX_MESSAGES: u32 = 20;
-pub const WIN_EVENTLOOP_POLL_WAIT_TIMEOUT_MS: u32 = 20;
+pub const WIN_EVENTLOOP_BUSY_LAST_FOR_MS: u64 = 1000;
+#[deprecated(note = "configured by processor.poll_max_messages")]
+pub const WIN_EVENTLOOP_POLL_MAX_MESSAGES: u32 = ProcessorSettings::default_poll_max_messages();
+#[deprecated(note = "configured by processor.poll_busy_timeout_ms")]
+pub const WIN_EVENTLOOP_POLL_WAIT_TIMEOUT_MS: u32 =
+    ProcessorSettings::default_poll_busy_timeout_ms();
 pub const RAWINPUT_MSG_INIT_BUF_SIZE: u32 = 1024;
 pub const RAWINPUT_MOUSE_FLAGS_ABSOLUTE: u16 = 1;
+pub const TRAY_CLICK_SUBCLASS_UID: usize = 12596;
+pub const DEBUG_OVERLAY_SUBCLASS_UID: usize = 12597;
 pub const SUBCLASS_UID: usize = 12598;
+pub const OVERLAY_SUBCLASS_UID: usize = 12599;
+// Plus index of dimmed monitor
+pub const FOCUS_DIM_SUBCLASS_UID: usize = 12600;
+pub const OVERLAY_JUMP_ANIMATE_MS: u64 = 250;
+pub const DEBUG_OVERLAY_MARK_MS: u64 = 600;
+pub const IDENTIFY_DEVICE_TIMEOUT_MS: u64 = 10000;
+// Rescans after startup, with doubled intervals
+pub const STARTUP_RESCAN_FIRST_MS: u64 = 1000;
+pub const STARTUP_RESCAN_WITHIN_MS: u64 = 60000;
+// Shortcuts taken by other programs are registered again, with doubled intervals up to max
+pub const SHORTCUT_RETRY_FIRST_MS: u64 = 2000;
+pub const SHORTCUT_RETRY_MAX_MS: u64 = 60000;
+// Mouse hook failed to install is installed again while polling, likewise
+pub const HOOK_RETRY_FIRST_MS: u64 = 5000;
+pub const HOOK_RETRY_MAX_MS: u64 = 300000;
+// Listing processes is costly, so it is done less often than checking cursor
+pub const KVM_RESCAN_PROCESSES_MS: u64 = 5000;
+pub const KVM_CHECK_REMOTE_MS: u64 = 500;
+pub const FOREGROUND_CHECK_MS: u64 = 250;
+// Input events waiting longer in queue are counted late. Times of messages are system ticks
+// of about 15ms, so it spans two of them.
+pub const LATE_INPUT_EVENT_MS: u32 = 32;
+pub const MMCSS_TASK_NAME: &str = "Games";
+// Eventloop window is found by its title, and actions are posted to it by other processes
+pub const EVENTLOOP_WINDOW_CLASS: &str = "Static";
+pub const EVENTLOOP_WINDOW_TITLE: &str = "MonMouseEventloop";
+pub const WM_REMOTE_ACTION: u32 = WM_APP + 1;
 
 pub const HRESULT_SHORTCUT_CONFLICT: HRESULT = HRESULT(0x80070581u32 as i32);
@@ -0,0 +1,79 @@
+// Replaces system cursor while some devices are active, e.g. a bigger one for touchscreen
+// or a crosshair for pen. Original cursors are reloaded from user's scheme when restored.
+
+use log::{debug, warn};
+use windows::Win32::UI::WindowsAndMessaging::{
+    OCR_CROSS, OCR_HAND, OCR_IBEAM, OCR_NORMAL, SYSTEM_CURSOR_ID,
+};
+
+use crate::errors::Result;
+use crate::setting::CursorStyle;
+
+use super::winwrap::{
+    load_system_cursor, reload_system_cursors, set_system_cursor, system_cursor_size,
+};
+
+// Cursors scaled by Large and ExtraLarge
+const SCALED_CURSORS: [SYSTEM_CURSOR_ID; 3] = [OCR_NORMAL, OCR_IBEAM, OCR_HAND];
+
+#[derive(Default)]
+pub struct CursorStyler {
+    applied: Option<CursorStyle>,
+}
+
+impl CursorStyler {
+    // Cursors may be left replaced by a run which didn't exit normally, e.g. killed
+    pub fn new() -> Self {
+        restore_system_cursors();
+        Self::default()
+    }
+
+    // None restores system cursors. Nothing is done if the style is already applied
+    pub fn set(&mut self, style: Option<CursorStyle>) {
+        if style == self.applied {
+            return;
+        }
+        debug!("Cursor style changed: {:?} -> {:?}", self.applied, style);
+        // Styles replace different cursors, so restore before applying another one
+        let result = reload_system_cursors().and_then(|_| match style {
+            Some(s) => apply(s),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            warn!("Set cursor style {:?} failed: {}", style, e);
+        }
+        // Not retried on failure, it would fail again on every event
+        self.applied = style;
+    }
+}
+
+impl Drop for CursorStyler {
+    fn drop(&mut self) {
+        self.set(None);
+    }
+}
+
+// Reloads cursors whatever is applied, e.g. from a panic hook where no styler is at hand
+pub fn restore_system_cursors() {
+    if let Err(e) = reload_system_cursors() {
+        warn!("Restore system cursors failed: {}", e);
+    }
+}
+
+fn apply(style: CursorStyle) -> Result<()> {
+    let size = system_cursor_size();
+    match style {
+        CursorStyle::Large => scale_cursors(size * 3 / 2),
+        CursorStyle::ExtraLarge => scale_cursors(size * 2),
+        CursorStyle::Precision => {
+            set_system_cursor(load_system_cursor(OCR_CROSS, size)?, OCR_NORMAL)
+        }
+    }
+}
+
+fn scale_cursors(size: i32) -> Result<()> {
+    for id in SCALED_CURSORS {
+        set_system_cursor(load_system_cursor(id, size)?, id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+// Optional ETW provider for profiling input latency. Only built with feature "tracing",
+// and registered while processor.etw_tracing is on. Each span is written as a string
+// event like "wm_input 35us", which can be collected by:
+//   logman start monmouse -p {31EB1A20-476F-4577-B703-B02D7351D67D} 0 5 -ets
+//   logman stop monmouse -ets
+
+#[derive(Clone, Copy, Debug)]
+pub enum SpanKind {
+    HookCallback,
+    WmInput,
+    Relocation,
+}
+
+impl SpanKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HookCallback => "hook_callback",
+            Self::WmInput => "wm_input",
+            Self::Relocation => "relocation",
+        }
+    }
+}
+
+// Writes an event with the elapsed time when dropped
+pub struct Span {
+    #[cfg(feature = "tracing")]
+    inner: Option<(SpanKind, std::time::Instant)>,
+}
+
+#[cfg(feature = "tracing")]
+mod provider {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use windows::core::GUID;
+    use windows::Win32::Foundation::WIN32_ERROR;
+    use windows::Win32::System::Diagnostics::Etw::{
+        EventProviderEnabled, EventRegister, EventUnregister, EventWriteString, TRACE_LEVEL_VERBOSE,
+    };
+
+    use crate::errors::Result;
+    use crate::windows::wintypes::{core_error, WString};
+
+    use super::{Span, SpanKind};
+
+    const PROVIDER_GUID: GUID = GUID::from_u128(0x31eb1a20_476f_4577_b703_b02d7351d67d);
+    const LEVEL: u8 = TRACE_LEVEL_VERBOSE as u8;
+
+    // 0 if not registered. Read by hook callback, so it is kept global.
+    static REG_HANDLE: AtomicU64 = AtomicU64::new(0);
+
+    pub fn set_enabled(enabled: bool) -> Result<()> {
+        let handle = REG_HANDLE.load(Ordering::Acquire);
+        if enabled == (handle != 0) {
+            return Ok(());
+        }
+        if enabled {
+            let mut handle = 0u64;
+            let rc = unsafe { EventRegister(&PROVIDER_GUID, None, None, &mut handle) };
+            WIN32_ERROR(rc).ok().map_err(core_error)?;
+            REG_HANDLE.store(handle, Ordering::Release);
+        } else {
+            REG_HANDLE.store(0, Ordering::Release);
+            let rc = unsafe { EventUnregister(handle) };
+            WIN32_ERROR(rc).ok().map_err(core_error)?;
+        }
+        Ok(())
+    }
+
+    pub fn span(kind: SpanKind) -> Span {
+        let handle = REG_HANDLE.load(Ordering::Relaxed);
+        // Skip timing at all, if no trace session is listening
+        let listening = handle != 0 && unsafe { EventProviderEnabled(handle, LEVEL, 0) }.as_bool();
+        Span {
+            inner: listening.then(|| (kind, std::time::Instant::now())),
+        }
+    }
+
+    impl Drop for Span {
+        fn drop(&mut self) {
+            let Some((kind, start)) = self.inner else {
+                return;
+            };
+            let handle = REG_HANDLE.load(Ordering::Relaxed);
+            if handle == 0 {
+                return;
+            }
+            let text = format!("{} {}us", kind.name(), start.elapsed().as_micros());
+            unsafe {
+                EventWriteString(
+                    handle,
+                    LEVEL,
+                    0,
+                    WString::encode_from_str(&text).as_pcwstr(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub use provider::{set_enabled, span};
+
+#[cfg(not(feature = "tracing"))]
+pub fn set_enabled(enabled: bool) -> crate::errors::Result<()> {
+    if enabled {
+        log::warn!("ETW tracing is not built in, requires feature \"tracing\"");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub fn span(_kind: SpanKind) -> Span {
+    Span {}
+}
@@ -0,0 +1,117 @@
+use log::{debug, warn};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateSolidBrush, DeleteObject, EndPaint, FillRect, PAINTSTRUCT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{WM_ERASEBKGND, WM_PAINT};
+
+use crate::mouse_control::MonitorArea;
+
+use super::constants::*;
+use super::winwrap::*;
+
+// Black windows over monitors other than the focused one. Each window is subclassed with
+// FOCUS_DIM_SUBCLASS_UID plus its index, to be found when painting.
+pub struct MonitorDimmer {
+    windows: Vec<HWND>,
+    focused: Option<MonitorArea>,
+}
+
+impl Default for MonitorDimmer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MonitorDimmer {
+    pub fn new() -> Self {
+        MonitorDimmer {
+            windows: Vec::new(),
+            focused: None,
+        }
+    }
+
+    pub fn focused(&self) -> Option<&MonitorArea> {
+        self.focused.as_ref()
+    }
+
+    // Should not move self while shown
+    pub fn show(&mut self, monitors: &[MonitorArea], focused: &MonitorArea, dim_percent: u32) {
+        self.hide();
+        let alpha = (dim_percent.min(100) * 255 / 100) as u8;
+        for area in monitors.iter().filter(|&a| a != focused) {
+            let hwnd = match create_translucent_window(None, alpha) {
+                Ok((_, v)) => v,
+                Err(e) => {
+                    warn!("Create dimming window failed: {}", e);
+                    continue;
+                }
+            };
+            let uid = FOCUS_DIM_SUBCLASS_UID + self.windows.len();
+            if let Err(e) = set_subclass(hwnd, uid, Some(self)) {
+                warn!("Set subclass of dimming window failed: {}", e);
+                let _ = destroy_window(hwnd);
+                continue;
+            }
+            self.windows.push(hwnd);
+            if let Err(e) = show_window_topmost(
+                hwnd,
+                area.lefttop.x,
+                area.lefttop.y,
+                area.rigtbtm.x - area.lefttop.x,
+                area.rigtbtm.y - area.lefttop.y,
+            ) {
+                warn!("Show dimming window failed: {}", e);
+            }
+        }
+        debug!(
+            "Focus mode on {:?}, {} monitors dimmed",
+            focused,
+            self.windows.len()
+        );
+        self.focused = Some(*focused);
+    }
+
+    pub fn hide(&mut self) {
+        for (i, hwnd) in self.windows.drain(..).enumerate() {
+            let _ = remove_subclass::<Self>(hwnd, FOCUS_DIM_SUBCLASS_UID + i);
+            let _ = destroy_window(hwnd);
+        }
+        if self.focused.take().is_some() {
+            debug!("Focus mode off");
+        }
+    }
+
+    fn paint(&self, hwnd: HWND) {
+        let mut ps = PAINTSTRUCT::default();
+        unsafe {
+            let hdc = BeginPaint(hwnd, &mut ps);
+            let brush = CreateSolidBrush(COLORREF(0));
+            FillRect(hdc, &ps.rcPaint as *const RECT, brush);
+            DeleteObject(brush);
+            EndPaint(hwnd, &ps);
+        }
+    }
+}
+
+impl Drop for MonitorDimmer {
+    fn drop(&mut self) {
+        self.hide();
+    }
+}
+
+impl SubclassHandler for MonitorDimmer {
+    fn subclass_callback(&mut self, umsg: u32, _wp: WPARAM, _lp: LPARAM, uid: usize) -> bool {
+        match umsg {
+            WM_PAINT => {
+                if let Some(&hwnd) = self.windows.get(uid - FOCUS_DIM_SUBCLASS_UID) {
+                    self.paint(hwnd);
+                }
+                false
+            }
+            // Whole client area is filled in WM_PAINT
+            WM_ERASEBKGND => false,
+            _ => true,
+        }
+    }
+}
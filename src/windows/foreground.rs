@@ -0,0 +1,46 @@
+// Watches which program is in foreground, for processor.app_rules. Polled like the KVM
+// watcher, since switching programs by user is slow compared to the eventloop.
+
+use log::debug;
+
+use super::constants::*;
+use super::winwrap::foreground_process_name;
+
+#[derive(Default)]
+pub struct ForegroundWatcher {
+    app: Option<String>,
+    last_check_tick: Option<u64>,
+}
+
+impl ForegroundWatcher {
+    // Returns executable name of the program newly in foreground, or None if no program is
+    // known to be there, e.g. desktop locked or an elevated program not opened
+    pub fn poll(&mut self, tick: u64) -> Option<Option<String>> {
+        if self
+            .last_check_tick
+            .is_some_and(|t| tick < t + FOREGROUND_CHECK_MS)
+        {
+            return None;
+        }
+        self.last_check_tick = Some(tick);
+        let app = match foreground_process_name() {
+            Ok(v) => v,
+            Err(e) => {
+                if self.app.is_some() {
+                    debug!("Foreground program unknown: {}", e);
+                }
+                None
+            }
+        };
+        if app == self.app {
+            return None;
+        }
+        self.app = app.clone();
+        Some(app)
+    }
+
+    // The program is reported again once watched again
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
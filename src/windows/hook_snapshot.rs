@@ -0,0 +1,31 @@
+// State read by the low-level mouse hook. Processor replaces the snapshot as a whole on
+// every update, so reading it never waits on a lock, even from another thread.
+
+use std::sync::Arc;
+
+use arc_swap::{ArcSwapOption, Guard};
+
+use crate::mouse_control::MonitorAreasList;
+use crate::setting::ProcessorSettings;
+
+#[derive(Clone, Default)]
+pub struct HookSnapshot {
+    pub settings: ProcessorSettings,
+    pub monitors: MonitorAreasList,
+}
+
+static SNAPSHOT: ArcSwapOption<HookSnapshot> = ArcSwapOption::const_empty();
+
+// None before the processor is initialized
+pub fn load() -> Guard<Option<Arc<HookSnapshot>>> {
+    SNAPSHOT.load()
+}
+
+// Only the processor writes, so copying the current one won't lose others' updates
+pub fn update(f: impl FnOnce(&mut HookSnapshot)) {
+    let mut snapshot = SNAPSHOT
+        .load_full()
+        .map_or_else(HookSnapshot::default, |s| (*s).clone());
+    f(&mut snapshot);
+    SNAPSHOT.store(Some(Arc::new(snapshot)));
+}
@@ -0,0 +1,82 @@
+// Detects software KVMs sending input to another machine, e.g. Mouse Without Borders or
+// Barrier. Either a KVM process is running and cursor is hidden by it, or a state file
+// written by KVM scripts says so.
+
+use log::{debug, warn};
+
+use crate::setting::KvmSettings;
+
+use super::constants::*;
+use super::winwrap::{is_cursor_hidden, running_process_names};
+
+#[derive(Default)]
+pub struct KvmWatcher {
+    remote: bool,
+    kvm_running: bool,
+    last_scan_tick: Option<u64>,
+    last_check_tick: Option<u64>,
+}
+
+fn due(last: Option<u64>, interval_ms: u64, tick: u64) -> bool {
+    last.is_none_or(|t| tick >= t + interval_ms)
+}
+
+impl KvmWatcher {
+    // Returns new state if changed
+    pub fn poll(&mut self, settings: &KvmSettings, tick: u64) -> Option<bool> {
+        let remote = if settings.pause_when_remote {
+            self.check(settings, tick)?
+        } else {
+            self.last_scan_tick = None;
+            false
+        };
+        if remote == self.remote {
+            return None;
+        }
+        self.remote = remote;
+        Some(remote)
+    }
+
+    fn check(&mut self, settings: &KvmSettings, tick: u64) -> Option<bool> {
+        if !due(self.last_check_tick, KVM_CHECK_REMOTE_MS, tick) {
+            return None;
+        }
+        self.last_check_tick = Some(tick);
+
+        if !settings.state_file.is_empty() {
+            // Missing file means local, since scripts may remove it
+            return Some(
+                std::fs::read_to_string(&settings.state_file)
+                    .is_ok_and(|s| s.trim().eq_ignore_ascii_case("remote")),
+            );
+        }
+
+        if due(self.last_scan_tick, KVM_RESCAN_PROCESSES_MS, tick) {
+            self.last_scan_tick = Some(tick);
+            let names = settings.process_list();
+            let running = match running_process_names() {
+                Ok(running) => running
+                    .iter()
+                    .any(|r| names.iter().any(|n| r.eq_ignore_ascii_case(n))),
+                Err(e) => {
+                    warn!("List processes failed: {}", e);
+                    false
+                }
+            };
+            if running != self.kvm_running {
+                debug!("Software KVM running: {}", running);
+                self.kvm_running = running;
+            }
+        }
+        if !self.kvm_running {
+            return Some(false);
+        }
+        match is_cursor_hidden() {
+            Ok(hidden) => Some(hidden),
+            Err(e) => {
+                warn!("Get cursor info failed: {}", e);
+                None
+            }
+        }
+    }
+}
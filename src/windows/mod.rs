@@ -1,5 +1,16 @@
+pub mod battery;
 pub mod constants;
+pub mod cursor_style;
+pub mod etw;
+pub mod focus_dim;
+pub mod foreground;
+pub mod hook_snapshot;
+pub mod kvm;
+pub mod overlay;
+pub mod tray_click;
+pub mod watchdog;
 pub mod win_processor;
+pub mod window_ops;
 pub mod wintypes;
 pub mod winwrap;
 
@@ -11,6 +22,9 @@ use self::{
     winwrap::{close_handle, create_mutex, release_mutex, try_lock_mutex},
 };
 
+// Held while running, released by exiting normally, or abandoned by crashing
+pub const SINGLE_PROCESS_MUTEX: &str = "Global\\MonmouseSingleProcessMutex";
+
 #[derive(Debug)]
 pub struct SingleProcess {
     handle: HANDLE,
@@ -18,7 +32,7 @@ pub struct SingleProcess {
 
 impl SingleProcess {
     pub fn create() -> Result<Self, Error> {
-        Self::new("Global\\MonmouseSingleProcessMutex")
+        Self::new(SINGLE_PROCESS_MUTEX)
     }
 
     fn new(mutex_name: &str) -> Result<Self, Error> {
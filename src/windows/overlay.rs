@@ -0,0 +1,324 @@
+use log::{debug, warn};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateSolidBrush, DeleteObject, Ellipse, EndPaint, FillRect, GetStockObject,
+    SelectObject, NULL_PEN, PAINTSTRUCT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{WM_ERASEBKGND, WM_PAINT};
+
+use crate::errors::Result;
+use crate::mouse_control::{MonitorArea, MousePos};
+
+use super::constants::*;
+use super::winwrap::*;
+
+// Pixels in this color are fully transparent
+const COLOR_KEY: u32 = 0x00FF00FF;
+// BGR order
+const COLOR_TRAIL: u32 = 0x00D77800;
+const TRAIL_DOTS: i32 = 12;
+const TRAIL_DOT_RADIUS: i32 = 10;
+const COLOR_LOCKED: u32 = 0x0000A000;
+const COLOR_RELOCATION: u32 = 0x000000FF;
+const RELOCATION_MARK_RADIUS: i32 = 16;
+const DEBUG_OVERLAY_ALPHA: u8 = 96;
+
+struct JumpAnimation {
+    from: MousePos,
+    to: MousePos,
+    start_tick: u64,
+    progress: u64, // in percent
+}
+
+// A transparent window covering all monitors, showing the trail of cursor jumping
+pub struct JumpOverlay {
+    hwnd: HWND,
+    origin: MousePos,
+    anim: Option<JumpAnimation>,
+}
+
+impl Default for JumpOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JumpOverlay {
+    pub fn new() -> Self {
+        JumpOverlay {
+            hwnd: HWND::default(),
+            origin: MousePos::default(),
+            anim: None,
+        }
+    }
+
+    // Window is created at the first jump, should not move self since then
+    fn ensure_window(&mut self) -> Result<HWND> {
+        if self.hwnd.0 != 0 {
+            return Ok(self.hwnd);
+        }
+        let (_, hwnd) = create_overlay_window(None, COLOR_KEY)?;
+        if let Err(e) = set_subclass(hwnd, OVERLAY_SUBCLASS_UID, Some(self)) {
+            let _ = destroy_window(hwnd);
+            return Err(e);
+        }
+        self.hwnd = hwnd;
+        Ok(hwnd)
+    }
+
+    pub fn start(&mut self, area: &MonitorArea, from: MousePos, to: MousePos, tick: u64) {
+        let hwnd = match self.ensure_window() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Create overlay window failed: {}", e);
+                return;
+            }
+        };
+        self.origin = area.lefttop;
+        self.anim = Some(JumpAnimation {
+            from,
+            to,
+            start_tick: tick,
+            progress: 0,
+        });
+        debug!("Animate jumping from {} to {}", from, to);
+        if let Err(e) = show_window_topmost(
+            hwnd,
+            area.lefttop.x,
+            area.lefttop.y,
+            area.rigtbtm.x - area.lefttop.x,
+            area.rigtbtm.y - area.lefttop.y,
+        ) {
+            warn!("Show overlay window failed: {}", e);
+            self.anim = None;
+        }
+    }
+
+    // Advance the animation, returns whether it is still running
+    pub fn tick(&mut self, tick: u64) -> bool {
+        let Some(anim) = &mut self.anim else {
+            return false;
+        };
+        let elapsed = tick.saturating_sub(anim.start_tick);
+        if elapsed >= OVERLAY_JUMP_ANIMATE_MS {
+            self.anim = None;
+            hide_window(self.hwnd);
+            return false;
+        }
+        anim.progress = elapsed * 100 / OVERLAY_JUMP_ANIMATE_MS;
+        invalidate_window(self.hwnd);
+        true
+    }
+
+    pub fn destroy(&mut self) {
+        if self.hwnd.0 == 0 {
+            return;
+        }
+        let _ = remove_subclass::<Self>(self.hwnd, OVERLAY_SUBCLASS_UID);
+        let _ = destroy_window(self.hwnd);
+        self.hwnd = HWND::default();
+        self.anim = None;
+    }
+
+    fn paint(&self) {
+        let mut ps = PAINTSTRUCT::default();
+        unsafe {
+            let hdc = BeginPaint(self.hwnd, &mut ps);
+            let key_brush = CreateSolidBrush(COLORREF(COLOR_KEY));
+            FillRect(hdc, &ps.rcPaint as *const RECT, key_brush);
+            DeleteObject(key_brush);
+
+            if let Some(anim) = &self.anim {
+                let trail_brush = CreateSolidBrush(COLORREF(COLOR_TRAIL));
+                let old_brush = SelectObject(hdc, trail_brush);
+                let old_pen = SelectObject(hdc, GetStockObject(NULL_PEN));
+                let shown = (TRAIL_DOTS as u64 * anim.progress / 100) as i32 + 1;
+                for i in 0..shown.min(TRAIL_DOTS + 1) {
+                    let x = anim.from.x + (anim.to.x - anim.from.x) * i / TRAIL_DOTS;
+                    let y = anim.from.y + (anim.to.y - anim.from.y) * i / TRAIL_DOTS;
+                    // Dots grow towards the destination
+                    let r = TRAIL_DOT_RADIUS * (i + TRAIL_DOTS) / (2 * TRAIL_DOTS);
+                    let (cx, cy) = (x - self.origin.x, y - self.origin.y);
+                    Ellipse(hdc, cx - r, cy - r, cx + r, cy + r);
+                }
+                SelectObject(hdc, old_pen);
+                SelectObject(hdc, old_brush);
+                DeleteObject(trail_brush);
+            }
+            EndPaint(self.hwnd, &ps);
+        }
+    }
+}
+
+impl SubclassHandler for JumpOverlay {
+    fn subclass_callback(&mut self, umsg: u32, _wp: WPARAM, _lp: LPARAM, _class: usize) -> bool {
+        match umsg {
+            WM_PAINT => {
+                self.paint();
+                false
+            }
+            // Whole client area is filled in WM_PAINT
+            WM_ERASEBKGND => false,
+            _ => true,
+        }
+    }
+}
+
+// A translucent window covering all monitors, showing locked areas of devices and where the
+// cursor was relocated recently. For debugging cursor escaping or getting stuck.
+pub struct DebugOverlay {
+    hwnd: HWND,
+    shown: bool,
+    area: MonitorArea,
+    locked: Vec<MonitorArea>,
+    // Relocation targets, and when they happened
+    marks: Vec<(MousePos, u64)>,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        DebugOverlay {
+            hwnd: HWND::default(),
+            shown: false,
+            area: MonitorArea::default(),
+            locked: Vec::new(),
+            marks: Vec::new(),
+        }
+    }
+
+    // Window is created at the first update, should not move self since then
+    fn ensure_window(&mut self) -> Result<HWND> {
+        if self.hwnd.0 != 0 {
+            return Ok(self.hwnd);
+        }
+        let (_, hwnd) = create_translucent_overlay_window(None, COLOR_KEY, DEBUG_OVERLAY_ALPHA)?;
+        if let Err(e) = set_subclass(hwnd, DEBUG_OVERLAY_SUBCLASS_UID, Some(self)) {
+            let _ = destroy_window(hwnd);
+            return Err(e);
+        }
+        self.hwnd = hwnd;
+        Ok(hwnd)
+    }
+
+    // Returns whether relocation marks are still flashing
+    pub fn update(
+        &mut self,
+        area: &MonitorArea,
+        locked: Vec<MonitorArea>,
+        relocations: Vec<MousePos>,
+        tick: u64,
+    ) -> bool {
+        let marks_before = self.marks.len();
+        self.marks
+            .retain(|(_, t)| tick.saturating_sub(*t) < DEBUG_OVERLAY_MARK_MS);
+        let changed = self.marks.len() != marks_before
+            || !relocations.is_empty()
+            || locked != self.locked
+            || *area != self.area;
+        self.marks
+            .extend(relocations.into_iter().map(|pos| (pos, tick)));
+        if !changed {
+            return !self.marks.is_empty();
+        }
+        self.locked = locked;
+
+        if self.locked.is_empty() && self.marks.is_empty() {
+            if self.shown {
+                hide_window(self.hwnd);
+                self.shown = false;
+            }
+            return false;
+        }
+        let hwnd = match self.ensure_window() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Create debug overlay window failed: {}", e);
+                return false;
+            }
+        };
+        if !self.shown || *area != self.area {
+            self.area = *area;
+            if let Err(e) = show_window_topmost(
+                hwnd,
+                area.lefttop.x,
+                area.lefttop.y,
+                area.rigtbtm.x - area.lefttop.x,
+                area.rigtbtm.y - area.lefttop.y,
+            ) {
+                warn!("Show debug overlay window failed: {}", e);
+                return false;
+            }
+            self.shown = true;
+        }
+        invalidate_window(hwnd);
+        !self.marks.is_empty()
+    }
+
+    pub fn destroy(&mut self) {
+        if self.hwnd.0 == 0 {
+            return;
+        }
+        let _ = remove_subclass::<Self>(self.hwnd, DEBUG_OVERLAY_SUBCLASS_UID);
+        let _ = destroy_window(self.hwnd);
+        self.hwnd = HWND::default();
+        self.shown = false;
+        self.locked.clear();
+        self.marks.clear();
+    }
+
+    fn paint(&self) {
+        let mut ps = PAINTSTRUCT::default();
+        let origin = self.area.lefttop;
+        unsafe {
+            let hdc = BeginPaint(self.hwnd, &mut ps);
+            let key_brush = CreateSolidBrush(COLORREF(COLOR_KEY));
+            FillRect(hdc, &ps.rcPaint as *const RECT, key_brush);
+            DeleteObject(key_brush);
+
+            let locked_brush = CreateSolidBrush(COLORREF(COLOR_LOCKED));
+            for a in self.locked.iter() {
+                let rect = RECT {
+                    left: a.lefttop.x - origin.x,
+                    top: a.lefttop.y - origin.y,
+                    right: a.rigtbtm.x - origin.x,
+                    bottom: a.rigtbtm.y - origin.y,
+                };
+                FillRect(hdc, &rect as *const RECT, locked_brush);
+            }
+            DeleteObject(locked_brush);
+
+            let mark_brush = CreateSolidBrush(COLORREF(COLOR_RELOCATION));
+            let old_brush = SelectObject(hdc, mark_brush);
+            let old_pen = SelectObject(hdc, GetStockObject(NULL_PEN));
+            let r = RELOCATION_MARK_RADIUS;
+            for (pos, _) in self.marks.iter() {
+                let (cx, cy) = (pos.x - origin.x, pos.y - origin.y);
+                Ellipse(hdc, cx - r, cy - r, cx + r, cy + r);
+            }
+            SelectObject(hdc, old_pen);
+            SelectObject(hdc, old_brush);
+            DeleteObject(mark_brush);
+            EndPaint(self.hwnd, &ps);
+        }
+    }
+}
+
+impl SubclassHandler for DebugOverlay {
+    fn subclass_callback(&mut self, umsg: u32, _wp: WPARAM, _lp: LPARAM, _class: usize) -> bool {
+        match umsg {
+            WM_PAINT => {
+                self.paint();
+                false
+            }
+            // Whole client area is filled in WM_PAINT
+            WM_ERASEBKGND => false,
+            _ => true,
+        }
+    }
+}
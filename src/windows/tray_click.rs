@@ -0,0 +1,56 @@
+// Middle clicks on the tray icon, which tray-icon does not report. Its window is subclassed to
+// catch them from the notifications of the icon, so it must be created by the calling thread.
+
+use std::cell::Cell;
+
+use log::warn;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::WM_MBUTTONUP;
+
+use super::constants::TRAY_CLICK_SUBCLASS_UID;
+use super::winwrap::*;
+
+// Of the window created by tray-icon 0.11, and the message its icon notifies with
+const TRAY_WINDOW_CLASS: &str = "tray_icon_app";
+const WM_USER_TRAYICON: u32 = 6002;
+
+pub struct TrayMiddleClick {
+    hwnd: HWND,
+    clicked: Cell<bool>,
+}
+
+impl TrayMiddleClick {
+    // Boxed, its address is kept by the window subclass. None if tray icon is not found.
+    pub fn attach() -> Option<Box<Self>> {
+        let hwnd = find_thread_window(TRAY_WINDOW_CLASS)?;
+        let mut watcher = Box::new(TrayMiddleClick {
+            hwnd,
+            clicked: Cell::new(false),
+        });
+        if let Err(e) = set_subclass(hwnd, TRAY_CLICK_SUBCLASS_UID, Some(watcher.as_mut())) {
+            warn!("Watch middle clicks on tray icon failed: {}", e);
+            return None;
+        }
+        Some(watcher)
+    }
+
+    // Whether clicked since last taken
+    pub fn take(&self) -> bool {
+        self.clicked.replace(false)
+    }
+}
+
+impl SubclassHandler for TrayMiddleClick {
+    fn subclass_callback(&mut self, umsg: u32, _wp: WPARAM, lp: LPARAM, _uid: usize) -> bool {
+        if umsg == WM_USER_TRAYICON && lp.0 as u32 == WM_MBUTTONUP {
+            self.clicked.set(true);
+        }
+        true
+    }
+}
+
+impl Drop for TrayMiddleClick {
+    fn drop(&mut self) {
+        let _ = remove_subclass::<Self>(self.hwnd, TRAY_CLICK_SUBCLASS_UID);
+    }
+}
@@ -0,0 +1,70 @@
+// Starts MonMouse again after it crashes, from a tiny process of the same executable. The
+// single process mutex is held while it runs: released when it exits normally, abandoned
+// by Windows when it dies holding it.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+
+use crate::errors::{Error, Result};
+
+use super::wintypes::WString;
+use super::winwrap::{close_handle, open_mutex, release_mutex, wait_mutex};
+use super::SINGLE_PROCESS_MUTEX;
+
+// First argument of the watchdog process, followed by those of the watched one
+pub const WATCHDOG_ARG: &str = "--watchdog";
+
+// Crashing sooner after the watchdog started is taken as crashing in a loop, e.g. by a bad
+// config, so it is not started again
+const MIN_UPTIME: Duration = Duration::from_secs(30);
+
+// Arguments of the watched process, if this one is the watchdog
+pub fn watchdog_args() -> Option<Vec<String>> {
+    let mut args = std::env::args().skip(1);
+    match args.next() {
+        Some(a) if a == WATCHDOG_ARG => Some(args.collect()),
+        _ => None,
+    }
+}
+
+// Must be called after the single process mutex is owned, or the watchdog may own it first.
+// A new watchdog is spawned by each start, since the old one exits after restarting.
+pub fn spawn_watchdog() -> Result<()> {
+    let exe = std::env::current_exe().map_err(Error::IO)?;
+    Command::new(exe)
+        .arg(WATCHDOG_ARG)
+        .args(std::env::args().skip(1))
+        .spawn()
+        .map_err(Error::IO)?;
+    Ok(())
+}
+
+// Body of the watchdog process, returns once the watched one exits or is started again
+pub fn run_watchdog(args: &[String]) -> Result<()> {
+    let started = Instant::now();
+    let handle = open_mutex(WString::encode_from_str(SINGLE_PROCESS_MUTEX))?;
+    let abandoned = wait_mutex(handle);
+    if abandoned.is_ok() {
+        // Owned now, and the restarted one must own it
+        let _ = release_mutex(handle);
+    }
+    let _ = close_handle(handle);
+
+    if !abandoned? {
+        info!("MonMouse exited, watchdog stops");
+        return Ok(());
+    }
+    if started.elapsed() < MIN_UPTIME {
+        error!(
+            "MonMouse crashed within {}s, not started again",
+            MIN_UPTIME.as_secs()
+        );
+        return Ok(());
+    }
+    warn!("MonMouse crashed, starting it again");
+    let exe = std::env::current_exe().map_err(Error::IO)?;
+    Command::new(exe).args(args).spawn().map_err(Error::IO)?;
+    Ok(())
+}
@@ -1,51 +1,116 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::bridge::{BusEvent, Topic};
+use crate::config_writer::{ConfigWriter, SaveState};
+use crate::crash_report;
 use crate::device_type::DeviceType;
 use crate::device_type::WindowsRawinput;
 use crate::errors::Error;
 use crate::errors::Result;
-use crate::keyboard::key_windows::shortcut_str_to_win;
+use crate::event_command::{DeviceEvent, EventCommandRunner};
+use crate::geometry::{layout_changed_much, layout_sizes, Direction};
+use crate::gesture::{Gesture, GestureDetector};
+use crate::keyboard::key_windows::{shortcut_str_to_hook, shortcut_str_to_win};
+use crate::message::ActiveDeviceEvent;
 use crate::message::DeviceStatus;
+use crate::message::DiagnosticCheck;
 use crate::message::GenericDevice;
 use crate::message::Message;
 use crate::message::MouseControlReactor;
 use crate::message::Positioning;
 use crate::message::ShortcutID;
+use crate::message::TrackingMode;
+use crate::message::ORPHAN_DEVICE_ID_PREFIX;
 use crate::mouse_control::DeviceController;
 use crate::mouse_control::MonitorArea;
 use crate::mouse_control::MonitorAreasList;
 use crate::mouse_control::MousePos;
 use crate::mouse_control::MouseRelocator;
 use crate::mouse_control::RelocatePos;
+use crate::remote_action::RemoteAction;
+use crate::risk;
+use crate::setting::limits;
+use crate::setting::DeviceRuleAction;
 use crate::setting::DeviceSetting;
+use crate::setting::DeviceSettingItem;
 use crate::setting::ProcessorSettings;
+use crate::setting::RawinputUsage;
 use crate::setting::Settings;
-use crate::utils::SimpleRatelimit;
+use crate::setting::ShortcutBackend;
+use crate::setting::ShortcutSettings;
+use crate::setting::SwitchMode;
+use crate::setting::ThreadPriority;
+use crate::setting::TrayAction;
+use crate::setting::FAILSAFE_SHORTCUT;
+#[cfg(debug_assertions)]
+use crate::simulate::simulated_device_id;
+#[cfg(debug_assertions)]
+use crate::simulate::SimulatedDeviceSpec;
+use crate::stats::StatsAggregator;
+use crate::trace::EventTracer;
+use crate::utils::{wildcard_match, SimpleRatelimit};
 
 use core::cell::OnceCell;
-use log::{debug, error, trace, warn};
+use log::{debug, error, info, trace, warn};
+use windows::Win32::System::Threading::{
+    THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_NORMAL,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{HOT_KEY_MODIFIERS, VIRTUAL_KEY};
 use windows::Win32::UI::Input::RAWINPUTDEVICE;
 use windows::Win32::UI::Input::RIDEV_PAGEONLY;
 use windows::Win32::UI::WindowsAndMessaging::MsgWaitForMultipleObjects;
 use windows::Win32::UI::WindowsAndMessaging::PeekMessageW;
+use windows::Win32::UI::WindowsAndMessaging::GIDC_ARRIVAL;
+use windows::Win32::UI::WindowsAndMessaging::GIDC_REMOVAL;
+use windows::Win32::UI::WindowsAndMessaging::KBDLLHOOKSTRUCT;
+use windows::Win32::UI::WindowsAndMessaging::LLMHF_INJECTED;
 use windows::Win32::UI::WindowsAndMessaging::PM_REMOVE;
 use windows::Win32::UI::WindowsAndMessaging::QS_ALLINPUT;
 use windows::Win32::UI::WindowsAndMessaging::WM_DISPLAYCHANGE;
 use windows::Win32::UI::WindowsAndMessaging::WM_DPICHANGED;
 use windows::Win32::UI::WindowsAndMessaging::WM_HOTKEY;
 use windows::Win32::UI::WindowsAndMessaging::WM_INPUT_DEVICE_CHANGE;
+use windows::Win32::UI::WindowsAndMessaging::WM_LBUTTONDOWN;
+use windows::Win32::UI::WindowsAndMessaging::WM_LBUTTONUP;
+use windows::Win32::UI::WindowsAndMessaging::WM_MBUTTONDOWN;
+use windows::Win32::UI::WindowsAndMessaging::WM_MBUTTONUP;
+use windows::Win32::UI::WindowsAndMessaging::WM_MOUSEHWHEEL;
+use windows::Win32::UI::WindowsAndMessaging::WM_MOUSEMOVE;
+use windows::Win32::UI::WindowsAndMessaging::WM_MOUSEWHEEL;
+use windows::Win32::UI::WindowsAndMessaging::WM_RBUTTONDOWN;
+use windows::Win32::UI::WindowsAndMessaging::WM_RBUTTONUP;
+use windows::Win32::UI::WindowsAndMessaging::WM_WTSSESSION_CHANGE;
+use windows::Win32::UI::WindowsAndMessaging::WTS_CONSOLE_CONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_CONSOLE_DISCONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_REMOTE_CONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_REMOTE_DISCONNECT;
+use windows::Win32::UI::WindowsAndMessaging::XBUTTON1;
+use windows::Win32::UI::WindowsAndMessaging::{WM_KEYDOWN, WM_SYSKEYDOWN};
+use windows::Win32::UI::WindowsAndMessaging::{WM_XBUTTONDOWN, WM_XBUTTONUP};
 use windows::Win32::{
     Foundation::{HANDLE, HWND, LPARAM, WPARAM},
     UI::{
-        Input::{RAWINPUT, RAWINPUTDEVICELIST, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK},
+        Input::{RAWINPUT, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK, RIDEV_REMOVE},
         WindowsAndMessaging::{
             DispatchMessageW, TranslateMessage, HHOOK, MSG, MSLLHOOKSTRUCT, WM_INPUT, WM_QUIT,
         },
     },
 };
 
+use super::battery::BatteryProvider;
 use super::constants::*;
+use super::cursor_style::CursorStyler;
+use super::etw::{self, SpanKind};
+use super::focus_dim::MonitorDimmer;
+use super::foreground::ForegroundWatcher;
+use super::hook_snapshot;
+use super::kvm::KvmWatcher;
+use super::overlay::{DebugOverlay, JumpOverlay};
+use super::window_ops::{forward_wheel_to_window_under, move_foreground_window_to};
 use super::wintypes::*;
 use super::winwrap::*;
 
@@ -57,9 +122,25 @@ pub struct WinDevice {
     pub iface: Option<DeviceIfaceInfo>,
     pub parents: Vec<WString>,
     pub hid: Option<HidDeviceInfo>,
+    // Of absolute devices only, for calibrating them
+    pub axis_ranges: Option<HidAxisRanges>,
+    // Only for placeholders of handles not listed by system
+    pub orphan: Option<OrphanHandle>,
     pub ctrl: DeviceController,
 }
 
+impl WinDevice {
+    // Some infos may fail to be collected right after hotplug
+    fn is_incomplete(&self) -> bool {
+        match &self.rawinput {
+            Some(rawinput) => {
+                self.id.is_none() || (rawinput.typ() == RawDeviceType::HID && self.hid.is_none())
+            }
+            None => false,
+        }
+    }
+}
+
 impl std::fmt::Display for WinDevice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Dev({})", self.handle.0)?;
@@ -123,7 +204,8 @@ impl std::fmt::Display for WinDevice {
 fn init_device_control(handle: HANDLE) -> DeviceController {
     let setting = DeviceSetting {
         locked_in_monitor: false,
-        switch: false,
+        switch: SwitchMode::Shared,
+        ..DeviceSetting::default()
     };
     DeviceController::new(handle.0 as u64, setting)
 }
@@ -142,10 +224,25 @@ fn unassociated_events_capture_device() -> WinDevice {
         iface: None,
         parents: Vec::new(),
         hid: None,
+        axis_ranges: None,
+        orphan: None,
         ctrl: init_device_control(handle),
     }
 }
 
+// Usage page and usage the device is reported under by raw input
+fn get_device_usage(rawinput: &RawinputInfo) -> Option<(u16, u16)> {
+    match rawinput.typ() {
+        RawDeviceType::MOUSE => Some(WindowsRawinput::MOUSE),
+        RawDeviceType::KEYBOARD => Some(WindowsRawinput::KEYBOARD),
+        RawDeviceType::HID => {
+            let hid = rawinput.get_hid();
+            Some((hid.usUsagePage, hid.usUsage))
+        }
+        RawDeviceType::UNKNOWN => None,
+    }
+}
+
 pub fn get_device_type(rawinput: &RawinputInfo) -> DeviceType {
     match rawinput.typ() {
         RawDeviceType::MOUSE => DeviceType::Mouse,
@@ -158,9 +255,9 @@ pub fn get_device_type(rawinput: &RawinputInfo) -> DeviceType {
     }
 }
 
-fn collect_rawinput_infos(dev: &RAWINPUTDEVICELIST) -> Result<RawinputInfo> {
-    let handlev = dev.hDevice.0;
-    match device_collect_rawinput_infos(dev.hDevice) {
+fn collect_rawinput_infos(handle: HANDLE) -> Result<RawinputInfo> {
+    let handlev = handle.0;
+    match device_collect_rawinput_infos(handle) {
         Ok(v) => Ok(v),
         Err(e) => {
             error!("Get dev info failed({}): {}", handlev, e);
@@ -214,6 +311,22 @@ fn collect_device_infos(
         },
         _ => None,
     };
+    // Keyboards have no axes, and are not worth opening
+    let axis_ranges = match rawinput.typ() {
+        RawDeviceType::MOUSE | RawDeviceType::HID => {
+            match device_query_hid_axis_ranges(&rawinput.iface) {
+                Ok(v) => v,
+                Err(e) => {
+                    debug!(
+                        "Get axis ranges failed({}): {}. interface={}",
+                        handlev, e, rawinput.iface
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
     let ctrl = init_device_control(handle);
 
     Ok(WinDevice {
@@ -224,14 +337,305 @@ fn collect_device_infos(
         iface,
         parents,
         hid,
+        axis_ranges,
+        orphan: None,
         ctrl,
     })
 }
 
+struct DeviceInfoRetry {
+    handle: HANDLE,
+    times: u32,
+    next_tick: u64,
+}
+
+impl DeviceInfoRetry {
+    fn new(handle: HANDLE, cur_tick: u64) -> Self {
+        DeviceInfoRetry {
+            handle,
+            times: 0,
+            next_tick: cur_tick + DEVICE_INFO_RETRY_BASE_MS,
+        }
+    }
+    // Exponential backoff. Return false if no more retry
+    fn backoff(&mut self, cur_tick: u64) -> bool {
+        self.times += 1;
+        self.next_tick = cur_tick + (DEVICE_INFO_RETRY_BASE_MS << self.times);
+        self.times < DEVICE_INFO_RETRY_MAX_TIMES
+    }
+}
+
+// Devices and monitors may enumerate late after login, so scanned again a few times
+struct StartupScan {
+    start_tick: u64,
+    next_tick: u64,
+    interval_ms: u64,
+    in_grace: bool,
+}
+
+impl StartupScan {
+    fn new(cur_tick: u64) -> Self {
+        StartupScan {
+            start_tick: cur_tick,
+            next_tick: cur_tick + STARTUP_RESCAN_FIRST_MS,
+            interval_ms: STARTUP_RESCAN_FIRST_MS,
+            in_grace: true,
+        }
+    }
+    // Return true if it is time to rescan, then the interval is doubled
+    fn due(&mut self, cur_tick: u64) -> bool {
+        if cur_tick < self.next_tick {
+            return false;
+        }
+        self.interval_ms *= 2;
+        self.next_tick = cur_tick + self.interval_ms;
+        true
+    }
+    fn finished(&self) -> bool {
+        !self.in_grace && self.next_tick > self.start_tick + STARTUP_RESCAN_WITHIN_MS
+    }
+}
+
+// Shortcuts failed to register for being taken by other programs, maybe only while they start.
+// Registered again with doubled intervals, until succeeded or shortcuts applied again.
+#[derive(Default)]
+struct ShortcutRetry {
+    pending: Vec<(&'static str /* name */, String, ShortcutID)>,
+    next_tick: u64,
+    interval_ms: u64,
+}
+
+impl ShortcutRetry {
+    fn add(&mut self, name: &'static str, shortcut: &str, id: ShortcutID, cur_tick: u64) {
+        self.pending.push((name, shortcut.to_owned(), id));
+        self.interval_ms = SHORTCUT_RETRY_FIRST_MS;
+        self.next_tick = cur_tick + SHORTCUT_RETRY_FIRST_MS;
+    }
+    // Return true if it is time to retry, then the interval is doubled
+    fn due(&mut self, cur_tick: u64) -> bool {
+        if self.pending.is_empty() || cur_tick < self.next_tick {
+            return false;
+        }
+        self.interval_ms = (self.interval_ms * 2).min(SHORTCUT_RETRY_MAX_MS);
+        self.next_tick = cur_tick + self.interval_ms;
+        true
+    }
+    fn names(&self) -> Vec<String> {
+        self.pending
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .collect()
+    }
+}
+
+// Mouse hook failed to install, e.g. blocked by security software until allowed. Installed
+// again with doubled intervals while cursor is polled.
+struct HookRetry {
+    next_tick: u64,
+    interval_ms: u64,
+}
+
+// After monitors changed much, e.g. while display driver restarts, monitors seen may be
+// temporary. Locks are held until no more changes for the grace time.
+struct MonitorSettling {
+    until_tick: u64,
+    // Sizes of monitors before and after, for telling user
+    layout: String,
+    // Waiting for user to confirm
+    asked: bool,
+}
+
+// Scheduling of eventloop thread as applied, changed only when settings differ from it
+#[derive(Default)]
+struct ThreadScheduling {
+    priority: ThreadPriority,
+    mmcss: Option<HANDLE>,
+}
+
+impl ThreadScheduling {
+    // Failures are not retried until settings change again
+    fn apply(&mut self, priority: ThreadPriority, mmcss: bool) {
+        if priority != self.priority {
+            self.priority = priority;
+            let value = match priority {
+                ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+                ThreadPriority::AboveNormal => THREAD_PRIORITY_ABOVE_NORMAL,
+                ThreadPriority::Highest => THREAD_PRIORITY_HIGHEST,
+            };
+            match set_current_thread_priority(value) {
+                Ok(_) => info!("Eventloop thread priority: {}", priority),
+                Err(e) => warn!("Set eventloop thread priority failed: {}", e),
+            }
+        }
+        match (mmcss, self.mmcss) {
+            (true, None) => match join_mmcss_task(MMCSS_TASK_NAME) {
+                Ok(handle) => {
+                    info!("Eventloop thread joined MMCSS task {}", MMCSS_TASK_NAME);
+                    self.mmcss = Some(handle);
+                }
+                Err(e) => {
+                    warn!("Join MMCSS task failed: {}", e);
+                    self.mmcss = Some(HANDLE::default());
+                }
+            },
+            (false, Some(handle)) => {
+                if !handle.is_invalid() {
+                    if let Err(e) = leave_mmcss_task(handle) {
+                        warn!("Leave MMCSS task failed: {}", e);
+                    }
+                }
+                self.mmcss = None;
+            }
+            _ => (),
+        }
+    }
+}
+
+// Config file written again with device settings toggled by shortcuts, in headless mode. UI
+// saves them by itself.
+struct ConfigPersister {
+    path: PathBuf,
+    // As loaded from the file, with devices merged since then
+    saved: Settings,
+    writer: ConfigWriter,
+}
+
+impl ConfigPersister {
+    fn new(path: PathBuf, saved: Settings) -> Self {
+        let writer = ConfigWriter::start(|state| match state {
+            SaveState::Retrying { error, attempts } => {
+                warn!("Config not written yet, attempts {}: {}", attempts, error)
+            }
+            SaveState::Failed(e) => {
+                error!("Write config file failed: {} [{}]", e.message, e.code)
+            }
+            _ => (),
+        });
+        ConfigPersister {
+            path,
+            saved,
+            writer,
+        }
+    }
+
+    fn save(&mut self, items: Vec<DeviceSettingItem>) {
+        self.saved.processor.merge_devices(items);
+        self.writer.submit(self.path.clone(), self.saved.clone());
+    }
+}
+
+// Statuses of devices pushed to UI when changed, only while its window is open
+#[derive(Default)]
+struct StatusWatch {
+    // None while not watched
+    interval_ms: Option<u64>,
+    last_tick: u64,
+    // None to push the next ones anyway
+    last: Option<Vec<(String, DeviceStatus)>>,
+}
+
+impl StatusWatch {
+    fn set(&mut self, interval_ms: Option<u64>) {
+        self.interval_ms = interval_ms;
+        self.last = None;
+    }
+
+    fn due(&self, tick: u64) -> bool {
+        self.interval_ms
+            .is_some_and(|ms| self.last.is_none() || tick >= self.last_tick + ms)
+    }
+
+    // Returns statuses only if changed since last pushed
+    fn update(
+        &mut self,
+        tick: u64,
+        status: Vec<(String, DeviceStatus)>,
+    ) -> Option<Vec<(String, DeviceStatus)>> {
+        self.last_tick = tick;
+        if self.last.as_ref() == Some(&status) {
+            return None;
+        }
+        self.last = Some(status.clone());
+        Some(status)
+    }
+}
+
+// Device setting applied for a while to try it, not in settings
+struct TemporarySetting {
+    id: String,
+    revert: DeviceSetting,
+    until_tick: u64,
+}
+
+#[cfg(debug_assertions)]
+struct SimulatedDevice {
+    handle: HANDLE,
+    spec: SimulatedDeviceSpec,
+    start_tick: u64,
+}
+
+#[cfg(debug_assertions)]
+impl SimulatedDevice {
+    fn to_win_device(&self, seq: usize) -> WinDevice {
+        WinDevice {
+            handle: self.handle,
+            id: Some(simulated_device_id(seq)),
+            device_type: self.spec.device_type,
+            rawinput: None,
+            iface: None,
+            parents: Vec::new(),
+            hid: None,
+            axis_ranges: None,
+            orphan: None,
+            ctrl: init_device_control(self.handle),
+        }
+    }
+}
+
+// A handle sending events without being listed by system, as some virtual drivers do. Once a
+// scan misses it, a placeholder device stands for it, so it can be managed or ignored.
+#[derive(Clone, Copy)]
+pub struct OrphanHandle {
+    handle: HANDLE,
+    // Events before the placeholder took them
+    events: u64,
+    positioning: Positioning,
+}
+
+impl OrphanHandle {
+    fn new(handle: HANDLE) -> Self {
+        OrphanHandle {
+            handle,
+            events: 0,
+            positioning: Positioning::Unknown,
+        }
+    }
+
+    fn to_win_device(self) -> WinDevice {
+        WinDevice {
+            handle: self.handle,
+            id: Some(format!("{}{:X}", ORPHAN_DEVICE_ID_PREFIX, self.handle.0)),
+            device_type: DeviceType::Unknown,
+            rawinput: None,
+            iface: None,
+            parents: Vec::new(),
+            hid: None,
+            axis_ranges: None,
+            orphan: Some(self),
+            ctrl: init_device_control(self.handle),
+        }
+    }
+}
+
 struct WinDeviceSet {
     devs: Vec<WinDevice>,
     indexs: HashMap<isize, usize>,
     active_id: Option<usize>,
+    active_changed: bool,
+    // Consecutive events required before another device becomes active
+    switch_after: u32,
+    // Device which is going to be active, and its consecutive events
+    pending: Option<(usize, u32)>,
 }
 
 impl WinDeviceSet {
@@ -244,9 +648,17 @@ impl WinDeviceSet {
             devs: Vec::new(),
             indexs: HashMap::new(),
             active_id: None,
+            active_changed: false,
+            switch_after: 1,
+            pending: None,
         }
     }
 
+    pub fn set_switch_after(&mut self, events: u32) {
+        self.switch_after = events.max(1);
+        self.pending = None;
+    }
+
     pub fn active(&mut self) -> Option<&mut WinDevice> {
         if let Some(id) = self.active_id {
             self.devs.get_mut(id)
@@ -259,15 +671,52 @@ impl WinDeviceSet {
         self.active().and_then(|d| d.id.as_ref())
     }
 
-    pub fn get_and_update_active(&mut self, handle: HANDLE) -> Option<&mut WinDevice> {
+    // Returns device of the handle, and whether it is active now. Another device becomes
+    // active only after switch_after consecutive events, or the events of its activation
+    // grace, so that occasional events from a second user's mouse or a nudge don't make the
+    // active device ping-pong.
+    pub fn get_and_update_active(
+        &mut self,
+        handle: HANDLE,
+        tick: u64,
+    ) -> Option<(&mut WinDevice, bool)> {
         if let Some(id) = self.active_id {
             let active_handle = self.devs.get(id).unwrap().handle;
             if active_handle == handle {
-                return self.active();
+                self.pending = None;
+                return self.active().map(|d| (d, true));
+            }
+        }
+        let new_id = self.indexs.get(&WinDeviceSet::map_key(handle)).copied();
+        let Some(new_id) = new_id else {
+            self.active_id = None;
+            self.pending = None;
+            return None;
+        };
+        if self.active_id.is_some() {
+            match self.devs[new_id].ctrl.count_activation(tick) {
+                Some(true) => (),
+                Some(false) => {
+                    self.pending = None;
+                    return self.devs.get_mut(new_id).map(|d| (d, false));
+                }
+                None if self.switch_after > 1 => {
+                    let events = match self.pending {
+                        Some((id, events)) if id == new_id => events + 1,
+                        _ => 1,
+                    };
+                    if events < self.switch_after {
+                        self.pending = Some((new_id, events));
+                        return self.devs.get_mut(new_id).map(|d| (d, false));
+                    }
+                }
+                None => (),
             }
         }
-        self.active_id = self.indexs.get(&WinDeviceSet::map_key(handle)).copied();
-        self.active()
+        self.pending = None;
+        self.active_changed = true;
+        self.active_id = Some(new_id);
+        self.active().map(|d| (d, true))
     }
 
     pub fn rebuild(&mut self, new_devs: Vec<WinDevice>) {
@@ -279,6 +728,59 @@ impl WinDeviceSet {
             .map(|(i, d)| (WinDeviceSet::map_key(d.handle), i))
             .collect();
         self.active_id = None;
+        self.active_changed = false;
+        self.pending = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devs.is_empty()
+    }
+
+    pub fn contains(&self, handle: HANDLE) -> bool {
+        self.indexs.contains_key(&WinDeviceSet::map_key(handle))
+    }
+
+    // Return false if not found
+    pub fn remove_one(&mut self, handle: HANDLE) -> bool {
+        let Some(i) = self.indexs.remove(&WinDeviceSet::map_key(handle)) else {
+            return false;
+        };
+        let last = self.devs.len() - 1;
+        self.devs.swap_remove(i);
+        if let Some(moved) = self.devs.get(i) {
+            self.indexs.insert(WinDeviceSet::map_key(moved.handle), i);
+        }
+        let fix_index = |v: Option<usize>| match v {
+            Some(a) if a == i => None,
+            Some(a) if a == last => Some(i),
+            v => v,
+        };
+        self.active_id = fix_index(self.active_id);
+        self.pending = self
+            .pending
+            .and_then(|(id, events)| fix_index(Some(id)).map(|id| (id, events)));
+        true
+    }
+
+    // Replace device with same handle, keeping its controller state, or add it as new one
+    pub fn replace_one(&mut self, mut dev: WinDevice) {
+        match self.indexs.get(&WinDeviceSet::map_key(dev.handle)) {
+            Some(&i) => {
+                std::mem::swap(&mut dev.ctrl, &mut self.devs[i].ctrl);
+                self.devs[i] = dev;
+            }
+            None => {
+                self.indexs
+                    .insert(WinDeviceSet::map_key(dev.handle), self.devs.len());
+                self.devs.push(dev);
+            }
+        }
+    }
+
+    pub fn pop_active_changed(&mut self) -> bool {
+        let v = self.active_changed;
+        self.active_changed = false;
+        v
     }
 
     pub fn iter(&self) -> std::slice::Iter<'_, WinDevice> {
@@ -307,28 +809,120 @@ impl WinDeviceSet {
 
 struct WinHook {
     mouse_ll_hook: Option<HHOOK>,
+    // Only installed while any shortcut is detected by it
+    keyboard_ll_hook: Option<HHOOK>,
 }
 
 impl WinHook {
     fn new() -> Self {
         WinHook {
             mouse_ll_hook: None,
+            keyboard_ll_hook: None,
         }
     }
     fn register(&mut self) -> Result<()> {
-        self.mouse_ll_hook = Some(set_windows_hook(HookWrap::mouse_ll::<WinHook>())?);
+        self.set_mouse(true)
+    }
+    fn set_mouse(&mut self, on: bool) -> Result<()> {
+        match (on, self.mouse_ll_hook) {
+            (true, None) => {
+                self.mouse_ll_hook = Some(set_windows_hook(HookWrap::mouse_ll::<WinHook>())?);
+            }
+            (false, Some(h)) => {
+                self.mouse_ll_hook = None;
+                let _ = unset_windows_hook(h);
+            }
+            _ => (),
+        }
         Ok(())
     }
-    fn unregister(&mut self) -> Result<()> {
-        if let Some(h) = self.mouse_ll_hook {
-            let _ = unset_windows_hook(h);
+    fn set_keyboard(&mut self, on: bool) -> Result<()> {
+        match (on, self.keyboard_ll_hook) {
+            (true, None) => {
+                self.keyboard_ll_hook = Some(set_windows_hook(HookWrap::keyboard_ll::<WinHook>())?);
+            }
+            (false, Some(h)) => {
+                self.keyboard_ll_hook = None;
+                let _ = unset_windows_hook(h);
+            }
+            _ => (),
         }
         Ok(())
     }
+    fn unregister(&mut self) -> Result<()> {
+        let _ = self.set_mouse(false);
+        self.set_keyboard(false)
+    }
+}
+
+impl KeyboardLowLevelHook for WinHook {
+    fn on_keyboard_ll(action: u32, e: &KBDLLHOOKSTRUCT) -> HookResult {
+        let processor = unsafe { G_PROCESSOR.get_mut().unwrap() };
+        let key = VIRTUAL_KEY(e.vkCode as u16);
+        let down = matches!(action, WM_KEYDOWN | WM_SYSKEYDOWN);
+        if let Some(modifier) = modifier_of_key(key) {
+            // Shortcuts of only modifiers are triggered when released, not to take the
+            // modifiers of other shortcuts. Modifiers are always passed to other programs.
+            if down {
+                let modifiers = pressed_modifiers() | modifier;
+                processor.modifier_tap = processor
+                    .hook_shortcuts
+                    .iter()
+                    .find(|(m, k, _)| k.is_none() && *m == modifiers)
+                    .map(|(_, _, id)| *id);
+            } else if let Some(id) = processor.modifier_tap.take() {
+                processor.pending_shortcuts.push(id);
+            }
+            return HookResult::Pass;
+        }
+
+        processor.modifier_tap = None;
+        if down {
+            // Repeats while held
+            if processor.hook_held == Some(key) {
+                return HookResult::Block;
+            }
+            let modifiers = pressed_modifiers();
+            let found = processor
+                .hook_shortcuts
+                .iter()
+                .find(|(m, k, _)| *k == Some(key) && *m == modifiers);
+            if let Some(&(_, _, id)) = found {
+                processor.hook_held = Some(key);
+                processor.pending_shortcuts.push(id);
+                return HookResult::Block;
+            }
+        } else if processor.hook_held == Some(key) {
+            processor.hook_held = None;
+            return HookResult::Block;
+        }
+        HookResult::Pass
+    }
+}
+
+// Button index for ClickDebounce, and whether it is pressed
+fn hook_button(action: u32, mouse_data: u32) -> Option<(usize, bool)> {
+    match action {
+        WM_LBUTTONDOWN => Some((0, true)),
+        WM_LBUTTONUP => Some((0, false)),
+        WM_RBUTTONDOWN => Some((1, true)),
+        WM_RBUTTONUP => Some((1, false)),
+        WM_MBUTTONDOWN => Some((2, true)),
+        WM_MBUTTONUP => Some((2, false)),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            let button = match (mouse_data >> 16) as u16 {
+                XBUTTON1 => 3,
+                _ => 4,
+            };
+            Some((button, action == WM_XBUTTONDOWN))
+        }
+        _ => None,
+    }
 }
 
 impl MouseLowLevelHook for WinHook {
-    fn on_mouse_ll(action: u32, e: &mut MSLLHOOKSTRUCT) -> bool {
+    fn on_mouse_ll(action: u32, e: &mut MSLLHOOKSTRUCT) -> HookResult {
+        let _span = etw::span(SpanKind::HookCallback);
         let processor = unsafe { G_PROCESSOR.get_mut().unwrap() };
 
         trace!(
@@ -338,11 +932,64 @@ impl MouseLowLevelHook for WinHook {
             e.pt.y
         );
 
+        // Modifiers held for Ctrl+click, Ctrl+wheel and the like are not tapped
+        if hook_button(action, e.mouseData).is_some_and(|(_, down)| down)
+            || action == WM_MOUSEWHEEL
+            || action == WM_MOUSEHWHEEL
+        {
+            processor.modifier_tap = None;
+        }
+
+        // Monitors layout is not reliable while session is disconnected
+        if processor.is_paused() {
+            return HookResult::Pass;
+        }
+
+        if let Some((button, down)) = hook_button(action, e.mouseData) {
+            let injected = (e.flags & LLMHF_INJECTED) != 0;
+            if let Some(dev) = processor.devices.active().filter(|_| !injected) {
+                if dev.ctrl.debounce_click(button, down, get_cur_tick()) {
+                    if down {
+                        DEBOUNCED_CLICKS.fetch_add(1, Ordering::Relaxed);
+                        debug!("Dropped bouncing press of button {}", button);
+                    }
+                    return HookResult::Block;
+                }
+            }
+        }
+
+        let snapshot = hook_snapshot::load();
+        let settings = snapshot.as_ref().map(|s| &s.settings);
+
+        if (action == WM_MOUSEWHEEL || action == WM_MOUSEHWHEEL)
+            && settings.is_some_and(|s| s.scroll_window_under_cursor)
+            && forward_wheel_to_window_under(action, e.pt, e.mouseData)
+        {
+            return HookResult::Block;
+        }
+
+        let pos = MousePos::from(e.pt.x, e.pt.y);
+        if action == WM_MOUSEMOVE && (e.flags & LLMHF_INJECTED) == 0 {
+            if let Some(scaled) = processor.try_turbo_scale(&pos) {
+                let ctrl = processor.devices.active().map(|v| &mut v.ctrl);
+                processor.relocator.on_pos_update(ctrl, scaled);
+                let MousePos { x, y } = match processor.relocator.pop_relocate_pos() {
+                    Some(RelocatePos(p)) => p,
+                    None => scaled,
+                };
+                let _ = set_cursor_pos(x, y);
+                return HookResult::Block;
+            }
+        }
+
         let ctrl = processor.devices.active().map(|v| &mut v.ctrl);
-        processor
-            .relocator
-            .on_pos_update(ctrl, MousePos::from(e.pt.x, e.pt.y));
-        true
+        if action == WM_MOUSEMOVE && (e.flags & LLMHF_INJECTED) == 0 {
+            processor.relocator.on_pos_update(ctrl, pos);
+            processor.detect_gesture(pos);
+        } else {
+            processor.relocator.on_cursor_update(ctrl, pos);
+        }
+        HookResult::Pass
     }
 }
 
@@ -354,8 +1001,54 @@ struct WinDeviceProcessor {
     tick_widen: TickWiden,
     relocator: MouseRelocator,
     settings: ProcessorSettings,
+    // Usages registered for raw input, empty until initialized
+    registered_usages: Vec<RawinputUsage>,
     to_update_devices: bool,
     to_update_monitors: bool,
+    monitors_settling: Option<MonitorSettling>,
+    turbo_key: Option<VIRTUAL_KEY>,
+    paused: bool,
+    user_paused: bool,
+    // Input goes to another machine by a software KVM
+    kvm_remote: bool,
+    info_retries: Vec<DeviceInfoRetry>,
+    // Handles of devices matching processor.ignored_devices, whose events are dropped
+    ignored: Vec<HANDLE>,
+    #[cfg(debug_assertions)]
+    simulated: Vec<SimulatedDevice>,
+    // Handles of events from devices not found, placeholders once missed by a scan
+    orphans: Vec<OrphanHandle>,
+    devices_changed: bool,
+    // Ids of devices as of last checking, to find which connected or disconnected
+    connected: BTreeSet<String>,
+    // Devices found by the first checking were there before starting, not connected
+    connections_checked: bool,
+    // Device settings changed by processor.device_rules, not reported to UI yet
+    rule_changes: Vec<(String, DeviceSetting)>,
+    temporary_settings: Vec<TemporarySetting>,
+    // Executable name of the program in foreground, while processor.app_rules are watched
+    foreground_app: Option<String>,
+    // Ids of devices whose settings are overridden by processor.app_rules, with what undoes it
+    app_overrides: Vec<(String, DeviceRuleAction)>,
+    // All risky features are kept off
+    safe_mode: bool,
+    battery: BatteryProvider,
+    stats: StatsAggregator,
+    tracer: Option<EventTracer>,
+    gestures: GestureDetector,
+    // Recognized in hook, action is run later by eventloop
+    pending_gesture: Option<Gesture>,
+    // Shortcuts of the hook backend, key is None for shortcuts of only modifiers
+    hook_shortcuts: Vec<(HOT_KEY_MODIFIERS, Option<VIRTUAL_KEY>, ShortcutID)>,
+    // Key of the hook shortcut held, whose repeats and release are blocked too
+    hook_held: Option<VIRTUAL_KEY>,
+    // Shortcut of only modifiers held, triggered if released without other keys
+    modifier_tap: Option<ShortcutID>,
+    // Pressed in hook, run later by eventloop
+    pending_shortcuts: Vec<ShortcutID>,
+    commands: EventCommandRunner,
+    // Relocation targets since last polled by eventloop
+    relocations: Vec<MousePos>,
 
     rl_update_mon: SimpleRatelimit,
     rl_update_dev: SimpleRatelimit,
@@ -377,11 +1070,41 @@ impl WinDeviceProcessor {
             tick_widen: TickWiden::new(),
             relocator: MouseRelocator::new(),
             settings: ProcessorSettings::default(),
+            registered_usages: Vec::new(),
             to_update_devices: false,
             to_update_monitors: false,
+            monitors_settling: None,
+            turbo_key: None,
+            paused: false,
+            user_paused: false,
+            kvm_remote: false,
+            info_retries: Vec::new(),
+            ignored: Vec::new(),
+            #[cfg(debug_assertions)]
+            simulated: Vec::new(),
+            orphans: Vec::new(),
+            devices_changed: false,
+            connected: BTreeSet::new(),
+            connections_checked: false,
+            rule_changes: Vec::new(),
+            temporary_settings: Vec::new(),
+            foreground_app: None,
+            app_overrides: Vec::new(),
+            safe_mode: false,
+            battery: BatteryProvider::new(),
+            stats: StatsAggregator::default(),
+            tracer: None,
+            gestures: GestureDetector::default(),
+            pending_gesture: None,
+            hook_shortcuts: Vec::new(),
+            hook_held: None,
+            modifier_tap: None,
+            pending_shortcuts: Vec::new(),
+            commands: EventCommandRunner::default(),
+            relocations: Vec::new(),
 
             rl_update_mon: SimpleRatelimit::new(
-                Duration::from_millis(RATELIMIT_UPDATE_MONITOR_ONCE_MS),
+                Duration::from_millis(ProcessorSettings::default().update_monitors_interval_ms),
                 None,
             ),
             rl_update_dev: SimpleRatelimit::new(
@@ -402,7 +1125,8 @@ impl WinDeviceProcessor {
         }
     }
     fn initialize(&mut self) -> Result<()> {
-        match self.register_raw_devices() {
+        let usages = self.settings.usages_to_register();
+        match self.register_raw_devices(&usages) {
             Ok(_) => (),
             Err(e) => {
                 error!("Register raw devices failed: {}", e);
@@ -420,13 +1144,47 @@ impl WinDeviceProcessor {
         Ok(())
     }
     fn terminate(&mut self) -> Result<()> {
-        Ok(())
+        if self.hwnd.0 == 0 {
+            return Ok(());
+        }
+        self.unregister_raw_devices()
     }
 }
 
 impl WinDeviceProcessor {
-    fn filter_rawinput_devices(device_type: DeviceType) -> bool {
-        device_type.is_pointer()
+    // Devices of usages not registered send no events, though listed by system
+    fn filter_rawinput_devices(
+        usages: &[RawinputUsage],
+        rawinput: &RawinputInfo,
+        device_type: DeviceType,
+    ) -> bool {
+        !matches!(device_type, DeviceType::Keyboard)
+            && get_device_usage(rawinput).is_some_and(|v| usages.iter().any(|u| u.matches(v)))
+    }
+
+    // Return None if it is not a device to be managed, or failed to collect
+    fn collect_one_raw_device(
+        usages: &[RawinputUsage],
+        handle: HANDLE,
+    ) -> Result<Option<WinDevice>> {
+        let rawinput = match collect_rawinput_infos(handle) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to collect rawinput info({}): {}", handle.0, e);
+                return Err(e);
+            }
+        };
+        let device_type = get_device_type(&rawinput);
+        if !Self::filter_rawinput_devices(usages, &rawinput, device_type) {
+            return Ok(None);
+        }
+        match collect_device_infos(handle, device_type, rawinput) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) => {
+                error!("Failed to collect device info({}): {}", handle.0, e);
+                Err(e)
+            }
+        }
     }
 
     fn collect_all_raw_devices(&mut self) -> Result<Vec<WinDevice>> {
@@ -434,55 +1192,173 @@ impl WinDeviceProcessor {
             Ok(v) => v,
             Err(e) => return Err(e),
         };
+        let tick = get_cur_tick();
+        self.info_retries.clear();
+        self.ignored.clear();
+        // Just missed by enumeration after hotplug, not orphans
+        self.orphans
+            .retain(|o| !all_devs.iter().any(|d| d.hDevice == o.handle));
+        let usages = &self.settings.usages_to_register();
         Ok(all_devs
             .into_iter()
-            .filter_map(|d| {
-                let rawinput = match collect_rawinput_infos(&d) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("Failed to collect rawinput info({}): {}", d.hDevice.0, e);
-                        return None;
-                    }
-                };
-                let device_type = get_device_type(&rawinput);
-                if !Self::filter_rawinput_devices(device_type) {
-                    return None;
+            .filter_map(|d| match Self::collect_one_raw_device(usages, d.hDevice) {
+                Ok(Some(v)) if Self::is_ignored(&self.settings, &v) => {
+                    self.ignored.push(v.handle);
+                    None
                 }
-                match collect_device_infos(d.hDevice, device_type, rawinput) {
-                    Ok(v) => Some(v),
-                    Err(e) => {
-                        error!("Failed to collect device info({}): {}", d.hDevice.0, e);
-                        None
+                Ok(Some(v)) => {
+                    if v.is_incomplete() {
+                        self.info_retries
+                            .push(DeviceInfoRetry::new(d.hDevice, tick));
                     }
+                    Some(v)
+                }
+                Ok(None) => None,
+                Err(_) => {
+                    self.info_retries
+                        .push(DeviceInfoRetry::new(d.hDevice, tick));
+                    None
                 }
             })
             .collect())
     }
 
-    fn register_raw_devices(&mut self) -> Result<()> {
-        let to_register: Vec<RAWINPUTDEVICE> = WindowsRawinput::REGISTER_USAGE_SET
+    // Re-collect infos of devices which failed last time
+    fn try_retry_device_infos(&mut self) {
+        if self.info_retries.is_empty() {
+            return;
+        }
+        let tick = get_cur_tick();
+        let mut retries = std::mem::take(&mut self.info_retries);
+        let usages = self.settings.usages_to_register();
+        retries.retain_mut(|r| {
+            if r.next_tick > tick {
+                return true;
+            }
+            match Self::collect_one_raw_device(&usages, r.handle) {
+                Ok(Some(dev)) if Self::is_ignored(&self.settings, &dev) => {
+                    self.ignored.push(dev.handle);
+                    false
+                }
+                Ok(Some(dev)) => {
+                    let incomplete = dev.is_incomplete();
+                    if dev.id.is_some() {
+                        debug!("Retried collecting device info({}): {}", r.handle.0, dev);
+                        self.devices.replace_one(dev);
+                        self.devices_changed = true;
+                    }
+                    incomplete && r.backoff(tick)
+                }
+                Ok(None) => false,
+                Err(_) => r.backoff(tick),
+            }
+        });
+        self.info_retries = retries;
+        if self.devices_changed {
+            self.apply_processor_settings(None);
+            self.check_connections();
+        }
+    }
+
+    fn is_ignored(settings: &ProcessorSettings, dev: &WinDevice) -> bool {
+        dev.id
+            .as_ref()
+            .is_some_and(|id| settings.is_device_ignored(id))
+    }
+
+    // Paused by session disconnected, by user, by software KVM, or until monitors settle
+    #[inline]
+    fn is_paused(&self) -> bool {
+        self.paused || self.user_paused || self.kvm_remote || self.monitors_settling.is_some()
+    }
+
+    fn poll_battery(&mut self) -> Option<Vec<(String, u8)>> {
+        if self.paused {
+            return None;
+        }
+        self.battery.poll(self.devices.iter(), get_cur_tick())
+    }
+
+    pub fn take_rule_changes(&mut self) -> Vec<(String, DeviceSetting)> {
+        std::mem::take(&mut self.rule_changes)
+    }
+
+    pub fn pop_devices_changed(&mut self) -> bool {
+        let v = self.devices_changed;
+        self.devices_changed = false;
+        v
+    }
+
+    fn register_raw_devices(&mut self, usages: &[RawinputUsage]) -> Result<()> {
+        let to_register: Vec<RAWINPUTDEVICE> = usages
             .iter()
-            .map(|(page, usage)| {
+            .map(|u| {
                 let mut flags = RIDEV_DEVNOTIFY | RIDEV_INPUTSINK;
-                if usage == &WindowsRawinput::ALL {
+                if u.is_page_only() {
                     flags |= RIDEV_PAGEONLY;
                 }
                 RAWINPUTDEVICE {
-                    usUsage: *usage,
-                    usUsagePage: *page,
+                    usUsage: u.usage,
+                    usUsagePage: u.page,
                     dwFlags: flags,
                     hwndTarget: self.hwnd,
                 }
             })
             .collect();
-        register_rawinput_devices(&to_register)
+        register_rawinput_devices(&to_register)?;
+        self.registered_usages = usages.to_vec();
+        Ok(())
+    }
+
+    // Flags must be RIDEV_REMOVE and target must be null to remove
+    fn unregister_raw_devices(&mut self) -> Result<()> {
+        let to_remove: Vec<RAWINPUTDEVICE> = self
+            .registered_usages
+            .iter()
+            .map(|u| {
+                let mut flags = RIDEV_REMOVE;
+                if u.is_page_only() {
+                    flags |= RIDEV_PAGEONLY;
+                }
+                RAWINPUTDEVICE {
+                    usUsage: u.usage,
+                    usUsagePage: u.page,
+                    dwFlags: flags,
+                    hwndTarget: HWND::default(),
+                }
+            })
+            .collect();
+        register_rawinput_devices(&to_remove)?;
+        self.registered_usages.clear();
+        Ok(())
     }
 
-    fn monitor_area_from(mi: &MonitorInfo) -> MonitorArea {
-        MonitorArea {
-            lefttop: MousePos::from(mi.rect.left, mi.rect.top),
-            rigtbtm: MousePos::from(mi.rect.right, mi.rect.bottom),
+    // Registers processor.rawinput_usages in place of those registered, if changed after
+    // initialized. The old ones are registered again if the new ones fail.
+    fn update_raw_devices(&mut self) {
+        let new = self.settings.usages_to_register();
+        if self.registered_usages.is_empty() || self.registered_usages == new {
+            return;
+        }
+        let old = self.registered_usages.clone();
+        if let Err(e) = self.unregister_raw_devices() {
+            error!("Unregister raw devices failed: {}", e);
+            return;
+        }
+        match self.register_raw_devices(&new) {
+            Ok(_) => {
+                let usages: Vec<String> = new.iter().map(ToString::to_string).collect();
+                info!("Raw input registered for usages {}", usages.join(", "));
+            }
+            Err(e) => {
+                error!("Register raw devices failed: {}", e);
+                if let Err(e) = self.register_raw_devices(&old) {
+                    error!("Register raw devices again failed: {}", e);
+                }
+            }
         }
+        // Devices of usages removed are listed until scanned again
+        self.to_update_devices = true;
     }
 
     fn try_update_devices(&mut self, must: bool) -> Result<()> {
@@ -498,35 +1374,272 @@ impl WinDeviceProcessor {
             }
         };
         rawdevices.push(unassociated_events_capture_device());
+        #[cfg(debug_assertions)]
+        for (i, d) in self.simulated.iter().enumerate() {
+            rawdevices.push(d.to_win_device(i));
+        }
+        for o in self.orphans.iter() {
+            let dev = o.to_win_device();
+            if Self::is_ignored(&self.settings, &dev) {
+                self.ignored.push(o.handle);
+                continue;
+            }
+            if !self.devices.contains(o.handle) {
+                warn!(
+                    "Device {} sends events, but is not listed by system",
+                    o.handle.0
+                );
+            }
+            rawdevices.push(dev);
+        }
 
         debug!("Updated rawdevices list: num={}", rawdevices.len());
         for d in rawdevices.iter() {
             debug!("Device: {}", d);
         }
         self.devices.rebuild(rawdevices);
+        self.battery.reset();
         self.apply_processor_settings(None); // Apply settings again
+        self.check_connections();
         self.to_update_devices = false;
         Ok(())
     }
 
-    fn try_update_monitors(&mut self, must: bool) -> Result<()> {
-        if !must && !self.rl_update_mon.allow(None).0 {
-            return Ok(());
+    // Runs commands and rules of devices connected or disconnected since last checking
+    fn check_connections(&mut self) {
+        let now: BTreeSet<String> = self.devices.iter().filter_map(|d| d.id.clone()).collect();
+        let connected: Vec<String> = now.difference(&self.connected).cloned().collect();
+        let disconnected: Vec<String> = self.connected.difference(&now).cloned().collect();
+        self.connected = now;
+        let startup = !std::mem::replace(&mut self.connections_checked, true);
+        for id in connected {
+            self.on_connection_changed(&id, true, startup);
+        }
+        for id in disconnected {
+            self.on_connection_changed(&id, false, false);
         }
+        // Also changed devices just connected, or those changed by device rules
+        if self.foreground_app.is_some() {
+            self.apply_app_rules();
+        }
+    }
 
-        let mons = match get_all_monitors_info() {
-            Ok(v) => v,
+    // Rules still apply to devices there at startup, but commands are only for new arrivals
+    fn on_connection_changed(&mut self, id: &str, connected: bool, startup: bool) {
+        let event = match connected {
+            true => DeviceEvent::Connected,
+            false => DeviceEvent::Disconnected,
+        };
+        if !startup {
+            self.commands
+                .run(&self.settings.on_event_command, event, Some(id));
+        }
+
+        let actions: Vec<DeviceRuleAction> = self
+            .settings
+            .device_rules
+            .iter()
+            .filter(|r| wildcard_match(&r.device, id))
+            .flat_map(|r| r.actions(connected).iter().cloned())
+            .collect();
+        if actions.is_empty() {
+            return;
+        }
+        debug!("Device rules of {} triggered, connected={}", id, connected);
+        let before = self.device_settings();
+        for action in actions {
+            let targets: Vec<String> = match action.target.is_empty() {
+                true => vec![id.to_owned()],
+                false => self
+                    .connected
+                    .iter()
+                    .filter(|t| wildcard_match(&action.target, t))
+                    .cloned()
+                    .collect(),
+            };
+            for target in targets {
+                let content = self.settings.ensure_mut_device(&target, |d| {
+                    action.apply(d);
+                    *d
+                });
+                self.devices.update_one_device_settings(&target, &content);
+                self.rule_changes.retain(|(t, _)| *t != target);
+                self.rule_changes.push((target, content));
+            }
+        }
+        self.run_toggled_commands(before);
+        self.publish_settings();
+    }
+
+    // Only collect infos of the changed device, instead of rebuilding the whole set
+    fn on_device_change(&mut self, change: u32, handle: HANDLE) {
+        if self.devices.is_empty() {
+            // Not built yet, also includes dummy and simulated devices
+            self.to_update_devices = true;
+            return;
+        }
+        self.info_retries.retain(|r| r.handle != handle);
+        self.orphans.retain(|o| o.handle != handle);
+        self.battery.forget(handle.0);
+        let usages = &self.settings.usages_to_register();
+        match change {
+            GIDC_ARRIVAL => match Self::collect_one_raw_device(usages, handle) {
+                Ok(Some(dev)) if Self::is_ignored(&self.settings, &dev) => {
+                    debug!("Device arrived, but ignored: {}", dev);
+                    self.ignored.push(handle);
+                }
+                Ok(Some(dev)) => {
+                    if dev.is_incomplete() {
+                        self.info_retries
+                            .push(DeviceInfoRetry::new(handle, get_cur_tick()));
+                    }
+                    debug!("Device arrived: {}", dev);
+                    self.devices.replace_one(dev);
+                    self.devices_changed = true;
+                    self.apply_processor_settings(None);
+                    self.check_connections();
+                }
+                Ok(None) => (),
+                Err(_) => self
+                    .info_retries
+                    .push(DeviceInfoRetry::new(handle, get_cur_tick())),
+            },
+            GIDC_REMOVAL => {
+                self.ignored.retain(|&h| h != handle);
+                if !self.devices.remove_one(handle) {
+                    return;
+                }
+                debug!("Device removed: {}", handle.0);
+                self.devices_changed = true;
+                self.check_connections();
+            }
+            _ => (),
+        }
+    }
+
+    // Take devices which arrived without WM_INPUT_DEVICE_CHANGE, keeping states of the others
+    fn rescan_arrived_devices(&mut self) {
+        if self.devices.is_empty() {
+            self.to_update_devices = true;
+            return;
+        }
+        let all_devs = match device_list_all() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Rescan devices failed: {}", e);
+                return;
+            }
+        };
+        for d in all_devs {
+            let handle = d.hDevice;
+            if self.devices.contains(handle)
+                || self.ignored.contains(&handle)
+                || self.info_retries.iter().any(|r| r.handle == handle)
+            {
+                continue;
+            }
+            self.on_device_change(GIDC_ARRIVAL, handle);
+        }
+    }
+
+    // Monitors are only updated if changed, since updating resets locks of devices
+    fn rescan_monitors(&mut self) {
+        let areas: Vec<MonitorArea> = match get_all_monitors_info() {
+            Ok(v) => v.iter().map(MonitorInfo::area).collect(),
+            Err(e) => {
+                warn!("Rescan monitors failed: {}", e);
+                return;
+            }
+        };
+        if areas != self.relocator.enumerated_areas() {
+            let _ = self.try_update_monitors(true);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn add_simulated_device(&mut self, spec: SimulatedDeviceSpec) -> Result<()> {
+        // Negative handles never conflict with real ones
+        let handle = HANDLE(-(self.simulated.len() as isize) - 1);
+        self.simulated.push(SimulatedDevice {
+            handle,
+            spec,
+            start_tick: get_cur_tick(),
+        });
+        self.try_update_devices(true)?;
+        self.devices_changed = true;
+        Ok(())
+    }
+
+    #[cfg(debug_assertions)]
+    fn clear_simulated_devices(&mut self) -> Result<()> {
+        if self.simulated.is_empty() {
+            return Ok(());
+        }
+        self.simulated.clear();
+        self.try_update_devices(true)?;
+        self.devices_changed = true;
+        Ok(())
+    }
+
+    // Feed scripted activity of simulated devices, as if they emitted rawinput events.
+    // Return true if any simulated device exists.
+    #[cfg(debug_assertions)]
+    fn step_simulated_devices(&mut self) -> bool {
+        if self.simulated.is_empty() || self.is_paused() {
+            return !self.simulated.is_empty();
+        }
+        let Some(center) = self.relocator.first_monitor_center() else {
+            return true;
+        };
+        let tick = get_cur_tick();
+        for i in 0..self.simulated.len() {
+            let SimulatedDevice {
+                handle,
+                spec,
+                start_tick,
+            } = self.simulated[i];
+            let Some(pos) = spec.pos_at(center, tick - start_tick) else {
+                continue;
+            };
+            let Some((dev, true)) = self.devices.get_and_update_active(handle, tick) else {
+                continue;
+            };
+            dev.ctrl.update_positioning(spec.positioning);
+            self.relocator.on_mouse_update(&mut dev.ctrl, tick);
+            self.relocator.on_pos_update(Some(&mut dev.ctrl), pos);
+            match self.relocator.pop_relocate_pos() {
+                Some(RelocatePos(p)) => {
+                    let _ = set_cursor_pos(p.x, p.y);
+                }
+                None => {
+                    let _ = set_cursor_pos(pos.x, pos.y);
+                }
+            }
+        }
+        true
+    }
+
+    fn try_update_monitors(&mut self, must: bool) -> Result<()> {
+        if !must && !self.rl_update_mon.allow(None).0 {
+            return Ok(());
+        }
+
+        let mons = match get_all_monitors_info() {
+            Ok(v) => v,
             Err(e) => {
                 error!("Update monitors info failed: {}", e);
                 return Err(e);
             }
         };
-        let mon_areas = MonitorAreasList::from(
-            mons.iter()
-                .map(WinDeviceProcessor::monitor_area_from)
-                .collect(),
-        );
+        let areas: Vec<MonitorArea> = mons.iter().map(MonitorInfo::area).collect();
+        self.check_monitors_settling(&areas);
+        let mon_areas = MonitorAreasList::from(areas)
+            .with_work_areas(mons.iter().map(MonitorInfo::work_area).collect())
+            .with_labels(mons.iter().map(|m| m.label.clone()).collect())
+            .with_names(mons.into_iter().map(|m| m.name).collect());
         debug!("Updated monitors: {}", mon_areas);
+        let monitors = mon_areas.clone();
+        hook_snapshot::update(|s| s.monitors = monitors);
         self.relocator.update_monitors(mon_areas);
         self.devices.iter_mut().for_each(|v| {
             v.ctrl.reset();
@@ -535,6 +1648,32 @@ impl WinDeviceProcessor {
         Ok(())
     }
 
+    // Changes while settling start the grace time again
+    fn check_monitors_settling(&mut self, areas: &[MonitorArea]) {
+        let old = self.relocator.enumerated_areas();
+        if old.is_empty() || !layout_changed_much(old, areas) {
+            return;
+        }
+        let grace_ms = self.settings.monitor_change_grace_secs as u64 * 1000;
+        if grace_ms == 0 && !self.settings.confirm_monitor_changes {
+            return;
+        }
+        let layout = format!(
+            "{} -> {}",
+            layout_sizes(old).join(", "),
+            layout_sizes(areas).join(", ")
+        );
+        info!(
+            "Monitors changed much, locks held until settled: {}",
+            layout
+        );
+        self.monitors_settling = Some(MonitorSettling {
+            until_tick: get_cur_tick() + grace_ms,
+            layout,
+            asked: false,
+        });
+    }
+
     fn cur_mouse_lock_toogle(&mut self) {
         let device = self.devices.active();
         let Some(device) = device else {
@@ -543,19 +1682,274 @@ impl WinDeviceProcessor {
         let Some(id) = &device.id else {
             return;
         };
+        let before = *device.ctrl.setting();
         let content = self.settings.ensure_mut_device(id, |d| {
             d.locked_in_monitor = !d.locked_in_monitor;
             *d
         });
         device.ctrl.update_settings(&content);
+        let id = id.clone();
+        self.run_toggled_commands(vec![(id, before)]);
+        self.publish_settings();
+    }
+
+    // Returns new settings of devices changed
+    fn cycle_switch_devices(&mut self) -> Vec<(String, DeviceSetting)> {
+        let ids = self.settings.cycle_switch();
+        let mut before = Vec::new();
+        for id in &ids {
+            let Some(device) = self.devices.iter_mut().find(|d| d.id.as_ref() == Some(id)) else {
+                continue;
+            };
+            before.push((id.clone(), *device.ctrl.setting()));
+            let content = self.settings.ensure_mut_device(id, |d| *d);
+            device.ctrl.update_settings(&content);
+        }
+        self.run_toggled_commands(before);
+        self.publish_settings();
+        ids.into_iter()
+            .map(|id| {
+                let content = self.settings.ensure_mut_device(&id, |d| *d);
+                (id, content)
+            })
+            .collect()
+    }
+
+    // Settings of identified devices, to find which are toggled by applying new ones
+    fn device_settings(&self) -> Vec<(String, DeviceSetting)> {
+        if self.settings.on_event_command.is_empty() {
+            return Vec::new();
+        }
+        self.devices
+            .iter()
+            .filter_map(|d| Some((d.id.clone()?, *d.ctrl.setting())))
+            .collect()
+    }
+
+    // Trying it again extends the time, still restoring the setting before the first try
+    fn apply_temporary(&mut self, item: DeviceSettingItem, secs: u32) {
+        let Some(dev) = self
+            .devices
+            .iter()
+            .find(|d| d.id.as_ref() == Some(&item.id))
+        else {
+            return;
+        };
+        let until_tick = get_cur_tick() + secs as u64 * 1000;
+        match self.temporary_settings.iter_mut().find(|t| t.id == item.id) {
+            Some(t) => t.until_tick = until_tick,
+            None => self.temporary_settings.push(TemporarySetting {
+                id: item.id.clone(),
+                revert: *dev.ctrl.setting(),
+                until_tick,
+            }),
+        }
+        debug!("Try setting of device {} for {}s", item.id, secs);
+        let content = self.gated_device_setting(&item.content);
+        self.devices.update_one_device_settings(&item.id, &content);
+    }
+
+    fn on_foreground_changed(&mut self, app: Option<String>) {
+        self.foreground_app = app;
+        self.apply_app_rules();
+    }
+
+    // Fields overridden before go back to their values first, so overrides of programs never
+    // stack. Other fields keep changes made meanwhile, e.g. by shortcuts.
+    fn apply_app_rules(&mut self) {
+        let before = self.device_settings();
+        for (id, revert) in std::mem::take(&mut self.app_overrides) {
+            let Some(dev) = self.devices.iter().find(|d| d.id.as_ref() == Some(&id)) else {
+                continue;
+            };
+            let mut content = *dev.ctrl.setting();
+            revert.apply(&mut content);
+            self.devices.update_one_device_settings(&id, &content);
+        }
+        let actions = match &self.foreground_app {
+            Some(app) => self.settings.app_actions(app),
+            None => Vec::new(),
+        };
+        for action in actions {
+            let targets: Vec<(String, DeviceSetting)> = self
+                .devices
+                .iter()
+                .filter_map(|d| Some((d.id.clone()?, *d.ctrl.setting())))
+                .filter(|(id, _)| action.target.is_empty() || wildcard_match(&action.target, id))
+                .collect();
+            for (id, mut content) in targets {
+                let revert = action.revert_of(&content);
+                action.apply(&mut content);
+                self.devices.update_one_device_settings(&id, &content);
+                // Values before the first action overriding each field
+                match self.app_overrides.iter_mut().find(|(v, _)| *v == id) {
+                    Some((_, r)) => {
+                        r.locked_in_monitor = r.locked_in_monitor.or(revert.locked_in_monitor);
+                        r.switch = r.switch.or(revert.switch);
+                    }
+                    None => self.app_overrides.push((id, revert)),
+                }
+            }
+        }
+        if !self.app_overrides.is_empty() {
+            debug!(
+                "Settings of {} devices overridden for {}",
+                self.app_overrides.len(),
+                self.foreground_app.as_deref().unwrap_or_default()
+            );
+        }
+        self.run_toggled_commands(before);
+    }
+
+    fn revert_temporary_settings(&mut self, tick: u64) {
+        let (expired, kept) = std::mem::take(&mut self.temporary_settings)
+            .into_iter()
+            .partition(|t| tick >= t.until_tick);
+        self.temporary_settings = kept;
+        for t in expired {
+            debug!("Restore setting of device {} after trying", t.id);
+            self.devices.update_one_device_settings(&t.id, &t.revert);
+        }
+    }
+
+    fn run_toggled_commands(&mut self, before: Vec<(String, DeviceSetting)>) {
+        for (id, old) in before {
+            let Some(dev) = self.devices.iter().find(|d| d.id.as_ref() == Some(&id)) else {
+                continue;
+            };
+            for event in DeviceEvent::toggled(&old, dev.ctrl.setting()) {
+                self.commands
+                    .run(&self.settings.on_event_command, event, Some(&id));
+            }
+        }
+    }
+
+    // Hook reads settings from its snapshot, which must follow every change
+    fn publish_settings(&self) {
+        let settings = self.settings.clone();
+        hook_snapshot::update(|s| s.settings = settings);
+    }
+
+    // Scale motion of relative device while turbo key is held
+    fn try_turbo_scale(&mut self, pos: &MousePos) -> Option<MousePos> {
+        let key = self.turbo_key?;
+        if !is_key_down(key) {
+            self.turbo_key = None;
+            return None;
+        }
+        let device = self.devices.active()?;
+        if !matches!(device.ctrl.positioning(), Positioning::Relative) {
+            return None;
+        }
+        Some(
+            self.relocator
+                .scale_motion(pos, self.settings.turbo_speed_percent),
+        )
     }
 
     fn apply_processor_settings(&mut self, new_settings: Option<ProcessorSettings>) {
+        let before = self.device_settings();
         if let Some(new) = new_settings {
-            self.settings = new;
+            self.replace_settings(new);
+        }
+        self.gate_risky_features();
+        self.apply_options();
+        self.apply_device_settings();
+        self.run_toggled_commands(before);
+    }
+
+    // All but devices and shortcuts. Controllers are kept, unless defaults of device
+    // types are changed.
+    fn apply_global_options(&mut self, new: ProcessorSettings) {
+        let new = ProcessorSettings {
+            devices: std::mem::take(&mut self.settings.devices),
+            shortcuts: self.settings.shortcuts.clone(),
+            ..new
+        };
+        if new.defaults_by_type != self.settings.defaults_by_type {
+            self.apply_processor_settings(Some(new));
+            return;
+        }
+        let rules_changed = new.app_rules != self.settings.app_rules;
+        self.replace_settings(new);
+        self.gate_risky_features();
+        self.apply_options();
+        if rules_changed {
+            self.apply_app_rules();
+        }
+    }
+
+    fn apply_device_list(&mut self, devices: Vec<DeviceSettingItem>) {
+        self.settings.devices = devices;
+        self.apply_processor_settings(None);
+    }
+
+    // Settings are kept as applied, with risky features not confirmed turned off
+    fn gate_risky_features(&mut self) {
+        for feature in risk::gate(&mut self.settings, self.safe_mode) {
+            warn!("{} is off, not confirmed or in safe mode", feature);
+        }
+    }
+
+    fn gated_device_setting(&self, s: &DeviceSetting) -> DeviceSetting {
+        let mut s = *s;
+        risk::gate_device(&mut s, &self.settings.confirmed_risks, self.safe_mode);
+        s
+    }
+
+    fn disable_risky_features(&mut self) {
+        for feature in risk::disable_all(&mut self.settings) {
+            warn!("{} is turned off", feature);
+        }
+        self.publish_settings();
+    }
+
+    fn replace_settings(&mut self, new: ProcessorSettings) {
+        self.battery
+            .set_interval_secs(new.battery_query_interval_secs);
+        if new.ignored_devices != self.settings.ignored_devices {
+            self.to_update_devices = true;
         }
+        if new.update_monitors_interval_ms != self.settings.update_monitors_interval_ms {
+            self.rl_update_mon =
+                SimpleRatelimit::new(Duration::from_millis(new.update_monitors_interval_ms), None);
+        }
+        self.settings = new;
+    }
+
+    // Options taking effect without touching device controllers
+    fn apply_options(&mut self) {
+        self.publish_settings();
+        set_high_resolution_tick(self.settings.high_resolution_timing);
+        self.relocator
+            .set_use_work_area(self.settings.use_work_area);
+        self.relocator
+            .set_monitor_order(self.settings.monitor_order);
+        self.relocator
+            .set_monitor_groups(&self.settings.monitor_groups);
+        self.devices
+            .set_switch_after(self.settings.switch_after_events);
+        self.update_raw_devices();
+    }
+
+    fn apply_device_settings(&mut self) {
+        // Overridden by settings applied
+        self.temporary_settings.clear();
+        self.app_overrides.clear();
         let settings = &self.settings;
 
+        // Devices not configured get defaults of their type
+        self.devices
+            .iter_mut()
+            .filter(|d| match &d.id {
+                Some(id) => !settings.devices.iter().any(|v| &v.id == id),
+                None => false,
+            })
+            .for_each(|d| {
+                d.ctrl
+                    .update_settings(&settings.default_for_type(d.device_type))
+            });
+
         let applied: usize = settings.devices.iter().fold(0, |applied, item| {
             let found = self
                 .devices
@@ -572,8 +1966,12 @@ impl WinDeviceProcessor {
             applied,
             settings.devices.len()
         );
+        if self.foreground_app.is_some() {
+            self.apply_app_rules();
+        }
     }
 
+    // Timed by message time, or when processed if high resolution timing is on
     fn on_raw_input(&mut self, _wparam: WPARAM, lparam: LPARAM, tick: u32) {
         match get_rawinput_data(lparam_as_rawinput(lparam), &mut self.raw_input_buf) {
             Ok(_) => (),
@@ -584,12 +1982,27 @@ impl WinDeviceProcessor {
         }
 
         let ri = self.raw_input_buf.get_ref::<RAWINPUT>();
-        let wtick = self.tick_widen.widen(tick);
+        let wtick = get_event_tick(self.tick_widen.widen(tick));
         let positioning = match check_mouse_event_is_absolute(ri) {
             Some(true) => Positioning::Absolute,
             Some(false) => Positioning::Relative,
             None => Positioning::Unknown,
         };
+        let handle = ri.header.hDevice;
+        let flags = rawinput_mouse_flags(ri);
+        let trace = |tracer: &mut Option<EventTracer>, id: Option<&String>, outcome: &str| {
+            if let Some(t) = tracer {
+                let positioning = format!("{:?}", positioning);
+                t.input(
+                    wtick,
+                    handle.0,
+                    id.map(|v| v.as_str()),
+                    flags,
+                    &positioning,
+                    outcome,
+                );
+            }
+        };
 
         trace!(
             "rawinput msg: tick={} msg {}",
@@ -599,15 +2012,23 @@ impl WinDeviceProcessor {
 
         // Try merging unassociated event
         if ri.header.hDevice == HANDLE(0) {
-            // If configured
-            if self.settings.merge_unassociated_events_ms >= 0 {
-                let merge_within = self.settings.merge_unassociated_events_ms as u64;
-                // If active device exists
-                if let Some(active_dev) = self.devices.active() {
+            // If active device exists
+            if let Some(active_dev) = self.devices.active() {
+                // If configured, by the device or globally
+                let merge_ms = active_dev
+                    .ctrl
+                    .setting()
+                    .merge_unassociated_events_ms
+                    .unwrap_or(self.settings.merge_unassociated_events_ms);
+                if merge_ms >= 0 {
                     if let Some((active_tick, _, _)) = active_dev.ctrl.get_last_pos() {
                         // If within time range
-                        if active_tick + merge_within >= wtick {
+                        if active_tick + merge_ms as u64 >= wtick {
                             // Eat the unassociated event
+                            if let Some(id) = &active_dev.id {
+                                self.stats.on_event(id, wtick);
+                            }
+                            trace(&mut self.tracer, active_dev.id.as_ref(), "merged");
                             active_dev.ctrl.update_positioning(positioning);
                             self.relocator.on_mouse_update(&mut active_dev.ctrl, wtick);
                             return;
@@ -617,19 +2038,88 @@ impl WinDeviceProcessor {
             }
         }
 
-        match self.devices.get_and_update_active(ri.header.hDevice) {
-            Some(dev) => {
+        if self.ignored.contains(&handle) {
+            trace(&mut self.tracer, None, "ignored");
+            return;
+        }
+        match self.devices.get_and_update_active(handle, wtick) {
+            Some((dev, true)) => {
+                if let Some(id) = &dev.id {
+                    self.stats.on_event(id, wtick);
+                }
+                trace(&mut self.tracer, dev.id.as_ref(), "device");
                 dev.ctrl.update_positioning(positioning);
                 self.relocator.on_mouse_update(&mut dev.ctrl, wtick);
             }
+            // Not active yet, its motion is still taken as of the active one
+            Some((dev, false)) => {
+                if let Some(id) = &dev.id {
+                    self.stats.on_event(id, wtick);
+                }
+                trace(&mut self.tracer, dev.id.as_ref(), "pending");
+            }
             None => {
-                self.to_update_devices = true;
+                trace(&mut self.tracer, None, "unknown");
+                self.on_unknown_handle(handle, positioning);
             }
         };
         self.resolve_pending_updating_task();
         self.resolve_relocation();
     }
 
+    // Devices are scanned again, and it stays an orphan if missed by the scan
+    fn on_unknown_handle(&mut self, handle: HANDLE, positioning: Positioning) {
+        let i = match self.orphans.iter().position(|o| o.handle == handle) {
+            Some(i) => i,
+            None => {
+                self.orphans.push(OrphanHandle::new(handle));
+                self.orphans.len() - 1
+            }
+        };
+        let o = &mut self.orphans[i];
+        o.events += 1;
+        o.positioning = positioning;
+        self.to_update_devices = true;
+    }
+
+    // Only positions from absolute devices are taken
+    fn detect_gesture(&mut self, pos: MousePos) {
+        if !self.settings.gestures.is_enabled() {
+            return;
+        }
+        let absolute = self
+            .devices
+            .active()
+            .is_some_and(|d| matches!(d.ctrl.positioning(), Positioning::Absolute));
+        if !absolute {
+            self.gestures.reset();
+            return;
+        }
+        let Some(area) = self.relocator.locate_area(&pos) else {
+            return;
+        };
+        if let Some(g) = self.gestures.on_pos(pos, &area, get_cur_tick()) {
+            debug!("Gesture recognized: {:?}", g);
+            self.pending_gesture = Some(g);
+        }
+    }
+
+    // Until settings are applied again, so also for devices found later
+    fn release_all_devices(&mut self) {
+        let before = self.device_settings();
+        self.settings.devices.clear();
+        self.settings.defaults_by_type.clear();
+        for dev in self.devices.iter_mut() {
+            dev.ctrl.update_settings(&DeviceSetting::default());
+        }
+        self.run_toggled_commands(before);
+        self.publish_settings();
+    }
+
+    fn flush_stats(&mut self, tick: u64) -> Result<()> {
+        self.stats.flush(&get_local_date(), tick)
+    }
+
     fn resolve_pending_updating_task(&mut self) {
         if self.relocator.pop_need_update_monitors() {
             self.to_update_monitors = true;
@@ -638,13 +2128,63 @@ impl WinDeviceProcessor {
         if self.to_update_devices {
             let _ = self.try_update_devices(false);
         }
+        self.try_retry_device_infos();
+        self.release_idle_locks();
+        self.park_idle_cursor();
         if self.to_update_monitors {
             let _ = self.try_update_monitors(false);
         }
     }
 
+    // Locks will be found again at the next event of device
+    fn release_idle_locks(&mut self) {
+        let tick = get_cur_tick();
+        for dev in self.devices.iter_mut() {
+            if dev.ctrl.release_lock_if_idle(tick) {
+                debug!("Release idle lock of device {}", dev.handle.0);
+            }
+        }
+    }
+
+    // Relocated with the next resolve_relocation()
+    fn park_idle_cursor(&mut self) {
+        // Relocations are dropped while paused, devices would be left parked but not moved
+        if self.is_paused() {
+            return;
+        }
+        let tick = get_cur_tick();
+        for dev in self.devices.iter_mut() {
+            if self.relocator.park_if_idle(&mut dev.ctrl, tick) {
+                debug!("Park cursor of idle device {}", dev.handle.0);
+            }
+        }
+    }
+
     fn resolve_relocation(&mut self) {
         if let Some(RelocatePos(new_pos)) = self.relocator.pop_relocate_pos() {
+            let _span = etw::span(SpanKind::Relocation);
+            if self.is_paused() {
+                return;
+            }
+            // Monitors may have changed since target was decided, e.g. hotplug races
+            let clamped = self.relocator.clamp_offscreen(&new_pos);
+            if let Some(t) = &mut self.tracer {
+                let MousePos { x, y } = clamped.as_ref().unwrap_or(&new_pos);
+                t.relocate(get_cur_tick(), *x, *y, clamped.is_some());
+            }
+            let new_pos = match clamped {
+                Some(clamped) => {
+                    warn!(
+                        "Relocating to offscreen ({},{}), clamped to ({},{})",
+                        new_pos.x, new_pos.y, clamped.x, clamped.y
+                    );
+                    OFFSCREEN_RELOCATIONS.fetch_add(1, Ordering::Relaxed);
+                    self.to_update_monitors = true;
+                    clamped
+                }
+                None => new_pos,
+            };
+            self.relocations.push(new_pos);
             let MousePos { x, y } = new_pos;
             let _ = set_cursor_pos(x, y);
             debug!("Reset cursor to ({},{})", x, y);
@@ -656,17 +2196,66 @@ pub struct WinEventLoop {
     hook: WinHook,
     processor: &'static mut WinDeviceProcessor,
     headless: bool,
+    // Neither hook nor shortcuts are registered, for recovering from a broken config
+    safe_mode: bool,
     hotkey_mgr: HotKeyManager<ShortcutID>,
-    mouse_control_reactor: MouseControlReactor,
+    tracking_mode: TrackingMode,
+    hook_retry: Option<HookRetry>,
+    // Cursor position of last sampling, while polling instead of hook
+    last_polled_pos: Option<MousePos>,
+    // Only taken by into_reactor()
+    mouse_control_reactor: Option<MouseControlReactor>,
+    last_busy_tick: u64,
+    // Boxed, its address is kept by the window subclass
+    overlay: Box<JumpOverlay>,
+    // Set by UI, see Message::AnimateJumps
+    animate_jumps: bool,
+    // As of last published BusEvent::PausedChanged
+    reported_paused: bool,
+    // Boxed, its address is kept by the window subclass
+    debug_overlay: Box<DebugOverlay>,
+    // Pending IdentifyDevice request, and its deadline
+    identifying: Option<(u64, Message)>,
+    // Pending CaptureTrace request, responded when the trace is finished
+    capturing_trace: Option<Message>,
+    // Pending CaptureDeviceExtent request, and its deadline
+    capturing_extent: Option<(u64, Message)>,
+    // Follows cursor_style of the active device
+    cursor_styler: CursorStyler,
+    // Boxed, its address is kept by subclasses of dimming windows
+    focus_dim: Box<MonitorDimmer>,
+    // Device which turned on focus mode
+    focus_device: Option<String>,
+    // None after the first minute
+    startup_scan: Option<StartupScan>,
+    shortcut_retry: ShortcutRetry,
+    kvm: KvmWatcher,
+    foreground: ForegroundWatcher,
+    scheduling: ThreadScheduling,
+    persister: Option<ConfigPersister>,
+    status_watch: StatusWatch,
+    shut_down: bool,
 }
 
+// Times of eventloop woken up, for diagnostics
+pub static EVENTLOOP_WAKEUPS: AtomicU64 = AtomicU64::new(0);
+// Times of relocation target clamped back onto monitors, for diagnostics
+pub static OFFSCREEN_RELOCATIONS: AtomicU64 = AtomicU64::new(0);
+// Button presses dropped by debounce_click_ms of devices, for diagnostics
+pub static DEBOUNCED_CLICKS: AtomicU64 = AtomicU64::new(0);
+// Raw input events processed, and those waiting in queue longer than LATE_INPUT_EVENT_MS,
+// to tell whether raising priority of eventloop helps
+pub static INPUT_EVENTS: AtomicU64 = AtomicU64::new(0);
+pub static LATE_INPUT_EVENTS: AtomicU64 = AtomicU64::new(0);
+
 impl SubclassHandler for WinEventLoop {
-    fn subclass_callback(&mut self, umsg: u32, _wp: WPARAM, _lp: LPARAM, _class: usize) -> bool {
+    fn subclass_callback(&mut self, umsg: u32, wp: WPARAM, _lp: LPARAM, _class: usize) -> bool {
         match umsg {
             WM_DISPLAYCHANGE | WM_DPICHANGED => {
                 debug!("Trigger updating monitors by WM {}", umsg);
                 self.processor.to_update_monitors = true;
             }
+            WM_WTSSESSION_CHANGE => self.on_session_change(wp.0 as u32),
             _ => (),
         }
         true
@@ -674,10 +2263,150 @@ impl SubclassHandler for WinEventLoop {
 }
 
 impl WinEventLoop {
-    fn apply_one_shortcut(
-        mgr: &mut HotKeyManager<ShortcutID>,
+    // Remote desktop connecting/disconnecting changes monitors, and invalidates device handles
+    fn on_session_change(&mut self, event: u32) {
+        let paused = match event {
+            WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => true,
+            WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => false,
+            _ => return,
+        };
+        info!("Session changed: event={}, paused={}", event, paused);
+        if !paused {
+            self.processor.to_update_devices = true;
+            self.processor.to_update_monitors = true;
+        }
+        if self.processor.paused != paused {
+            self.processor.paused = paused;
+            self.reactor().report_session_changed(paused);
+        }
+    }
+
+    fn toggle_user_pause(&mut self) {
+        let paused = !self.processor.user_paused;
+        info!("Paused by user: {}", paused);
+        self.processor.user_paused = paused;
+        if !self.headless {
+            self.reactor().report_user_pause_changed(paused);
+        }
+    }
+
+    fn run_action(&mut self, action: TrayAction) {
+        match action {
+            TrayAction::None => (),
+            TrayAction::OpenUI => {
+                if !self.headless {
+                    self.reactor().restart_ui();
+                }
+            }
+            TrayAction::TogglePause => self.toggle_user_pause(),
+            TrayAction::JumpNext => {
+                self.on_shortcut_cur_mouse_jump_next();
+                self.processor.resolve_relocation();
+            }
+        }
+    }
+
+    // Also follows changed settings of the active device, not only switching of it
+    fn update_cursor_style(&mut self) {
+        let style = self
+            .processor
+            .devices
+            .active()
+            .and_then(|d| d.ctrl.setting().cursor_style);
+        self.cursor_styler.set(style);
+    }
+
+    fn poll_hook_shortcuts(&mut self) {
+        for id in std::mem::take(&mut self.processor.pending_shortcuts) {
+            self.run_shortcut(id);
+        }
+    }
+
+    fn poll_gestures(&mut self) {
+        if let Some(g) = self.processor.pending_gesture.take() {
+            self.run_action(self.processor.settings.gestures.action(g));
+        }
+    }
+
+    fn poll_hot_corners(&mut self) {
+        let hot_corners = &self.processor.settings.hot_corners;
+        if !hot_corners.is_enabled() {
+            return;
+        }
+        if !hot_corners.only_device.is_empty() {
+            let active_id = self.processor.devices.active().and_then(|d| d.id.as_ref());
+            if active_id != Some(&hot_corners.only_device) {
+                return;
+            }
+        }
+        let tick = get_cur_tick();
+        let corner = self
+            .processor
+            .relocator
+            .poll_corner_dwell(hot_corners.dwell_ms, tick);
+        if self.processor.relocator.is_corner_dwelling() {
+            // Keep polling frequently, or dwelling is noticed too late
+            self.last_busy_tick = tick;
+        }
+        if let Some(corner) = corner {
+            let action = self.processor.settings.hot_corners.action(corner);
+            debug!("Hot corner {:?} triggers {}", corner, action);
+            self.run_action(action);
+        }
+    }
+
+    // Respond the pending request with device id, or with None once it is timed out
+    fn resolve_identifying(&mut self, tick: u64, id: Option<String>) {
+        let Some((deadline, _)) = &self.identifying else {
+            return;
+        };
+        if id.is_none() && tick < *deadline {
+            return;
+        }
+        let (_, mut msg) = self.identifying.take().unwrap();
+        if let Message::IdentifyDevice(data) = &mut msg {
+            data.set_ok(id);
+        }
+        self.reactor().return_msg(msg);
+    }
+
+    fn report_active_device_changed(&mut self) {
+        if !self.processor.devices.pop_active_changed() {
+            return;
+        }
+        let Some(dev) = self.processor.devices.active() else {
+            return;
+        };
+        let Some(id) = &dev.id else {
+            return;
+        };
+        let event = ActiveDeviceEvent {
+            id: id.clone(),
+            device_type: dev.device_type,
+            positioning: dev.ctrl.positioning(),
+        };
+        if self.processor.settings.log_active_device {
+            info!(
+                "Active device changed: id={}, type={}, positioning={:?}",
+                event.id, event.device_type, event.positioning
+            );
+        }
+        let settings = &self.processor.settings;
+        self.processor.commands.run(
+            &settings.on_event_command,
+            DeviceEvent::Activated,
+            Some(&event.id),
+        );
+        if !self.headless {
+            self.reactor().report_active_device_changed(event);
+        }
+    }
+
+    fn apply_one_shortcut<B: HotKeyBackend>(
+        mgr: &mut HotKeyManager<ShortcutID, B>,
         hwnd: HWND,
         shortcut_str: &str,
+        positional: bool,
         id: ShortcutID,
     ) -> Result<()> {
         if shortcut_str.is_empty() {
@@ -685,7 +2414,7 @@ impl WinEventLoop {
             return Ok(());
         }
         let _ = mgr.unregister(hwnd, id as i32);
-        match shortcut_str_to_win(shortcut_str) {
+        match shortcut_str_to_win(shortcut_str, positional) {
             Some((modifier, key)) => {
                 match mgr.register(hwnd, id as i32, modifier, key, false, id) {
                     Err(Error::ShortcutConflict(_)) => {
@@ -698,62 +2427,485 @@ impl WinEventLoop {
         }
     }
 
-    fn register_shortcuts(&mut self) -> Result<()> {
-        let shortcuts = &self.processor.settings.shortcuts;
-        let mut last_error: Result<()> = Ok(());
-
-        if let Err(e) = Self::apply_one_shortcut(
-            &mut self.hotkey_mgr,
-            self.processor.hwnd,
-            &shortcuts.cur_mouse_lock,
-            ShortcutID::CurMouseLock,
-        ) {
-            error!("register shortcut cur_mouse_lock error: {}", e);
-            last_error = Err(e);
+    fn shortcut_list(shortcuts: &ShortcutSettings) -> [(&'static str, &String, ShortcutID); 10] {
+        // In order of ShortcutSettings::named()
+        const IDS: [ShortcutID; 10] = [
+            ShortcutID::CurMouseLock,
+            ShortcutID::CurMouseJumpNext,
+            ShortcutID::CurMouseTurbo,
+            ShortcutID::CurMouseJumpLeft,
+            ShortcutID::CurMouseJumpRight,
+            ShortcutID::CurMouseJumpUp,
+            ShortcutID::CurMouseJumpDown,
+            ShortcutID::MoveWindowHere,
+            ShortcutID::CurMouseFocusMode,
+            ShortcutID::CycleSwitchDevices,
+        ];
+        let named = shortcuts.named();
+        std::array::from_fn(|i| (named[i].0, named[i].1, IDS[i]))
+    }
+
+    // Independent of user shortcuts, which may be the broken ones
+    fn register_failsafe_shortcut(&mut self) {
+        if let Err(e) = Self::apply_one_shortcut(
+            &mut self.hotkey_mgr,
+            self.processor.hwnd,
+            FAILSAFE_SHORTCUT,
+            true,
+            ShortcutID::Failsafe,
+        ) {
+            warn!(
+                "Register failsafe shortcut {} failed: {}",
+                FAILSAFE_SHORTCUT, e
+            );
+            if let Error::ShortcutConflict(_) = e {
+                self.shortcut_retry.add(
+                    "failsafe",
+                    FAILSAFE_SHORTCUT,
+                    ShortcutID::Failsafe,
+                    get_cur_tick(),
+                );
+                self.report_pending_shortcuts();
+            }
+        }
+    }
+
+    fn register_shortcuts(&mut self) -> Result<()> {
+        let shortcuts = &self.processor.settings.shortcuts;
+        let mut last_error: Result<()> = Ok(());
+        let mut hooked = Vec::new();
+        let pending_before = self.shortcut_retry.names();
+        self.shortcut_retry
+            .pending
+            .retain(|(_, _, id)| matches!(id, ShortcutID::Failsafe));
+
+        for (name, shortcut_str, id) in Self::shortcut_list(shortcuts) {
+            if shortcuts.backend(name) == ShortcutBackend::Hook && !shortcut_str.is_empty() {
+                let _ = self.hotkey_mgr.unregister(self.processor.hwnd, id as i32);
+                match shortcut_str_to_hook(shortcut_str, shortcuts.positional_keys) {
+                    Some((modifier, key)) => hooked.push((modifier, key, id)),
+                    None => last_error = Err(Error::InvalidShortcut(shortcut_str.to_owned())),
+                }
+                continue;
+            }
+            if let Err(e) = Self::apply_one_shortcut(
+                &mut self.hotkey_mgr,
+                self.processor.hwnd,
+                shortcut_str,
+                shortcuts.positional_keys,
+                id,
+            ) {
+                error!("register shortcut {:?} error: {}", id, e);
+                if let Error::ShortcutConflict(_) = e {
+                    self.shortcut_retry
+                        .add(name, shortcut_str, id, get_cur_tick());
+                }
+                last_error = Err(e);
+            }
+        }
+        if self.shortcut_retry.names() != pending_before {
+            self.report_pending_shortcuts();
+        }
+
+        if let Err(e) = self.hook.set_keyboard(!hooked.is_empty()) {
+            error!("Set keyboard hook for shortcuts failed: {}", e);
+            hooked.clear();
+            last_error = Err(e);
+        }
+        self.processor.hook_shortcuts = hooked;
+        self.processor.hook_held = None;
+        self.processor.modifier_tap = None;
+        last_error
+    }
+
+    fn poll_shortcut_retry(&mut self, tick: u64) {
+        if !self.shortcut_retry.due(tick) {
+            return;
+        }
+        let hwnd = self.processor.hwnd;
+        let positional = self.processor.settings.shortcuts.positional_keys;
+        let mgr = &mut self.hotkey_mgr;
+        let before = self.shortcut_retry.pending.len();
+        self.shortcut_retry.pending.retain(|(name, shortcut, id)| {
+            match Self::apply_one_shortcut(mgr, hwnd, shortcut, positional, *id) {
+                Ok(_) => {
+                    info!("Shortcut {} {} registered by retrying", name, shortcut);
+                    false
+                }
+                Err(Error::ShortcutConflict(_)) => true,
+                Err(e) => {
+                    warn!("Retry registering shortcut {} failed: {}", name, e);
+                    false
+                }
+            }
+        });
+        if self.shortcut_retry.pending.len() != before {
+            self.report_pending_shortcuts();
+        }
+    }
+
+    // Published even if headless, only without waking a UI
+    fn report_pending_shortcuts(&self) {
+        let names = self.shortcut_retry.names();
+        if !self.headless {
+            self.reactor().report_shortcuts_pending(names);
+        } else if let Some(reactor) = &self.mouse_control_reactor {
+            reactor.bus().publish(BusEvent::ShortcutsPending(names));
+        }
+    }
+
+    fn on_shortcut(&mut self, hotkey_id: i32, lparam: u32, time: u32) {
+        match self.hotkey_mgr.get_callback(hotkey_id, lparam, time) {
+            Some(&id) => self.run_shortcut(id),
+            None => debug!("Stale hotkey event dropped: id={}", hotkey_id),
+        }
+    }
+
+    fn run_shortcut(&mut self, id: ShortcutID) {
+        match id {
+            ShortcutID::CurMouseLock => self.on_shortcut_cur_mouse_lock(),
+            ShortcutID::CurMouseJumpNext => self.on_shortcut_cur_mouse_jump_next(),
+            ShortcutID::CurMouseTurbo => self.on_shortcut_cur_mouse_turbo(),
+            ShortcutID::CurMouseJumpLeft => self.on_shortcut_cur_mouse_jump_to(Direction::Left),
+            ShortcutID::CurMouseJumpRight => self.on_shortcut_cur_mouse_jump_to(Direction::Right),
+            ShortcutID::CurMouseJumpUp => self.on_shortcut_cur_mouse_jump_to(Direction::Up),
+            ShortcutID::CurMouseJumpDown => self.on_shortcut_cur_mouse_jump_to(Direction::Down),
+            ShortcutID::MoveWindowHere => self.on_shortcut_move_window_here(),
+            ShortcutID::Failsafe => self.on_shortcut_failsafe(),
+            ShortcutID::CurMouseFocusMode => self.on_shortcut_cur_mouse_focus_mode(),
+            ShortcutID::CycleSwitchDevices => self.on_shortcut_cycle_switch_devices(),
+        }
+    }
+
+    fn on_remote_action(&mut self, action: RemoteAction) {
+        debug!("Remote action {} received", action.name());
+        match action {
+            RemoteAction::JumpNext => self.on_shortcut_cur_mouse_jump_next(),
+            RemoteAction::JumpLeft => self.on_shortcut_cur_mouse_jump_to(Direction::Left),
+            RemoteAction::JumpRight => self.on_shortcut_cur_mouse_jump_to(Direction::Right),
+            RemoteAction::JumpUp => self.on_shortcut_cur_mouse_jump_to(Direction::Up),
+            RemoteAction::JumpDown => self.on_shortcut_cur_mouse_jump_to(Direction::Down),
+            RemoteAction::LockToggle => self.on_shortcut_cur_mouse_lock(),
+            RemoteAction::Pause => self.toggle_user_pause(),
+            RemoteAction::MoveWindowHere => self.on_shortcut_move_window_here(),
+            RemoteAction::OpenUI => self.run_action(TrayAction::OpenUI),
+            RemoteAction::FocusMode => self.on_shortcut_cur_mouse_focus_mode(),
+        }
+    }
+
+    fn on_shortcut_failsafe(&mut self) {
+        warn!("Failsafe shortcut pressed, locking and switching of all devices dropped");
+        self.processor.disable_risky_features();
+        self.processor.release_all_devices();
+        if let Err(e) = self.apply_shortcuts(None) {
+            error!("Register shortcuts without hook failed: {}", e);
+        }
+        match get_primary_monitor_work_area() {
+            Ok(r) => {
+                let _ = set_cursor_pos((r.left + r.right) / 2, (r.top + r.bottom) / 2);
+            }
+            Err(e) => error!("Get primary monitor failed: {}", e),
+        }
+        if !self.headless {
+            self.reactor().report_failsafe_triggered();
+        }
+    }
+
+    fn on_shortcut_cur_mouse_lock(&mut self) {
+        debug!("Shortcut cur_mouse_lock pressed");
+        if self.headless {
+            self.processor.cur_mouse_lock_toogle();
+            if let Some(id) = self.processor.devices.active_id().cloned() {
+                self.persist_devices(&[id]);
+            }
+            return;
+        }
+        if let Some(id) = self.processor.devices.active_id().cloned() {
+            self.reactor().ui_tx.send(Message::LockCurMouse(id));
+        }
+    }
+
+    fn on_shortcut_cycle_switch_devices(&mut self) {
+        debug!("Shortcut cycle_switch_devices pressed");
+        let changes = self.processor.cycle_switch_devices();
+        if changes.is_empty() {
+            return;
+        }
+        if self.headless {
+            let ids: Vec<String> = changes.into_iter().map(|(id, _)| id).collect();
+            self.persist_devices(&ids);
+            return;
+        }
+        self.reactor().ui_tx.send(Message::SwitchCycled(changes));
+    }
+
+    // Headless only, see ConfigPersister
+    fn persist_devices(&mut self, ids: &[String]) {
+        let settings = &self.processor.settings;
+        if !settings.persist_shortcut_changes {
+            return;
+        }
+        let Some(persister) = &mut self.persister else {
+            return;
+        };
+        let items = settings
+            .devices
+            .iter()
+            .filter(|d| ids.contains(&d.id))
+            .cloned()
+            .collect();
+        persister.save(items);
+    }
+
+    fn on_shortcut_cur_mouse_jump_next(&mut self) {
+        debug!("Shortcut cut_mouse_jump pressed");
+        let from = get_cursor_pos().ok();
+        self.processor.relocator.jump_to_next_monitor(
+            self.processor.devices.active().map(|d| &mut d.ctrl),
+            self.processor.settings.jump_targets.jump_next,
+        );
+        self.report_monitor_jumped();
+        self.animate_jump(from);
+    }
+
+    fn on_shortcut_cur_mouse_jump_to(&mut self, dir: Direction) {
+        debug!("Shortcut cur_mouse_jump_to {:?} pressed", dir);
+        let from = get_cursor_pos().ok();
+        self.processor.relocator.jump_to_direction(
+            self.processor.devices.active().map(|d| &mut d.ctrl),
+            dir,
+            self.processor.settings.jump_targets.direction(dir),
+        );
+        self.report_monitor_jumped();
+        self.animate_jump(from);
+    }
+
+    fn report_monitor_jumped(&mut self) {
+        if self.processor.relocator.peek_relocate_pos().is_none() {
+            return;
+        }
+        let settings = &self.processor.settings;
+        let active_id = self.processor.devices.active_id();
+        self.processor.commands.run(
+            &settings.on_event_command,
+            DeviceEvent::Jumped,
+            active_id.map(|id| id.as_str()),
+        );
+        if !self.headless {
+            self.reactor().report_monitor_jumped();
+        }
+    }
+
+    fn animate_jump(&mut self, from: Option<(i32, i32)>) {
+        if !self.animate_jumps || self.headless {
+            return;
+        }
+        let relocator = &self.processor.relocator;
+        if let (Some((x, y)), Some(to), Some(area)) = (
+            from,
+            relocator.peek_relocate_pos(),
+            relocator.spanning_area(),
+        ) {
+            let tick = get_cur_tick();
+            self.overlay.start(&area, MousePos::from(x, y), to, tick);
+            self.last_busy_tick = tick;
+        }
+    }
+
+    // Published to the event bus, and shown by debug overlay if enabled
+    fn poll_relocations(&mut self, tick: u64) {
+        let relocations = std::mem::take(&mut self.processor.relocations);
+        if let Some(reactor) = &self.mouse_control_reactor {
+            for pos in relocations.iter() {
+                reactor.bus().publish(BusEvent::Relocated(*pos));
+            }
+        }
+        // Debug builds show it regardless of settings, not to have it saved into configs
+        let enabled = self.processor.settings.debug_overlay || cfg!(debug_assertions);
+        if !enabled || self.headless {
+            self.debug_overlay.destroy();
+            return;
+        }
+        let Some(area) = self.processor.relocator.spanning_area() else {
+            return;
+        };
+        let locked = self
+            .processor
+            .devices
+            .iter()
+            .filter_map(|d| d.ctrl.locked_area().copied())
+            .collect();
+        if self.debug_overlay.update(&area, locked, relocations, tick) {
+            // Keep polling frequently while marks are flashing
+            self.last_busy_tick = tick;
+        }
+    }
+
+    fn on_shortcut_move_window_here(&mut self) {
+        debug!("Shortcut move_window_here pressed");
+        let result = get_cursor_pos().and_then(|(x, y)| move_foreground_window_to(x, y));
+        match result {
+            Ok(true) => debug!("Foreground window moved"),
+            Ok(false) => (),
+            Err(e) => warn!("Move foreground window failed: {}", e),
+        }
+    }
+
+    // Focus on the monitor current mouse is locked to, or the one of cursor if not locked
+    fn on_shortcut_cur_mouse_focus_mode(&mut self) {
+        debug!("Shortcut cur_mouse_focus_mode pressed");
+        if self.focus_dim.focused().is_some() {
+            self.release_focus_mode();
+            return;
+        }
+        let Some(dev) = self.processor.devices.active() else {
+            return;
+        };
+        let (id, locked) = (dev.id.clone(), dev.ctrl.locked_area().copied());
+        let relocator = &self.processor.relocator;
+        let area = locked.or_else(|| {
+            let (x, y) = get_cursor_pos().ok()?;
+            relocator.locate_area(&MousePos::from(x, y))
+        });
+        let Some(area) = area else {
+            return;
+        };
+        let dim_percent = self.processor.settings.focus_mode.dim_percent;
+        self.focus_dim
+            .show(relocator.monitor_areas(), &area, dim_percent);
+        self.focus_device = id;
+    }
+
+    fn release_focus_mode(&mut self) {
+        self.focus_dim.hide();
+        self.focus_device = None;
+    }
+
+    fn poll_startup_scan(&mut self, tick: u64) {
+        let grace_ms = self.processor.settings.startup_grace_secs as u64 * 1000;
+        let Some(scan) = &mut self.startup_scan else {
+            return;
+        };
+        let grace_ended = scan.in_grace && tick >= scan.start_tick + grace_ms;
+        if grace_ended {
+            scan.in_grace = false;
+        }
+        if scan.due(tick) {
+            debug!("Rescan devices and monitors after startup");
+            self.processor.rescan_arrived_devices();
+            self.processor.rescan_monitors();
+        }
+        if self
+            .startup_scan
+            .as_ref()
+            .is_some_and(StartupScan::finished)
+        {
+            self.startup_scan = None;
+        }
+        if grace_ended && !self.headless {
+            self.reactor().report_initial_scan(false);
+        }
+    }
+
+    fn poll_monitors_settling(&mut self, tick: u64) {
+        let ask = self.processor.settings.confirm_monitor_changes && !self.headless;
+        let Some(settling) = &mut self.processor.monitors_settling else {
+            return;
+        };
+        if settling.asked || tick < settling.until_tick {
+            return;
         }
-
-        if let Err(e) = Self::apply_one_shortcut(
-            &mut self.hotkey_mgr,
-            self.processor.hwnd,
-            &shortcuts.cur_mouse_jump_next,
-            ShortcutID::CurMouseJumpNext,
-        ) {
-            error!("register shortcut cur_mouse_jump_next error: {}", e);
-            last_error = Err(e);
+        if ask {
+            settling.asked = true;
+            let layout = settling.layout.clone();
+            self.reactor().report_monitors_changed(layout);
+        } else {
+            self.end_monitors_settling(true);
         }
+    }
 
-        last_error
+    // Locks are applied again on the monitors then, unless paused instead
+    fn end_monitors_settling(&mut self, apply: bool) {
+        if self.processor.monitors_settling.take().is_none() {
+            return;
+        }
+        info!("Monitors settled, apply locks: {}", apply);
+        self.processor.to_update_monitors = true;
+        if !apply && !self.processor.user_paused {
+            self.toggle_user_pause();
+        }
     }
 
-    fn on_shortcut(&mut self, cb: u32) {
-        let id = match self.hotkey_mgr.get_callback(cb) {
-            Some(v) => v,
-            None => return,
+    fn poll_kvm(&mut self, tick: u64) {
+        let Some(remote) = self.kvm.poll(&self.processor.settings.kvm, tick) else {
+            return;
         };
-        match id {
-            ShortcutID::CurMouseLock => self.on_shortcut_cur_mouse_lock(),
-            ShortcutID::CurMouseJumpNext => self.on_shortcut_cur_mouse_jump_next(),
+        info!(
+            "Input routed to another machine by software KVM: {}",
+            remote
+        );
+        self.processor.kvm_remote = remote;
+        if let Some(reactor) = &self.mouse_control_reactor {
+            reactor.bus().publish(BusEvent::KvmRemoteChanged(remote));
         }
     }
 
-    fn on_shortcut_cur_mouse_lock(&mut self) {
-        debug!("Shortcut cur_mouse_lock pressed");
-        if self.headless {
-            self.processor.cur_mouse_lock_toogle();
+    // Published whenever is_paused() changes, whatever the reason is
+    fn poll_paused(&mut self) {
+        let paused = self.processor.is_paused();
+        if paused == self.reported_paused {
             return;
         }
-        if let Some(id) = self.processor.devices.active_id() {
-            self.mouse_control_reactor
-                .ui_tx
-                .send(Message::LockCurMouse(id.clone()));
+        self.reported_paused = paused;
+        if let Some(reactor) = &self.mouse_control_reactor {
+            reactor.bus().publish(BusEvent::PausedChanged(paused));
         }
     }
 
-    fn on_shortcut_cur_mouse_jump_next(&mut self) {
-        debug!("Shortcut cut_mouse_jump pressed");
-        self.processor
-            .relocator
-            .jump_to_next_monitor(self.processor.devices.active().map(|d| &mut d.ctrl))
+    // Only watched while any app rule is enabled
+    fn poll_foreground(&mut self, tick: u64) {
+        if !self.processor.settings.app_rules.iter().any(|r| r.enabled) {
+            if self.processor.foreground_app.is_some() {
+                self.foreground.reset();
+                self.processor.on_foreground_changed(None);
+            }
+            return;
+        }
+        let Some(app) = self.foreground.poll(tick) else {
+            return;
+        };
+        debug!("Foreground program: {}", app.as_deref().unwrap_or("none"));
+        self.processor.on_foreground_changed(app);
+    }
+
+    // Released if its device goes idle or away, or monitors change
+    fn poll_focus_mode(&mut self, tick: u64) {
+        let Some(focused) = self.focus_dim.focused() else {
+            return;
+        };
+        let idle_ms = self.processor.settings.focus_mode.release_after_idle_secs as u64 * 1000;
+        let last_tick = self
+            .processor
+            .devices
+            .iter()
+            .find(|d| d.id.is_some() && d.id == self.focus_device)
+            .map(|d| d.ctrl.get_last_pos().map_or(0, |(t, _, _)| t));
+        let release = match last_tick {
+            None => true,
+            Some(t) => idle_ms > 0 && t + idle_ms < tick,
+        } || !self.processor.relocator.monitor_areas().contains(focused);
+        if release {
+            self.release_focus_mode();
+        }
+    }
+
+    fn on_shortcut_cur_mouse_turbo(&mut self) {
+        debug!("Shortcut cur_mouse_turbo pressed");
+        // Hotkey only notifies pressing. Releasing is checked inside hook.
+        let shortcuts = &self.processor.settings.shortcuts;
+        self.processor.turbo_key =
+            shortcut_str_to_win(&shortcuts.cur_mouse_turbo, shortcuts.positional_keys)
+                .map(|(_, key)| key);
     }
 }
 
@@ -765,26 +2917,280 @@ impl WinEventLoop {
             hook,
             processor,
             headless,
+            safe_mode: false,
             hotkey_mgr: HotKeyManager::new(),
-            mouse_control_reactor,
+            tracking_mode: TrackingMode::Hook,
+            hook_retry: None,
+            last_polled_pos: None,
+            mouse_control_reactor: Some(mouse_control_reactor),
+            last_busy_tick: 0,
+            overlay: Box::default(),
+            animate_jumps: false,
+            reported_paused: false,
+            debug_overlay: Box::default(),
+            identifying: None,
+            capturing_trace: None,
+            capturing_extent: None,
+            cursor_styler: CursorStyler::new(),
+            focus_dim: Box::default(),
+            focus_device: None,
+            startup_scan: None,
+            shortcut_retry: ShortcutRetry::default(),
+            kvm: KvmWatcher::default(),
+            foreground: ForegroundWatcher::default(),
+            scheduling: ThreadScheduling::default(),
+            persister: None,
+            status_watch: StatusWatch::default(),
+            shut_down: false,
         }
     }
 
     pub fn initialize(&mut self) -> Result<()> {
+        self.shut_down = false;
         self.setup_window()?;
         self.processor.initialize()?;
-        self.hook.register()?;
+        self.startup_scan = Some(StartupScan::new(get_cur_tick()));
+        if !self.headless {
+            self.reactor().report_initial_scan(true);
+        }
+        self.register_failsafe_shortcut();
+        if self.safe_mode {
+            warn!("Safe mode, mouse hook and shortcuts are not registered");
+        } else {
+            self.apply_tracking_mode();
+        }
+        Ok(())
+    }
+
+    // Security software may block the mouse hook, then cursor position is polled instead
+    fn apply_tracking_mode(&mut self) {
+        if self.safe_mode {
+            return;
+        }
+        let mode = if self.processor.settings.cursor_polling {
+            let _ = self.hook.set_mouse(false);
+            self.hook_retry = None;
+            TrackingMode::Polling
+        } else {
+            match self.hook.set_mouse(true) {
+                Ok(_) => {
+                    self.hook_retry = None;
+                    TrackingMode::Hook
+                }
+                Err(e) => {
+                    warn!("Install mouse hook failed, polling cursor instead: {}", e);
+                    let interval_ms = self.hook_retry.as_ref().map_or(HOOK_RETRY_FIRST_MS, |r| {
+                        (r.interval_ms * 2).min(HOOK_RETRY_MAX_MS)
+                    });
+                    self.hook_retry = Some(HookRetry {
+                        next_tick: get_cur_tick() + interval_ms,
+                        interval_ms,
+                    });
+                    TrackingMode::Polling
+                }
+            }
+        };
+        if mode == self.tracking_mode {
+            return;
+        }
+        info!("Cursor tracked by {}", mode);
+        self.tracking_mode = mode;
+        self.last_polled_pos = None;
+        if !self.headless {
+            self.reactor().report_tracking_mode_changed(mode);
+        }
+    }
+
+    fn poll_hook_retry(&mut self, tick: u64) {
+        if self
+            .hook_retry
+            .as_ref()
+            .is_some_and(|r| tick >= r.next_tick)
+        {
+            debug!("Retry installing mouse hook");
+            self.apply_tracking_mode();
+        }
+    }
+
+    // Moves between samples are missed, so cursor may stay out of its locked monitor
+    // for a moment. Turbo, gestures and click debouncing need the hook.
+    fn poll_cursor_pos(&mut self) {
+        if self.tracking_mode != TrackingMode::Polling || self.processor.is_paused() {
+            return;
+        }
+        let Ok((x, y)) = get_cursor_pos() else {
+            return;
+        };
+        let pos = MousePos::from(x, y);
+        if self.last_polled_pos == Some(pos) {
+            return;
+        }
+        self.last_polled_pos = Some(pos);
+        let processor = &mut *self.processor;
+        let ctrl = processor.devices.active().map(|v| &mut v.ctrl);
+        processor.relocator.on_cursor_update(ctrl, pos);
+        processor.resolve_relocation();
+        // Keep sampling frequently while moving
+        self.last_busy_tick = get_cur_tick();
+    }
+
+    // Must be set before initialize()
+    pub fn set_safe_mode(&mut self, safe_mode: bool) {
+        self.safe_mode = safe_mode;
+        self.processor.safe_mode = safe_mode;
+    }
+
+    // Headless only, UI saves config by itself. Written only if persist_shortcut_changes is on.
+    pub fn set_config_file(&mut self, path: PathBuf, saved: Settings) {
+        self.persister = Some(ConfigPersister::new(path, saved));
+    }
+
+    // Load usage stats from the file, which is flushed periodically. Kept only in
+    // memory if not set.
+    pub fn set_stats_path(&mut self, path: Option<PathBuf>) {
+        self.processor.stats = StatsAggregator::load(path);
+    }
+
+    // Record raw events into the file for some seconds. Only one capture at a time.
+    pub fn start_trace(&mut self, path: &Path, secs: u32) -> Result<()> {
+        if self.processor.tracer.is_some() {
+            return Err(Error::InvalidParam(
+                "trace".to_owned(),
+                "already capturing".to_owned(),
+            ));
+        }
+        self.processor.tracer = Some(EventTracer::start(path, secs, get_cur_tick())?);
+        info!("Capturing trace into {} for {}s", path.display(), secs);
         Ok(())
     }
 
+    fn resolve_trace(&mut self, tick: u64, force: bool) {
+        if !self
+            .processor
+            .tracer
+            .as_ref()
+            .is_some_and(|t| force || t.is_due(tick))
+        {
+            return;
+        }
+        let result = self.processor.tracer.take().unwrap().finish();
+        match &result {
+            Ok((path, records)) => {
+                info!("Trace of {} records saved to {}", records, path.display())
+            }
+            Err(e) => error!("Capture trace failed: {}", e),
+        }
+        if let Some(mut msg) = self.capturing_trace.take() {
+            if let Message::CaptureTrace(data) = &mut msg {
+                data.set_result(result);
+            }
+            self.reactor().return_msg(msg);
+        }
+    }
+
+    fn start_extent_capture(&mut self, id: &str, secs: u32) -> Result<()> {
+        let processor = &mut *self.processor;
+        let Some(dev) = processor
+            .devices
+            .iter()
+            .find(|d| d.id.as_deref() == Some(id))
+        else {
+            return Err(Error::InvalidParam(
+                "device".to_owned(),
+                format!("not connected: {}", id),
+            ));
+        };
+        debug!("Capturing extent of device {} for {}s", id, secs);
+        processor
+            .relocator
+            .start_extent_capture(dev.handle.0 as u64);
+        Ok(())
+    }
+
+    // Respond the pending request with the extent, once it is due
+    fn resolve_extent_capture(&mut self, tick: u64) {
+        if !self
+            .capturing_extent
+            .as_ref()
+            .is_some_and(|(deadline, _)| tick >= *deadline)
+        {
+            return;
+        }
+        let (_, mut msg) = self.capturing_extent.take().unwrap();
+        let extent = self.processor.relocator.finish_extent_capture();
+        if let Message::CaptureDeviceExtent(data) = &mut msg {
+            match extent {
+                Some(v) if v.rigtbtm.x > v.lefttop.x && v.rigtbtm.y > v.lefttop.y => {
+                    data.set_ok((v, self.processor.relocator.monitor_labels()))
+                }
+                _ => data.set_error(Error::InvalidParam(
+                    "extent".to_owned(),
+                    "device was not swept over its surface".to_owned(),
+                )),
+            }
+        }
+        self.reactor().return_msg(msg);
+    }
+
+    // Give back the reactor, and release the global processor, so that a new eventloop can
+    // be created later(e.g. restarting after a crashed eventloop thread).
+    pub fn into_reactor(mut self) -> MouseControlReactor {
+        // Must be done before the processor released, Drop will not touch it again
+        let _ = self.shutdown();
+        unsafe {
+            let _ = G_PROCESSOR.take();
+        }
+        self.mouse_control_reactor.take().unwrap()
+    }
+
+    #[inline]
+    fn reactor(&self) -> &MouseControlReactor {
+        self.mouse_control_reactor.as_ref().unwrap()
+    }
+
     pub fn load_config(&mut self, config: Settings) -> Result<()> {
         self.apply_new_settings(config.processor)
     }
 
-    pub fn terminate(&mut self) -> Result<()> {
-        self.hook.unregister()?;
-        self.processor.terminate()?;
-        Ok(())
+    // Release all resources registered to system. Safe to be called more than once, and
+    // it is also called when dropped. Returns the last error, though it tries releasing all.
+    pub fn shutdown(&mut self) -> Result<()> {
+        if self.shut_down {
+            return Ok(());
+        }
+        self.shut_down = true;
+        let mut last_error = Ok(());
+        let mut check = |what: &str, r: Result<()>| {
+            if let Err(e) = r {
+                warn!("Shutdown: {} failed: {}", what, e);
+                last_error = Err(e);
+            }
+        };
+
+        check("unregister hook", self.hook.unregister());
+        check("unregister etw provider", etw::set_enabled(false));
+        check(
+            "flush usage stats",
+            self.processor.flush_stats(get_cur_tick()),
+        );
+        self.resolve_trace(get_cur_tick(), true);
+        self.overlay.destroy();
+        self.debug_overlay.destroy();
+        self.release_focus_mode();
+        self.cursor_styler.set(None);
+        let hwnd = self.processor.hwnd;
+        if hwnd.0 != 0 {
+            check("unregister hotkeys", self.hotkey_mgr.unregister_all(hwnd));
+            let _ = unregister_session_notification(hwnd);
+            check("unregister rawinput", self.processor.terminate());
+            check(
+                "remove subclass",
+                remove_subclass::<Self>(hwnd, SUBCLASS_UID),
+            );
+            check("destroy window", destroy_window(hwnd));
+            self.processor.hwnd = HWND::default();
+        }
+        last_error
     }
 
     pub fn setup_window(&mut self) -> Result<()> {
@@ -806,34 +3212,86 @@ impl WinEventLoop {
                 return Err(e);
             }
         };
+        if let Err(e) = register_session_notification(hwnd) {
+            warn!("Register session notification failed: {}", e);
+        }
+        if let Err(e) = set_window_text(hwnd, EVENTLOOP_WINDOW_TITLE) {
+            warn!("Set eventloop window title failed: {}", e);
+        }
         self.processor.hwnd = hwnd;
         Ok(())
     }
 
     fn handle_wm_message(&mut self, msg: &MSG) {
         match msg.message {
-            WM_INPUT => self
-                .processor
-                .on_raw_input(msg.wParam, msg.lParam, msg.time),
+            WM_INPUT => {
+                let _span = etw::span(SpanKind::WmInput);
+                INPUT_EVENTS.fetch_add(1, Ordering::Relaxed);
+                if ms_since_message(msg.time) > LATE_INPUT_EVENT_MS {
+                    LATE_INPUT_EVENTS.fetch_add(1, Ordering::Relaxed);
+                }
+                self.processor
+                    .on_raw_input(msg.wParam, msg.lParam, msg.time);
+                self.report_active_device_changed();
+                if self.identifying.is_some() {
+                    let id = self.processor.devices.active().and_then(|d| d.id.clone());
+                    self.resolve_identifying(get_cur_tick(), id);
+                }
+            }
             WM_INPUT_DEVICE_CHANGE => {
-                debug!("Trigger updating devices by WM_INPUT_DEVICE_CHANGE");
-                self.processor.to_update_devices = true;
+                let handle = HANDLE(msg.lParam.0);
+                debug!(
+                    "Update device by WM_INPUT_DEVICE_CHANGE: {} {}",
+                    msg.wParam.0, handle.0
+                );
+                self.processor.on_device_change(msg.wParam.0 as u32, handle);
             }
             WM_HOTKEY => {
-                self.on_shortcut(msg.lParam.0 as u32);
+                self.on_shortcut(msg.wParam.0 as i32, msg.lParam.0 as u32, msg.time);
                 self.processor.resolve_relocation();
             }
+            WM_REMOTE_ACTION => match RemoteAction::from_id(msg.wParam.0) {
+                Some(action) => {
+                    self.on_remote_action(action);
+                    self.processor.resolve_relocation();
+                }
+                None => warn!("Unknown remote action: {}", msg.wParam.0),
+            },
             // And some messages caught by self.subclass_callback()
             _ => (),
         }
     }
 
+    // Short timeout while events are flowing, longer one when idle to reduce wakeups
+    // Clamped, since settings of UI are not validated, and 0 of them spins the eventloop
+    pub fn poll_params(&self) -> (u32, u32) {
+        let s = &self.processor.settings;
+        let timeout_ms = if get_cur_tick() < self.last_busy_tick + WIN_EVENTLOOP_BUSY_LAST_FOR_MS {
+            s.poll_busy_timeout_ms.clamp(
+                *limits::POLL_BUSY_TIMEOUT_MS.start(),
+                *limits::POLL_BUSY_TIMEOUT_MS.end(),
+            )
+        } else {
+            s.poll_idle_timeout_ms.clamp(
+                *limits::POLL_IDLE_TIMEOUT_MS.start(),
+                *limits::POLL_IDLE_TIMEOUT_MS.end(),
+            )
+        };
+        let max_messages = s.poll_max_messages.clamp(
+            *limits::POLL_MAX_MESSAGES.start(),
+            *limits::POLL_MAX_MESSAGES.end(),
+        );
+        (max_messages, timeout_ms)
+    }
+
     #[inline]
     pub fn poll_wm_messages(&mut self, mut max_events: u32, timeout_ms: u32) -> Result<bool> {
         let mut msg = MSG::default();
+        let max_events_orig = max_events;
 
         unsafe {
             MsgWaitForMultipleObjects(None, false, timeout_ms, QS_ALLINPUT);
+            EVENTLOOP_WAKEUPS.fetch_add(1, Ordering::Relaxed);
             while max_events > 0
                 && PeekMessageW(&mut msg, HWND::default(), 0, 0, PM_REMOVE).as_bool()
             {
@@ -843,12 +3301,79 @@ impl WinEventLoop {
                 self.handle_wm_message(&msg);
                 TranslateMessage(&msg);
                 DispatchMessageW(&msg);
+                resume_callback_panic();
                 max_events -= 1;
             }
         }
+        // Hooks may also be called while waiting or peeking
+        resume_callback_panic();
+
+        if max_events < max_events_orig {
+            self.last_busy_tick = get_cur_tick();
+        }
 
         // Also try to update resources if need, though no external messages come
+        self.poll_startup_scan(get_cur_tick());
+        self.poll_monitors_settling(get_cur_tick());
+        self.processor.revert_temporary_settings(get_cur_tick());
         self.processor.resolve_pending_updating_task();
+        self.processor.resolve_relocation();
+        self.poll_hook_retry(get_cur_tick());
+        self.poll_cursor_pos();
+        self.poll_hot_corners();
+        self.poll_gestures();
+        self.poll_hook_shortcuts();
+        self.poll_shortcut_retry(get_cur_tick());
+        self.poll_focus_mode(get_cur_tick());
+        self.poll_kvm(get_cur_tick());
+        self.poll_foreground(get_cur_tick());
+        self.poll_paused();
+        let s = &self.processor.settings;
+        self.scheduling.apply(s.thread_priority, s.mmcss);
+        self.update_cursor_style();
+        self.resolve_identifying(get_cur_tick(), None);
+        self.resolve_trace(get_cur_tick(), false);
+        self.resolve_extent_capture(get_cur_tick());
+        if self.overlay.tick(get_cur_tick()) {
+            // Keep polling frequently while animating
+            self.last_busy_tick = get_cur_tick();
+        }
+        self.poll_relocations(get_cur_tick());
+        self.poll_status_watch(get_cur_tick());
+        #[cfg(debug_assertions)]
+        if self.processor.step_simulated_devices() {
+            // Keep polling frequently while simulating
+            self.last_busy_tick = get_cur_tick();
+        }
+        let tick = get_cur_tick();
+        if self.processor.stats.should_flush(tick) {
+            if let Err(e) = self.processor.flush_stats(tick) {
+                warn!("Flush usage stats failed: {}", e);
+            }
+        }
+        if let Some(levels) = self.processor.poll_battery() {
+            if !self.headless {
+                self.reactor().report_battery_updated(levels);
+            }
+        }
+        let rule_changes = self.processor.take_rule_changes();
+        if !rule_changes.is_empty() && !self.headless {
+            self.reactor()
+                .ui_tx
+                .send(Message::DeviceSettingsChanged(rule_changes));
+        }
+        if self.processor.pop_devices_changed() {
+            let devices = self.valid_generic_devices();
+            crash_report::set_devices(
+                devices
+                    .iter()
+                    .map(|d| format!("{:?} {} {}", d.device_type, d.product_name, d.id))
+                    .collect(),
+            );
+            if !self.headless {
+                self.reactor().report_devices_updated(devices);
+            }
+        }
 
         Ok(true)
     }
@@ -856,40 +3381,172 @@ impl WinEventLoop {
     pub fn run(&mut self) -> Result<()> {
         self.initialize()?;
         loop {
-            if !self.poll_wm_messages(
-                WIN_EVENTLOOP_POLL_MAX_MESSAGES,
-                WIN_EVENTLOOP_POLL_WAIT_TIMEOUT_MS,
-            )? {
+            let (max_events, timeout_ms) = self.poll_params();
+            if !self.poll_wm_messages(max_events, timeout_ms)? {
                 break;
             }
         }
-        self.terminate()?;
-        Ok(())
+        self.shutdown()
+    }
+}
+
+impl WinEventLoop {
+    // Try registering everything the eventloop needs, then release them. Must be called
+    // instead of initialize().
+    pub fn diagnose(&mut self, settings: &Settings) -> Vec<DiagnosticCheck> {
+        let mut checks = vec![DiagnosticCheck::new(
+            "dpi_awareness",
+            match process_set_dpi_aware() {
+                true => Ok("per monitor"),
+                false => Err("cannot be set"),
+            },
+            "Check compatibility settings of the executable, which may override DPI awareness",
+        )];
+
+        if let Err(e) = self.setup_window() {
+            checks.push(DiagnosticCheck::new::<&str, _>(
+                "window",
+                Err(e),
+                "Cannot create the message window, other checks are skipped",
+            ));
+            return checks;
+        }
+
+        checks.push(DiagnosticCheck::new(
+            "rawinput",
+            self.processor
+                .register_raw_devices(&settings.processor.usages_to_register())
+                .map(|_| "registered"),
+            "Make sure it runs in an interactive desktop session, not as a service",
+        ));
+
+        checks.push(DiagnosticCheck::new(
+            "mouse_hook",
+            self.hook.register().map(|_| "installed"),
+            "Security software may block low-level mouse hooks, try allowing monmouse. Cursor is polled instead meanwhile.",
+        ));
+
+        let mut failed = Vec::new();
+        let mut registered = 0;
+        let shortcuts = &settings.processor.shortcuts;
+        for (name, shortcut_str, id) in Self::shortcut_list(shortcuts) {
+            // Detected by keyboard hook, never registered
+            if shortcut_str.is_empty() || shortcuts.backend(name) == ShortcutBackend::Hook {
+                continue;
+            }
+            match Self::apply_one_shortcut(
+                &mut self.hotkey_mgr,
+                self.processor.hwnd,
+                shortcut_str,
+                shortcuts.positional_keys,
+                id,
+            ) {
+                Ok(_) => registered += 1,
+                Err(e) => failed.push(e.to_string()),
+            }
+        }
+        checks.push(DiagnosticCheck::new(
+            "hotkeys",
+            match failed.is_empty() {
+                true => Ok(format!("{} registered", registered)),
+                false => Err(failed.join(", ")),
+            },
+            "Choose other shortcuts, or close the program already holding them",
+        ));
+
+        checks.push(DiagnosticCheck::new(
+            "monitors",
+            match get_all_monitors_info() {
+                Ok(v) if v.is_empty() => Err("none found".to_owned()),
+                Ok(v) => Ok(format!("{} found", v.len())),
+                Err(e) => Err(e.to_string()),
+            },
+            "Check display drivers, and whether the session is locked or disconnected",
+        ));
+
+        if let Err(e) = self.shutdown() {
+            warn!("Release resources of diagnostics failed: {}", e);
+        }
+        checks
+    }
+}
+
+impl Drop for WinEventLoop {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
     }
 }
 
 impl WinEventLoop {
+    // Returns labels of monitors found, in jumping order
+    pub fn refresh_monitors(&mut self) -> Result<Vec<String>> {
+        self.processor.try_update_monitors(true)?;
+        Ok(self.processor.relocator.monitor_labels())
+    }
+
     pub fn scan_devices(&mut self) -> Result<Vec<GenericDevice>> {
         match self.processor.try_update_devices(true) {
-            Ok(_) => Ok(self
-                .processor
-                .devices
-                .iter()
-                .filter(|&v| Self::is_valid_win_device(v))
-                .map(Self::win_device_to_generic)
-                .collect()),
+            Ok(_) => Ok(self.valid_generic_devices()),
             Err(e) => Err(e),
         }
     }
 
+    fn valid_generic_devices(&self) -> Vec<GenericDevice> {
+        self.processor
+            .devices
+            .iter()
+            .filter(|&v| Self::is_valid_win_device(v))
+            .map(Self::win_device_to_generic)
+            .collect()
+    }
+
     fn apply_new_settings(&mut self, new_settings: ProcessorSettings) -> Result<()> {
         self.processor.apply_processor_settings(Some(new_settings));
+        self.switch_etw_tracing();
+        self.apply_tracking_mode();
+        self.publish_settings_applied();
+        self.apply_shortcuts(None)
+    }
+
+    fn apply_global_options(&mut self, new_settings: ProcessorSettings) {
+        self.processor.apply_global_options(new_settings);
+        self.switch_etw_tracing();
+        self.apply_tracking_mode();
+        self.publish_settings_applied();
+    }
+
+    fn publish_settings_applied(&self) {
+        let Some(reactor) = &self.mouse_control_reactor else {
+            return;
+        };
+        if reactor.bus().has_subscribers(Topic::Settings) {
+            let settings = Arc::new(self.processor.settings.clone());
+            reactor.bus().publish(BusEvent::SettingsApplied(settings));
+        }
+    }
+
+    fn switch_etw_tracing(&self) {
+        if let Err(e) = etw::set_enabled(self.processor.settings.etw_tracing) {
+            warn!("Failed to switch ETW tracing: {}", e);
+        }
+    }
+
+    // Register shortcuts again, with new ones if given
+    fn apply_shortcuts(&mut self, shortcuts: Option<ShortcutSettings>) -> Result<()> {
+        if let Some(shortcuts) = shortcuts {
+            self.processor.settings.shortcuts = shortcuts;
+            self.processor.gate_risky_features();
+            self.processor.publish_settings();
+        }
+        if self.safe_mode {
+            return Ok(());
+        }
         self.register_shortcuts()
     }
 
     pub fn poll_messages(&mut self) -> bool {
         loop {
-            let mut msg = match self.mouse_control_reactor.mouse_control_rx.try_recv() {
+            let mut msg = match self.reactor().mouse_control_rx.try_recv() {
                 Some(msg) => msg,
                 None => return false,
             };
@@ -900,36 +3557,109 @@ impl WinEventLoop {
                 }
                 Message::ScanDevices(data) => {
                     data.set_result(self.scan_devices());
-                    self.mouse_control_reactor.return_msg(msg)
+                    self.reactor().return_msg(msg)
                 }
                 Message::InspectDevicesStatus(data) => {
-                    let tick = get_cur_tick();
-                    let ret = self
-                        .processor
-                        .devices
-                        .iter()
-                        .filter(|&v| Self::is_valid_win_device(v))
-                        .map(|d| {
-                            (
-                                d.id.as_ref().unwrap().clone(),
-                                Self::build_device_status(d, tick),
-                            )
-                        })
-                        .collect();
-                    data.set_ok(ret);
-                    self.mouse_control_reactor.return_msg(msg)
+                    data.set_ok(self.devices_status(get_cur_tick()));
+                    self.reactor().return_msg(msg)
+                }
+                Message::WatchDevicesStatus(interval_ms) => self.status_watch.set(*interval_ms),
+                Message::RefreshMonitors(data) => {
+                    data.set_result(self.refresh_monitors());
+                    self.reactor().return_msg(msg)
+                }
+                Message::InspectUsageStats(data) => {
+                    // Make pending events visible, the file is also written then
+                    match self.processor.flush_stats(get_cur_tick()) {
+                        Ok(_) => {
+                            data.set_ok((get_local_date(), self.processor.stats.stats().clone()))
+                        }
+                        Err(e) => data.set_error(e),
+                    }
+                    self.reactor().return_msg(msg)
+                }
+                Message::CaptureTrace(data) => {
+                    let (path, secs) = data.take_req();
+                    match self.start_trace(&path, secs) {
+                        Ok(_) => self.capturing_trace = Some(msg),
+                        Err(e) => {
+                            data.set_error(e);
+                            self.reactor().return_msg(msg)
+                        }
+                    }
+                }
+                Message::CaptureDeviceExtent(data) => {
+                    let (id, secs) = data.take_req();
+                    match self.start_extent_capture(&id, secs) {
+                        Ok(_) => {
+                            let deadline = get_cur_tick() + secs as u64 * 1000;
+                            self.capturing_extent = Some((deadline, msg));
+                        }
+                        Err(e) => {
+                            data.set_error(e);
+                            self.reactor().return_msg(msg)
+                        }
+                    }
+                }
+                Message::IdentifyDevice(_) => {
+                    debug!("Identifying device by its next event");
+                    self.identifying = Some((get_cur_tick() + IDENTIFY_DEVICE_TIMEOUT_MS, msg));
                 }
                 Message::ApplyProcessorSetting(data) => {
                     let req = data.take_req();
                     data.set_result(self.apply_new_settings(req));
-                    self.mouse_control_reactor.return_msg(msg)
+                    self.reactor().return_msg(msg)
+                }
+                Message::ApplyShortcuts(data) => {
+                    let req = data.take_req();
+                    data.set_result(self.apply_shortcuts(Some(req)));
+                    self.reactor().return_msg(msg)
+                }
+                Message::ApplyGlobalProcessorOptions(data) => {
+                    let req = data.take_req();
+                    self.apply_global_options(req);
+                    data.set_ok(());
+                    self.reactor().return_msg(msg)
+                }
+                Message::ApplyDeviceList(data) => {
+                    let req = data.take_req();
+                    self.processor.apply_device_list(req);
+                    data.set_ok(());
+                    self.reactor().return_msg(msg)
+                }
+                Message::ApplyDeviceSettings(data) => {
+                    let before = self.processor.device_settings();
+                    for item in data.take() {
+                        self.processor
+                            .temporary_settings
+                            .retain(|t| t.id != item.id);
+                        let content = self.processor.gated_device_setting(&item.content);
+                        self.processor
+                            .devices
+                            .update_one_device_settings(&item.id, &content);
+                    }
+                    self.processor.run_toggled_commands(before);
+                }
+                Message::TemporaryApply(data) => {
+                    let (item, secs) = data.take();
+                    self.processor.apply_temporary(item, secs);
+                }
+                #[cfg(debug_assertions)]
+                Message::AddSimulatedDevice(data) => {
+                    if let Err(e) = self.processor.add_simulated_device(data.take()) {
+                        error!("Add simulated device failed: {}", e);
+                    }
                 }
-                Message::ApplyOneDeviceSetting(data) => {
-                    let item = data.take();
-                    self.processor
-                        .devices
-                        .update_one_device_settings(&item.id, &item.content);
+                #[cfg(debug_assertions)]
+                Message::ClearSimulatedDevices => {
+                    if let Err(e) = self.processor.clear_simulated_devices() {
+                        error!("Clear simulated devices failed: {}", e);
+                    }
                 }
+                Message::TogglePause => self.toggle_user_pause(),
+                Message::ConfirmMonitorsChanged(apply) => self.end_monitors_settling(*apply),
+                Message::JumpNextMonitor => self.run_action(TrayAction::JumpNext),
+                Message::AnimateJumps(on) => self.animate_jumps = *on,
                 _ => panic!("recv unexpected ui msg: {:?}", msg),
             };
         }
@@ -948,10 +3678,37 @@ impl WinEventLoop {
         }
     }
 
+    fn devices_status(&self, tick: u64) -> Vec<(String, DeviceStatus)> {
+        self.processor
+            .devices
+            .iter()
+            .filter(|&v| Self::is_valid_win_device(v))
+            .map(|d| {
+                (
+                    d.id.as_ref().unwrap().clone(),
+                    Self::build_device_status(d, tick),
+                )
+            })
+            .collect()
+    }
+
+    fn poll_status_watch(&mut self, tick: u64) {
+        if self.headless || !self.status_watch.due(tick) {
+            return;
+        }
+        let status = self.devices_status(tick);
+        if let Some(status) = self.status_watch.update(tick, status) {
+            self.reactor().report_devices_status(status);
+        }
+    }
+
     pub fn build_device_status(d: &WinDevice, cur_tick: u64) -> DeviceStatus {
         if let Some((last_tick, _, positioning)) = d.ctrl.get_last_pos() {
             if last_tick + MOUSE_EVENT_ACTIVE_LAST_FOR_MS > cur_tick {
-                DeviceStatus::Active(positioning)
+                DeviceStatus::Active {
+                    positioning,
+                    raw: d.ctrl.raw_positioning(),
+                }
             } else {
                 DeviceStatus::Idle
             }
@@ -986,12 +3743,24 @@ impl WinEventLoop {
             name.push_str(iface.name.to_string().as_str());
             return name;
         }
+        if d.orphan.is_some() {
+            return format!("Unlisted device {:X}", d.handle.0);
+        }
         d.id.as_ref().unwrap().clone()
     }
 
     pub fn build_platform_specific_infos(d: &WinDevice) -> Vec<(String, String)> {
         let tag = |s: &str| s.to_owned();
 
+        if let Some(o) = &d.orphan {
+            return vec![
+                (tag("handle"), format!("0x{:X}", o.handle.0)),
+                (tag("listedBySystem"), "false".to_owned()),
+                (tag("eventsBeforePlaceholder"), o.events.to_string()),
+                (tag("positioning"), format!("{:?}", o.positioning)),
+            ];
+        }
+
         let rawinput = match &d.rawinput {
             Some(v) => v,
             None => return Vec::new(),
@@ -1046,7 +3815,88 @@ impl WinEventLoop {
             }
             RawDeviceType::UNKNOWN => (),
         }
+        if let Some(r) = &d.axis_ranges {
+            vs.push((tag("logicalRangeX"), r.x.to_string()));
+            vs.push((tag("logicalRangeY"), r.y.to_string()));
+        }
 
         vs
     }
 }
+
+// Trigger an action in the running instance, from another process
+pub fn send_remote_action(action: RemoteAction) -> Result<()> {
+    let hwnd = match find_window(EVENTLOOP_WINDOW_CLASS, EVENTLOOP_WINDOW_TITLE) {
+        Some(v) => v,
+        None => return Err(Error::NotRunning),
+    };
+    post_message(hwnd, WM_REMOTE_ACTION, action.id(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Registers like the system, except combinations taken by other programs
+    #[derive(Default)]
+    struct MockHotKeys {
+        taken: Vec<u32>,
+        registered: Vec<i32>,
+    }
+
+    impl HotKeyBackend for MockHotKeys {
+        fn register(
+            &mut self,
+            _hwnd: HWND,
+            id: i32,
+            modifiers: HOT_KEY_MODIFIERS,
+            key: VIRTUAL_KEY,
+            _repeat: bool,
+        ) -> Result<u32> {
+            let lparam = hot_key_lparam(modifiers, key);
+            if self.taken.contains(&lparam) {
+                return Err(Error::ShortcutConflict(None.into()));
+            }
+            self.registered.push(id);
+            Ok(lparam)
+        }
+
+        fn unregister(&mut self, _hwnd: HWND, id: i32) -> Result<()> {
+            self.registered.retain(|v| *v != id);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_apply_one_shortcut() {
+        let (m, k) = shortcut_str_to_win("Ctrl+Alt+N", true).unwrap();
+        let mut mgr = HotKeyManager::with_backend(MockHotKeys {
+            taken: vec![hot_key_lparam(m, k)],
+            ..Default::default()
+        });
+        let hwnd = HWND(0);
+        let mut apply = |shortcut: &str, id: ShortcutID| {
+            WinEventLoop::apply_one_shortcut(&mut mgr, hwnd, shortcut, true, id)
+        };
+
+        apply("Ctrl+Alt+L", ShortcutID::CurMouseLock).unwrap();
+        // Conflicts name the shortcut, as reported and retried
+        assert!(matches!(
+            apply("Ctrl+Alt+N", ShortcutID::CurMouseJumpNext),
+            Err(Error::ShortcutConflict(s)) if s.as_deref() == Some("Ctrl+Alt+N")
+        ));
+        assert!(matches!(
+            apply("Ctrl+Alt+Nothing", ShortcutID::CurMouseJumpLeft),
+            Err(Error::InvalidShortcut(_))
+        ));
+        assert_eq!(
+            mgr.backend().registered,
+            vec![ShortcutID::CurMouseLock as i32]
+        );
+
+        // Cleared ones are unregistered
+        WinEventLoop::apply_one_shortcut(&mut mgr, hwnd, "", true, ShortcutID::CurMouseLock)
+            .unwrap();
+        assert!(mgr.backend().registered.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+// Operations on windows of other programs
+
+use windows::Win32::Foundation::{LPARAM, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    MonitorFromPoint, MonitorFromWindow, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    VIRTUAL_KEY, VK_CONTROL, VK_LBUTTON, VK_MBUTTON, VK_RBUTTON, VK_SHIFT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetAncestor, GetForegroundWindow, GetShellWindow, GetWindowRect, IsIconic, IsZoomed,
+    MoveWindow, PostMessageW, ShowWindow, WindowFromPoint, GA_ROOT, SW_MAXIMIZE, SW_RESTORE,
+};
+
+use crate::errors::Result;
+
+use super::wintypes::*;
+use super::winwrap::{get_monitor_work_area, is_key_down};
+
+// Move the foreground window onto the monitor containing (x,y), keeping its relative
+// position inside work area. Maximized window is maximized again there.
+// Returns false if nothing to move.
+pub fn move_foreground_window_to(x: i32, y: i32) -> Result<bool> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.0 == 0 || hwnd == unsafe { GetShellWindow() } || unsafe { IsIconic(hwnd) }.as_bool() {
+        return Ok(false);
+    }
+    let dst_monitor = unsafe { MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST) };
+    let src_monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    if dst_monitor == src_monitor {
+        return Ok(false);
+    }
+    let src = get_monitor_work_area(src_monitor)?;
+    let dst = get_monitor_work_area(dst_monitor)?;
+
+    let maximized = unsafe { IsZoomed(hwnd) }.as_bool();
+    if maximized {
+        unsafe { ShowWindow(hwnd, SW_RESTORE) };
+    }
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect) }.map_err(core_error)?;
+    let r = place_in_work_area(&rect, &src, &dst);
+    unsafe {
+        MoveWindow(
+            hwnd,
+            r.left,
+            r.top,
+            r.right - r.left,
+            r.bottom - r.top,
+            true,
+        )
+    }
+    .map_err(core_error)?;
+    if maximized {
+        unsafe { ShowWindow(hwnd, SW_MAXIMIZE) };
+    }
+    Ok(true)
+}
+
+// Free space around window is split in the same ratio as in source. Window is shrunk if
+// larger than destination.
+fn place_in_work_area(rect: &RECT, src: &RECT, dst: &RECT) -> RECT {
+    let place = |lo: i32, hi: i32, src_lo: i32, src_hi: i32, dst_lo: i32, dst_hi: i32| {
+        let len = (hi - lo).min(dst_hi - dst_lo);
+        let src_free = (src_hi - src_lo) - (hi - lo);
+        let dst_free = (dst_hi - dst_lo) - len;
+        let offset = if src_free > 0 {
+            let off = (lo - src_lo).clamp(0, src_free) as i64;
+            (off * dst_free as i64 / src_free as i64) as i32
+        } else {
+            0
+        };
+        (dst_lo + offset, dst_lo + offset + len)
+    };
+    let (left, right) = place(
+        rect.left, rect.right, src.left, src.right, dst.left, dst.right,
+    );
+    let (top, bottom) = place(
+        rect.top,
+        rect.bottom,
+        src.top,
+        src.bottom,
+        dst.top,
+        dst.bottom,
+    );
+    RECT {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+// Key state flags carried in wParam of wheel messages(MK_*)
+const WHEEL_KEY_STATES: [(VIRTUAL_KEY, usize); 5] = [
+    (VK_LBUTTON, 0x0001),
+    (VK_RBUTTON, 0x0002),
+    (VK_SHIFT, 0x0004),
+    (VK_CONTROL, 0x0008),
+    (VK_MBUTTON, 0x0010),
+];
+
+// Post a wheel message(WM_MOUSEWHEEL or WM_MOUSEHWHEEL) caught by low-level hook to the
+// window under cursor, if it does not belong to the foreground window. Returns false if
+// not forwarded, then the original one should go on.
+pub fn forward_wheel_to_window_under(msg: u32, pt: POINT, mouse_data: u32) -> bool {
+    let hwnd = unsafe { WindowFromPoint(pt) };
+    if hwnd.0 == 0 {
+        return false;
+    }
+    if unsafe { GetAncestor(hwnd, GA_ROOT) } == unsafe { GetForegroundWindow() } {
+        return false;
+    }
+    let keys = WHEEL_KEY_STATES
+        .iter()
+        .filter(|(vk, _)| is_key_down(*vk))
+        .fold(0, |keys, (_, mk)| keys | mk);
+    // High word of mouse_data is the wheel delta
+    let wparam = (mouse_data as usize & 0xFFFF0000) | keys;
+    let lparam = ((pt.y as u16 as isize) << 16) | (pt.x as u16 as isize);
+    // Fails for windows of higher integrity level
+    unsafe { PostMessageW(hwnd, msg, WPARAM(wparam), LPARAM(lparam)) }.is_ok()
+}
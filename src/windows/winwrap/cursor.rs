@@ -0,0 +1,114 @@
+// Cursor position, visibility and shapes, and moves injected as input
+
+use crate::errors::Result;
+use crate::windows::wintypes::*;
+
+#[cfg(feature = "devsim")]
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_MOVE,
+    MOUSEEVENTF_VIRTUALDESK, MOUSEINPUT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetCursorInfo, GetSystemMetrics, LoadImageW, SetSystemCursor, SystemParametersInfoW,
+    CURSORINFO, CURSOR_SHOWING, HCURSOR, IMAGE_CURSOR, LR_DEFAULTCOLOR, SM_CXCURSOR,
+    SPI_SETCURSORS, SYSTEM_CURSOR_ID, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::POINT,
+        UI::WindowsAndMessaging::{GetPhysicalCursorPos, SetPhysicalCursorPos},
+    },
+};
+
+#[cfg(feature = "devsim")]
+use super::get_last_error;
+
+// Width and height of cursors in pixels, at current system settings
+pub fn system_cursor_size() -> i32 {
+    unsafe { GetSystemMetrics(SM_CXCURSOR) }
+}
+
+// A new copy of system cursor, e.g. OCR_NORMAL, scaled to size
+pub fn load_system_cursor(id: SYSTEM_CURSOR_ID, size: i32) -> Result<HCURSOR> {
+    // System cursors are loaded by ordinal, without module
+    let name = PCWSTR(id.0 as usize as *const u16);
+    match unsafe { LoadImageW(None, name, IMAGE_CURSOR, size, size, LR_DEFAULTCOLOR) } {
+        Ok(h) => Ok(HCURSOR(h.0)),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// System takes and destroys the cursor, so it mustn't be used again
+pub fn set_system_cursor(cursor: HCURSOR, id: SYSTEM_CURSOR_ID) -> Result<()> {
+    match unsafe { SetSystemCursor(cursor, id) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Reload system cursors from user's scheme, undoing set_system_cursor()
+pub fn reload_system_cursors() -> Result<()> {
+    match unsafe {
+        SystemParametersInfoW(
+            SPI_SETCURSORS,
+            0,
+            None,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn get_cursor_pos() -> Result<(i32, i32)> {
+    let mut pt = POINT::default();
+    match unsafe { GetPhysicalCursorPos(&mut pt) } {
+        Ok(()) => Ok((pt.x, pt.y)),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Hidden by an app, e.g. software KVMs while controlling another machine
+pub fn is_cursor_hidden() -> Result<bool> {
+    let mut ci = CURSORINFO {
+        cbSize: wsize_of::<CURSORINFO>(),
+        ..Default::default()
+    };
+    match unsafe { GetCursorInfo(&mut ci) } {
+        Ok(()) => Ok(ci.flags.0 & CURSOR_SHOWING.0 == 0),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn set_cursor_pos(x: i32, y: i32) -> Result<()> {
+    match unsafe { SetPhysicalCursorPos(x, y) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Absolute ones are normalized to the virtual desktop, 0 to 65535
+#[cfg(feature = "devsim")]
+pub fn send_mouse_move(dx: i32, dy: i32, absolute: bool) -> Result<()> {
+    let mut flags = MOUSEEVENTF_MOVE;
+    if absolute {
+        flags |= MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK;
+    }
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    };
+    match unsafe { SendInput(&[input], wsize_of::<INPUT>() as i32) } {
+        0 => Err(get_last_error()),
+        _ => Ok(()),
+    }
+}
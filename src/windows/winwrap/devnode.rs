@@ -0,0 +1,567 @@
+// Device nodes and interfaces by Configuration Manager, and HID reports of devices
+
+use std::fmt::{self, Display};
+
+use crate::errors::{Error, Result};
+use crate::windows::wintypes::*;
+
+use windows::{
+    core::GUID,
+    Win32::{
+        Devices::{
+            DeviceAndDriverInstallation::{
+                CM_Get_DevNode_PropertyW, CM_Get_Device_Interface_ListW,
+                CM_Get_Device_Interface_List_SizeW, CM_Get_Device_Interface_PropertyW,
+                CM_Locate_DevNodeW, CM_GET_DEVICE_INTERFACE_LIST_ALL_DEVICES,
+                CM_GET_DEVICE_INTERFACE_LIST_PRESENT, CM_LOCATE_DEVNODE_NORMAL, CR_BUFFER_SMALL,
+                CR_NO_SUCH_VALUE, CR_SUCCESS,
+            },
+            HumanInterfaceDevice::{
+                HidD_FreePreparsedData, HidD_GetFeature, HidD_GetHidGuid, HidD_GetInputReport,
+                HidD_GetManufacturerString, HidD_GetPreparsedData, HidD_GetProductString,
+                HidD_GetSerialNumberString, HidP_Feature, HidP_GetCaps, HidP_GetSpecificValueCaps,
+                HidP_GetUsageValue, HidP_Input, HIDP_CAPS, HIDP_REPORT_TYPE, HIDP_STATUS_SUCCESS,
+                HIDP_VALUE_CAPS, PHIDP_PREPARSED_DATA,
+            },
+            Properties::{
+                DEVPKEY_Device_Class, DEVPKEY_Device_InstanceId, DEVPKEY_Device_Manufacturer,
+                DEVPKEY_Device_Parent, DEVPKEY_Device_Service, DEVPKEY_NAME, DEVPROPKEY,
+                DEVPROPTYPE, DEVPROP_TYPE_STRING,
+            },
+        },
+        Foundation::{BOOLEAN, GENERIC_READ, GENERIC_WRITE, HANDLE},
+        Storage::FileSystem::{
+            CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+        },
+    },
+};
+
+use super::{get_last_error, ScopeHandle};
+
+pub enum WStringOption {
+    Some(WString),
+    NoValue,
+    GetErr(Error),
+}
+
+impl Display for WStringOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WStringOption::Some(s) => write!(f, "{}", s),
+            WStringOption::NoValue => write!(f, "NoValue"),
+            WStringOption::GetErr(e) => write!(f, "GetPropErr({})", e),
+        }
+    }
+}
+
+impl WStringOption {
+    // Decoded from a string property as read, NoValue if device doesn't have it
+    fn from_prop(prop: Result<Option<WBuffer>>) -> Self {
+        match prop {
+            Ok(Some(v)) => WStringOption::Some(v.to_wstring()),
+            Ok(None) => WStringOption::NoValue,
+            Err(e) => WStringOption::GetErr(e),
+        }
+    }
+}
+
+pub struct HidDeviceInfo {
+    pub serial_number: WStringOption,
+    pub manufacturer: WStringOption,
+    pub product: WStringOption,
+}
+
+pub struct DeviceIfaceInfo {
+    pub instance_id: WString,
+    pub name: WStringOption,
+    pub service: WStringOption,
+    pub class: WStringOption,
+    pub manufacurer: WStringOption,
+}
+
+pub fn device_get_iface_prop(
+    iface: &WString,
+    key: DEVPROPKEY,
+    typ: DEVPROPTYPE,
+) -> Result<Option<WBuffer>> {
+    let mut size: WSize = 0;
+    let mut mtyp = typ;
+
+    let cr = unsafe {
+        CM_Get_Device_Interface_PropertyW(
+            iface.as_pcwstr(),
+            wptr(&key),
+            wmut_ptr(&mut mtyp),
+            None,
+            &mut size,
+            0,
+        )
+    };
+    match cr {
+        CR_BUFFER_SMALL | CR_SUCCESS => {
+            if mtyp != typ {
+                return Err(cr_error(cr));
+            }
+        }
+        CR_NO_SUCH_VALUE => return Ok(None),
+        _ => return Err(cr_error(cr)),
+    }
+
+    let mut buf = WBuffer::new(size);
+    let cr = unsafe {
+        CM_Get_Device_Interface_PropertyW(
+            iface.as_pcwstr(),
+            wptr(&key),
+            wmut_ptr(&mut mtyp),
+            Some(buf.as_mut_ptr()),
+            &mut size,
+            0,
+        )
+    };
+    match cr {
+        CR_SUCCESS => {
+            if mtyp != typ {
+                return Err(cr_error(cr));
+            }
+            Ok(Some(buf))
+        }
+        _ => Err(cr_error(cr)),
+    }
+}
+
+pub fn device_get_node_prop(
+    devinst: u32,
+    key: DEVPROPKEY,
+    typ: DEVPROPTYPE,
+) -> Result<Option<WBuffer>> {
+    let mut size: WSize = 0;
+    let mut mtyp = typ;
+
+    let cr = unsafe {
+        CM_Get_DevNode_PropertyW(devinst, wptr(&key), wmut_ptr(&mut mtyp), None, &mut size, 0)
+    };
+    match cr {
+        CR_BUFFER_SMALL | CR_SUCCESS => {
+            if mtyp != typ {
+                return Err(cr_error(cr));
+            }
+        }
+        CR_NO_SUCH_VALUE => return Ok(None),
+        _ => return Err(cr_error(cr)),
+    }
+
+    let mut buf = WBuffer::new(size);
+    let cr = unsafe {
+        CM_Get_DevNode_PropertyW(
+            devinst,
+            wptr(&key),
+            wmut_ptr(&mut mtyp),
+            Some(buf.as_mut_ptr()),
+            &mut size,
+            0,
+        )
+    };
+    match cr {
+        CR_SUCCESS => {
+            if mtyp != typ {
+                return Err(cr_error(cr));
+            }
+            Ok(Some(buf))
+        }
+        _ => Err(cr_error(cr)),
+    }
+}
+
+pub fn locate_devnode_handle(instance_id: &WString) -> Result<u32> {
+    let mut handle: u32 = 0;
+    let cr = unsafe {
+        CM_Locate_DevNodeW(
+            &mut handle,
+            instance_id.as_pcwstr(),
+            CM_LOCATE_DEVNODE_NORMAL,
+        )
+    };
+    match cr {
+        CR_SUCCESS => Ok(handle),
+        _ => Err(cr_error(cr)),
+    }
+}
+
+pub fn device_get_iface_infos(iface: &WString) -> Result<DeviceIfaceInfo> {
+    let instance_id =
+        match device_get_iface_prop(iface, DEVPKEY_Device_InstanceId, DEVPROP_TYPE_STRING)? {
+            Some(v) => v,
+            None => return Err(Error::WinDeviceNoInstanceID(iface.to_string())),
+        }
+        .to_wstring();
+    let devinst = locate_devnode_handle(&instance_id)?;
+
+    let getf = |key| -> WStringOption {
+        WStringOption::from_prop(device_get_node_prop(devinst, key, DEVPROP_TYPE_STRING))
+    };
+
+    Ok(DeviceIfaceInfo {
+        instance_id,
+        name: getf(DEVPKEY_NAME),
+        service: getf(DEVPKEY_Device_Service),
+        class: getf(DEVPKEY_Device_Class),
+        manufacurer: getf(DEVPKEY_Device_Manufacturer),
+    })
+}
+
+pub fn device_get_ifaces_list(
+    instance_id: &WString,
+    class_guid: &GUID,
+    present: bool,
+) -> Result<Vec<WString>> {
+    loop {
+        let mut size: WSize = 0;
+        let cr = unsafe {
+            CM_Get_Device_Interface_List_SizeW(
+                &mut size,
+                wptr(class_guid),
+                instance_id.as_pcwstr(),
+                CM_GET_DEVICE_INTERFACE_LIST_PRESENT,
+            )
+        };
+        match cr {
+            CR_SUCCESS => (),
+            _ => return Err(cr_error(cr)),
+        }
+
+        let mut buf = WString::new(size);
+        let pre_flag = if present {
+            CM_GET_DEVICE_INTERFACE_LIST_PRESENT
+        } else {
+            CM_GET_DEVICE_INTERFACE_LIST_ALL_DEVICES
+        };
+        let cr = unsafe {
+            CM_Get_Device_Interface_ListW(
+                wptr(class_guid),
+                instance_id.as_pcwstr(),
+                buf.as_mut_slice(),
+                pre_flag,
+            )
+        };
+        match cr {
+            CR_SUCCESS => return Ok(buf.split_by_eos()),
+            CR_BUFFER_SMALL => {
+                continue;
+            }
+            _ => return Err(cr_error(cr)),
+        }
+    }
+}
+
+pub fn device_get_parents(instance_id: &WString, dep_limit: Option<usize>) -> Result<Vec<WString>> {
+    let get_parent = |inst_id: &WString| -> Result<Option<WString>> {
+        let handle = locate_devnode_handle(inst_id)?;
+        let ret = device_get_node_prop(handle, DEVPKEY_Device_Parent, DEVPROP_TYPE_STRING)?;
+        Ok(ret.map(|v| v.to_wstring()))
+    };
+
+    let mut ret = Vec::<WString>::new();
+    let mut inst = instance_id;
+    loop {
+        if dep_limit.is_some() && ret.len() >= dep_limit.unwrap() {
+            break;
+        }
+        match get_parent(inst)? {
+            Some(v) => {
+                ret.push(v);
+                inst = &ret.last().unwrap();
+            }
+            None => break,
+        }
+    }
+    Ok(ret)
+}
+
+pub fn device_open_iface(iface: &WString, metaonly: bool) -> Result<ScopeHandle> {
+    let desire_access = if metaonly {
+        0
+    } else {
+        (GENERIC_READ | GENERIC_WRITE).0
+    };
+    let share_mode = FILE_SHARE_READ | FILE_SHARE_WRITE;
+
+    let result = unsafe {
+        CreateFileW(
+            iface.as_pcwstr(),
+            desire_access,
+            share_mode,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            HANDLE(0),
+        )
+    };
+
+    match result {
+        Ok(h) => {
+            if h.is_invalid() {
+                Err(Error::WinInvalidHandle(h.0))
+            } else {
+                Ok(ScopeHandle::new(h))
+            }
+        }
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn device_get_hid_info(instance_id: &WString, present: bool) -> Result<HidDeviceInfo> {
+    let hid_class = unsafe { HidD_GetHidGuid() };
+    let ifaces = device_get_ifaces_list(instance_id, &hid_class, present)?;
+    let iface = match ifaces.last() {
+        Some(v) => v,
+        None => return Err(Error::WinDeviceInterfaceListEmpty(instance_id.to_string())),
+    };
+
+    let iface_hdl = device_open_iface(iface, true)?;
+
+    let mut data = WString::new(256);
+    let result_as_str = |ok: BOOLEAN, buf: &WString| -> WStringOption {
+        if ok.as_bool() {
+            WStringOption::Some(buf.str_before_null())
+        } else {
+            WStringOption::NoValue
+        }
+    };
+
+    let r = HidDeviceInfo {
+        serial_number: result_as_str(
+            unsafe {
+                HidD_GetSerialNumberString(*iface_hdl.get(), data.as_mut_data(), data.capacity())
+            },
+            &data,
+        ),
+        manufacturer: result_as_str(
+            unsafe {
+                HidD_GetManufacturerString(*iface_hdl.get(), data.as_mut_data(), data.capacity())
+            },
+            &data,
+        ),
+        product: result_as_str(
+            unsafe { HidD_GetProductString(*iface_hdl.get(), data.as_mut_data(), data.capacity()) },
+            &data,
+        ),
+    };
+
+    // No need get caps, use us_usage instead
+    // let mut prepared_data = device_get_rawinput_info::<WBuffer>(dev_hdl, RIDI_PREPARSEDDATA)?;
+    // let pd = PHIDP_PREPARSED_DATA(prepared_data.as_mut_data() as isize);
+    // match unsafe { HidP_GetCaps(pd, wmut_ptr(&mut result.caps)) } {
+    //     HIDP_STATUS_SUCCESS => (),
+    //     v => return Err(ERR_WINDOWS_HIDP_ERROR.with_info(v.0)),
+    // }
+
+    Ok(r)
+}
+
+// (usage page, usage) reporting battery level:
+// Generic Device Controls/Battery Strength, Battery System/Remaining Capacity
+const HID_BATTERY_USAGES: [(u16, u16); 2] = [(0x06, 0x20), (0x85, 0x66)];
+
+// Query battery level in percent from HID reports. Ok(None) if device does not report it.
+pub fn device_query_hid_battery(iface: &WString) -> Result<Option<u8>> {
+    // Collections like mouse are opened exclusively by system, only metadata access allowed
+    let iface_hdl = match device_open_iface(iface, false) {
+        Ok(h) => h,
+        Err(_) => device_open_iface(iface, true)?,
+    };
+    let mut pd = PHIDP_PREPARSED_DATA::default();
+    if !unsafe { HidD_GetPreparsedData(*iface_hdl.get(), &mut pd) }.as_bool() {
+        return Err(get_last_error());
+    }
+    let r = hid_query_battery(*iface_hdl.get(), pd);
+    unsafe { HidD_FreePreparsedData(pd) };
+    r
+}
+
+fn hid_query_battery(hdl: HANDLE, pd: PHIDP_PREPARSED_DATA) -> Result<Option<u8>> {
+    let mut caps = HIDP_CAPS::default();
+    if unsafe { HidP_GetCaps(pd, &mut caps) } != HIDP_STATUS_SUCCESS {
+        return Ok(None);
+    }
+    let reports: [(HIDP_REPORT_TYPE, u16); 2] = [
+        (HidP_Feature, caps.FeatureReportByteLength),
+        (HidP_Input, caps.InputReportByteLength),
+    ];
+    for (report_type, report_len) in reports {
+        if report_len == 0 {
+            continue;
+        }
+        for (page, usage) in HID_BATTERY_USAGES {
+            let mut vcaps = [HIDP_VALUE_CAPS::default()];
+            let mut vcaps_len = vcaps.len() as u16;
+            let status = unsafe {
+                HidP_GetSpecificValueCaps(
+                    report_type,
+                    page,
+                    0,
+                    usage,
+                    vcaps.as_mut_ptr(),
+                    &mut vcaps_len,
+                    pd,
+                )
+            };
+            if status != HIDP_STATUS_SUCCESS || vcaps_len == 0 {
+                continue;
+            }
+            let vc = &vcaps[0];
+
+            let mut report = vec![0u8; report_len as usize];
+            report[0] = vc.ReportID;
+            let buf = report.as_mut_ptr() as *mut std::ffi::c_void;
+            let ok = if report_type == HidP_Feature {
+                unsafe { HidD_GetFeature(hdl, buf, report_len as u32) }
+            } else {
+                unsafe { HidD_GetInputReport(hdl, buf, report_len as u32) }
+            };
+            if !ok.as_bool() {
+                return Err(get_last_error());
+            }
+
+            let mut value: u32 = 0;
+            let status = unsafe {
+                HidP_GetUsageValue(
+                    report_type,
+                    page,
+                    vc.LinkCollection,
+                    usage,
+                    &mut value,
+                    pd,
+                    &report,
+                )
+            };
+            if status != HIDP_STATUS_SUCCESS {
+                continue;
+            }
+            if let Some(percent) = battery_percent(value, vc.LogicalMin, vc.LogicalMax) {
+                return Ok(Some(percent));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Value of a battery usage scaled by its logical range, None if the range is invalid
+fn battery_percent(value: u32, min: i32, max: i32) -> Option<u8> {
+    let (min, max) = (min as i64, max as i64);
+    if max <= min {
+        return None;
+    }
+    let percent = (value as i64 - min).clamp(0, max - min) * 100 / (max - min);
+    Some(percent as u8)
+}
+
+// Logical range an absolute axis reports, both ends included
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HidAxisRange {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl HidAxisRange {
+    // None for ranges reported empty or reversed, which can't be scaled from
+    fn from_logical(min: i32, max: i32) -> Option<Self> {
+        (max > min).then_some(HidAxisRange { min, max })
+    }
+}
+
+impl Display for HidAxisRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HidAxisRanges {
+    pub x: HidAxisRange,
+    pub y: HidAxisRange,
+}
+
+// (usage page, usage) of Generic Desktop/X and Y
+const HID_AXIS_USAGES: [(u16, u16); 2] = [(0x01, 0x30), (0x01, 0x31)];
+
+// Logical ranges of X and Y in input reports, parsed from value caps. Ok(None) if device
+// has no absolute X and Y, e.g. mice reporting relative motion only.
+pub fn device_query_hid_axis_ranges(iface: &WString) -> Result<Option<HidAxisRanges>> {
+    let iface_hdl = device_open_iface(iface, true)?;
+    let mut pd = PHIDP_PREPARSED_DATA::default();
+    if !unsafe { HidD_GetPreparsedData(*iface_hdl.get(), &mut pd) }.as_bool() {
+        return Err(get_last_error());
+    }
+    let x = hid_axis_range(pd, HID_AXIS_USAGES[0]);
+    let y = hid_axis_range(pd, HID_AXIS_USAGES[1]);
+    unsafe { HidD_FreePreparsedData(pd) };
+    match (x, y) {
+        (Some(x), Some(y)) => Ok(Some(HidAxisRanges { x, y })),
+        _ => Ok(None),
+    }
+}
+
+fn hid_axis_range(pd: PHIDP_PREPARSED_DATA, (page, usage): (u16, u16)) -> Option<HidAxisRange> {
+    let mut vcaps = [HIDP_VALUE_CAPS::default(); 4];
+    let mut vcaps_len = vcaps.len() as u16;
+    let status = unsafe {
+        HidP_GetSpecificValueCaps(
+            HidP_Input,
+            page,
+            0,
+            usage,
+            vcaps.as_mut_ptr(),
+            &mut vcaps_len,
+            pd,
+        )
+    };
+    if status != HIDP_STATUS_SUCCESS {
+        return None;
+    }
+    // Pens may report the axis in several collections, the absolute one is taken
+    vcaps[..vcaps_len as usize]
+        .iter()
+        .filter(|vc| vc.IsAbsolute.as_bool())
+        .find_map(|vc| HidAxisRange::from_logical(vc.LogicalMin, vc.LogicalMax))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_prop() {
+        let buf = WString::encode_from_str("HidUsb").to_wbuffer();
+        let v = WStringOption::from_prop(Ok(Some(buf)));
+        assert!(matches!(v, WStringOption::Some(_)));
+        assert_eq!(v.to_string(), "HidUsb");
+        assert_eq!(WStringOption::from_prop(Ok(None)).to_string(), "NoValue");
+        let v = WStringOption::from_prop(Err(Error::WinUnknown));
+        assert!(matches!(v, WStringOption::GetErr(Error::WinUnknown)));
+    }
+
+    #[test]
+    fn test_battery_percent() {
+        assert_eq!(battery_percent(50, 0, 100), Some(50));
+        assert_eq!(battery_percent(3, 0, 4), Some(75));
+        // Out of range values are clamped
+        assert_eq!(battery_percent(0, 10, 20), Some(0));
+        assert_eq!(battery_percent(255, 0, 100), Some(100));
+        assert_eq!(battery_percent(5, 0, 0), None);
+        assert_eq!(battery_percent(5, 100, 0), None);
+    }
+
+    #[test]
+    fn test_axis_range() {
+        assert_eq!(
+            HidAxisRange::from_logical(0, 32767),
+            Some(HidAxisRange { min: 0, max: 32767 })
+        );
+        assert_eq!(HidAxisRange::from_logical(0, 0), None);
+        assert_eq!(HidAxisRange::from_logical(100, -100), None);
+        assert_eq!(
+            HidAxisRange::from_logical(-100, 100).unwrap().to_string(),
+            "-100..100"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// Low level mouse and keyboard hooks
+
+use crate::errors::Result;
+use crate::windows::wintypes::*;
+
+use windows::Win32::{
+    Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, SetWindowsHookExA, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT,
+        MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL, WINDOWS_HOOK_ID,
+    },
+};
+
+use super::catch_callback_panic;
+
+pub enum HookResult {
+    // Not handled, left to other hooks and programs
+    Pass,
+    // Eaten, not seen by other programs
+    Block,
+}
+
+pub trait MouseLowLevelHook {
+    fn on_mouse_ll(action: u32, e: &mut MSLLHOOKSTRUCT) -> HookResult;
+}
+
+pub trait KeyboardLowLevelHook {
+    fn on_keyboard_ll(action: u32, e: &KBDLLHOOKSTRUCT) -> HookResult;
+}
+
+pub struct HookWrap {
+    id: WINDOWS_HOOK_ID,
+    f: extern "system" fn(ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT,
+}
+impl HookWrap {
+    extern "system" fn mouse_ll_hook<T: MouseLowLevelHook>(
+        ncode: i32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if ncode < 0 {
+            return unsafe { CallNextHookEx(HHOOK(0), ncode, wparam, lparam) };
+        }
+        let result = catch_callback_panic(HookResult::Pass, || {
+            T::on_mouse_ll(wparam.0 as u32, lparam_ref::<MSLLHOOKSTRUCT>(&lparam))
+        });
+        match result {
+            // Returned without calling next hooks, as the mouse hook has always done
+            HookResult::Pass => LRESULT(0),
+            HookResult::Block => LRESULT(1),
+        }
+    }
+
+    pub fn mouse_ll<T: MouseLowLevelHook>() -> HookWrap {
+        HookWrap {
+            id: WH_MOUSE_LL,
+            f: HookWrap::mouse_ll_hook::<T>,
+        }
+    }
+
+    extern "system" fn keyboard_ll_hook<T: KeyboardLowLevelHook>(
+        ncode: i32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if ncode < 0 {
+            return unsafe { CallNextHookEx(HHOOK(0), ncode, wparam, lparam) };
+        }
+        let result = catch_callback_panic(HookResult::Pass, || {
+            T::on_keyboard_ll(wparam.0 as u32, lparam_ref::<KBDLLHOOKSTRUCT>(&lparam))
+        });
+        match result {
+            HookResult::Pass => unsafe { CallNextHookEx(HHOOK(0), ncode, wparam, lparam) },
+            HookResult::Block => LRESULT(1),
+        }
+    }
+
+    pub fn keyboard_ll<T: KeyboardLowLevelHook>() -> HookWrap {
+        HookWrap {
+            id: WH_KEYBOARD_LL,
+            f: HookWrap::keyboard_ll_hook::<T>,
+        }
+    }
+}
+
+pub fn set_windows_hook(hook: HookWrap) -> Result<HHOOK> {
+    match unsafe { SetWindowsHookExA(hook.id, Some(hook.f), HINSTANCE::default(), 0) } {
+        Ok(v) => Ok(v),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn unset_windows_hook(hook: HHOOK) -> Result<()> {
+    match unsafe { UnhookWindowsHookEx(hook) } {
+        Ok(v) => Ok(v),
+        Err(e) => Err(core_error(e)),
+    }
+}
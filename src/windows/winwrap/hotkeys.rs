@@ -0,0 +1,287 @@
+// Hotkeys registered by RegisterHotKey, and keys held now
+
+use std::collections::BTreeMap;
+
+use crate::errors::{Error, Result};
+use crate::windows::wintypes::*;
+
+use crate::windows::constants::*;
+use crate::windows::winwrap::message_tick;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL,
+    MOD_NOREPEAT, MOD_SHIFT, MOD_WIN, VIRTUAL_KEY, VK_CONTROL, VK_LCONTROL, VK_LMENU, VK_LSHIFT,
+    VK_LWIN, VK_MENU, VK_RCONTROL, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT,
+};
+
+pub fn register_hot_key(
+    hwnd: HWND,
+    id: i32,
+    mut modifiers: HOT_KEY_MODIFIERS,
+    key: VIRTUAL_KEY,
+    repeat: bool,
+) -> Result<u32> {
+    let callback_lparam = hot_key_lparam(modifiers, key);
+    if !repeat {
+        modifiers |= MOD_NOREPEAT;
+    }
+    match unsafe { RegisterHotKey(hwnd, id, modifiers, key.0 as u32) } {
+        Ok(_) => Ok(callback_lparam),
+        Err(e) => match e.code() {
+            HRESULT_SHORTCUT_CONFLICT => Err(Error::ShortcutConflict(None.into())),
+            _ => Err(core_error(e)),
+        },
+    }
+}
+
+pub fn unregister_hot_key(hwnd: HWND, id: i32) -> Result<()> {
+    match unsafe { UnregisterHotKey(hwnd, id) } {
+        Ok(v) => Ok(v),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn is_key_down(key: VIRTUAL_KEY) -> bool {
+    let state = unsafe { GetAsyncKeyState(key.0 as i32) };
+    (state as u16 & 0x8000) != 0
+}
+
+pub fn modifier_of_key(key: VIRTUAL_KEY) -> Option<HOT_KEY_MODIFIERS> {
+    match key {
+        VK_CONTROL | VK_LCONTROL | VK_RCONTROL => Some(MOD_CONTROL),
+        VK_MENU | VK_LMENU | VK_RMENU => Some(MOD_ALT),
+        VK_SHIFT | VK_LSHIFT | VK_RSHIFT => Some(MOD_SHIFT),
+        VK_LWIN | VK_RWIN => Some(MOD_WIN),
+        _ => None,
+    }
+}
+
+// Modifiers held now, in the form of RegisterHotKey. Within keyboard hook, the key being
+// handled is not counted yet.
+pub fn pressed_modifiers() -> HOT_KEY_MODIFIERS {
+    let mut m = HOT_KEY_MODIFIERS(0);
+    if is_key_down(VK_CONTROL) {
+        m |= MOD_CONTROL;
+    }
+    if is_key_down(VK_MENU) {
+        m |= MOD_ALT;
+    }
+    if is_key_down(VK_SHIFT) {
+        m |= MOD_SHIFT;
+    }
+    if is_key_down(VK_LWIN) || is_key_down(VK_RWIN) {
+        m |= MOD_WIN;
+    }
+    m
+}
+
+// The lparam a WM_HOTKEY of this registration carries, modifiers low and key high
+pub fn hot_key_lparam(modifiers: HOT_KEY_MODIFIERS, key: VIRTUAL_KEY) -> u32 {
+    ((key.0 as u32) << 16) | modifiers.0
+}
+
+struct HotKeyEntry<T> {
+    lparam: u32,
+    since: u32,
+    cb: T,
+}
+
+// Bookkeeping of registered hotkeys, keyed strictly by hotkey id. Every registration is
+// stamped with the message tick it was made at, since WM_HOTKEY carries nothing else to tell
+// registrations apart. A WM_HOTKEY already queued when its id was re-registered or
+// unregistered no longer matches, and is dropped.
+pub struct HotKeyTable<T> {
+    entries: BTreeMap<i32, HotKeyEntry<T>>,
+}
+
+impl<T> HotKeyTable<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, id: i32, lparam: u32, since: u32, cb: T) {
+        self.entries.insert(id, HotKeyEntry { lparam, since, cb });
+    }
+
+    pub fn remove(&mut self, id: i32) -> bool {
+        self.entries.remove(&id).is_some()
+    }
+
+    pub fn ids(&self) -> Vec<i32> {
+        self.entries.keys().copied().collect()
+    }
+
+    // Callback of a WM_HOTKEY with (wParam, lParam, msg.time). Events of other ids, another
+    // key combination, or posted before the current registration are stale.
+    pub fn lookup(&self, id: i32, lparam: u32, time: u32) -> Option<&T> {
+        let e = self.entries.get(&id)?;
+        if e.lparam != lparam || (time.wrapping_sub(e.since) as i32) < 0 {
+            return None;
+        }
+        Some(&e.cb)
+    }
+}
+
+// Where hotkeys get registered, the system unless replaced in tests of callers
+pub trait HotKeyBackend {
+    // Returns the lparam of WM_HOTKEY of it, see hot_key_lparam()
+    fn register(
+        &mut self,
+        hwnd: HWND,
+        id: i32,
+        modifiers: HOT_KEY_MODIFIERS,
+        key: VIRTUAL_KEY,
+        repeat: bool,
+    ) -> Result<u32>;
+    fn unregister(&mut self, hwnd: HWND, id: i32) -> Result<()>;
+}
+
+pub struct SystemHotKeys;
+
+impl HotKeyBackend for SystemHotKeys {
+    fn register(
+        &mut self,
+        hwnd: HWND,
+        id: i32,
+        modifiers: HOT_KEY_MODIFIERS,
+        key: VIRTUAL_KEY,
+        repeat: bool,
+    ) -> Result<u32> {
+        register_hot_key(hwnd, id, modifiers, key, repeat)
+    }
+
+    fn unregister(&mut self, hwnd: HWND, id: i32) -> Result<()> {
+        unregister_hot_key(hwnd, id)
+    }
+}
+
+pub struct HotKeyManager<T, B: HotKeyBackend = SystemHotKeys> {
+    table: HotKeyTable<T>,
+    backend: B,
+}
+
+impl<T> HotKeyManager<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::with_backend(SystemHotKeys)
+    }
+}
+
+impl<T, B: HotKeyBackend> HotKeyManager<T, B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            table: HotKeyTable::new(),
+            backend,
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn register(
+        &mut self,
+        hwnd: HWND,
+        id: i32,
+        modifiers: HOT_KEY_MODIFIERS,
+        key: VIRTUAL_KEY,
+        repeat: bool,
+        cb: T,
+    ) -> Result<()> {
+        self.table.remove(id);
+        let _ = self.backend.unregister(hwnd, id);
+
+        let since = message_tick();
+        let lparam = self.backend.register(hwnd, id, modifiers, key, repeat)?;
+        self.table.insert(id, lparam, since, cb);
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, hwnd: HWND, id: i32) -> Result<()> {
+        if self.table.remove(id) {
+            return self.backend.unregister(hwnd, id);
+        }
+        Ok(())
+    }
+
+    pub fn unregister_all(&mut self, hwnd: HWND) -> Result<()> {
+        let mut last_error = Ok(());
+        for id in self.table.ids() {
+            if let Err(e) = self.unregister(hwnd, id) {
+                last_error = Err(e);
+            }
+        }
+        last_error
+    }
+
+    pub fn get_callback(&self, id: i32, lparam: u32, time: u32) -> Option<&T> {
+        self.table.lookup(id, lparam, time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotkey_table_lookup() {
+        let mut t = HotKeyTable::new();
+        let l1 = hot_key_lparam(MOD_CONTROL, VIRTUAL_KEY(0x41));
+        let l2 = hot_key_lparam(MOD_ALT, VIRTUAL_KEY(0x42));
+        t.insert(1, l1, 100, "a");
+        t.insert(2, l2, 100, "b");
+        assert_eq!(t.lookup(1, l1, 150), Some(&"a"));
+        assert_eq!(t.lookup(2, l2, 100), Some(&"b"));
+        // Right combination, wrong id
+        assert_eq!(t.lookup(2, l1, 150), None);
+        assert_eq!(t.lookup(3, l1, 150), None);
+    }
+
+    #[test]
+    fn test_hotkey_table_reregister() {
+        let mut t = HotKeyTable::new();
+        let l1 = hot_key_lparam(MOD_CONTROL, VIRTUAL_KEY(0x41));
+        let l2 = hot_key_lparam(MOD_CONTROL, VIRTUAL_KEY(0x42));
+        t.insert(1, l1, 100, "lock");
+        // Swap combinations between two ids
+        t.remove(1);
+        t.insert(1, l2, 200, "lock");
+        t.insert(2, l1, 200, "jump");
+        assert_eq!(t.ids(), vec![1, 2]);
+        // Queued under the old registration of id 1
+        assert_eq!(t.lookup(1, l1, 150), None);
+        assert_eq!(t.lookup(1, l2, 150), None);
+        // Queued before id 2 existed, though the combination now belongs to it
+        assert_eq!(t.lookup(2, l1, 150), None);
+        assert_eq!(t.lookup(1, l2, 210), Some(&"lock"));
+        assert_eq!(t.lookup(2, l1, 210), Some(&"jump"));
+    }
+
+    #[test]
+    fn test_hotkey_table_unregister() {
+        let mut t = HotKeyTable::new();
+        let l1 = hot_key_lparam(MOD_WIN, VIRTUAL_KEY(0x4C));
+        t.insert(7, l1, 100, "x");
+        assert!(t.remove(7));
+        assert!(!t.remove(7));
+        assert_eq!(t.lookup(7, l1, 150), None);
+        assert!(t.ids().is_empty());
+
+        // Registered again with the same combination, only later events count
+        t.insert(7, l1, 300, "y");
+        assert_eq!(t.lookup(7, l1, 250), None);
+        assert_eq!(t.lookup(7, l1, 300), Some(&"y"));
+    }
+
+    #[test]
+    fn test_hotkey_table_tick_wrap() {
+        let mut t = HotKeyTable::new();
+        let l1 = hot_key_lparam(MOD_SHIFT, VIRTUAL_KEY(0x70));
+        t.insert(1, l1, u32::MAX - 10, ());
+        assert!(t.lookup(1, l1, u32::MAX - 20).is_none());
+        assert!(t.lookup(1, l1, 5).is_some());
+    }
+}
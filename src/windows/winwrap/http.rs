@@ -0,0 +1,115 @@
+// HTTPS requests by WinHTTP, which uses proxy and certificates configured in system
+
+use std::ffi::c_void;
+
+use crate::errors::{Error, Result};
+use crate::windows::wintypes::*;
+
+use windows::core::PCWSTR;
+use windows::Win32::Networking::WinHttp::{
+    WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest, WinHttpQueryHeaders,
+    WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest, WinHttpSetTimeouts,
+    INTERNET_DEFAULT_HTTPS_PORT, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE,
+};
+
+use super::get_last_error;
+
+struct HttpHandle(*mut c_void);
+
+impl HttpHandle {
+    fn new(h: *mut c_void) -> Result<Self> {
+        if h.is_null() {
+            return Err(get_last_error());
+        }
+        Ok(HttpHandle(h))
+    }
+}
+
+impl Drop for HttpHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { WinHttpCloseHandle(self.0) };
+    }
+}
+
+// Body of a GET request, failed unless answered with status 200
+pub fn https_get(
+    host: &str,
+    path: &str,
+    user_agent: &str,
+    timeout_ms: i32,
+    max_bytes: usize,
+) -> Result<String> {
+    let agent = WString::encode_from_str(user_agent);
+    let host = WString::encode_from_str(host);
+    let path = WString::encode_from_str(path);
+    let verb = WString::encode_from_str("GET");
+
+    let session = HttpHandle::new(unsafe {
+        WinHttpOpen(
+            agent.as_pcwstr(),
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        )
+    })?;
+    unsafe { WinHttpSetTimeouts(session.0, timeout_ms, timeout_ms, timeout_ms, timeout_ms) }
+        .map_err(core_error)?;
+    let connect = HttpHandle::new(unsafe {
+        WinHttpConnect(session.0, host.as_pcwstr(), INTERNET_DEFAULT_HTTPS_PORT, 0)
+    })?;
+    let request = HttpHandle::new(unsafe {
+        WinHttpOpenRequest(
+            connect.0,
+            verb.as_pcwstr(),
+            path.as_pcwstr(),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            std::ptr::null(),
+            WINHTTP_FLAG_SECURE,
+        )
+    })?;
+    unsafe { WinHttpSendRequest(request.0, None, None, 0, 0, 0) }.map_err(core_error)?;
+    unsafe { WinHttpReceiveResponse(request.0, std::ptr::null_mut()) }.map_err(core_error)?;
+
+    let mut status = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    unsafe {
+        WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some(&mut status as *mut u32 as *mut c_void),
+            &mut size,
+            std::ptr::null_mut(),
+        )
+    }
+    .map_err(core_error)?;
+    if status != 200 {
+        return Err(Error::UpdateCheck(format!("HTTP status {}", status)));
+    }
+
+    let mut body = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut read = 0u32;
+        unsafe {
+            WinHttpReadData(
+                request.0,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as u32,
+                &mut read,
+            )
+        }
+        .map_err(core_error)?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&buf[..read as usize]);
+        if body.len() > max_bytes {
+            return Err(Error::UpdateCheck("response too large".to_owned()));
+        }
+    }
+    String::from_utf8(body).map_err(|e| Error::UpdateCheck(e.to_string()))
+}
@@ -0,0 +1,94 @@
+// Wrappers of Win32 APIs, in submodules by capability. Unsafe calls and raw buffers stay
+// inside them, and callers use the safe functions re-exported here.
+
+mod cursor;
+mod devnode;
+mod hooks;
+mod hotkeys;
+mod http;
+mod monitors;
+mod rawinput;
+mod sync;
+mod system;
+mod window;
+
+pub use cursor::*;
+pub use devnode::*;
+pub use hooks::*;
+pub use hotkeys::*;
+pub use http::*;
+pub use monitors::*;
+pub use rawinput::*;
+pub use sync::*;
+pub use system::*;
+pub use window::*;
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::errors::{Error, Result};
+use crate::windows::wintypes::*;
+
+use windows::Win32::Foundation::{CloseHandle, GetLastError, HANDLE};
+
+thread_local! {
+    // Caught in a callback called by Windows, since unwinding into Windows aborts the process
+    static CALLBACK_PANIC: RefCell<Option<Box<dyn Any + Send>>> = const { RefCell::new(None) };
+}
+
+// Body of a callback called by Windows, returning fallback if it panics. Only the first
+// panic is kept, until resumed by resume_callback_panic.
+fn catch_callback_panic<R>(fallback: R, f: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(v) => v,
+        Err(payload) => {
+            CALLBACK_PANIC.with(|p| {
+                let mut p = p.borrow_mut();
+                if p.is_none() {
+                    *p = Some(payload);
+                }
+            });
+            fallback
+        }
+    }
+}
+
+// Called after dispatching messages, so that a panic of hooks, subclass procs or timers
+// unwinds the thread as if raised there, e.g. to the supervisor of mouse control thread
+pub fn resume_callback_panic() {
+    if let Some(payload) = CALLBACK_PANIC.with(|p| p.borrow_mut().take()) {
+        panic::resume_unwind(payload);
+    }
+}
+
+pub fn get_last_error() -> Error {
+    match unsafe { GetLastError().err() } {
+        Some(e) => core_error(e),
+        None => Error::WinUnknown,
+    }
+}
+
+pub struct ScopeHandle(HANDLE);
+
+impl ScopeHandle {
+    fn new(h: HANDLE) -> Self {
+        ScopeHandle(h)
+    }
+    fn get(&self) -> &HANDLE {
+        &self.0
+    }
+}
+
+impl Drop for ScopeHandle {
+    fn drop(&mut self) {
+        let _ = close_handle(self.0);
+    }
+}
+
+pub fn close_handle(handle: HANDLE) -> Result<()> {
+    match unsafe { CloseHandle(handle) } {
+        Ok(_) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
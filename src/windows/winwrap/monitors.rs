@@ -0,0 +1,393 @@
+// Monitors, their work areas and DPI
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::errors::{Error, Result};
+use crate::geometry::{MonitorArea, MonitorId, MousePos};
+use crate::windows::wintypes::*;
+
+use windows::Win32::Devices::Display::{
+    DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes, QueryDisplayConfig,
+    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME, DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+    DISPLAYCONFIG_DEVICE_INFO_HEADER, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO,
+    DISPLAYCONFIG_SOURCE_DEVICE_NAME, DISPLAYCONFIG_TARGET_DEVICE_NAME, QDC_ONLY_ACTIVE_PATHS,
+};
+use windows::Win32::Graphics::Gdi::{MonitorFromPoint, MONITOR_DEFAULTTOPRIMARY};
+use windows::Win32::UI::HiDpi::{
+    SetProcessDpiAwareness, SetProcessDpiAwarenessContext,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, PROCESS_PER_MONITOR_DPI_AWARE,
+};
+use windows::Win32::UI::WindowsAndMessaging::{SetProcessDPIAware, EDD_GET_DEVICE_INTERFACE_NAME};
+use windows::Win32::{
+    Foundation::{BOOL, LPARAM, POINT, RECT},
+    Graphics::Gdi::{
+        EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, DISPLAY_DEVICEW, HDC, HMONITOR,
+        MONITORINFO, MONITORINFOEXW,
+    },
+    UI::{
+        HiDpi::{
+            GetDpiForMonitor, SetThreadDpiAwarenessContext, DPI_AWARENESS_CONTEXT,
+            DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE, MDT_EFFECTIVE_DPI,
+        },
+        WindowsAndMessaging::USER_DEFAULT_SCREEN_DPI,
+    },
+};
+
+use super::get_last_error;
+
+pub struct MonitorInfo {
+    pub handle: HMONITOR,
+    pub rect: RECT,
+    // Excluding taskbar and docked toolbars
+    pub work: RECT,
+    pub scale: u32,
+    // Device interface path of the monitor, which is built from its EDID, or the display
+    // name like "\\.\DISPLAY1" if unavailable
+    pub name: String,
+    // Readable name from EDID, e.g. "DELL U2720Q". Empty if unknown.
+    pub label: String,
+}
+
+impl MonitorInfo {
+    // Stays the same when monitors are reordered or their positions change
+    pub fn id(&self) -> MonitorId {
+        let mut h = DefaultHasher::new();
+        self.name.hash(&mut h);
+        h.finish()
+    }
+
+    pub fn area(&self) -> MonitorArea {
+        area_from_rect(&self.rect, self.id())
+    }
+
+    pub fn work_area(&self) -> MonitorArea {
+        area_from_rect(&self.work, self.id())
+    }
+}
+
+fn area_from_rect(rect: &RECT, id: MonitorId) -> MonitorArea {
+    MonitorArea {
+        lefttop: MousePos::from(rect.left, rect.top),
+        rigtbtm: MousePos::from(rect.right, rect.bottom),
+        id,
+    }
+}
+
+// Percent of scaling set for a monitor, from its effective DPI
+fn scale_from_dpi(dpi: u32) -> u32 {
+    dpi * 100 / USER_DEFAULT_SCREEN_DPI
+}
+
+pub struct ScopeDpiAwareness {
+    old: DPI_AWARENESS_CONTEXT,
+}
+
+impl ScopeDpiAwareness {
+    pub fn new(v: DPI_AWARENESS_CONTEXT) -> Self {
+        let old = unsafe { SetThreadDpiAwarenessContext(v) };
+        ScopeDpiAwareness { old }
+    }
+}
+
+impl Drop for ScopeDpiAwareness {
+    fn drop(&mut self) {
+        unsafe { SetThreadDpiAwarenessContext(self.old) };
+    }
+}
+
+pub fn get_monitor_scale_factor(hm: HMONITOR) -> Result<u32> {
+    // GetScaleFactorForMonitor() returns a wrong scale value, which is different from the monitor setting.
+    // The right value should be calculated from per-screen dpi.
+    // Ref: https://stackoverflow.com/questions/31348823/getscalefactorformonitor-value-doesnt-match-actual-scale-applied
+    //      https://learn.microsoft.com/en-us/windows/win32/hidpi/wm-dpichanged
+
+    // use windows::Win32::UI::Shell::GetScaleFactorForMonitor;
+    // match unsafe { GetScaleFactorForMonitor(hm) } {
+    //     Ok(v) => Ok(v.0 as u32),
+    //     Err(e) => Err(core_error(e)),
+    // }
+
+    let set_aware = ScopeDpiAwareness::new(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE);
+    let mut dpix: u32 = 0;
+    let mut dpiy: u32 = 0;
+    match unsafe { GetDpiForMonitor(hm, MDT_EFFECTIVE_DPI, &mut dpix, &mut dpiy) } {
+        Ok(_) => (),
+        Err(e) => return Err(core_error(e)),
+    };
+    drop(set_aware);
+
+    Ok(scale_from_dpi(dpix))
+}
+
+pub fn get_monitor_work_area(hm: HMONITOR) -> Result<RECT> {
+    let mut mi = MONITORINFO {
+        cbSize: wsize_of::<MONITORINFO>(),
+        ..Default::default()
+    };
+    match unsafe { GetMonitorInfoW(hm, &mut mi) }.as_bool() {
+        true => Ok(mi.rcWork),
+        false => Err(get_last_error()),
+    }
+}
+
+pub fn get_monitor_name(hm: HMONITOR) -> Result<String> {
+    let mut mi = MONITORINFOEXW::default();
+    mi.monitorInfo.cbSize = wsize_of::<MONITORINFOEXW>();
+    if !unsafe { GetMonitorInfoW(hm, &mut mi as *mut MONITORINFOEXW as *mut MONITORINFO) }.as_bool()
+    {
+        return Err(get_last_error());
+    }
+    let mut dd = DISPLAY_DEVICEW {
+        cb: wsize_of::<DISPLAY_DEVICEW>(),
+        ..Default::default()
+    };
+    let display = WString(mi.szDevice.to_vec());
+    let found = unsafe {
+        EnumDisplayDevicesW(
+            display.as_pcwstr(),
+            0,
+            &mut dd,
+            EDD_GET_DEVICE_INTERFACE_NAME,
+        )
+    }
+    .as_bool();
+    let device_id = WString(dd.DeviceID.to_vec()).to_string();
+    if found && !device_id.is_empty() {
+        Ok(device_id)
+    } else {
+        Ok(display.to_string())
+    }
+}
+
+pub fn get_primary_monitor_work_area() -> Result<RECT> {
+    get_monitor_work_area(unsafe { MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY) })
+}
+
+pub fn thread_set_dpi_aware() {
+    unsafe {
+        SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+    }
+}
+
+pub fn process_set_dpi_aware() -> bool {
+    unsafe {
+        if SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2).is_ok() {
+            return true;
+        }
+        if SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE).is_ok() {
+            return true;
+        }
+        if SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE).is_ok() {
+            return true;
+        }
+        if SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE).is_ok() {
+            return true;
+        }
+        SetProcessDPIAware().as_bool()
+    }
+}
+
+pub fn get_all_monitors_info() -> Result<Vec<MonitorInfo>> {
+    unsafe extern "system" fn enum_fn(
+        hm: HMONITOR,
+        _hdc: HDC,
+        rect: *mut RECT,
+        lparam: LPARAM,
+    ) -> BOOL {
+        let hms = lparam_ref::<Vec<MonitorInfo>>(&lparam);
+        hms.push(MonitorInfo {
+            handle: hm,
+            rect: *rect,
+            work: *rect,
+            scale: 0,
+            name: String::new(),
+            label: String::new(),
+        });
+        BOOL(1)
+    }
+
+    let mut hms: Vec<MonitorInfo> = Vec::new();
+    match unsafe {
+        EnumDisplayMonitors(HDC(0), None, Some(enum_fn), lparam_from(&mut hms)).as_bool()
+    } {
+        true => (),
+        false => return Err(Error::WinUnknown),
+    }
+
+    for m in &mut hms {
+        match get_monitor_scale_factor(m.handle) {
+            Ok(scale) => m.scale = scale,
+            Err(e) => return Err(e),
+        }
+        match get_monitor_work_area(m.handle) {
+            Ok(work) => m.work = work,
+            Err(e) => return Err(e),
+        }
+        match get_monitor_name(m.handle) {
+            Ok(name) => m.name = name,
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Labels are only for showing, monitors are still usable without them
+    if let Ok(names) = get_display_config_names() {
+        for m in &mut hms {
+            if let Some(label) = label_of(&names, &m.name) {
+                m.label = label.to_owned();
+            }
+        }
+    }
+
+    Ok(hms)
+}
+
+struct DisplayConfigNames {
+    // Like "\\.\DISPLAY1"
+    display: String,
+    // The same as device interface path from EnumDisplayDevicesW
+    device_path: String,
+    friendly: String,
+}
+
+// Name of a monitor is its device interface path, or display name if the path is unknown
+fn label_of<'a>(names: &'a [DisplayConfigNames], name: &str) -> Option<&'a str> {
+    names
+        .iter()
+        .find(|n| n.device_path.eq_ignore_ascii_case(name) || n.display.eq_ignore_ascii_case(name))
+        .map(|n| n.friendly.as_str())
+}
+
+// Names of active monitors by display config, which knows their EDID names
+fn get_display_config_names() -> Result<Vec<DisplayConfigNames>> {
+    let mut path_num = 0u32;
+    let mut mode_num = 0u32;
+    unsafe { GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_num, &mut mode_num) }
+        .ok()
+        .map_err(core_error)?;
+    let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_num as usize];
+    let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_num as usize];
+    unsafe {
+        QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut path_num,
+            paths.as_mut_ptr(),
+            &mut mode_num,
+            modes.as_mut_ptr(),
+            None,
+        )
+    }
+    .ok()
+    .map_err(core_error)?;
+    paths.truncate(path_num as usize);
+
+    let mut names = Vec::new();
+    for path in &paths {
+        let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+            header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                size: wsize_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>(),
+                adapterId: path.sourceInfo.adapterId,
+                id: path.sourceInfo.id,
+            },
+            ..Default::default()
+        };
+        let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+            header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+                size: wsize_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>(),
+                adapterId: path.targetInfo.adapterId,
+                id: path.targetInfo.id,
+            },
+            ..Default::default()
+        };
+        if unsafe { DisplayConfigGetDeviceInfo(&mut source.header) } != 0
+            || unsafe { DisplayConfigGetDeviceInfo(&mut target.header) } != 0
+        {
+            continue;
+        }
+        names.push(DisplayConfigNames {
+            display: WString(source.viewGdiDeviceName.to_vec()).to_string(),
+            device_path: WString(target.monitorDevicePath.to_vec()).to_string(),
+            friendly: WString(target.monitorFriendlyDeviceName.to_vec()).to_string(),
+        });
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, rect: RECT, work: RECT) -> MonitorInfo {
+        MonitorInfo {
+            handle: HMONITOR(0),
+            rect,
+            work,
+            scale: 100,
+            name: name.to_owned(),
+            label: String::new(),
+        }
+    }
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_monitor_areas() {
+        let m = monitor(
+            r"\\?\DISPLAY#DEL41A8#5&1b2c3d&0&UID4353",
+            rect(-1920, 0, 0, 1080),
+            rect(-1920, 0, 0, 1040),
+        );
+        let area = m.area();
+        assert_eq!(area.lefttop, MousePos::from(-1920, 0));
+        assert_eq!(area.rigtbtm, MousePos::from(0, 1080));
+        let work = m.work_area();
+        assert_eq!(work.rigtbtm, MousePos::from(0, 1040));
+        assert_eq!(area.id, work.id);
+
+        // Same monitor moved elsewhere keeps its id, another one doesn't share it
+        let moved = monitor(&m.name, rect(0, 0, 1920, 1080), rect(0, 0, 1920, 1040));
+        assert_eq!(moved.id(), m.id());
+        let other = monitor(r"\\.\DISPLAY2", m.rect, m.work);
+        assert_ne!(other.id(), m.id());
+    }
+
+    #[test]
+    fn test_scale_from_dpi() {
+        assert_eq!(scale_from_dpi(96), 100);
+        assert_eq!(scale_from_dpi(120), 125);
+        assert_eq!(scale_from_dpi(144), 150);
+        assert_eq!(scale_from_dpi(192), 200);
+    }
+
+    #[test]
+    fn test_label_of() {
+        let names = vec![
+            DisplayConfigNames {
+                display: r"\\.\DISPLAY1".to_owned(),
+                device_path: r"\\?\DISPLAY#DEL41A8#5&1b2c3d&0&UID4353".to_owned(),
+                friendly: "DELL U2720Q".to_owned(),
+            },
+            DisplayConfigNames {
+                display: r"\\.\DISPLAY2".to_owned(),
+                device_path: String::new(),
+                friendly: "Generic PnP".to_owned(),
+            },
+        ];
+        // Paths differ in case between APIs
+        assert_eq!(
+            label_of(&names, r"\\?\display#del41a8#5&1b2c3d&0&uid4353"),
+            Some("DELL U2720Q")
+        );
+        assert_eq!(label_of(&names, r"\\.\DISPLAY2"), Some("Generic PnP"));
+        assert_eq!(label_of(&names, r"\\.\DISPLAY3"), None);
+    }
+}
@@ -0,0 +1,256 @@
+// Raw input devices, and events read from them
+
+use std::fmt;
+use std::mem::size_of;
+
+use crate::errors::{Error, Result};
+use crate::windows::wintypes::*;
+
+use crate::windows::constants::*;
+use windows::Win32::UI::Input::RAWINPUT;
+use windows::Win32::{
+    Foundation::{GetLastError, ERROR_INSUFFICIENT_BUFFER, HANDLE},
+    UI::Input::{
+        GetRawInputData, GetRawInputDeviceInfoW, GetRawInputDeviceList, RegisterRawInputDevices,
+        HRAWINPUT, RAWINPUTDEVICE, RAWINPUTDEVICELIST, RAWINPUTHEADER,
+        RAW_INPUT_DEVICE_INFO_COMMAND, RIDI_DEVICEINFO, RIDI_DEVICENAME, RID_DEVICE_INFO,
+        RID_DEVICE_INFO_HID, RID_DEVICE_INFO_MOUSE, RID_DEVICE_INFO_TYPE, RID_INPUT, RIM_TYPEHID,
+        RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+    },
+};
+
+use super::get_last_error;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum RawDeviceType {
+    MOUSE,
+    KEYBOARD,
+    HID,
+    UNKNOWN,
+}
+
+impl RawDeviceType {
+    pub fn from_rid(t: RID_DEVICE_INFO_TYPE) -> Self {
+        match t {
+            RIM_TYPEMOUSE => RawDeviceType::MOUSE,
+            RIM_TYPEKEYBOARD => RawDeviceType::KEYBOARD,
+            RIM_TYPEHID => RawDeviceType::HID,
+            _ => RawDeviceType::UNKNOWN,
+        }
+    }
+}
+
+impl fmt::Display for RawDeviceType {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+pub struct RawinputInfo {
+    pub rid_info: RID_DEVICE_INFO,
+    pub iface: WString,
+}
+
+impl RawinputInfo {
+    #[inline]
+    pub fn typ(&self) -> RawDeviceType {
+        RawDeviceType::from_rid(self.rid_info.dwType)
+    }
+    #[inline]
+    pub fn get_mouse(&self) -> &RID_DEVICE_INFO_MOUSE {
+        assert!(self.typ() == RawDeviceType::MOUSE);
+        unsafe { &self.rid_info.Anonymous.mouse }
+    }
+    #[inline]
+    pub fn get_hid(&self) -> &RID_DEVICE_INFO_HID {
+        assert!(self.typ() == RawDeviceType::HID);
+        unsafe { &self.rid_info.Anonymous.hid }
+    }
+}
+
+pub fn device_list_all() -> Result<Vec<RAWINPUTDEVICELIST>> {
+    let mut cnt: WSize = 0;
+    let mut dev_list: Vec<RAWINPUTDEVICELIST> = Vec::new();
+
+    let res = unsafe { GetRawInputDeviceList(None, &mut cnt, wsize_of::<RAWINPUTDEVICELIST>()) };
+    if res == u32::MAX {
+        return Err(get_last_error());
+    }
+
+    loop {
+        dev_list.resize(cnt as usize, RAWINPUTDEVICELIST::default());
+
+        let res = unsafe {
+            GetRawInputDeviceList(
+                Some(wmut_vec(&mut dev_list)),
+                &mut cnt,
+                wsize_of::<RAWINPUTDEVICELIST>(),
+            )
+        };
+        if res != u32::MAX {
+            dev_list.shrink_to(res as usize);
+            return Ok(dev_list);
+        }
+
+        let e = unsafe { GetLastError().unwrap_err() };
+        if e.code() != ERROR_INSUFFICIENT_BUFFER.to_hresult() {
+            continue;
+        }
+    }
+}
+
+pub fn device_get_rawinput_rid_info(handle: HANDLE) -> Result<RID_DEVICE_INFO> {
+    let mut dst = RID_DEVICE_INFO::default();
+    let mut size = wsize_of_val(&dst);
+    let r = unsafe {
+        GetRawInputDeviceInfoW(handle, RIDI_DEVICEINFO, Some(wmut_obj(&mut dst)), &mut size)
+    };
+    if r == u32::MAX {
+        if size <= wsize_of_val(&dst) {
+            return Err(get_last_error());
+        }
+        return Err(Error::WinPredefineBufSmall(wsize_of_val(&dst), size));
+    }
+    Ok(dst)
+}
+
+pub fn device_get_rawinput_info<T: IBuffer>(
+    handle: HANDLE,
+    cmd: RAW_INPUT_DEVICE_INFO_COMMAND,
+) -> Result<T> {
+    let mut size: WSize = 0;
+    let r = unsafe { GetRawInputDeviceInfoW(handle, cmd, None, &mut size) };
+    if r != 0 {
+        return Err(get_last_error());
+    }
+
+    let mut buf = T::new(size);
+    loop {
+        let r = unsafe { GetRawInputDeviceInfoW(handle, cmd, Some(buf.as_mut_data()), &mut size) };
+        if r == u32::MAX {
+            if size <= buf.capacity() {
+                return Err(get_last_error());
+            }
+            buf.resize(size);
+            continue;
+        }
+        return Ok(buf);
+    }
+}
+
+pub fn device_collect_rawinput_infos(dev_handle: HANDLE) -> Result<RawinputInfo> {
+    Ok(RawinputInfo {
+        rid_info: device_get_rawinput_rid_info(dev_handle)?,
+        iface: device_get_rawinput_info::<WString>(dev_handle, RIDI_DEVICENAME)?,
+    })
+}
+
+pub fn register_rawinput_devices(devs: &[RAWINPUTDEVICE]) -> Result<()> {
+    let cbsize = size_of::<RAWINPUTDEVICE>() as u32;
+    match unsafe { RegisterRawInputDevices(devs, cbsize) } {
+        Ok(_) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn get_rawinput_data(handle: HRAWINPUT, data_buf: &mut WBuffer) -> Result<()> {
+    let mut size: u32 = 0;
+    let header_size = wsize_of::<RAWINPUTHEADER>();
+    let res = unsafe { GetRawInputData(handle, RID_INPUT, None, &mut size, header_size) };
+    if res != 0 {
+        return Err(get_last_error());
+    }
+
+    if data_buf.capacity() < size {
+        data_buf.resize(size);
+    }
+
+    let res = unsafe {
+        GetRawInputData(
+            handle,
+            RID_INPUT,
+            Some(data_buf.as_mut_data()),
+            &mut size,
+            header_size,
+        )
+    };
+    if res == u32::MAX {
+        return Err(get_last_error());
+    }
+    Ok(())
+}
+
+pub fn rawinput_to_string(ri: &RAWINPUT) -> String {
+    match RID_DEVICE_INFO_TYPE(ri.header.dwType) {
+        RIM_TYPEMOUSE => {
+            let m = unsafe { &ri.data.mouse };
+            format!(
+                "{{mouse({}); hdl={}, llast=({},{}), flag={}, extra={}}}",
+                ri.header.dwType,
+                ri.header.hDevice.0,
+                m.lLastX,
+                m.lLastY,
+                m.usFlags,
+                m.ulExtraInformation
+            )
+        }
+        RIM_TYPEHID => {
+            let m = unsafe { &ri.data.hid };
+            format!(
+                "{{hid({}); hdl={}, size={}, count={} }}",
+                ri.header.dwType, ri.header.hDevice.0, m.dwSizeHid, m.dwCount
+            )
+        }
+        _ => {
+            format!(
+                "{{other({}), hdl={}}}",
+                ri.header.dwType, ri.header.hDevice.0
+            )
+        }
+    }
+}
+
+// Of usFlags in a mouse event. Other bits, e.g. MOUSE_VIRTUAL_DESKTOP, only tell how an
+// absolute position is mapped.
+pub fn mouse_flags_is_absolute(flags: u16) -> bool {
+    (flags & RAWINPUT_MOUSE_FLAGS_ABSOLUTE) > 0
+}
+
+pub fn check_mouse_event_is_absolute(ri: &RAWINPUT) -> Option<bool> {
+    rawinput_mouse_flags(ri).map(mouse_flags_is_absolute)
+}
+
+pub fn rawinput_mouse_flags(ri: &RAWINPUT) -> Option<u16> {
+    match RID_DEVICE_INFO_TYPE(ri.header.dwType) {
+        RIM_TYPEMOUSE => unsafe { Some(ri.data.mouse.usFlags) },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mouse_flags() {
+        // MOUSE_MOVE_RELATIVE
+        assert!(!mouse_flags_is_absolute(0x00));
+        assert!(mouse_flags_is_absolute(0x01));
+        // With MOUSE_VIRTUAL_DESKTOP, e.g. from tablets spanning all monitors
+        assert!(mouse_flags_is_absolute(0x03));
+        assert!(!mouse_flags_is_absolute(0x02));
+        // MOUSE_ATTRIBUTES_CHANGED alone
+        assert!(!mouse_flags_is_absolute(0x04));
+    }
+
+    #[test]
+    fn test_raw_device_type() {
+        assert_eq!(RawDeviceType::from_rid(RIM_TYPEMOUSE), RawDeviceType::MOUSE);
+        assert_eq!(RawDeviceType::from_rid(RIM_TYPEHID), RawDeviceType::HID);
+        assert_eq!(
+            RawDeviceType::from_rid(RID_DEVICE_INFO_TYPE(7)),
+            RawDeviceType::UNKNOWN
+        );
+        assert_eq!(RawDeviceType::KEYBOARD.to_string(), "keyboard");
+    }
+}
@@ -0,0 +1,55 @@
+// Named mutexes, keeping a single process running
+
+use crate::errors::Result;
+use crate::windows::wintypes::*;
+
+use super::get_last_error;
+
+use windows::Win32::Foundation::{ERROR_ALREADY_EXISTS, HANDLE, WAIT_ABANDONED, WAIT_OBJECT_0};
+use windows::Win32::System::Threading::{
+    CreateMutexW, OpenMutexW, ReleaseMutex, WaitForSingleObject, INFINITE,
+    SYNCHRONIZATION_SYNCHRONIZE,
+};
+
+pub fn create_mutex(name: WString) -> Result<Option<HANDLE>> {
+    match unsafe { CreateMutexW(None, false, name.as_pcwstr()) } {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => {
+            if e.code() == ERROR_ALREADY_EXISTS.to_hresult() {
+                Ok(None)
+            } else {
+                Err(core_error(e))
+            }
+        }
+    }
+}
+
+// Also owned if abandoned, i.e. its last owner died without releasing it
+pub fn try_lock_mutex(handle: HANDLE) -> bool {
+    let r = unsafe { WaitForSingleObject(handle, 0) };
+    r == WAIT_OBJECT_0 || r == WAIT_ABANDONED
+}
+
+// Mutex created by another process, only for waiting on it
+pub fn open_mutex(name: WString) -> Result<HANDLE> {
+    match unsafe { OpenMutexW(SYNCHRONIZATION_SYNCHRONIZE, false, name.as_pcwstr()) } {
+        Ok(v) => Ok(v),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Blocks until the mutex is owned. Returns true if it was abandoned.
+pub fn wait_mutex(handle: HANDLE) -> Result<bool> {
+    match unsafe { WaitForSingleObject(handle, INFINITE) } {
+        WAIT_OBJECT_0 => Ok(false),
+        WAIT_ABANDONED => Ok(true),
+        _ => Err(get_last_error()),
+    }
+}
+
+pub fn release_mutex(handle: HANDLE) -> Result<()> {
+    match unsafe { ReleaseMutex(handle) } {
+        Ok(_) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
@@ -0,0 +1,283 @@
+// Ticks, sounds, local date, processes, threads and registry
+
+use crate::errors::Result;
+use crate::paths::THEME_REGISTRY_KEY;
+use crate::utils::TickClock;
+use crate::windows::wintypes::*;
+
+use windows::core::PWSTR;
+use windows::Win32::Media::Audio::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use windows::Win32::System::Registry::{
+    RegDeleteKeyValueW, RegGetValueW, HKEY_CURRENT_USER, RRF_RT_ANY, RRF_RT_REG_DWORD,
+    RRF_RT_REG_SZ,
+};
+use windows::Win32::System::Threading::{
+    AvRevertMmThreadCharacteristics, AvSetMmThreadCharacteristicsW, GetCurrentThread, OpenProcess,
+    QueryFullProcessImageNameW, SetThreadPriority, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION, THREAD_PRIORITY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+use windows::Win32::{
+    Foundation::{HANDLE, HMODULE},
+    System::SystemInformation::{GetLocalTime, GetTickCount64},
+};
+
+use super::close_handle;
+
+// TickWiden widens a DWORD tick which returned by some 32 API, which will reset to zero every 49.7 days.
+// Ref: https://learn.microsoft.com/en-us/windows/win32/api/sysinfoapi/nf-sysinfoapi-gettickcount
+pub struct TickWiden {
+    accu_tick: u64,
+    last_tick: u32,
+}
+
+impl Default for TickWiden {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TickWiden {
+    const MIN_FLUSH_TICK: u32 = 1000;
+    const MAX_SHORT_TICK: u64 = u32::MAX as u64;
+    pub fn new() -> Self {
+        let mut tw = TickWiden {
+            accu_tick: 0,
+            last_tick: 0,
+        };
+        tw.flush();
+        tw
+    }
+    #[inline]
+    pub fn flush(&mut self) {
+        let t = unsafe { GetTickCount64() };
+        self.accu_tick = t / Self::MAX_SHORT_TICK * Self::MAX_SHORT_TICK;
+    }
+    #[inline]
+    pub fn widen(&mut self, t: u32) -> u64 {
+        if t >= self.last_tick {
+            if t - self.last_tick > Self::MIN_FLUSH_TICK {
+                self.last_tick = t;
+            }
+        } else {
+            self.flush();
+            self.last_tick = t;
+        }
+        self.accu_tick + t as u64
+    }
+}
+
+fn get_system_tick() -> u64 {
+    unsafe { GetTickCount64() }
+}
+
+static TICK_CLOCK: TickClock = TickClock::new(get_system_tick);
+
+// Milliseconds for timing events, by system ticks until high resolution is turned on
+pub fn get_cur_tick() -> u64 {
+    TICK_CLOCK.now()
+}
+
+// Tick of an event stamped with system ticks, e.g. by message time, or now if high resolution
+// is turned on, since such stamps are of system tick resolution
+pub fn get_event_tick(system_tick: u64) -> u64 {
+    TICK_CLOCK
+        .from_coarse(system_tick)
+        .unwrap_or_else(get_cur_tick)
+}
+
+pub fn set_high_resolution_tick(on: bool) {
+    TICK_CLOCK.set_precise(on);
+}
+
+// Milliseconds since a message was posted, by time of the message
+pub fn ms_since_message(time: u32) -> u32 {
+    message_tick().wrapping_sub(time)
+}
+
+// Now, in the truncated ticks that MSG.time carries
+pub fn message_tick() -> u32 {
+    get_system_tick() as u32
+}
+
+pub fn set_current_thread_priority(priority: THREAD_PRIORITY) -> Result<()> {
+    match unsafe { SetThreadPriority(GetCurrentThread(), priority) } {
+        Ok(_) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Registers calling thread as a task of Multimedia Class Scheduler Service, like "Games"
+pub fn join_mmcss_task(task: &str) -> Result<HANDLE> {
+    let task = WString::encode_from_str(task);
+    let mut index = 0u32;
+    match unsafe { AvSetMmThreadCharacteristicsW(task.as_pcwstr(), &mut index) } {
+        Ok(v) => Ok(v),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn leave_mmcss_task(handle: HANDLE) -> Result<()> {
+    match unsafe { AvRevertMmThreadCharacteristics(handle) } {
+        Ok(_) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Played asynchronously, returns false if not found
+pub fn play_sound_file(path: &str) -> bool {
+    let path = WString::encode_from_str(path);
+    unsafe {
+        PlaySoundW(
+            path.as_pcwstr(),
+            HMODULE(0),
+            SND_FILENAME | SND_ASYNC | SND_NODEFAULT,
+        )
+    }
+    .as_bool()
+}
+
+// Played asynchronously, alias is one of registered system sounds, e.g. "SystemAsterisk"
+pub fn play_sound_alias(alias: &str) -> bool {
+    let alias = WString::encode_from_str(alias);
+    unsafe {
+        PlaySoundW(
+            alias.as_pcwstr(),
+            HMODULE(0),
+            SND_ALIAS | SND_ASYNC | SND_NODEFAULT,
+        )
+    }
+    .as_bool()
+}
+
+// Local date as "YYYY-MM-DD"
+pub fn get_local_date() -> String {
+    let t = unsafe { GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", t.wYear, t.wMonth, t.wDay)
+}
+
+// Executable names of all running processes, like "explorer.exe"
+pub fn running_process_names() -> Result<Vec<String>> {
+    let snapshot = match unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) } {
+        Ok(v) => v,
+        Err(e) => return Err(core_error(e)),
+    };
+    let mut names = Vec::new();
+    let mut entry = PROCESSENTRY32W {
+        dwSize: wsize_of::<PROCESSENTRY32W>(),
+        ..Default::default()
+    };
+    let mut next = unsafe { Process32FirstW(snapshot, &mut entry) };
+    while next.is_ok() {
+        names.push(WString(entry.szExeFile.to_vec()).to_string());
+        next = unsafe { Process32NextW(snapshot, &mut entry) };
+    }
+    let _ = close_handle(snapshot);
+    Ok(names)
+}
+
+// Executable name of the program owning the foreground window, None if no window is in
+// foreground, e.g. while switching windows
+pub fn foreground_process_name() -> Result<Option<String>> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.0 == 0 {
+        return Ok(None);
+    }
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    if pid == 0 {
+        return Ok(None);
+    }
+    let process = match unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) } {
+        Ok(v) => v,
+        Err(e) => return Err(core_error(e)),
+    };
+    let mut buf = [0u16; 1024];
+    let mut size = buf.len() as u32;
+    let result = unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buf.as_mut_ptr()),
+            &mut size,
+        )
+    };
+    let _ = close_handle(process);
+    if let Err(e) = result {
+        return Err(core_error(e));
+    }
+    let path = WString(buf[..size as usize].to_vec()).to_string();
+    Ok(path.rsplit('\\').next().map(|s| s.to_owned()))
+}
+
+// Theme of taskbar and notification area, which may differ from the theme of apps
+pub fn system_uses_light_theme() -> Result<bool> {
+    let key = WString::encode_from_str(THEME_REGISTRY_KEY);
+    let value = WString::encode_from_str("SystemUsesLightTheme");
+    let mut data: u32 = 0;
+    let mut size = wsize_of::<u32>();
+    match unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_pcwstr(),
+            value.as_pcwstr(),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut data as *mut u32 as *mut std::ffi::c_void),
+            Some(&mut size),
+        )
+    } {
+        Ok(()) => Ok(data != 0),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn registry_value_exists(key: &str, value: &str) -> bool {
+    let key = WString::encode_from_str(key);
+    let value = WString::encode_from_str(value);
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_pcwstr(),
+            value.as_pcwstr(),
+            RRF_RT_ANY,
+            None,
+            None,
+            None,
+        )
+    }
+    .is_ok()
+}
+
+// None if missing or not a string
+pub fn registry_string_value(key: &str, value: &str) -> Option<String> {
+    let key = WString::encode_from_str(key);
+    let value = WString::encode_from_str(value);
+    let mut buf = [0u16; 2048];
+    let mut size = (buf.len() * 2) as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_pcwstr(),
+            value.as_pcwstr(),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buf.as_mut_ptr() as *mut std::ffi::c_void),
+            Some(&mut size),
+        )
+    }
+    .ok()?;
+    // Size in bytes, including the terminating null
+    let len = (size as usize / 2).saturating_sub(1);
+    Some(WString(buf[..len].to_vec()).to_string())
+}
+
+pub fn delete_registry_value(key: &str, value: &str) -> Result<()> {
+    let key = WString::encode_from_str(key);
+    let value = WString::encode_from_str(value);
+    unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_pcwstr(), value.as_pcwstr()) }
+        .map_err(core_error)
+}
@@ -0,0 +1,407 @@
+// Windows of mouse control, their subclasses and timers, and dialogs shown to user
+
+use crate::errors::Result;
+use crate::windows::wintypes::*;
+
+use windows::Win32::Foundation::{BOOL, COLORREF};
+use windows::Win32::Graphics::Gdi::InvalidateRect;
+use windows::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DestroyWindow, EnumThreadWindows, FindWindowW, GetClassNameW, MessageBoxExW, PostMessageW,
+    SetLayeredWindowAttributes, SetWindowPos, SetWindowTextW, ShowWindow, HWND_DESKTOP,
+    HWND_TOPMOST, IDYES, LWA_ALPHA, LWA_COLORKEY, MB_ICONQUESTION, MB_TOPMOST, MB_YESNO,
+    MESSAGEBOX_RESULT, SWP_NOACTIVATE, SWP_SHOWWINDOW, SW_HIDE, SW_SHOWNORMAL, WS_EX_LAYERED,
+    WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_OVERLAPPEDWINDOW,
+    WS_POPUP,
+};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{HMODULE, HWND, LPARAM, LRESULT, WPARAM},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass, ShellExecuteW},
+            WindowsAndMessaging::{
+                CreateWindowExW, SetTimer, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE,
+            },
+        },
+    },
+};
+
+use super::{catch_callback_panic, get_last_error};
+
+pub fn create_dummy_window(module: Option<HMODULE>) -> Result<(HMODULE, HWND)> {
+    let hinstance = match module {
+        Some(m) => m,
+        None => match unsafe { GetModuleHandleW(None) } {
+            Ok(v) => v,
+            Err(e) => return Err(core_error(e)),
+        },
+    };
+    let class = WString::encode_from_str("Static").as_pcwstr();
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class,
+            None,
+            WS_OVERLAPPEDWINDOW,
+            0,
+            0,
+            0,
+            0,
+            HWND_DESKTOP,
+            None,
+            hinstance,
+            None,
+        )
+    };
+    if hwnd.0 == 0 {
+        return Err(get_last_error());
+    }
+    Ok((hinstance, hwnd))
+}
+
+pub fn create_message_only_window(module: Option<HMODULE>) -> Result<(HMODULE, HWND)> {
+    let hinstance = match module {
+        Some(m) => m,
+        None => match unsafe { GetModuleHandleW(None) } {
+            Ok(v) => v,
+            Err(e) => return Err(core_error(e)),
+        },
+    };
+    let class = WString::encode_from_str("Message").as_pcwstr();
+
+    // create message-only window
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class,
+            None,
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            hinstance,
+            None,
+        )
+    };
+    if hwnd.0 == 0 {
+        return Err(get_last_error());
+    }
+    Ok((hinstance, hwnd))
+}
+
+// A click-through topmost popup window, pixels in color_key are transparent
+pub fn create_overlay_window(module: Option<HMODULE>, color_key: u32) -> Result<(HMODULE, HWND)> {
+    let (hinstance, hwnd) = create_layered_popup(module)?;
+    if let Err(e) =
+        unsafe { SetLayeredWindowAttributes(hwnd, COLORREF(color_key), 0, LWA_COLORKEY) }
+    {
+        let _ = destroy_window(hwnd);
+        return Err(core_error(e));
+    }
+    Ok((hinstance, hwnd))
+}
+
+// Same as create_overlay_window(), while other pixels are translucent by alpha
+pub fn create_translucent_overlay_window(
+    module: Option<HMODULE>,
+    color_key: u32,
+    alpha: u8,
+) -> Result<(HMODULE, HWND)> {
+    let (hinstance, hwnd) = create_layered_popup(module)?;
+    if let Err(e) = unsafe {
+        SetLayeredWindowAttributes(hwnd, COLORREF(color_key), alpha, LWA_COLORKEY | LWA_ALPHA)
+    } {
+        let _ = destroy_window(hwnd);
+        return Err(core_error(e));
+    }
+    Ok((hinstance, hwnd))
+}
+
+// A click-through topmost popup window, translucent as a whole by alpha
+pub fn create_translucent_window(module: Option<HMODULE>, alpha: u8) -> Result<(HMODULE, HWND)> {
+    let (hinstance, hwnd) = create_layered_popup(module)?;
+    if let Err(e) = unsafe { SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA) } {
+        let _ = destroy_window(hwnd);
+        return Err(core_error(e));
+    }
+    Ok((hinstance, hwnd))
+}
+
+fn create_layered_popup(module: Option<HMODULE>) -> Result<(HMODULE, HWND)> {
+    let hinstance = match module {
+        Some(m) => m,
+        None => match unsafe { GetModuleHandleW(None) } {
+            Ok(v) => v,
+            Err(e) => return Err(core_error(e)),
+        },
+    };
+    let class = WString::encode_from_str("Static").as_pcwstr();
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            class,
+            None,
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            HWND_DESKTOP,
+            None,
+            hinstance,
+            None,
+        )
+    };
+    if hwnd.0 == 0 {
+        return Err(get_last_error());
+    }
+    Ok((hinstance, hwnd))
+}
+
+pub fn destroy_window(hwnd: HWND) -> Result<()> {
+    match unsafe { DestroyWindow(hwnd) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn set_window_text(hwnd: HWND, text: &str) -> Result<()> {
+    let text = WString::encode_from_str(text);
+    match unsafe { SetWindowTextW(hwnd, text.as_pcwstr()) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Top-level window of given class and title, of any process
+pub fn find_window(class: &str, title: &str) -> Option<HWND> {
+    let class = WString::encode_from_str(class);
+    let title = WString::encode_from_str(title);
+    let hwnd = unsafe { FindWindowW(class.as_pcwstr(), title.as_pcwstr()) };
+    if hwnd.0 == 0 {
+        None
+    } else {
+        Some(hwnd)
+    }
+}
+
+// Window of given class, owned by the calling thread, e.g. created by a library
+pub fn find_thread_window(class: &str) -> Option<HWND> {
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let (class, found) = &mut *(lparam.0 as *mut (&str, Option<HWND>));
+        let mut buf = [0u16; 256];
+        let len = GetClassNameW(hwnd, &mut buf) as usize;
+        if WString(buf[..len].to_vec()).to_string() == *class {
+            *found = Some(hwnd);
+            return BOOL::from(false);
+        }
+        BOOL::from(true)
+    }
+    let mut state: (&str, Option<HWND>) = (class, None);
+    unsafe {
+        let _ = EnumThreadWindows(
+            GetCurrentThreadId(),
+            Some(visit),
+            LPARAM(&mut state as *mut _ as isize),
+        );
+    }
+    state.1
+}
+
+pub fn post_message(hwnd: HWND, msg: u32, wparam: usize, lparam: isize) -> Result<()> {
+    match unsafe { PostMessageW(hwnd, msg, WPARAM(wparam), LPARAM(lparam)) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+// Show window at given position on top, without activating it
+pub fn show_window_topmost(hwnd: HWND, x: i32, y: i32, cx: i32, cy: i32) -> Result<()> {
+    match unsafe {
+        SetWindowPos(
+            hwnd,
+            HWND_TOPMOST,
+            x,
+            y,
+            cx,
+            cy,
+            SWP_NOACTIVATE | SWP_SHOWWINDOW,
+        )
+    } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn hide_window(hwnd: HWND) {
+    unsafe {
+        ShowWindow(hwnd, SW_HIDE);
+    }
+}
+
+pub fn invalidate_window(hwnd: HWND) {
+    unsafe {
+        InvalidateRect(hwnd, None, false);
+    }
+}
+
+pub trait SubclassHandler {
+    fn subclass_callback(&mut self, umsg: u32, wp: WPARAM, lp: LPARAM, uidsubclass: usize) -> bool;
+}
+
+unsafe extern "system" fn subclass_proc<T: SubclassHandler>(
+    hwnd: HWND,
+    umsg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    uidsubclass: usize,
+    dwrefdata: usize,
+) -> LRESULT {
+    let dp_ptr = dwrefdata as *mut T;
+    let dp = dp_ptr.as_mut().unwrap();
+
+    let call_next = catch_callback_panic(true, || {
+        dp.subclass_callback(umsg, wparam, lparam, uidsubclass)
+    });
+    if call_next {
+        DefSubclassProc(hwnd, umsg, wparam, lparam)
+    } else {
+        LRESULT(0)
+    }
+}
+
+pub fn set_subclass<T: SubclassHandler>(
+    hwnd: HWND,
+    uidsubclass: usize,
+    handler: Option<&mut T>,
+) -> Result<()> {
+    let ok = match handler {
+        Some(h) => unsafe {
+            SetWindowSubclass(
+                hwnd,
+                Some(subclass_proc::<T>),
+                uidsubclass,
+                wmut_ptr(h) as usize,
+            )
+        },
+        None => unsafe { SetWindowSubclass(hwnd, None, uidsubclass, 0) },
+    }
+    .as_bool();
+
+    if ok {
+        Ok(())
+    } else {
+        Err(get_last_error())
+    }
+}
+
+pub fn remove_subclass<T: SubclassHandler>(hwnd: HWND, uidsubclass: usize) -> Result<()> {
+    if unsafe { RemoveWindowSubclass(hwnd, Some(subclass_proc::<T>), uidsubclass) }.as_bool() {
+        Ok(())
+    } else {
+        Err(get_last_error())
+    }
+}
+
+pub trait TimerCallback {
+    fn on_timer(hwnd: HWND, msg: u32, nid: usize, time: u32);
+}
+
+pub fn set_timer<T: TimerCallback>(hwnd: HWND, nid: usize, elapse_ms: u32) -> Result<()> {
+    unsafe extern "system" fn timer_proc<T: TimerCallback>(
+        hwnd: HWND,
+        msg: u32,
+        nid: usize,
+        time: u32,
+    ) {
+        catch_callback_panic((), || T::on_timer(hwnd, msg, nid, time))
+    }
+
+    let res = unsafe { SetTimer(hwnd, nid, elapse_ms, Some(timer_proc::<T>)) };
+    match res {
+        0 => Err(get_last_error()),
+        _ => Ok(()),
+    }
+}
+
+// Receive WM_WTSSESSION_CHANGE for current session
+pub fn register_session_notification(hwnd: HWND) -> Result<()> {
+    match unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn unregister_session_notification(hwnd: HWND) -> Result<()> {
+    match unsafe { WTSUnRegisterSessionNotification(hwnd) } {
+        Ok(()) => Ok(()),
+        Err(e) => Err(core_error(e)),
+    }
+}
+
+pub fn popup_message_box(caption: WString, text: WString) -> Result<MESSAGEBOX_RESULT> {
+    let ret = unsafe {
+        MessageBoxExW(
+            HWND(0),
+            text.as_pcwstr(),
+            caption.as_pcwstr(),
+            MB_TOPMOST,
+            0,
+        )
+    };
+    if ret.0 == 0 {
+        Err(get_last_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+// True if answered yes
+pub fn popup_confirm_box(caption: WString, text: WString) -> Result<bool> {
+    let ret = unsafe {
+        MessageBoxExW(
+            HWND(0),
+            text.as_pcwstr(),
+            caption.as_pcwstr(),
+            MB_TOPMOST | MB_YESNO | MB_ICONQUESTION,
+            0,
+        )
+    };
+    if ret.0 == 0 {
+        Err(get_last_error())
+    } else {
+        Ok(ret == IDYES)
+    }
+}
+
+// Open a folder or file by its associated program, e.g. explorer for folders
+pub fn shell_open(path: &str) -> Result<()> {
+    let operation = WString::encode_from_str("open");
+    let file = WString::encode_from_str(path);
+    let ret = unsafe {
+        ShellExecuteW(
+            HWND(0),
+            operation.as_pcwstr(),
+            file.as_pcwstr(),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // Values not greater than 32 are error codes
+    if ret.0 <= 32 {
+        Err(get_last_error())
+    } else {
+        Ok(())
+    }
+}